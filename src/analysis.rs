@@ -0,0 +1,305 @@
+//! The analysis report of a finished game, opened from the game over screen. The AI goes over the
+//! moves one per frame, see [`crate::engine::analysis`], and the report shows the evaluation after
+//! every move as a graph, together with the inaccuracies, mistakes and blunders. Clicking a move
+//! on the graph or in the list starts the replay at the position it was played in.
+
+use bevy::prelude::*;
+
+use crate::engine::ai::AICache;
+use crate::engine::analysis::{self, Judgement, MoveAnalysis};
+use crate::engine::units::Team;
+use crate::gamemanager::Game;
+use crate::replay::ReplayFrom;
+use crate::ui::{BUTTON_COLOR, HOVERED_BUTTON_COLOR};
+use crate::AppState;
+
+/// The evaluation at the top and the bottom of the graph, in pawns
+const GRAPH_RANGE: f32 = 10.;
+const GRAPH_HEIGHT: f32 = 120.;
+const WHITE_BAR_COLOR: Color = Color::rgb(0.9, 0.9, 0.85);
+const BLACK_BAR_COLOR: Color = Color::rgb(0.25, 0.25, 0.25);
+
+/// The analysis of the game that is over. It is kept until the main menu, so that the report
+/// doesn't start over after a replay.
+#[derive(Resource, Default)]
+pub(crate) struct GameAnalysis {
+    /// The analysed moves, with the number of turns played before each
+    moves: Vec<(usize, MoveAnalysis)>,
+    /// The turns that have been gone over
+    turns_done: usize,
+    ai_cache: AICache,
+}
+
+impl GameAnalysis {
+    fn is_done(&self, game: &Game) -> bool {
+        self.turns_done >= game.history.turns_played()
+    }
+
+    /// How many moves of `team` got each judgement
+    fn count(&self, team: Team, judgement: Judgement) -> usize {
+        self.moves
+            .iter()
+            .filter(|(_, analysis)| analysis.team == team && analysis.judgement == Some(judgement))
+            .count()
+    }
+}
+
+/// A on the game over screen opens the report, for the variants that can be analysed
+pub(crate) fn enter_analysis(
+    input: Res<Input<KeyCode>>,
+    game: Res<Game>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if input.just_pressed(KeyCode::A) && analysis::can_analyse(game.variant) {
+        next_state.set(AppState::Analysis);
+    }
+}
+
+/// Goes over the next turn of the game. Turns with more than one move, like the extra moves of a
+/// handicap, are left out.
+pub(crate) fn analyse_next_turn(mut game_analysis: ResMut<GameAnalysis>, game: Res<Game>) {
+    if game_analysis.is_done(&game) {
+        return;
+    }
+    let game_analysis = &mut *game_analysis;
+    let turn = game_analysis.turns_done;
+    game_analysis.turns_done += 1;
+    let Some(moves) = game.history.turns().nth(turn) else {
+        return;
+    };
+    let [recorded_move] = moves else {
+        return;
+    };
+    let Some(played) = recorded_move.game_move else {
+        return;
+    };
+    let analysis = analysis::analyse_move(
+        &game.board,
+        &game.history.positions()[turn].units,
+        recorded_move.team,
+        played,
+        game.variant,
+        &mut game_analysis.ai_cache,
+    );
+    game_analysis.moves.push((turn, analysis));
+}
+
+/// Starts the replay at the position the move was played in
+#[derive(Component)]
+pub(crate) struct AnalysisRow(usize);
+
+#[derive(Component)]
+pub(crate) struct AnalysisReport;
+
+pub(crate) fn spawn_analysis_report(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(10.),
+                    left: Val::Px(10.),
+                    ..default()
+                },
+                size: Size::new(Val::Percent(60.), Val::Auto),
+                max_size: Size::new(Val::Auto, Val::Percent(95.)),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(10.)),
+                overflow: Overflow::Hidden,
+                ..default()
+            },
+            background_color: Color::rgba(0., 0., 0., 0.7).into(),
+            ..default()
+        },
+        AnalysisReport,
+    ));
+}
+
+pub(crate) fn despawn_analysis_report(
+    mut commands: Commands,
+    query: Query<Entity, With<AnalysisReport>>,
+) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Fills the report once it is opened, and again whenever another move has been analysed
+pub(crate) fn update_analysis_report(
+    mut commands: Commands,
+    game_analysis: Res<GameAnalysis>,
+    game: Res<Game>,
+    query: Query<(Entity, Option<&Children>), With<AnalysisReport>>,
+) {
+    for (entity, children) in &query {
+        if children.is_some() && !game_analysis.is_changed() {
+            continue;
+        }
+        commands.entity(entity).despawn_descendants();
+        commands.entity(entity).with_children(|parent| {
+            spawn_report_contents(parent, &game_analysis, &game);
+        });
+    }
+}
+
+fn spawn_report_contents(parent: &mut ChildBuilder, game_analysis: &GameAnalysis, game: &Game) {
+    let text_style = |font_size| TextStyle {
+        font_size,
+        color: Color::WHITE,
+        ..default()
+    };
+    parent.spawn(TextBundle::from_section("Analysis", text_style(40.)));
+    let progress = if game_analysis.is_done(game) {
+        "Click a move to replay the game from there, Escape goes back".to_string()
+    } else {
+        format!(
+            "Analysing turn {} of {}",
+            game_analysis.turns_done + 1,
+            game.history.turns_played()
+        )
+    };
+    let mut summary = format!("{}\n\nInaccuracies, mistakes and blunders:", progress);
+    for team in [Team::White, Team::Black] {
+        summary.push_str(&format!(
+            " {:?} {}/{}/{}",
+            team,
+            game_analysis.count(team, Judgement::Inaccuracy),
+            game_analysis.count(team, Judgement::Mistake),
+            game_analysis.count(team, Judgement::Blunder)
+        ));
+    }
+    parent.spawn(TextBundle::from_section(summary, text_style(20.)));
+
+    let num_turns = game.history.turns_played().max(1);
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Px(GRAPH_HEIGHT)),
+                margin: UiRect::vertical(Val::Px(10.)),
+                flex_shrink: 0.,
+                ..default()
+            },
+            background_color: Color::rgba(1., 1., 1., 0.05).into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            for (turn, analysis) in &game_analysis.moves {
+                spawn_graph_bar(parent, *turn, analysis, num_turns);
+            }
+        });
+
+    for (turn, analysis) in &game_analysis.moves {
+        let Some(judgement) = analysis.judgement else {
+            continue;
+        };
+        let mut row = format!(
+            "{}. {:?} {}{} {}",
+            turn + 1,
+            analysis.team,
+            analysis.played,
+            judgement.mark(),
+            judgement.name()
+        );
+        if let Some(best) = &analysis.best {
+            row.push_str(&format!(", {} was {:.1} better", best, analysis.loss));
+        }
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(2.)),
+                        flex_shrink: 0.,
+                        ..default()
+                    },
+                    background_color: BUTTON_COLOR.into(),
+                    ..default()
+                },
+                AnalysisRow(*turn),
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(row, text_style(20.)));
+            });
+    }
+}
+
+/// A column of the graph, with a bar up from the middle when white is ahead and down when black is
+fn spawn_graph_bar(
+    parent: &mut ChildBuilder,
+    turn: usize,
+    analysis: &MoveAnalysis,
+    num_turns: usize,
+) {
+    let background = match analysis.judgement {
+        Some(Judgement::Blunder) => Color::rgba(0.9, 0.1, 0.1, 0.6),
+        Some(Judgement::Mistake) => Color::rgba(0.9, 0.5, 0.1, 0.5),
+        Some(Judgement::Inaccuracy) => Color::rgba(0.9, 0.9, 0.1, 0.3),
+        None => Color::NONE,
+    };
+    let share = analysis.evaluation.clamp(-GRAPH_RANGE, GRAPH_RANGE) / GRAPH_RANGE * 50.;
+    let (top, color) = if share >= 0. {
+        (50. - share, WHITE_BAR_COLOR)
+    } else {
+        (50., BLACK_BAR_COLOR)
+    };
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Percent(turn as f32 / num_turns as f32 * 100.),
+                        ..default()
+                    },
+                    size: Size::new(Val::Percent(100. / num_turns as f32), Val::Percent(100.)),
+                    ..default()
+                },
+                background_color: background.into(),
+                ..default()
+            },
+            AnalysisRow(turn),
+        ))
+        .with_children(|parent| {
+            parent.spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        top: Val::Percent(top),
+                        ..default()
+                    },
+                    size: Size::new(Val::Percent(100.), Val::Percent(share.abs())),
+                    ..default()
+                },
+                background_color: color.into(),
+                ..default()
+            });
+        });
+}
+
+/// Clicking a move starts the replay at it, and Escape goes back to the game over screen
+pub(crate) fn analysis_input(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    mut query: Query<(&Interaction, &AnalysisRow, &mut BackgroundColor), Changed<Interaction>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for (interaction, row, mut color) in &mut query {
+        match interaction {
+            Interaction::Clicked => {
+                commands.insert_resource(ReplayFrom(row.0));
+                next_state.set(AppState::Replay);
+            }
+            // The columns of the graph keep the color of their judgement
+            Interaction::Hovered if color.0 == BUTTON_COLOR => color.0 = HOVERED_BUTTON_COLOR,
+            Interaction::None if color.0 == HOVERED_BUTTON_COLOR => color.0 = BUTTON_COLOR,
+            _ => {}
+        }
+    }
+    if input.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::GameOver);
+    }
+}
+
+/// The analysis belongs to the game that is over
+pub(crate) fn forget_analysis(mut game_analysis: ResMut<GameAnalysis>) {
+    *game_analysis = GameAnalysis::default();
+}
@@ -0,0 +1,281 @@
+//! Arrows and circles that the player draws on the cube while thinking, like on Lichess. Dragging
+//! with the right mouse button from one cell to another draws an arrow over the surface of the
+//! cube, going over edges, and right clicking a cell circles it. Drawing the same one again takes
+//! it away. The game doesn't read them, and they are cleared when the next move is made.
+//!
+//! The right mouse button orbits the camera instead while the free camera is on.
+
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, VecDeque};
+
+use bevy::prelude::*;
+
+use crate::engine::cell::CellCoordinates;
+use crate::engine::direction::CartesianDirection;
+use crate::gamemanager::{Game, MoveMade};
+use crate::settings::Settings;
+
+const ANNOTATION_COLOR: Color = Color::rgba(0.1, 0.6, 0.2, 0.8);
+
+/// How far above the cells the annotations are, relative to the size of a cell
+const ANNOTATION_LIFT: f32 = 0.05;
+
+/// How wide the lines of an arrow are, relative to the size of a cell
+const ARROW_WIDTH: f32 = 0.08;
+
+/// How long the sides of the head of an arrow are, relative to the size of a cell
+const ARROW_HEAD: f32 = 0.3;
+
+#[derive(Resource, Default)]
+pub(crate) struct Annotations {
+    arrows: Vec<(CellCoordinates, CellCoordinates)>,
+    circles: Vec<CellCoordinates>,
+}
+
+impl Annotations {
+    fn is_empty(&self) -> bool {
+        self.arrows.is_empty() && self.circles.is_empty()
+    }
+}
+
+#[derive(Component)]
+pub(crate) struct AnnotationMark;
+
+/// The meshes of the annotations, which are sized for a cell 1 wide
+pub(crate) struct AnnotationAssets {
+    line: Handle<Mesh>,
+    circle: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+/// Adds the item to the list, or removes it if it is already there
+fn toggle<T: PartialEq>(list: &mut Vec<T>, item: T) {
+    match list.iter().position(|other| *other == item) {
+        Some(index) => {
+            list.remove(index);
+        }
+        None => list.push(item),
+    }
+}
+
+/// Draws an arrow from the cell the right mouse button was pressed on to the cell it is released
+/// on, or a circle if they are the same cell
+pub(crate) fn draw_annotations(
+    mouse_input: Res<Input<MouseButton>>,
+    game: Res<Game>,
+    settings: Res<Settings>,
+    mut annotations: ResMut<Annotations>,
+    mut drag_start: Local<Option<CellCoordinates>>,
+) {
+    if settings.orbit_camera {
+        *drag_start = None;
+        return;
+    }
+    if mouse_input.just_pressed(MouseButton::Right) {
+        *drag_start = game.hovered_cell;
+    }
+    if !mouse_input.just_released(MouseButton::Right) {
+        return;
+    }
+    let (Some(from), Some(to)) = (drag_start.take(), game.hovered_cell) else {
+        return;
+    };
+    if from == to {
+        toggle(&mut annotations.circles, from);
+    } else {
+        toggle(&mut annotations.arrows, (from, to));
+    }
+}
+
+pub(crate) fn clear_annotations_on_move(
+    mut move_made: EventReader<MoveMade>,
+    mut annotations: ResMut<Annotations>,
+) {
+    if move_made.iter().count() > 0 && !annotations.is_empty() {
+        *annotations = Annotations::default();
+    }
+}
+
+pub(crate) fn forget_annotations(mut annotations: ResMut<Annotations>) {
+    if !annotations.is_empty() {
+        *annotations = Annotations::default();
+    }
+}
+
+/// Spawns the annotations again when they change
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn update_annotation_marks(
+    mut commands: Commands,
+    annotations: Res<Annotations>,
+    game: Res<Game>,
+    mark_query: Query<Entity, With<AnnotationMark>>,
+    cell_query: Query<&Transform, Without<AnnotationMark>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut assets: Local<Option<AnnotationAssets>>,
+) {
+    if !annotations.is_changed() {
+        return;
+    }
+    for entity in &mark_query {
+        commands.entity(entity).despawn_recursive();
+    }
+    let assets = assets.get_or_insert_with(|| AnnotationAssets {
+        line: meshes.add(shape::Cube { size: 1. }.into()),
+        circle: meshes.add(
+            shape::Torus {
+                radius: 0.42,
+                ring_radius: 0.04,
+                ..default()
+            }
+            .into(),
+        ),
+        material: materials.add(StandardMaterial {
+            base_color: ANNOTATION_COLOR,
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+    });
+    // Where a cell is, which way its face points and how big it is
+    let cell_position = |coords: CellCoordinates| {
+        let cell = game.board.get_cell(coords)?;
+        let transform = cell_query.get(cell.plane.into()).ok()?;
+        let normal = coords.normal_direction().as_vec3();
+        let size = transform.scale.x;
+        Some((
+            transform.translation + normal * ANNOTATION_LIFT * size,
+            normal,
+            size,
+        ))
+    };
+
+    for &coords in &annotations.circles {
+        let Some((position, normal, size)) = cell_position(coords) else {
+            continue;
+        };
+        commands.spawn((
+            PbrBundle {
+                mesh: assets.circle.clone(),
+                material: assets.material.clone(),
+                transform: Transform {
+                    translation: position,
+                    rotation: Quat::from_rotation_arc(Vec3::Y, normal),
+                    scale: Vec3::splat(size),
+                },
+                ..default()
+            },
+            AnnotationMark,
+        ));
+    }
+
+    for &(from, to) in &annotations.arrows {
+        let path = surface_path(from, to, game.board.cube_side_length);
+        let positions: Option<Vec<_>> = path.into_iter().map(cell_position).collect();
+        let Some(positions) = positions.filter(|positions| positions.len() >= 2) else {
+            continue;
+        };
+        let size = positions[0].2;
+        let mut points = vec![positions[0].0];
+        for pair in positions.windows(2) {
+            let [(from, from_normal, _), (to, to_normal, _)] = [pair[0], pair[1]];
+            if from_normal == to_normal {
+                continue;
+            }
+            // The arrow bends over the edge instead of going through the cube. Walking off a face
+            // goes the way the face on the other side of the edge points.
+            let lift = ANNOTATION_LIFT * size;
+            points.push(from);
+            points.push(from + to_normal * (size / 2. + lift));
+            points.push(to);
+        }
+        let (end, end_normal, _) = positions[positions.len() - 1];
+        let before_end = points
+            .iter()
+            .rev()
+            .find(|point| point.distance(end) > f32::EPSILON)
+            .unwrap_or(&points[0]);
+        let direction = (end - *before_end).normalize_or_zero();
+        // The tip stops short of the middle of the cell, so the unit there can still be seen
+        let tip = end - direction * 0.25 * size;
+        points.push(tip);
+        points.dedup();
+
+        let mut lines: Vec<(Vec3, Vec3)> =
+            points.windows(2).map(|pair| (pair[0], pair[1])).collect();
+        let side = direction.cross(end_normal).normalize_or_zero();
+        for sign in [1., -1.] {
+            let back =
+                direction * 30_f32.to_radians().cos() + side * sign * 30_f32.to_radians().sin();
+            lines.push((tip, tip - back * ARROW_HEAD * size));
+        }
+        for (start, end) in lines {
+            let length = start.distance(end);
+            if length <= f32::EPSILON {
+                continue;
+            }
+            let width = ARROW_WIDTH * size;
+            commands.spawn((
+                PbrBundle {
+                    mesh: assets.line.clone(),
+                    material: assets.material.clone(),
+                    transform: Transform {
+                        translation: (start + end) / 2.,
+                        rotation: Quat::from_rotation_arc(Vec3::Z, (end - start) / length),
+                        // Longer by its width so that the lines of an arrow join up
+                        scale: Vec3::new(width, width, length + width),
+                    },
+                    ..default()
+                },
+                AnnotationMark,
+            ));
+        }
+    }
+}
+
+/// The cells of one of the shortest ways from `from` to `to` over the surface of the cube, found by
+/// a breadth first search from neighbor to neighbor. Only the first and last cell of the way on
+/// every face are kept, so the arrow is straight across each face.
+fn surface_path(
+    from: CellCoordinates,
+    to: CellCoordinates,
+    cube_side_length: u32,
+) -> Vec<CellCoordinates> {
+    let mut previous = BTreeMap::from([(from, from)]);
+    let mut queue = VecDeque::from([from]);
+    while let Some(coords) = queue.pop_front() {
+        if coords == to {
+            break;
+        }
+        for direction in CartesianDirection::directions() {
+            let Some((next, _)) = coords.get_cell_in_direction(direction, cube_side_length) else {
+                continue;
+            };
+            if let Entry::Vacant(entry) = previous.entry(next) {
+                entry.insert(coords);
+                queue.push_back(next);
+            }
+        }
+    }
+    if !previous.contains_key(&to) {
+        return Vec::new();
+    }
+    let mut path = vec![to];
+    while path[path.len() - 1] != from {
+        path.push(previous[&path[path.len() - 1]]);
+    }
+    path.reverse();
+
+    let normal = |coords: CellCoordinates| coords.normal_direction();
+    let last = path.len() - 1;
+    path.iter()
+        .enumerate()
+        .filter(|&(i, &coords)| {
+            i == 0
+                || i == last
+                || normal(path[i - 1]) != normal(coords)
+                || normal(path[i + 1]) != normal(coords)
+        })
+        .map(|(_, &coords)| coords)
+        .collect()
+}
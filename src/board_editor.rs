@@ -0,0 +1,354 @@
+//! Setting up a position by hand, for puzzles and analysis. Units are picked on a palette and
+//! placed by clicking cells, and the position is played from or copied as CubeFEN once it has a
+//! king of each team. The editor starts from the position of the preset picked on the main menu.
+
+use bevy::prelude::*;
+
+use crate::cli::LaunchGame;
+use crate::clipboard;
+use crate::engine::cell::CellCoordinates;
+use crate::engine::direction::RadialDirection;
+use crate::engine::pieces;
+use crate::engine::units::{Team, Unit, UnitType, Units};
+use crate::gamemanager::{self, Game};
+use crate::match_play::Match;
+use crate::presets::SetupPresets;
+use crate::reinforcements;
+use crate::scene;
+use crate::settings::Settings;
+use crate::ui::{BUTTON_COLOR, CLICKED_BUTTON_COLOR, HOVERED_BUTTON_COLOR};
+use crate::AppState;
+
+/// The units on the palette, followed by the ones from [`crate::engine::pieces`]. Pawns walk
+/// toward the nearest edge of their face when placed, and can be turned afterwards.
+const PALETTE: [UnitType; 12] = [
+    UnitType::King,
+    UnitType::Queen,
+    UnitType::Rook,
+    UnitType::Bishop,
+    UnitType::Knight,
+    UnitType::Pawn(RadialDirection::ClockwiseX, false),
+    UnitType::Archbishop,
+    UnitType::Chancellor,
+    UnitType::Amazon,
+    UnitType::Wazir,
+    UnitType::Ferz,
+    UnitType::Comet,
+];
+
+/// Exists while the editor is open
+#[derive(Resource)]
+pub(crate) struct BoardEditor {
+    /// The index in [`palette`] of the unit that clicks place
+    picked: usize,
+    /// The team of the units that are placed. Comets are always neutral.
+    team: Team,
+    /// What happened when the position was last started or copied
+    message: Option<String>,
+}
+
+/// Opens the editor on the position of the preset picked on the main menu, which is built like
+/// the cube of a game when the main menu is left
+pub(crate) fn open_board_editor(
+    commands: &mut Commands,
+    presets: &SetupPresets,
+    settings: &Settings,
+    next_state: &mut NextState<AppState>,
+) {
+    let mut game = presets.chosen().new_game(settings);
+    game.ai_playing = None;
+    commands.insert_resource(LaunchGame(Some(game)));
+    commands.remove_resource::<Match>();
+    commands.insert_resource(BoardEditor {
+        picked: 0,
+        team: Team::White,
+        message: None,
+    });
+    next_state.set(AppState::Editor);
+}
+
+pub(crate) fn palette() -> Vec<UnitType> {
+    let defined = (0..pieces::definitions().len()).map(|index| UnitType::Defined(index as u8));
+    PALETTE.into_iter().chain(defined).collect()
+}
+
+pub(crate) fn display_unit_type(unit_type: UnitType) -> String {
+    match unit_type {
+        UnitType::Pawn(_, _) => "Pawn".to_string(),
+        UnitType::Defined(index) => pieces::definition(index).name.clone(),
+        unit_type => format!("{:?}", unit_type),
+    }
+}
+
+/// Why the position can't be played, if it can't
+fn position_problem(game: &Game) -> Option<String> {
+    for team in [Team::White, Team::Black] {
+        let kings = game
+            .units
+            .all_units_iter()
+            .filter(|unit| unit.team == team && unit.unit_type == UnitType::King)
+            .count();
+        if kings != 1 {
+            return Some(format!("{:?} needs one king, not {}", team, kings));
+        }
+    }
+    None
+}
+
+/// A unit on the palette, by its index in [`palette`]
+#[derive(Component)]
+pub(crate) struct PaletteButton(usize);
+
+/// The text under the palette, with the keys of the editor and what keeps the position from being
+/// played
+#[derive(Component)]
+pub(crate) struct EditorText;
+
+#[derive(Component)]
+pub(crate) struct EditorPanel;
+
+pub(crate) fn spawn_editor_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        top: Val::Px(10.),
+                        right: Val::Px(10.),
+                        ..default()
+                    },
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::FlexEnd,
+                    ..default()
+                },
+                ..default()
+            },
+            EditorPanel,
+        ))
+        .with_children(|parent| {
+            for (index, unit_type) in palette().into_iter().enumerate() {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(170.), Val::Px(26.)),
+                                margin: UiRect::all(Val::Px(2.)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: BUTTON_COLOR.into(),
+                            ..default()
+                        },
+                        PaletteButton(index),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            format!("{} {}", unit_type.symbol(), display_unit_type(unit_type)),
+                            TextStyle {
+                                font_size: 20.,
+                                color: Color::WHITE,
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 20.,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                EditorText,
+            ));
+        });
+}
+
+/// Clicking a cell places the picked unit on it, or takes it away if it is already there. T
+/// switches the team of the placed units, M the team to move, and D turns the pawn under the
+/// mouse. X clears the board, C copies the position, Enter plays it and Escape leaves the editor.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn editor_input(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    mut editor: ResMut<BoardEditor>,
+    mut game: ResMut<Game>,
+    presets: Res<SetupPresets>,
+    settings: Res<Settings>,
+    button_query: Query<(&Interaction, &PaletteButton)>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let game = &mut *game;
+    if input.get_just_pressed().next().is_some() || mouse.just_pressed(MouseButton::Left) {
+        editor.message = None;
+    }
+    for (interaction, button) in &button_query {
+        if *interaction == Interaction::Clicked && editor.picked != button.0 {
+            editor.picked = button.0;
+        }
+    }
+    let over_palette = button_query
+        .iter()
+        .any(|(interaction, _)| *interaction != Interaction::None);
+    if mouse.just_pressed(MouseButton::Left) && !over_palette {
+        if let Some(coords) = game.hovered_cell {
+            let unit_type = palette()[editor.picked];
+            place_unit(&mut commands, game, coords, unit_type, editor.team);
+        }
+    }
+    if input.just_pressed(KeyCode::D) {
+        if let Some(coords) = game.hovered_cell {
+            turn_pawn(game, coords);
+        }
+    }
+    if input.just_pressed(KeyCode::T) {
+        editor.team = editor.team.opposite();
+    }
+    if input.just_pressed(KeyCode::M) {
+        game.turn = game.turn.opposite();
+    }
+    if input.just_pressed(KeyCode::X) {
+        for unit in game.units.all_units_iter() {
+            if let Some(entity) = unit.entity {
+                scene::kill_unit(&mut commands, entity.into());
+            }
+        }
+        game.units = Units::default();
+        game.entities_to_move.clear();
+    }
+    if input.just_pressed(KeyCode::C) {
+        let position = game.to_cubefen();
+        editor.message = Some(match clipboard::copy(&position) {
+            Ok(()) => {
+                println!("Copied the position: {}", position);
+                "Copied the position".to_string()
+            }
+            Err(err) => err,
+        });
+    }
+    if input.just_pressed(KeyCode::Return) {
+        match position_problem(game) {
+            Some(problem) => editor.message = Some(problem),
+            None => {
+                // The picked preset decides who plays, the position decides the rest
+                let mut preset = presets.chosen().clone();
+                preset.position = Some(game.to_cubefen());
+                preset.handicap = None;
+                commands.insert_resource(LaunchGame(Some(preset.new_game(&settings))));
+                next_state.set(AppState::MainMenu);
+            }
+        }
+    }
+    if input.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::MainMenu);
+    }
+}
+
+/// Puts a unit of `unit_type` on the cell instead of the unit that is there, or only takes that
+/// unit away if it is the same
+fn place_unit(
+    commands: &mut Commands,
+    game: &mut Game,
+    coords: CellCoordinates,
+    unit_type: UnitType,
+    team: Team,
+) {
+    let team = match unit_type {
+        UnitType::Comet => Team::Neutral,
+        _ => team,
+    };
+    let unit_type = match unit_type {
+        UnitType::Pawn(_, _) => UnitType::Pawn(
+            reinforcements::pawn_direction(coords, game.board.cube_side_length),
+            false,
+        ),
+        unit_type => unit_type,
+    };
+    let same_unit = |unit: &Unit| {
+        let same_type = match (unit.unit_type, unit_type) {
+            (UnitType::Pawn(_, _), UnitType::Pawn(_, _)) => true,
+            (a, b) => a == b,
+        };
+        same_type && unit.team == team
+    };
+    let removed = game.units.remove_unit(coords);
+    if let Some(entity) = removed.as_ref().and_then(|unit| unit.entity) {
+        scene::kill_unit(commands, entity.into());
+    }
+    gamemanager::deselect(game);
+    if !removed.as_ref().is_some_and(same_unit) {
+        game.units.add_unit(Unit::new(unit_type, team, coords));
+    }
+}
+
+/// Turns the pawn on the cell to walk the next way it can walk on its face
+fn turn_pawn(game: &mut Game, coords: CellCoordinates) {
+    let Some(unit) = game.units.get_unit_mut(coords) else {
+        return;
+    };
+    let UnitType::Pawn(direction, moved) = unit.unit_type else {
+        return;
+    };
+    let normal = coords.normal_direction();
+    let directions: Vec<RadialDirection> = RadialDirection::directions()
+        .into_iter()
+        .filter(|direction| direction.to_cartesian_direction(normal).is_some())
+        .collect();
+    let Some(index) = directions.iter().position(|&other| other == direction) else {
+        return;
+    };
+    unit.unit_type = UnitType::Pawn(directions[(index + 1) % directions.len()], moved);
+    // Turns the model of the pawn to the new way
+    if let Some(entity) = unit.entity {
+        game.entities_to_move.push((entity.into(), coords));
+    }
+}
+
+pub(crate) fn update_editor_panel(
+    editor: Res<BoardEditor>,
+    game: Res<Game>,
+    mut text_query: Query<&mut Text, With<EditorText>>,
+    mut button_query: Query<(&Interaction, &PaletteButton, &mut BackgroundColor)>,
+) {
+    for (interaction, button, mut color) in &mut button_query {
+        *color = match interaction {
+            _ if button.0 == editor.picked => CLICKED_BUTTON_COLOR,
+            Interaction::Hovered => HOVERED_BUTTON_COLOR,
+            _ => BUTTON_COLOR,
+        }
+        .into();
+    }
+    if !editor.is_changed() && !game.is_changed() {
+        return;
+    }
+    let status = editor
+        .message
+        .clone()
+        .or_else(|| position_problem(&game))
+        .unwrap_or_else(|| "Ready to play".to_string());
+    let value = format!(
+        "Placing {:?} units, {:?} to move\n\
+         Click a cell to place or take away\n\
+         T: team, M: team to move\n\
+         D: turn the pawn under the mouse\n\
+         X: clear, C: copy as CubeFEN\n\
+         Enter: play, Escape: leave\n\n\
+         {}",
+        editor.team, game.turn, status
+    );
+    for mut text in &mut text_query {
+        text.sections[0].value = value.clone();
+    }
+}
+
+pub(crate) fn close_board_editor(mut commands: Commands, query: Query<Entity, With<EditorPanel>>) {
+    commands.remove_resource::<BoardEditor>();
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,170 @@
+//! Themes that give the cells a texture and bumps on top of the colors of the palette. The
+//! textures are drawn when a theme is picked, so that the game needs no image files for them.
+
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use serde::{Deserialize, Serialize};
+
+/// The width and height of the textures, which cover one cell
+const TEXTURE_SIZE: u32 = 128;
+
+/// How bright the glowing edges of the neon theme are, relative to the color of the cell
+const NEON_GLOW: f32 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub(crate) enum BoardTheme {
+    /// Only the colors of the palette
+    #[default]
+    Flat,
+    Wood,
+    Marble,
+    /// Dark cells with glowing edges
+    Neon,
+}
+
+impl BoardTheme {
+    pub(crate) const ALL: [BoardTheme; 4] = [Self::Flat, Self::Wood, Self::Marble, Self::Neon];
+
+    /// Draws the textures of the theme. The flat theme has none.
+    pub(crate) fn textures(self, images: &mut Assets<Image>) -> Option<ThemeTextures> {
+        if self == Self::Flat {
+            return None;
+        }
+        let mut base = Vec::new();
+        let mut normal_map = Vec::new();
+        let mut glow = Vec::new();
+        let texel = 1. / TEXTURE_SIZE as f32;
+        for row in 0..TEXTURE_SIZE {
+            for column in 0..TEXTURE_SIZE {
+                let (x, y) = ((column as f32 + 0.5) * texel, (row as f32 + 0.5) * texel);
+                let height = self.height(x, y);
+                let brightness = (self.brightness(height) * 255.) as u8;
+                base.extend([brightness, brightness, brightness, 255]);
+
+                // The slope of the height gives the direction the surface faces
+                let dx = self.height(x + texel, y) - self.height(x - texel, y);
+                let dy = self.height(x, y + texel) - self.height(x, y - texel);
+                let normal = Vec3::new(-dx, -dy, 2. * texel / self.bumpiness()).normalize();
+                let encoded = (normal * 0.5 + 0.5) * 255.;
+                normal_map.extend([encoded.x as u8, encoded.y as u8, encoded.z as u8, 255]);
+
+                let glow_value = (self.glow(x, y) * 255.) as u8;
+                glow.extend([glow_value, glow_value, glow_value, 255]);
+            }
+        }
+        let image = |data, format| {
+            let size = Extent3d {
+                width: TEXTURE_SIZE,
+                height: TEXTURE_SIZE,
+                depth_or_array_layers: 1,
+            };
+            Image::new(size, TextureDimension::D2, data, format)
+        };
+        Some(ThemeTextures {
+            base: images.add(image(base, TextureFormat::Rgba8UnormSrgb)),
+            // Normal maps hold directions, not colors, so they are linear
+            normal_map: images.add(image(normal_map, TextureFormat::Rgba8Unorm)),
+            emissive: (self == Self::Neon)
+                .then(|| images.add(image(glow, TextureFormat::Rgba8UnormSrgb))),
+        })
+    }
+
+    /// The height of the surface at `x` and `y`, which go from 0 to 1 across the cell
+    fn height(self, x: f32, y: f32) -> f32 {
+        match self {
+            Self::Flat => 0.,
+            Self::Wood => {
+                // Rings around a trunk to the left of the cell, crossed by the grain
+                let distance = ((x + 1.3).powi(2) + ((y - 0.5) * 0.3).powi(2)).sqrt();
+                let ring = (distance * 12. + fractal_noise(x * 4., y * 4.) * 1.5).fract();
+                ring * 0.7 + value_noise(x * 40., y * 3.) * 0.3
+            }
+            Self::Marble => {
+                let turbulence = fractal_noise(x * 5., y * 5.) * 6.;
+                ((x + y) * 6. + turbulence).sin().abs()
+            }
+            Self::Neon => self.glow(x, y),
+        }
+    }
+
+    /// How bright the cell is where its surface has `height`
+    fn brightness(self, height: f32) -> f32 {
+        match self {
+            Self::Flat => 1.,
+            Self::Wood => 1. - 0.3 * height,
+            // The veins are where the height is low
+            Self::Marble => 0.7 + 0.3 * height.sqrt(),
+            Self::Neon => 0.15 + 0.85 * height,
+        }
+    }
+
+    /// How high the bumps of the normal map are
+    fn bumpiness(self) -> f32 {
+        match self {
+            Self::Flat => 0.,
+            Self::Wood => 0.02,
+            Self::Marble => 0.005,
+            Self::Neon => 0.03,
+        }
+    }
+
+    /// How much the cell glows at `x` and `y`, only the edges of the neon theme do
+    fn glow(self, x: f32, y: f32) -> f32 {
+        if self != Self::Neon {
+            return 0.;
+        }
+        let distance_to_edge = x.min(1. - x).min(y).min(1. - y);
+        (1. - distance_to_edge / 0.12).clamp(0., 1.).powi(2)
+    }
+}
+
+/// The textures of a theme, used by every cell material
+pub(crate) struct ThemeTextures {
+    base: Handle<Image>,
+    normal_map: Handle<Image>,
+    emissive: Option<Handle<Image>>,
+}
+
+/// Puts the textures of the theme on a cell material whose color has already been set, or takes
+/// them off for the flat theme
+pub(crate) fn apply_textures(material: &mut StandardMaterial, textures: Option<&ThemeTextures>) {
+    material.base_color_texture = textures.map(|textures| textures.base.clone());
+    material.normal_map_texture = textures.map(|textures| textures.normal_map.clone());
+    material.emissive_texture = textures.and_then(|textures| textures.emissive.clone());
+    material.emissive = match material.emissive_texture {
+        // The edges glow in the color of the cell, which also shows if it is selected
+        Some(_) => material.base_color * NEON_GLOW,
+        None => Color::BLACK,
+    };
+}
+
+/// A random value from 0 to 1 for every corner of a grid, the same every time
+fn grid_value(x: i32, y: i32) -> f32 {
+    let mut hash = (x as u32).wrapping_mul(374_761_393) ^ (y as u32).wrapping_mul(668_265_263);
+    hash = (hash ^ (hash >> 13)).wrapping_mul(1_274_126_177);
+    (hash ^ (hash >> 16)) as f32 / u32::MAX as f32
+}
+
+/// Smooth noise from 0 to 1 that changes about once per unit
+fn value_noise(x: f32, y: f32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let smooth = |t: f32| t * t * (3. - 2. * t);
+    let (tx, ty) = (smooth(x - x0), smooth(y - y0));
+    let (x0, y0) = (x0 as i32, y0 as i32);
+    let top = grid_value(x0, y0) * (1. - tx) + grid_value(x0 + 1, y0) * tx;
+    let bottom = grid_value(x0, y0 + 1) * (1. - tx) + grid_value(x0 + 1, y0 + 1) * tx;
+    top * (1. - ty) + bottom * ty
+}
+
+/// Noise with detail at several scales, from 0 to about 1
+fn fractal_noise(x: f32, y: f32) -> f32 {
+    let mut sum = 0.;
+    let mut amplitude = 0.5;
+    let mut frequency = 1.;
+    for _ in 0..4 {
+        sum += value_noise(x * frequency, y * frequency) * amplitude;
+        amplitude /= 2.;
+        frequency *= 2.;
+    }
+    sum
+}
@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+use std::fs;
+
+use bevy::prelude::*;
+
+use crate::engine::history::RecordedMove;
+use crate::gamemanager::Game;
+use crate::presets::SetupPresets;
+use crate::rng::GameRng;
+use crate::settings::Settings;
+
+const REPORT_PATH: &str = "bug-report.md";
+
+/// How many of the latest log lines are kept for bug reports
+const MAX_LOG_LINES: usize = 50;
+
+/// The latest log lines of the current game, kept so they can be put in a bug report
+#[derive(Resource, Default)]
+pub(crate) struct GameLog {
+    /// The lines with the number of turns that had been played when they were logged
+    recent_lines: VecDeque<(usize, String)>,
+}
+
+impl GameLog {
+    pub(crate) fn push_line(&mut self, turns_played: usize, line: String) {
+        if self.recent_lines.len() >= MAX_LOG_LINES {
+            self.recent_lines.pop_front();
+        }
+        self.recent_lines.push_back((turns_played, line));
+    }
+
+    /// Drops the lines of the turns after `turns_played`, once they have been taken back
+    pub(crate) fn truncate(&mut self, turns_played: usize) {
+        while self
+            .recent_lines
+            .back()
+            .is_some_and(|(turn, _)| *turn > turns_played)
+        {
+            self.recent_lines.pop_back();
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.recent_lines.clear();
+    }
+}
+
+/// B on the pause menu saves everything needed to reproduce the current game to a file, formatted
+/// to be pasted into a GitHub issue
+pub(crate) fn export_bug_report(
+    input: Res<Input<KeyCode>>,
+    game: Res<Game>,
+    game_log: Res<GameLog>,
+    settings: Res<Settings>,
+    presets: Res<SetupPresets>,
+    rng: Res<GameRng>,
+) {
+    if !input.just_pressed(KeyCode::B) {
+        return;
+    }
+    let report = bug_report(&game, &game_log, &settings, &presets, &rng);
+    match fs::write(REPORT_PATH, report) {
+        Ok(()) => println!("Saved a bug report to {}", REPORT_PATH),
+        Err(err) => warn!("Couldn't write {}: {}", REPORT_PATH, err),
+    }
+}
+
+fn bug_report(
+    game: &Game,
+    game_log: &GameLog,
+    settings: &Settings,
+    presets: &SetupPresets,
+    rng: &GameRng,
+) -> String {
+    let position = game.units.all_units_iter().map(|unit| {
+        format!(
+            "{:?} {:?} at {} (level {})",
+            unit.team,
+            unit.unit_type,
+            unit.coords.display(),
+            unit.level
+        )
+    });
+    let moves = (1..)
+        .zip(game.history.moves())
+        .map(|(number, recorded_move)| format!("{}. {}", number, describe_move(recorded_move)));
+    let pretty = ron::ser::PrettyConfig::default();
+    let settings = [
+        ron::ser::to_string_pretty(settings, pretty.clone()),
+        ron::ser::to_string_pretty(presets.chosen(), pretty),
+    ]
+    .into_iter()
+    .map(|text| text.unwrap_or_default());
+
+    format!(
+        "### What happened\n\n<!-- Describe the bug here -->\n\n\
+        ### Game\n\n\
+        - Version: {}\n\
+        - Cube size: {}\n\
+        - Variant: {:?}\n\
+        - Turn: {:?}\n\
+        - AI: {:?}, depth {}\n\
+        - Seed: {}\n\
+        - Setup seed: {:?}\n\n\
+        ### Position\n\n{}\n{}\n\
+        ### Moves\n\n{}\n\
+        ### Settings\n\n{}\n\
+        ### Recent log\n\n{}",
+        env!("CARGO_PKG_VERSION"),
+        game.board.cube_side_length,
+        game.variant,
+        game.turn,
+        game.ai_playing,
+        game.ai_depth,
+        rng.seed(),
+        game.setup_seed,
+        code_block("", std::iter::once(game.to_cubefen())),
+        code_block("", position),
+        code_block("", moves),
+        code_block("ron", settings),
+        code_block(
+            "",
+            game_log.recent_lines.iter().map(|(_, line)| line.clone())
+        ),
+    )
+}
+
+/// The notation of the move, with when it was made and what it did spelled out
+fn describe_move(recorded_move: &RecordedMove) -> String {
+    let mut line = format!(
+        "{:.1}s {:?} {}",
+        recorded_move.time.as_secs_f32(),
+        recorded_move.team,
+        recorded_move.notation
+    );
+    if let Some(game_move) = recorded_move.game_move {
+        line.push_str(&format!(
+            ", {} to {}",
+            game_move.from.display(),
+            game_move.to.display()
+        ));
+    }
+    if recorded_move.crossed_edge {
+        line.push_str(", over an edge");
+    }
+    if let Some(captured_unit) = &recorded_move.captured_unit {
+        line.push_str(&format!(
+            ", captured {:?} {:?}",
+            captured_unit.team, captured_unit.unit_type
+        ));
+    }
+    for exploded_unit in &recorded_move.exploded_units {
+        line.push_str(&format!(
+            ", blew up {:?} {:?}",
+            exploded_unit.team, exploded_unit.unit_type
+        ));
+    }
+    line
+}
+
+/// A fenced code block with one line per element of `lines`
+fn code_block(language: &str, lines: impl Iterator<Item = String>) -> String {
+    let mut block = format!("```{}\n", language);
+    for line in lines {
+        block.push_str(&line);
+        block.push('\n');
+    }
+    block.push_str("```\n");
+    block
+}
@@ -0,0 +1,120 @@
+//! Shows the units each team has captured at the side of the screen, and which team is ahead in
+//! material
+
+use bevy::prelude::*;
+
+use crate::engine::units::{Team, UnitType};
+use crate::gamemanager::{Game, UnitCaptured};
+
+#[derive(Component)]
+pub(crate) struct CapturesTray;
+
+pub(crate) fn setup_captures_tray(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Percent(40.),
+                left: Val::Px(10.),
+                ..default()
+            },
+            ..default()
+        }),
+        CapturesTray,
+    ));
+}
+
+pub(crate) fn despawn_captures_tray(
+    mut commands: Commands,
+    query: Query<Entity, With<CapturesTray>>,
+) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Counts the captures again when a unit is captured, when turns are undone or redone, and when the
+/// tray is spawned for a new game
+pub(crate) fn update_captures_tray(
+    mut units_captured: EventReader<UnitCaptured>,
+    game: Res<Game>,
+    mut query: Query<&mut Text, With<CapturesTray>>,
+    added_query: Query<(), Added<CapturesTray>>,
+    mut turns_played: Local<usize>,
+) {
+    let captured = units_captured.iter().count() > 0;
+    if !captured && added_query.is_empty() && *turns_played == game.history.turns_played() {
+        return;
+    }
+    *turns_played = game.history.turns_played();
+
+    let mut captured_by = [String::new(), String::new()];
+    // How far White is ahead
+    let mut balance = 0.;
+    for recorded_move in game.history.moves() {
+        let mover = recorded_move.team;
+        let lost_units = recorded_move.captured_unit.iter();
+        for unit in lost_units.chain(&recorded_move.exploded_units) {
+            // Units blown up by a capture of their own team in the atomic variant count as taken
+            // by the other team
+            let taker = if unit.team == mover {
+                mover.opposite()
+            } else {
+                mover
+            };
+            if let Some(i) = tray_index(taker) {
+                captured_by[i].push(unit.unit_type.symbol());
+            }
+            // The game is over once a king is captured, and its value would dwarf the rest
+            if matches!(unit.unit_type, UnitType::King) {
+                continue;
+            }
+            match unit.team {
+                Team::Black => balance += unit.unit_type.material_value(),
+                Team::White => balance -= unit.unit_type.material_value(),
+                Team::Neutral => {}
+            }
+        }
+    }
+
+    let line = |team: Team, balance: f32| {
+        let captured = &captured_by[tray_index(team).unwrap()];
+        match balance {
+            _ if balance > 0. => format!("{:?}: {} +{}", team, captured, balance),
+            _ if balance < 0. => format!("{:?}: {} \u{2212}{}", team, captured, -balance),
+            _ => format!("{:?}: {}", team, captured),
+        }
+    };
+    for mut text in &mut query {
+        text.sections[0].value = format!(
+            "Captured\n{}\n{}",
+            line(Team::White, balance),
+            line(Team::Black, -balance)
+        );
+    }
+}
+
+/// The material value of the units of the team that are left on the board, not counting the king
+pub(crate) fn material_on_board(game: &Game, team: Team) -> f32 {
+    game.units
+        .all_units_iter()
+        .filter(|unit| unit.team == team && !matches!(unit.unit_type, UnitType::King))
+        .map(|unit| unit.unit_type.material_value())
+        .sum()
+}
+
+fn tray_index(team: Team) -> Option<usize> {
+    match team {
+        Team::White => Some(0),
+        Team::Black => Some(1),
+        Team::Neutral => None,
+    }
+}
@@ -0,0 +1,302 @@
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::view::RenderLayers;
+use bevy::window::WindowRef;
+
+use crate::engine::ai::{self, AICache};
+use crate::engine::analysis::ANALYSIS_DEPTH;
+use crate::engine::cell::CellCoordinates;
+use crate::engine::movement::{self, GameMove};
+use crate::engine::units::Team;
+use crate::gamemanager::Game;
+use crate::settings::Settings;
+use crate::MainCamera;
+
+/// Render layer that only the caster camera sees, so the player's own view stays clean
+const CASTER_LAYER: u8 = 1;
+
+/// State of the observer window meant for streaming, toggled with F10
+#[derive(Resource, Default)]
+pub(crate) struct CasterMode {
+    window: Option<Entity>,
+    camera: Option<Entity>,
+    /// The position the overlay was last built for, so analysis only runs when it changes
+    analysed_position: Option<(Team, Vec<CellCoordinates>)>,
+    ai_cache: AICache,
+}
+
+/// Marks entities that get rebuilt every time the analysed position changes
+#[derive(Component)]
+pub(crate) struct CasterOverlay;
+
+#[derive(Component)]
+pub(crate) struct EvalBarWhite;
+
+pub(crate) fn toggle_caster_mode(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    mut caster_mode: ResMut<CasterMode>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    settings: Res<Settings>,
+    windows: Query<(), With<Window>>,
+) {
+    let caster_mode = &mut *caster_mode;
+    // The window might have been closed by the user
+    let window_closed = matches!(caster_mode.window, Some(window) if windows.get(window).is_err());
+    if !input.just_pressed(KeyCode::F10) && !window_closed {
+        return;
+    }
+
+    if caster_mode.window.is_some() {
+        if let Some(camera) = caster_mode.camera.take() {
+            commands.entity(camera).despawn_recursive();
+        }
+        if let Some(window) = caster_mode.window.take() {
+            if !window_closed {
+                commands.entity(window).despawn();
+            }
+        }
+        caster_mode.analysed_position = None;
+        return;
+    }
+
+    let window = commands
+        .spawn(Window {
+            title: "Caster view".to_string(),
+            ..default()
+        })
+        .id();
+
+    let camera = commands
+        .spawn((
+            Camera3dBundle {
+                camera: Camera {
+                    target: RenderTarget::Window(WindowRef::Entity(window)),
+                    ..default()
+                },
+                ..default()
+            },
+            RenderLayers::layer(0).with(CASTER_LAYER),
+        ))
+        .id();
+    spawn_eval_bar(
+        &mut commands,
+        camera,
+        &mut meshes,
+        &mut materials,
+        &settings,
+    );
+
+    caster_mode.window = Some(window);
+    caster_mode.camera = Some(camera);
+}
+
+/// The eval bar is parented to the caster camera so that it stays fixed on screen
+fn spawn_eval_bar(
+    commands: &mut Commands,
+    camera: Entity,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    settings: &Settings,
+) {
+    let quad = meshes.add(shape::Quad::new(Vec2::new(0.04, 0.7)).into());
+    let unlit = |color: Color| StandardMaterial {
+        base_color: color,
+        unlit: true,
+        ..default()
+    };
+
+    commands.entity(camera).with_children(|parent| {
+        parent.spawn((
+            PbrBundle {
+                mesh: quad.clone(),
+                material: materials.add(unlit(settings.team_material(Team::Black).base_color)),
+                transform: Transform::from_xyz(-0.6, 0., -1.),
+                ..default()
+            },
+            RenderLayers::layer(CASTER_LAYER),
+        ));
+        parent.spawn((
+            PbrBundle {
+                mesh: quad,
+                material: materials.add(unlit(settings.team_material(Team::White).base_color)),
+                // Slightly in front of the black part so it is drawn on top
+                transform: Transform::from_xyz(-0.6, 0., -0.999),
+                ..default()
+            },
+            RenderLayers::layer(CASTER_LAYER),
+            EvalBarWhite,
+        ));
+    });
+}
+
+pub(crate) fn follow_main_camera(
+    caster_mode: Res<CasterMode>,
+    main_camera: Query<&Transform, (With<MainCamera>, With<Camera>)>,
+    mut transforms: Query<&mut Transform, Without<MainCamera>>,
+) {
+    let Some(caster_camera) = caster_mode.camera else {
+        return;
+    };
+    let Ok(main_transform) = main_camera.get_single() else {
+        return;
+    };
+    if let Ok(mut transform) = transforms.get_mut(caster_camera) {
+        *transform = *main_transform;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn update_caster_overlay(
+    mut commands: Commands,
+    mut caster_mode: ResMut<CasterMode>,
+    game: Res<Game>,
+    overlay_query: Query<Entity, With<CasterOverlay>>,
+    mut eval_bar_query: Query<&mut Transform, With<EvalBarWhite>>,
+    cell_transforms: Query<&Transform, Without<EvalBarWhite>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let caster_mode = &mut *caster_mode;
+    if caster_mode.camera.is_none() {
+        for entity in &overlay_query {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+
+    let position = (
+        game.turn,
+        game.units
+            .all_units_iter()
+            .map(|unit| unit.coords)
+            .collect(),
+    );
+    if caster_mode.analysed_position.as_ref() == Some(&position) {
+        return;
+    }
+    caster_mode.analysed_position = Some(position);
+
+    for entity in &overlay_query {
+        commands.entity(entity).despawn();
+    }
+
+    // Eval bar, the white part grows from the bottom as white gets ahead
+    let eval = ai::static_evaluation(&game.board, &game.units, game.variant);
+    let white_fraction = 0.5 + 0.5 * (eval / 10.).tanh();
+    if let Ok(mut transform) = eval_bar_query.get_single_mut() {
+        transform.scale.y = white_fraction.max(0.001);
+        transform.translation.y = -0.35 * (1. - white_fraction);
+    }
+
+    // Threat map, the cells the side not to move is attacking
+    let threat_material = materials.add(StandardMaterial {
+        base_color: Color::rgba(1., 0., 0., 0.35),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..default()
+    });
+    let plane_mesh = meshes.add(shape::Plane::default().into());
+    for coords in movement::get_attacked_cells(&game.board, &game.units, game.turn.opposite()) {
+        let Some(mut transform) = cell_overlay_transform(coords, &game, &cell_transforms) else {
+            continue;
+        };
+        transform.translation += coords.normal_direction().as_vec3() * 0.002;
+        commands.spawn((
+            PbrBundle {
+                mesh: plane_mesh.clone(),
+                material: threat_material.clone(),
+                transform,
+                ..default()
+            },
+            RenderLayers::layer(CASTER_LAYER),
+            CasterOverlay,
+        ));
+    }
+
+    // Best move arrow
+    let Some(best_move) = ai::next_move(
+        &game.board,
+        &game.units,
+        game.turn,
+        ANALYSIS_DEPTH,
+        game.variant,
+        &mut caster_mode.ai_cache,
+    ) else {
+        return;
+    };
+    spawn_move_arrow(
+        &mut commands,
+        best_move,
+        &game,
+        &cell_transforms,
+        &mut meshes,
+        &mut materials,
+    );
+}
+
+fn cell_overlay_transform(
+    coords: CellCoordinates,
+    game: &Game,
+    cell_transforms: &Query<&Transform, Without<EvalBarWhite>>,
+) -> Option<Transform> {
+    let plane = game.board.get_cell(coords)?.plane;
+    cell_transforms.get(plane.into()).ok().copied()
+}
+
+fn spawn_move_arrow(
+    commands: &mut Commands,
+    game_move: GameMove,
+    game: &Game,
+    cell_transforms: &Query<&Transform, Without<EvalBarWhite>>,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+) {
+    let lift = 0.03;
+    let (Some(from), Some(to)) = (
+        cell_overlay_transform(game_move.from, game, cell_transforms),
+        cell_overlay_transform(game_move.to, game, cell_transforms),
+    ) else {
+        return;
+    };
+    let start = from.translation + game_move.from.normal_direction().as_vec3() * lift;
+    let end = to.translation + game_move.to.normal_direction().as_vec3() * lift;
+
+    let material = materials.add(StandardMaterial {
+        base_color: Color::rgba(0.2, 0.8, 0.2, 0.8),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..default()
+    });
+    let width = 0.2 / game.board.cube_side_length as f32;
+    let length = start.distance(end);
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(shape::Box::new(width, width / 4., length).into()),
+            material: material.clone(),
+            transform: Transform::from_translation((start + end) / 2.)
+                .looking_at(end, game_move.from.normal_direction().as_vec3()),
+            ..default()
+        },
+        RenderLayers::layer(CASTER_LAYER),
+        CasterOverlay,
+    ));
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(
+                shape::Icosphere {
+                    radius: width,
+                    subdivisions: 2,
+                }
+                .try_into()
+                .unwrap(),
+            ),
+            material,
+            transform: Transform::from_translation(end),
+            ..default()
+        },
+        RenderLayers::layer(CASTER_LAYER),
+        CasterOverlay,
+    ));
+}
@@ -0,0 +1,238 @@
+//! A cursor that walks from cell to cell, for playing without a mouse. The cursor is the hovered
+//! cell of [`Game::hovered_cell`], so it is highlighted like a cell under the mouse and the
+//! selected unit is previewed on it. A frame is drawn around it too, until the mouse is moved.
+//! The keys of [`KeyBindings`] or h, j, k and l move it, and Enter does what clicking the cell
+//! does.
+
+use bevy::input::mouse::MouseMotion;
+use bevy::prelude::*;
+use std::f32::consts::PI;
+
+use crate::cube_rotation::RevealCell;
+use crate::engine::cell::CellCoordinates;
+use crate::engine::direction::CartesianDirection;
+use crate::gamemanager::{self, Game, GameEvents};
+use crate::input_state::InputState;
+use crate::rng::GameRng;
+use crate::scene::MainCube;
+use crate::settings::{KeyBindings, Settings};
+use crate::{AppState, MainCamera};
+
+/// How far above the cell the frame is, relative to the size of a cell
+const FRAME_LIFT: f32 = 0.01;
+
+/// How wide the sides of the frame are, relative to the size of a cell
+const FRAME_WIDTH: f32 = 0.08;
+
+const FRAME_COLOR: Color = Color::rgba(1., 1., 1., 0.8);
+
+/// If the cursor was last moved with the keyboard or a gamepad, which shows its frame
+#[derive(Resource, Default)]
+pub(crate) struct CellCursor {
+    active: bool,
+}
+
+#[derive(Component)]
+pub(crate) struct CursorFrame;
+
+/// Moves the cursor to the neighbor of its cell that is the most in `direction` on the screen,
+/// where up is positive y. The cursor goes over the edges of the cube, which is turned to show it
+/// if it ends up on a face that can't be seen. A cursor that hasn't been placed yet starts on the
+/// cell closest to the camera.
+pub(crate) fn move_cursor(
+    game: &mut Game,
+    direction: Vec2,
+    camera_transform: &GlobalTransform,
+    cell_query: &Query<(Option<&MainCube>, &mut Transform)>,
+    reveal_cell_events: &mut EventWriter<RevealCell>,
+    cursor: &mut CellCursor,
+) {
+    cursor.active = true;
+    let Some(coords) = game.hovered_cell.or(game.selected_cell) else {
+        game.hovered_cell = closest_cell(camera_transform.translation(), cell_query);
+        return;
+    };
+    let on_screen = camera_transform.right() * direction.x + camera_transform.up() * direction.y;
+    let normal = coords.normal_direction();
+    let step = CartesianDirection::directions()
+        .into_iter()
+        .filter(|step| step.abs() != normal.abs())
+        .max_by(|a, b| {
+            let a = a.as_vec3().dot(on_screen);
+            a.total_cmp(&b.as_vec3().dot(on_screen))
+        })
+        .unwrap();
+    let Some((next, _)) = coords.get_cell_in_direction(step, game.board.cube_side_length) else {
+        return;
+    };
+    game.hovered_cell = Some(next);
+    reveal_cell_events.send(RevealCell(next));
+}
+
+fn closest_cell(
+    position: Vec3,
+    cell_query: &Query<(Option<&MainCube>, &mut Transform)>,
+) -> Option<CellCoordinates> {
+    cell_query
+        .iter()
+        .filter_map(|(main_cube, transform)| {
+            Some((main_cube?.coords, transform.translation.distance(position)))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(coords, _)| coords)
+}
+
+/// Does what clicking the cell under the cursor does
+pub(crate) fn choose_cursor_cell(
+    game: &mut Game,
+    query: &mut Query<(Option<&MainCube>, &mut Transform)>,
+    events: GameEvents,
+    rng: &mut GameRng,
+) {
+    let plane = game
+        .hovered_cell
+        .and_then(|coords| game.board.get_cell(coords))
+        .map(|cell| cell.plane.into());
+    if let Some(plane) = plane {
+        gamemanager::choose_cell(plane, query, game, events, rng);
+    }
+}
+
+/// Moves the cursor and chooses its cell with the keyboard
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn keyboard_cursor(
+    input: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    camera_query: Query<&GlobalTransform, (With<MainCamera>, With<Camera>)>,
+    mut query: Query<(Option<&MainCube>, &mut Transform)>,
+    mut game: ResMut<Game>,
+    events: GameEvents,
+    mut rng: ResMut<GameRng>,
+    mut reveal_cell_events: EventWriter<RevealCell>,
+    mut cursor: ResMut<CellCursor>,
+    input_state: Res<InputState>,
+) {
+    let game = &mut *game;
+    let direction = key_direction(&input, &settings.key_bindings);
+    if direction != Vec2::ZERO {
+        if let Ok(camera_transform) = camera_query.get_single() {
+            move_cursor(
+                game,
+                direction,
+                camera_transform,
+                &query,
+                &mut reveal_cell_events,
+                &mut cursor,
+            );
+        }
+    }
+    if input.just_pressed(KeyCode::Return) && input_state.can_select() {
+        choose_cursor_cell(game, &mut query, events, &mut rng);
+    }
+}
+
+/// The direction of the cursor keys that were just pressed, with up as positive y
+fn key_direction(input: &Input<KeyCode>, key_bindings: &KeyBindings) -> Vec2 {
+    let pressed = |keys: [KeyCode; 2]| f32::from(u8::from(input.any_just_pressed(keys)));
+    Vec2::new(
+        pressed([key_bindings.cursor_right, KeyCode::L])
+            - pressed([key_bindings.cursor_left, KeyCode::H]),
+        pressed([key_bindings.cursor_up, KeyCode::K])
+            - pressed([key_bindings.cursor_down, KeyCode::J]),
+    )
+}
+
+/// Keeps the frame around the cursor while it is used, and hides it once the mouse is moved
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn update_cursor_frame(
+    mut commands: Commands,
+    game: Res<Game>,
+    state: Res<State<AppState>>,
+    mut cursor: ResMut<CellCursor>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut frame_query: Query<(&mut Transform, &mut Visibility), With<CursorFrame>>,
+    cell_query: Query<&Transform, Without<CursorFrame>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if mouse_motion.iter().count() > 0 {
+        cursor.active = false;
+    }
+    let cell_transform = game
+        .hovered_cell
+        .filter(|_| cursor.active && state.0.is_playing())
+        .and_then(|coords| Some((coords, game.board.get_cell(coords)?)))
+        .and_then(|(coords, cell)| Some((coords, cell_query.get(cell.plane.into()).ok()?)));
+    let Some((coords, cell_transform)) = cell_transform else {
+        for (_, mut visibility) in &mut frame_query {
+            *visibility = Visibility::Hidden;
+        }
+        return;
+    };
+    let transform = frame_transform(coords, cell_transform);
+    if let Ok((mut frame_transform, mut visibility)) = frame_query.get_single_mut() {
+        *frame_transform = transform;
+        *visibility = Visibility::Inherited;
+        return;
+    }
+    let frame = spawn_frame(
+        &mut commands,
+        transform,
+        FRAME_COLOR,
+        &mut meshes,
+        &mut materials,
+    );
+    commands.entity(frame).insert(CursorFrame);
+}
+
+/// Where a frame around the cell at `cell_transform` goes, just above it
+pub(crate) fn frame_transform(coords: CellCoordinates, cell_transform: &Transform) -> Transform {
+    let normal = coords.normal_direction().as_vec3();
+    let size = cell_transform.scale.x;
+    Transform {
+        translation: cell_transform.translation + normal * FRAME_LIFT * size,
+        rotation: Quat::from_rotation_arc(Vec3::Y, normal),
+        scale: Vec3::splat(size),
+    }
+}
+
+/// The four sides of a square around a cell 1 wide, see [`frame_transform`]
+pub(crate) fn spawn_frame(
+    commands: &mut Commands,
+    transform: Transform,
+    color: Color,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+) -> Entity {
+    let side = meshes.add(shape::Box::new(1. + FRAME_WIDTH, 0.02, FRAME_WIDTH).into());
+    let material = materials.add(StandardMaterial {
+        base_color: color,
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..default()
+    });
+    commands
+        .spawn(SpatialBundle::from_transform(transform))
+        .with_children(|parent| {
+            for (offset, angle) in [(0.5, 0.), (-0.5, 0.), (0.5, PI / 2.), (-0.5, PI / 2.)] {
+                let rotation = Quat::from_rotation_y(angle);
+                parent.spawn(PbrBundle {
+                    mesh: side.clone(),
+                    material: material.clone(),
+                    transform: Transform::from_translation(rotation * Vec3::new(0., 0., offset))
+                        .with_rotation(rotation),
+                    ..default()
+                });
+            }
+        })
+        .id()
+}
+
+pub(crate) fn despawn_cursor_frame(
+    mut commands: Commands,
+    query: Query<Entity, With<CursorFrame>>,
+) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,191 @@
+//! The clocks of a game played on this computer with a time control. Only the clock of the team
+//! whose turn it is runs, and only while the game is being played, not while it is paused. A team
+//! whose clock runs out loses the game.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::engine::units::Team;
+use crate::gamemanager::{self, EndReason, Game, GameEvents, GameResult};
+use crate::network::Network;
+use crate::presets::TimeControl;
+use crate::AppState;
+
+#[derive(Resource)]
+pub(crate) struct ChessClock {
+    /// Indexed by [`Team::index`]
+    remaining: [Duration; 2],
+    increment: Duration,
+    delay: Duration,
+    /// The team whose clock is running
+    turn: Team,
+    /// How long the team has been thinking this turn
+    turn_time: Duration,
+}
+
+impl ChessClock {
+    fn new(time_control: TimeControl, turn: Team) -> Self {
+        let time = Duration::from_secs(u64::from(time_control.minutes) * 60);
+        ChessClock {
+            remaining: [time; 2],
+            increment: Duration::from_secs(time_control.increment_seconds.into()),
+            delay: Duration::from_secs(time_control.delay_seconds.into()),
+            turn,
+            turn_time: Duration::ZERO,
+        }
+    }
+
+    /// Runs the clock of the team to move. Time within the delay at the start of the turn isn't
+    /// taken off the clock.
+    fn run(&mut self, elapsed: Duration) {
+        let delay_left = self.delay.saturating_sub(self.turn_time);
+        self.turn_time += elapsed;
+        let remaining = &mut self.remaining[self.turn.index()];
+        *remaining = remaining.saturating_sub(elapsed.saturating_sub(delay_left));
+    }
+
+    /// Stops the clock of the team that just moved, adding the increment, and starts the clock of
+    /// the other team
+    fn switch(&mut self, turn: Team) {
+        self.remaining[self.turn.index()] += self.increment;
+        self.turn = turn;
+        self.turn_time = Duration::ZERO;
+    }
+}
+
+/// Formats a time left on a clock like 4:05
+pub(crate) fn display_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[derive(Component)]
+pub(crate) struct ClockDisplay;
+
+#[derive(Component)]
+pub(crate) struct ClockText;
+
+/// Starts the clocks of a new game if it has a time control. Online games are timed by the host.
+pub(crate) fn setup_chess_clock(
+    mut commands: Commands,
+    game: Res<Game>,
+    network: Option<Res<Network>>,
+) {
+    let Some(time_control) = game.time_control.filter(|_| network.is_none()) else {
+        return;
+    };
+    commands.insert_resource(ChessClock::new(time_control, game.turn));
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        top: Val::Px(10.),
+                        ..default()
+                    },
+                    size: Size::width(Val::Percent(100.)),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            ClockDisplay,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 28.,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                ClockText,
+            ));
+        });
+}
+
+pub(crate) fn despawn_chess_clock(
+    mut commands: Commands,
+    query: Query<Entity, With<ClockDisplay>>,
+) {
+    commands.remove_resource::<ChessClock>();
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// The time since the last frame is taken off the clock of the team that was to move during it,
+/// before the clocks are switched for a move made in the last frame. That way the time the AI
+/// spends searching is taken off its own clock.
+pub(crate) fn run_chess_clock(
+    time: Res<Time>,
+    mut clock: ResMut<ChessClock>,
+    mut game: ResMut<Game>,
+    mut events: GameEvents,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if game.result.is_some() {
+        return;
+    }
+    clock.run(time.delta());
+    let team = clock.turn;
+    if clock.remaining[team.index()].is_zero() {
+        let result = GameResult::Win(team.opposite());
+        gamemanager::end_game(
+            &mut game,
+            &mut events,
+            &mut next_state,
+            result,
+            EndReason::Time,
+        );
+        return;
+    }
+    if game.turn != clock.turn && game.turn != Team::Neutral {
+        clock.switch(game.turn);
+    }
+}
+
+pub(crate) fn update_clock_display(
+    clock: Res<ChessClock>,
+    mut query: Query<&mut Text, With<ClockText>>,
+) {
+    let running = |team: Team| if clock.turn == team { "> " } else { "" };
+    for mut text in &mut query {
+        text.sections[0].value = format!(
+            "{}White {}   {}Black {}",
+            running(Team::White),
+            display_time(clock.remaining[Team::White.index()]),
+            running(Team::Black),
+            display_time(clock.remaining[Team::Black.index()])
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_is_free_and_increment_is_added() {
+        let time_control = TimeControl {
+            minutes: 1,
+            increment_seconds: 2,
+            delay_seconds: 3,
+        };
+        let mut clock = ChessClock::new(time_control, Team::White);
+        clock.run(Duration::from_secs(2));
+        assert_eq!(clock.remaining[0].as_secs(), 60);
+        // One more second of the delay is left, then the clock runs
+        clock.run(Duration::from_secs(5));
+        assert_eq!(clock.remaining[0].as_secs(), 56);
+
+        clock.switch(Team::Black);
+        assert_eq!(clock.remaining[0].as_secs(), 58);
+        clock.run(Duration::from_secs(4));
+        assert_eq!(clock.remaining[1].as_secs(), 59);
+    }
+}
@@ -0,0 +1,235 @@
+use std::path::PathBuf;
+use std::time::Instant;
+
+use bevy::prelude::*;
+
+use crate::engine::ai::{self, AICache};
+use crate::engine::cubefen::CubeFen;
+use crate::engine::movement::{GameMove, Variant};
+use crate::engine::notation;
+use crate::engine::units::{Team, UnitType, Units};
+use crate::gamemanager::{Game, Palette};
+use crate::presets::{AiSettings, SetupPreset, CUBE_SIDE_LENGTHS};
+use crate::settings::Settings;
+
+pub(crate) const USAGE: &str = "\
+Usage: unnamed-game [options]
+
+Giving any option other than --benchmark, --seed and --engine starts a game right away, set up like the chosen preset
+except for the options given.
+
+Options:
+    --size <2-8>             Cells along each edge of the cube
+    --variant <name>         standard, experience, reinforcements, wandering, duel or atomic
+    --no-ai                  Two players on the same computer
+    --ai-team <white|black>  The team the AI plays
+    --ai-depth <moves>       How many moves ahead the AI searches
+    --palette <name>         filippa or pinkish
+    --position <cubefen>     Start from this position, which sets the size and variant too
+    --setup-seed <number>    Start from the shuffled setup with this seed, which is shown on the
+                             pause menu of shuffled games
+    --load <file>            Continue a game saved in the games folder, instead of setting one up
+    --replay <file>          Step through the turns of a saved game
+    --seed <number>          Seed the randomness of the game, so it plays out the same way again
+    --engine <command>       Let the external engine started with this command play instead of
+                             the built-in AI
+    --benchmark <moves>      Let the AI play against itself for this many moves without opening a
+                             window, and print how long every move took
+    --help                   Print this";
+
+/// The game set up or loaded on the command line, or continued from the main menu. It is played
+/// instead of the chosen preset when the next game starts.
+#[derive(Resource)]
+pub(crate) struct LaunchGame(pub(crate) Option<Game>);
+
+/// How the game was asked to be launched on the command line
+#[derive(Debug, Default)]
+pub(crate) struct LaunchOptions {
+    /// Changes to the chosen preset. The game starts right away if any is given.
+    size: Option<u32>,
+    variant: Option<Variant>,
+    /// Some(None) for a game without AI
+    ai_team: Option<Option<Team>>,
+    ai_depth: Option<u32>,
+    palette: Option<Palette>,
+    /// Checked to be a valid CubeFEN while parsing
+    position: Option<String>,
+    /// See [`Units::shuffled_starting_configuration`]
+    setup_seed: Option<u64>,
+    /// A game saved by [`crate::game_record`]
+    pub(crate) load: Option<PathBuf>,
+    /// If the loaded game is replayed instead of continued
+    pub(crate) replay: bool,
+    /// Used instead of the seed in the settings, see [`crate::rng::GameRng`]
+    pub(crate) seed: Option<u64>,
+    /// Used instead of the engine in the settings, see [`crate::external_engine`]
+    pub(crate) engine: Option<String>,
+    pub(crate) benchmark_moves: Option<u32>,
+    pub(crate) help: bool,
+}
+
+impl LaunchOptions {
+    pub(crate) fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = LaunchOptions::default();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--size" => {
+                    let size = parse_number(&value()?)?;
+                    if !CUBE_SIDE_LENGTHS.contains(&size) {
+                        return Err(format!("The cube can't be {} cells wide", size));
+                    }
+                    options.size = Some(size);
+                }
+                "--variant" => options.variant = Some(parse_variant(&value()?)?),
+                "--no-ai" => options.ai_team = Some(None),
+                "--ai-team" => options.ai_team = Some(Some(parse_team(&value()?)?)),
+                "--ai-depth" => options.ai_depth = Some(parse_number(&value()?)?),
+                "--palette" => options.palette = Some(parse_palette(&value()?)?),
+                "--position" => {
+                    let position = value()?;
+                    position.parse::<CubeFen>()?;
+                    options.position = Some(position);
+                }
+                "--setup-seed" => options.setup_seed = Some(parse_seed(&value()?)?),
+                "--load" => options.load = Some(PathBuf::from(value()?)),
+                "--replay" => {
+                    options.load = Some(PathBuf::from(value()?));
+                    options.replay = true;
+                }
+                "--seed" => options.seed = Some(parse_seed(&value()?)?),
+                "--engine" => options.engine = Some(value()?),
+                "--benchmark" => options.benchmark_moves = Some(parse_number(&value()?)?),
+                "--help" | "-h" => options.help = true,
+                _ => return Err(format!("Unknown option {}", arg)),
+            }
+        }
+        Ok(options)
+    }
+
+    /// The chosen preset with the options applied, or None if no option changes it
+    pub(crate) fn preset(&self, chosen: &SetupPreset) -> Option<SetupPreset> {
+        let changed = self.size.is_some()
+            || self.variant.is_some()
+            || self.ai_team.is_some()
+            || self.ai_depth.is_some()
+            || self.palette.is_some()
+            || self.position.is_some()
+            || self.setup_seed.is_some();
+        if !changed {
+            return None;
+        }
+        let mut preset = chosen.clone();
+        preset.name = "Command line".to_string();
+        preset.cube_side_length = self.size.unwrap_or(preset.cube_side_length);
+        preset.variant = self.variant.unwrap_or(preset.variant);
+        if let Some(ai_team) = self.ai_team {
+            preset.ai = ai_team.map(|team| AiSettings {
+                team,
+                depth: preset.ai.and_then(|ai| ai.depth),
+            });
+        }
+        if let (Some(ai), Some(depth)) = (&mut preset.ai, self.ai_depth) {
+            ai.depth = Some(depth);
+        }
+        preset.palette = self.palette.or(preset.palette);
+        preset.position = self.position.clone().or(preset.position);
+        if self.setup_seed.is_some() {
+            preset.shuffled = true;
+            preset.setup_seed = self.setup_seed;
+        }
+        Some(preset)
+    }
+}
+
+fn parse_number(value: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} is not a number", value))
+}
+
+fn parse_seed(value: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} is not a seed, it has to be a number", value))
+}
+
+fn parse_variant(value: &str) -> Result<Variant, String> {
+    Variant::from_name(value).ok_or(format!("Unknown variant {}", value))
+}
+
+fn parse_team(value: &str) -> Result<Team, String> {
+    match value {
+        "white" => Ok(Team::White),
+        "black" => Ok(Team::Black),
+        _ => Err(format!(
+            "The AI can only play white or black, not {}",
+            value
+        )),
+    }
+}
+
+fn parse_palette(value: &str) -> Result<Palette, String> {
+    match value {
+        "filippa" => Ok(Palette::Filippa),
+        "pinkish" => Ok(Palette::Pinkish),
+        _ => Err(format!("Unknown palette {}", value)),
+    }
+}
+
+/// Lets the AI play against itself on the starting position of `preset`, timing every move. The
+/// board is never rendered, so this runs without a window.
+pub(crate) fn run_benchmark(preset: &SetupPreset, settings: &Settings, moves: u32) {
+    let game = preset.new_game(settings);
+    let board = &game.board;
+    let mut units = game.units.clone();
+    let mut team = Team::White;
+    let mut ai_cache = AICache::default();
+    let started = Instant::now();
+    println!(
+        "Benchmarking {:?} on a {}-cube, depth {}",
+        game.variant, board.cube_side_length, game.ai_depth
+    );
+    for number in 1..=moves {
+        let move_started = Instant::now();
+        let Some(game_move) = ai::next_move(
+            board,
+            &units,
+            team,
+            game.ai_depth,
+            game.variant,
+            &mut ai_cache,
+        ) else {
+            println!("{:?} has no moves", team);
+            break;
+        };
+        println!(
+            "{}. {:?} {} in {:?}",
+            number,
+            team,
+            notation::write_move(game_move, board, &units),
+            move_started.elapsed()
+        );
+        make_move(game_move, &mut units);
+        let has_king = units
+            .all_units_iter()
+            .any(|unit| unit.team == team.opposite() && unit.unit_type == UnitType::King);
+        if !has_king {
+            println!("{:?} wins", team);
+            break;
+        }
+        team = team.opposite();
+    }
+    println!("Total: {:?}", started.elapsed());
+}
+
+fn make_move(game_move: GameMove, units: &mut Units) {
+    units.remove_unit(game_move.to);
+    let Some(unit) = units.get_unit_mut(game_move.from) else {
+        return;
+    };
+    unit.move_unit_to(game_move.to);
+    if let UnitType::Pawn(_, ref mut has_moved) = unit.unit_type {
+        *has_moved = true;
+    }
+}
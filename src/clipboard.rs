@@ -0,0 +1,92 @@
+//! The system clipboard, used to share positions as CubeFEN. Bevy can't reach the clipboard, so the
+//! clipboard tools of the platform are run instead.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use bevy::prelude::*;
+
+use crate::gamemanager::Game;
+
+type Tool = (&'static str, &'static [&'static str]);
+
+/// Tried in order until one of them works, since Linux desktops have different tools
+fn copy_tools() -> &'static [Tool] {
+    if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else if cfg!(windows) {
+        &[("clip", &[])]
+    } else {
+        &[
+            ("wl-copy", &[]),
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+        ]
+    }
+}
+
+fn paste_tools() -> &'static [Tool] {
+    if cfg!(target_os = "macos") {
+        &[("pbpaste", &[])]
+    } else if cfg!(windows) {
+        &[("powershell", &["-NoProfile", "-Command", "Get-Clipboard"])]
+    } else {
+        &[
+            ("wl-paste", &["--no-newline"]),
+            ("xclip", &["-selection", "clipboard", "-out"]),
+            ("xsel", &["--clipboard", "--output"]),
+        ]
+    }
+}
+
+pub(crate) fn copy(text: &str) -> Result<(), String> {
+    for (program, args) in copy_tools() {
+        let Ok(mut child) = Command::new(program)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .spawn()
+        else {
+            continue;
+        };
+        let written = match child.stdin.take() {
+            Some(mut stdin) => stdin.write_all(text.as_bytes()).is_ok(),
+            None => false,
+        };
+        if written && matches!(child.wait(), Ok(status) if status.success()) {
+            return Ok(());
+        }
+    }
+    Err(no_tool_error(copy_tools()))
+}
+
+pub(crate) fn paste() -> Result<String, String> {
+    for (program, args) in paste_tools() {
+        let Ok(output) = Command::new(program).args(*args).output() else {
+            continue;
+        };
+        if output.status.success() {
+            return Ok(String::from_utf8_lossy(&output.stdout).trim().to_string());
+        }
+    }
+    Err(no_tool_error(paste_tools()))
+}
+
+fn no_tool_error(tools: &[Tool]) -> String {
+    let programs: Vec<&str> = tools.iter().map(|(program, _)| *program).collect();
+    format!(
+        "Couldn't reach the clipboard, install one of {}",
+        programs.join(", ")
+    )
+}
+
+/// C on the pause menu copies the current position as a CubeFEN
+pub(crate) fn copy_position(input: Res<Input<KeyCode>>, game: Res<Game>) {
+    if !input.just_pressed(KeyCode::C) {
+        return;
+    }
+    let position = game.to_cubefen();
+    match copy(&position) {
+        Ok(()) => println!("Copied the position: {}", position),
+        Err(err) => warn!("{}", err),
+    }
+}
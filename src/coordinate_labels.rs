@@ -0,0 +1,100 @@
+//! Writes the notation of every cell on it, see [`CellCoordinates::display`], for learning the
+//! notation and for telling exactly which cell a bug happened on. The labels are text on the
+//! screen that follows the cells, so they always face the camera. Turned on and off on the
+//! settings menu.
+
+use bevy::prelude::*;
+
+use crate::engine::cell::CellCoordinates;
+use crate::gamemanager::Game;
+use crate::settings::Settings;
+use crate::{AppState, MainCamera};
+
+/// Half the size of a label in pixels, to center it on its cell
+const LABEL_HALF_SIZE: Vec2 = Vec2::new(14., 7.);
+
+#[derive(Component)]
+pub(crate) struct CoordinateLabel(CellCoordinates);
+
+/// Spawns a label for every cell when they are turned on, and despawns them when they are turned
+/// off or the cube can't be seen
+pub(crate) fn update_coordinate_labels(
+    mut commands: Commands,
+    game: Res<Game>,
+    settings: Res<Settings>,
+    state: Res<State<AppState>>,
+    query: Query<Entity, With<CoordinateLabel>>,
+) {
+    let cells = game.board.get_all_cells();
+    let wanted = match settings.show_coordinates && state.0.can_rotate() {
+        true => cells.len(),
+        false => 0,
+    };
+    if query.iter().count() == wanted {
+        return;
+    }
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+    if wanted == 0 {
+        return;
+    }
+    for cell in cells {
+        commands.spawn((
+            TextBundle::from_section(
+                cell.coords.display(),
+                TextStyle {
+                    font_size: 12.,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                ..default()
+            })
+            .with_background_color(Color::rgba(0., 0., 0., 0.4)),
+            CoordinateLabel(cell.coords),
+        ));
+    }
+}
+
+/// Keeps every label on its cell while the cube is rotated, hiding the labels of the cells on the
+/// back of the cube
+pub(crate) fn place_coordinate_labels(
+    game: Res<Game>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    cell_query: Query<&GlobalTransform>,
+    mut label_query: Query<(&CoordinateLabel, &mut Style, &mut Visibility)>,
+) {
+    let Ok((camera, camera_transform)) = camera_query.get_single() else {
+        return;
+    };
+    for (label, mut style, mut visibility) in &mut label_query {
+        let cell_transform = game
+            .board
+            .get_cell(label.0)
+            .and_then(|cell| cell_query.get(cell.plane.into()).ok());
+        let Some(cell_transform) = cell_transform else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        let position = cell_transform.translation();
+        let normal = label.0.normal_direction().as_vec3();
+        let facing_camera = normal.dot(camera_transform.translation() - position) > 0.;
+        // The viewport starts at the bottom left corner of the window
+        let on_screen = camera
+            .world_to_viewport(camera_transform, position)
+            .filter(|_| facing_camera);
+        let Some(on_screen) = on_screen else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        style.position = UiRect {
+            left: Val::Px(on_screen.x - LABEL_HALF_SIZE.x),
+            bottom: Val::Px(on_screen.y - LABEL_HALF_SIZE.y),
+            ..default()
+        };
+        *visibility = Visibility::Inherited;
+    }
+}
@@ -1,15 +1,66 @@
-use crate::utils::{self, CartesianDirection};
+use crate::engine::cell::CellCoordinates;
+use crate::engine::direction::CartesianDirection;
+use crate::engine::units::{Team, UnitType};
+use crate::gamemanager::{Game, MoveMade, TurnChanged};
+use crate::input_state::InputState;
+use crate::settings::Settings;
 use crate::MainCamera;
+use bevy::input::mouse::MouseMotion;
 use bevy::prelude::*;
 use std::f32::consts::PI;
 use std::time::Duration;
 
+/// Seconds a rotation started by pressing a key takes, unless changed in the settings
+pub(crate) const ROTATION_DURATION: f32 = 1.;
+/// While a key is held, every rotation after the first takes this fraction of the time of the one
+/// before
+const REPEAT_SPEEDUP: f32 = 0.7;
+const MIN_ROTATION_DURATION: f32 = 0.25;
+/// Seconds after a rotation started in which pressing the same key again makes it a half turn
+const DOUBLE_PRESS_WINDOW: f32 = 0.3;
+/// Seconds a press that came while the cube was turning waits to be started before it is dropped
+const BUFFERED_PRESS_WINDOW: f32 = 0.5;
+/// Radians per second that the cube turns by itself when idle
+const IDLE_ROTATION_SPEED: f32 = 0.15;
+/// The fraction of the idle rotation that is left after blending back to the player's rotation for
+/// a second
+const IDLE_BLEND_BACK: f32 = 0.002;
+/// Radians the free orbiting camera turns for every pixel the mouse is dragged
+const ORBIT_SENSITIVITY: f32 = 0.006;
+/// The fraction of its speed that the free orbiting camera keeps a second after it is let go
+const ORBIT_DAMPING: f32 = 0.02;
+/// How close to straight above or below the cube the free orbiting camera can go, so that it
+/// never flips over the poles
+const ORBIT_MAX_PITCH: f32 = PI / 2. - 0.1;
+
 #[derive(Debug)]
 pub(crate) struct RotationData {
     current_rotation: Quat,
     current_camera_up: CartesianDirection,
     time_started_rotations: [Duration; 4],
     reversed_axes: [bool; 4],
+    rotation_durations: [f32; 4],
+    /// 2 for the world axes that are doing a half turn after a double press, otherwise 1
+    quarter_turns: [u32; 3],
+    /// The last key that was pressed while its rotation couldn't start yet, and when. Only one press
+    /// is kept, so mashing keys never lines up more than one turn.
+    buffered_press: Option<(KeyCode, Duration)>,
+    /// Number of rotations that have been started by holding a key down since it was pressed
+    repeats: i32,
+    /// The team whose turn it was when the orientation was last remembered
+    last_turn: Option<Team>,
+    /// The orientation each team last had on its own turn in hot-seat games, indexed by
+    /// [`Team::index`]
+    team_orientations: [Option<(Quat, CartesianDirection)>; 2],
+    /// The orientation that is being animated away from when going back to a remembered one, and
+    /// when that started
+    restoring_from: Option<(Quat, CartesianDirection, Duration)>,
+    time_last_input: Duration,
+    /// How far the cube has turned by itself around the camera up, on top of the other rotations
+    idle_angle: f32,
+    /// The normal of the face to turn to once the rotations that are going on are done, see
+    /// [`FaceCell`]
+    facing: Option<CartesianDirection>,
 }
 
 impl Default for RotationData {
@@ -19,28 +70,117 @@ impl Default for RotationData {
             current_camera_up: CartesianDirection::Y,
             time_started_rotations: Default::default(),
             reversed_axes: Default::default(),
+            rotation_durations: [ROTATION_DURATION; 4],
+            quarter_turns: [1; 3],
+            buffered_press: None,
+            repeats: 0,
+            last_turn: None,
+            team_orientations: [None; 2],
+            restoring_from: None,
+            time_last_input: Duration::ZERO,
+            idle_angle: 0.,
+            facing: None,
         }
     }
 }
 
+impl RotationData {
+    /// If a turn of the player or a turn to a face is going on. The idle rotation doesn't count.
+    fn is_turning(&self) -> bool {
+        let rotating = self
+            .time_started_rotations
+            .iter()
+            .any(|time_started| !time_started.is_zero());
+        rotating || self.restoring_from.is_some()
+    }
+}
+
+/// Turns the cube so that the face of the cell is on top, facing the camera. Sent by anything
+/// that wants to show the player a cell, like [`face_king`].
+pub(crate) struct FaceCell(pub(crate) CellCoordinates);
+
+/// Like [`FaceCell`], but only turns the cube if the face of the cell can't be seen, see
+/// [`reveal_ai_moves`]
+pub(crate) struct RevealCell(pub(crate) CellCoordinates);
+
+/// If the cube is turning, see [`RotationData::is_turning`]. Read by [`crate::input_state`].
+#[derive(Resource, Default)]
+pub(crate) struct CubeTurning(pub(crate) bool);
+
+/// Rotations pressed on other devices than the keyboard, like [`crate::gamepad`]. Indexed in the
+/// order left, right, down and up.
+#[derive(Resource, Default)]
+pub(crate) struct RotationButtons {
+    pub(crate) pressed: [bool; 4],
+    pub(crate) just_pressed: [bool; 4],
+    pub(crate) turn_camera: bool,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn rotate(
     mut query: Query<(&mut Transform, &MainCamera)>,
+    mut face_cell_events: EventReader<FaceCell>,
+    mut reveal_cell_events: EventReader<RevealCell>,
     time: Res<Time>,
     input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    rotation_buttons: Res<RotationButtons>,
+    game: Res<Game>,
+    settings: Res<Settings>,
+    input_state: Res<InputState>,
+    mut cube_turning: ResMut<CubeTurning>,
     mut rotation_data: Local<RotationData>,
 ) {
+    // The camera is moved by [`orbit`] instead
+    if settings.orbit_camera {
+        if cube_turning.0 {
+            cube_turning.0 = false;
+        }
+        return;
+    }
     let time = &*time;
     let rotation_data = &mut *rotation_data;
-    let rotation_duration = 1.;
+    let rotation_duration = settings.rotation_duration;
+    let key_bindings = &settings.key_bindings;
+
+    remember_team_orientation(rotation_data, &game, &settings, time);
+    if let Some(FaceCell(coords)) = face_cell_events.iter().last() {
+        rotation_data.facing = Some(coords.normal_direction());
+    }
+    for RevealCell(coords) in reveal_cell_events.iter() {
+        let normal = coords.normal_direction();
+        if !shows_face(rotation_data.current_rotation, normal) {
+            rotation_data.facing = Some(normal);
+        }
+    }
+    turn_to_face(rotation_data, time);
 
     dbg!(
         &rotation_data,
         rotation_data.current_rotation.mul_vec3(Vec3::splat(1.))
     );
 
+    if let Some((_, time_pressed)) = rotation_data.buffered_press {
+        if (time.elapsed() - time_pressed).as_secs_f32() > BUFFERED_PRESS_WINDOW {
+            rotation_data.buffered_press = None;
+        }
+    }
+    // In the same order as the rotations of [`RotationButtons`]
+    let rotation_keys = [
+        key_bindings.rotate_left,
+        key_bindings.rotate_right,
+        key_bindings.rotate_down,
+        key_bindings.rotate_up,
+    ];
     let mut input_handling =
-        |keycode: KeyCode, axis: CartesianDirection, camera_rotation: i32, reversed: bool| {
-            if input.just_pressed(keycode) {
+        |button: usize, axis: CartesianDirection, camera_rotation: i32, reversed: bool| {
+            let keycode = rotation_keys[button];
+            let just_pressed = input.just_pressed(keycode) || rotation_buttons.just_pressed[button];
+            let pressed = input.pressed(keycode) || rotation_buttons.pressed[button];
+            // Holding the key keeps rotating once the previous rotation is done
+            let held = pressed && !just_pressed;
+            let buffered = matches!(rotation_data.buffered_press, Some((key, _)) if key == keycode);
+            if just_pressed || held || buffered {
                 let axis_rotated = direction_after_camera_turn(
                     axis.abs(),
                     rotation_data.current_rotation,
@@ -49,27 +189,61 @@ pub(crate) fn rotate(
                 )
                 .expect("Current rotation does not have anything other than quarter turns");
                 let axis_num = axis_rotated.axis_num() as usize;
-                if rotation_data.time_started_rotations[axis_num].is_zero()
+                let time_started = rotation_data.time_started_rotations[axis_num];
+                // Up and down also turn the camera, so they stay quarter turns
+                if just_pressed
+                    && camera_rotation == 0
+                    && !time_started.is_zero()
+                    && rotation_data.reversed_axes[axis_num] == reversed
+                    && rotation_data.quarter_turns[axis_num] == 1
+                    && (time.elapsed() - time_started).as_secs_f32() < DOUBLE_PRESS_WINDOW
+                {
+                    rotation_data.quarter_turns[axis_num] = 2;
+                    // Twice as long at the same speed, so the turn goes on without a jump
+                    rotation_data.rotation_durations[axis_num] *= 2.;
+                    return;
+                }
+                if time_started.is_zero()
                     && (rotation_data.time_started_rotations[3].is_zero() || camera_rotation == 0)
                 {
+                    let duration = if held {
+                        rotation_data.repeats += 1;
+                        (rotation_duration * REPEAT_SPEEDUP.powi(rotation_data.repeats))
+                            .max(MIN_ROTATION_DURATION)
+                    } else {
+                        rotation_data.repeats = 0;
+                        rotation_duration
+                    };
                     rotation_data.reversed_axes[axis_num] = reversed;
                     rotation_data.time_started_rotations[axis_num] = time.elapsed();
+                    rotation_data.rotation_durations[axis_num] = duration;
+                    rotation_data.quarter_turns[axis_num] = 1;
                     if camera_rotation != 0 {
                         rotation_data.time_started_rotations[3] = time.elapsed();
                         rotation_data.reversed_axes[3] = camera_rotation == -1;
+                        rotation_data.rotation_durations[3] = duration;
+                    }
+                    if buffered {
+                        rotation_data.buffered_press = None;
                     }
+                } else if just_pressed {
+                    // Replaces an older press, which is what the player meant last
+                    rotation_data.buffered_press = Some((keycode, time.elapsed()));
                 }
             };
         };
 
     // Input
-    input_handling(KeyCode::Left, CartesianDirection::Y, 0, true);
-    input_handling(KeyCode::Right, CartesianDirection::Y, 0, false);
-    input_handling(KeyCode::Down, CartesianDirection::Z, 1, false);
-    input_handling(KeyCode::Up, CartesianDirection::Z, -1, true);
-    if input.just_pressed(KeyCode::Space) {
-        rotation_data.time_started_rotations[3] = time.elapsed();
-        rotation_data.reversed_axes[3] = input.pressed(KeyCode::A);
+    if rotation_data.restoring_from.is_none() && input_state.can_rotate() {
+        input_handling(0, CartesianDirection::Y, 0, true);
+        input_handling(1, CartesianDirection::Y, 0, false);
+        input_handling(2, CartesianDirection::Z, 1, false);
+        input_handling(3, CartesianDirection::Z, -1, true);
+        if input.just_pressed(key_bindings.turn_camera) || rotation_buttons.turn_camera {
+            rotation_data.time_started_rotations[3] = time.elapsed();
+            rotation_data.reversed_axes[3] = input.pressed(key_bindings.reverse_camera_turn);
+            rotation_data.rotation_durations[3] = rotation_duration;
+        }
     }
 
     let mut rotation_needed = rotation_data.current_rotation;
@@ -79,7 +253,7 @@ pub(crate) fn rotate(
         time,
         &mut rotation_data.time_started_rotations[3],
         &mut rotation_data.current_camera_up,
-        rotation_duration,
+        rotation_data.rotation_durations[3],
         &mut camera_rotation_up_needed,
         rotation_data.reversed_axes[3],
         rotation_data.current_rotation,
@@ -91,7 +265,8 @@ pub(crate) fn rotate(
         time,
         &mut rotation_data.time_started_rotations[0],
         &mut rotation_data.current_rotation,
-        rotation_duration,
+        rotation_data.rotation_durations[0],
+        rotation_data.quarter_turns[0],
         EulerRot::XYZ,
         &mut rotation_needed,
         rotation_data.reversed_axes[0],
@@ -101,7 +276,8 @@ pub(crate) fn rotate(
         time,
         &mut rotation_data.time_started_rotations[1],
         &mut rotation_data.current_rotation,
-        rotation_duration,
+        rotation_data.rotation_durations[1],
+        rotation_data.quarter_turns[1],
         EulerRot::YXZ,
         &mut rotation_needed,
         rotation_data.reversed_axes[1],
@@ -111,12 +287,42 @@ pub(crate) fn rotate(
         time,
         &mut rotation_data.time_started_rotations[2],
         &mut rotation_data.current_rotation,
-        rotation_duration,
+        rotation_data.rotation_durations[2],
+        rotation_data.quarter_turns[2],
         EulerRot::ZXY,
         &mut rotation_needed,
         rotation_data.reversed_axes[2],
     );
 
+    // Animate going back to a remembered orientation
+    if let Some((from_rotation, from_camera_up, time_started)) = rotation_data.restoring_from {
+        let progress =
+            rotation_curve((time.elapsed() - time_started).as_secs_f32() / rotation_duration);
+        rotation_needed = from_rotation.slerp(rotation_data.current_rotation, progress);
+        camera_rotation_up_needed = Quat::IDENTITY
+            .slerp(
+                Quat::from_rotation_arc(
+                    from_camera_up.as_vec3(),
+                    rotation_data.current_camera_up.as_vec3(),
+                ),
+                progress,
+            )
+            .mul_vec3(from_camera_up.as_vec3());
+        if progress >= 1. {
+            rotation_data.restoring_from = None;
+        }
+    }
+
+    if cube_turning.0 != rotation_data.is_turning() {
+        cube_turning.0 = rotation_data.is_turning();
+    }
+
+    let had_input = input.get_just_pressed().next().is_some()
+        || mouse_input.get_just_pressed().next().is_some();
+    let idle_angle = idle_rotation(rotation_data, &settings, time, had_input);
+    rotation_needed =
+        Quat::from_axis_angle(camera_rotation_up_needed, idle_angle) * rotation_needed;
+
     dbg!(rotation_needed.mul_vec3(Vec3::splat(1.)));
     // Apply the rotation
     for mut camera in &mut query {
@@ -132,6 +338,254 @@ pub(crate) fn rotate(
     }
 }
 
+/// Starts turning to the face of [`RotationData::facing`] once nothing else is turning
+fn turn_to_face(rotation_data: &mut RotationData, time: &Time) {
+    if rotation_data.is_turning() {
+        return;
+    }
+    let Some(normal) = rotation_data.facing.take() else {
+        return;
+    };
+    let rotation = orientation_facing(normal, rotation_data.current_rotation);
+    if rotation == rotation_data.current_rotation && rotation_data.current_camera_up == normal {
+        return;
+    }
+    // Animated the same way as going back to a remembered orientation
+    rotation_data.restoring_from = Some((
+        rotation_data.current_rotation,
+        rotation_data.current_camera_up,
+        time.elapsed(),
+    ));
+    rotation_data.current_rotation = rotation;
+    rotation_data.current_camera_up = normal;
+}
+
+/// The camera sees the three faces that the orientation turns the positive axes to
+fn shows_face(rotation: Quat, normal: CartesianDirection) -> bool {
+    rotation
+        .inverse()
+        .mul_vec3(normal.as_vec3())
+        .cmpgt(Vec3::splat(0.5))
+        .any()
+}
+
+/// The orientation made of quarter turns closest to `rotation` that shows the face with `normal`,
+/// so that it can be on top with the camera up along `normal`
+fn orientation_facing(normal: CartesianDirection, rotation: Quat) -> Quat {
+    let mut closest = rotation;
+    let mut closest_angle = f32::INFINITY;
+    for x in CartesianDirection::directions() {
+        for y in CartesianDirection::directions() {
+            if x.abs() == y.abs() {
+                continue;
+            }
+            let (x, y) = (x.as_vec3(), y.as_vec3());
+            let candidate = Quat::from_mat3(&Mat3::from_cols(x, y, x.cross(y)));
+            let angle = candidate.angle_between(rotation);
+            if shows_face(candidate, normal) && angle < closest_angle {
+                closest = candidate;
+                closest_angle = angle;
+            }
+        }
+    }
+    closest
+}
+
+/// K turns the cube to the king of the player, or of the team to move in hot-seat games
+pub(crate) fn face_king(
+    input: Res<Input<KeyCode>>,
+    game: Res<Game>,
+    settings: Res<Settings>,
+    mut face_cell_events: EventWriter<FaceCell>,
+) {
+    if !input.just_pressed(settings.key_bindings.face_king) {
+        return;
+    }
+    let team = match game.ai_playing.or(game.remote_team) {
+        Some(other_team) => other_team.opposite(),
+        None => game.turn,
+    };
+    if let Some(coords) = king_cell(&game, team) {
+        face_cell_events.send(FaceCell(coords));
+    }
+}
+
+fn king_cell(game: &Game, team: Team) -> Option<CellCoordinates> {
+    game.units
+        .all_units_iter()
+        .find(|unit| unit.unit_type == UnitType::King && unit.team == team)
+        .map(|unit| unit.coords)
+}
+
+/// In hot-seat games, turns the cube to the king of the player whose turn it is after every move,
+/// so that both players get the same view of their own side. Can be turned on on the settings
+/// menu.
+pub(crate) fn face_king_on_turn_change(
+    mut turns_changed: EventReader<TurnChanged>,
+    game: Res<Game>,
+    settings: Res<Settings>,
+    mut face_cell_events: EventWriter<FaceCell>,
+) {
+    let Some(TurnChanged { turn }) = turns_changed.iter().last() else {
+        return;
+    };
+    if !settings.hot_seat_face_king || !game.is_hot_seat() || game.result.is_some() {
+        return;
+    }
+    if let Some(coords) = king_cell(&game, *turn) {
+        face_cell_events.send(FaceCell(coords));
+    }
+}
+
+/// Turns the cube to the cell the AI moved to when it can't be seen, so that the player sees what
+/// happened. Can be turned off on the settings menu.
+pub(crate) fn reveal_ai_moves(
+    mut moves_made: EventReader<MoveMade>,
+    game: Res<Game>,
+    settings: Res<Settings>,
+    mut reveal_cell_events: EventWriter<RevealCell>,
+) {
+    for move_made in moves_made.iter() {
+        if settings.reveal_ai_moves && game.ai_playing == Some(move_made.team) {
+            reveal_cell_events.send(RevealCell(move_made.game_move.to));
+        }
+    }
+}
+
+/// Where the free orbiting camera is, as angles around the cube
+#[derive(Debug)]
+pub(crate) struct OrbitData {
+    yaw: f32,
+    pitch: f32,
+    /// Radians per second, which keep the camera gliding for a while after it is let go
+    velocity: Vec2,
+}
+
+/// The free orbit mode of the camera, picked on the settings menu instead of the quarter turns of
+/// [`rotate`]. Holding the right mouse button drags the camera around the cube.
+pub(crate) fn orbit(
+    mut query: Query<(&mut Transform, &MainCamera)>,
+    time: Res<Time>,
+    mouse_input: Res<Input<MouseButton>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    settings: Res<Settings>,
+    mut orbit_data: Local<Option<OrbitData>>,
+) {
+    let dragged: Vec2 = mouse_motion.iter().map(|motion| motion.delta).sum();
+    if !settings.orbit_camera {
+        *orbit_data = None;
+        return;
+    }
+    // Starts where the quarter turns left the camera
+    let orbit_data = orbit_data.get_or_insert_with(|| {
+        let direction = query
+            .iter()
+            .next()
+            .map_or(Vec3::ONE, |(transform, _)| transform.translation)
+            .normalize();
+        OrbitData {
+            yaw: direction.x.atan2(direction.z),
+            pitch: direction.y.asin(),
+            velocity: Vec2::ZERO,
+        }
+    });
+
+    let delta_seconds = time.delta_seconds();
+    if mouse_input.pressed(MouseButton::Right) {
+        orbit_data.velocity = dragged * ORBIT_SENSITIVITY / delta_seconds.max(f32::EPSILON);
+    } else {
+        orbit_data.velocity *= ORBIT_DAMPING.powf(delta_seconds);
+    }
+    // Dragging to the left turns the cube to the left, like grabbing it
+    orbit_data.yaw -= orbit_data.velocity.x * delta_seconds;
+    orbit_data.pitch = (orbit_data.pitch + orbit_data.velocity.y * delta_seconds)
+        .clamp(-ORBIT_MAX_PITCH, ORBIT_MAX_PITCH);
+
+    let direction = Vec3::new(
+        orbit_data.pitch.cos() * orbit_data.yaw.sin(),
+        orbit_data.pitch.sin(),
+        orbit_data.pitch.cos() * orbit_data.yaw.cos(),
+    );
+    for (mut transform, camera) in &mut query {
+        transform.translation = direction * camera.start_coords.length();
+        transform.look_at(Vec3::ZERO, Vec3::Y);
+    }
+}
+
+/// Slowly turns the cube around the camera up when nothing has been pressed for a while, as a
+/// showcase. Any input blends back to the rotation the player left the cube in. Returns the angle
+/// to turn by.
+fn idle_rotation(
+    rotation_data: &mut RotationData,
+    settings: &Settings,
+    time: &Time,
+    had_input: bool,
+) -> f32 {
+    if had_input {
+        rotation_data.time_last_input = time.elapsed();
+    }
+    let idle_for = (time.elapsed() - rotation_data.time_last_input).as_secs_f32();
+    if settings.idle_rotation && idle_for > settings.idle_rotation_delay {
+        rotation_data.idle_angle =
+            (rotation_data.idle_angle + IDLE_ROTATION_SPEED * time.delta_seconds()) % (2. * PI);
+    } else {
+        // Go back the shortest way around
+        if rotation_data.idle_angle > PI {
+            rotation_data.idle_angle -= 2. * PI;
+        }
+        rotation_data.idle_angle *= IDLE_BLEND_BACK.powf(time.delta_seconds());
+    }
+    rotation_data.idle_angle
+}
+
+/// F7 turns the idle rotation on and off
+pub(crate) fn toggle_idle_rotation(input: Res<Input<KeyCode>>, mut settings: ResMut<Settings>) {
+    if !input.just_pressed(KeyCode::F7) {
+        return;
+    }
+    settings.idle_rotation = !settings.idle_rotation;
+}
+
+/// In hot-seat games, every player gets back the orientation they had at the end of their
+/// previous turn when their turn begins, unless [`face_king_on_turn_change`] turns the cube instead
+fn remember_team_orientation(
+    rotation_data: &mut RotationData,
+    game: &Game,
+    settings: &Settings,
+    time: &Time,
+) {
+    let rotating = rotation_data
+        .time_started_rotations
+        .iter()
+        .any(|time_started| !time_started.is_zero());
+    if rotating
+        || rotation_data.restoring_from.is_some()
+        || rotation_data.last_turn == Some(game.turn)
+    {
+        return;
+    }
+
+    let last_turn = rotation_data.last_turn.replace(game.turn);
+    if game.ai_playing.is_some() || settings.hot_seat_face_king {
+        return;
+    }
+    if let Some(last_turn) = last_turn {
+        rotation_data.team_orientations[last_turn.index()] = Some((
+            rotation_data.current_rotation,
+            rotation_data.current_camera_up,
+        ));
+    }
+    if let Some((rotation, camera_up)) = rotation_data.team_orientations[game.turn.index()] {
+        rotation_data.restoring_from = Some((
+            rotation_data.current_rotation,
+            rotation_data.current_camera_up,
+            time.elapsed(),
+        ));
+        rotation_data.current_rotation = rotation;
+        rotation_data.current_camera_up = camera_up;
+    }
+}
+
 fn animate_camera_rotation(
     time: &Time,
     time_started_rotation: &mut Duration,
@@ -297,11 +751,13 @@ fn order_of_sides(up: CartesianDirection) -> [CartesianDirection; 4] {
 }
 /// # Arguments
 /// axis: The first axis in the EulerRot should correspond to the axis animated
+#[allow(clippy::too_many_arguments)]
 fn animate_axis(
     time: &Time,
     time_started_rotation: &mut Duration,
     current_rotation: &mut Quat,
     rotation_duration: f32,
+    quarter_turns: u32,
     axis: EulerRot,
     rotation_needed: &mut Quat,
     reversed: bool,
@@ -310,25 +766,23 @@ fn animate_axis(
         return; // No rotation happening on axis
     }
     let time_elapsed = time.elapsed() - time_started_rotation.to_owned();
-    let rotation_amount = if reversed { -1. } else { 1. }
-        * rotation_curve(time_elapsed.as_secs_f32() / rotation_duration)
-        * PI
-        / 2.;
+    let full_amount = if reversed { -1. } else { 1. } * quarter_turns as f32 * PI / 2.;
+    let rotation_amount =
+        rotation_curve(time_elapsed.as_secs_f32() / rotation_duration) * full_amount;
 
     *rotation_needed *= Quat::from_euler(axis, rotation_amount, 0., 0.);
 
     if time_elapsed.as_secs_f32() > rotation_duration {
         *time_started_rotation = Duration::default();
-        *current_rotation *=
-            Quat::from_euler(axis, if reversed { -1. } else { 1. } * PI / 2., 0., 0.);
+        *current_rotation *= Quat::from_euler(axis, full_amount, 0., 0.);
     }
 }
 
 mod tests {
     #[test]
     fn new_axis_on_side_after_rotation_test() {
-        for direction in crate::utils::CartesianDirection::directions() {
-            for direction2 in crate::utils::CartesianDirection::directions() {
+        for direction in crate::engine::direction::CartesianDirection::directions() {
+            for direction2 in crate::engine::direction::CartesianDirection::directions() {
                 let o = crate::cube_rotation::new_axis_on_side_after_rotation(
                     direction,
                     bevy::prelude::Quat::from_rotation_arc(
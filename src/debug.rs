@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+
+use crate::engine::cell::CellCoordinates;
+use crate::engine::direction::{CartesianDirection, RadialDirection};
+use crate::engine::movement;
+use crate::engine::units::{Unit, Units};
+use crate::gamemanager::Game;
+
+/// F8 prints every legal move of the unit on the selected cell, in a form that can be pasted into
+/// bug reports
+pub(crate) fn dump_selected_cell_moves(input: Res<Input<KeyCode>>, game: Res<Game>) {
+    if !input.just_pressed(KeyCode::F8) {
+        return;
+    }
+    let Some(selected_cell) = game.selected_cell else {
+        println!("Select a cell to dump the moves of its unit");
+        return;
+    };
+    println!("{}", describe_moves(selected_cell, &game));
+}
+
+fn describe_moves(coords: CellCoordinates, game: &Game) -> String {
+    let cube_side_length = game.board.cube_side_length;
+    let Some(unit) = game.units.get_unit(coords) else {
+        return format!("No unit at {}", cell_text(coords));
+    };
+
+    let unit_moves = movement::get_unit_moves(unit, &game.board, &game.units);
+    let mut output = format!(
+        "{:?} {:?} at {}, level {}, cube side length {}, {} legal moves",
+        unit.team,
+        unit.unit_type,
+        cell_text(coords),
+        unit.level,
+        cube_side_length,
+        unit_moves.len()
+    );
+    let traces = trace_paths(unit, &game.units, cube_side_length);
+    for to in unit_moves {
+        output.push_str(&format!("\n  to {}", cell_text(to)));
+        // The shortest way of getting there is most likely the one the move generation used
+        match traces
+            .iter()
+            .filter(|trace| trace.0.last() == Some(&to))
+            .min_by_key(|trace| trace.0.len())
+        {
+            Some((path, edges_crossed)) => {
+                let path: Vec<String> = path.iter().map(|&cell| cell_text(cell)).collect();
+                output.push_str(&format!(
+                    ", path: {}, edges crossed: {}",
+                    path.join(" > "),
+                    edges_crossed
+                ));
+            }
+            None => output.push_str(", path: unknown"),
+        }
+        if let Some(target) = game.units.get_unit(to) {
+            output.push_str(&format!(
+                ", captures: {:?} {:?}",
+                target.team, target.unit_type
+            ));
+        }
+    }
+    output
+}
+
+fn cell_text(coords: CellCoordinates) -> String {
+    format!(
+        "({}, {}, {}) on {:?}",
+        coords[0],
+        coords[1],
+        coords[2],
+        coords.normal_direction()
+    )
+}
+
+/// All straight and diagonal lines from the unit up to the first unit in the way, and all knight
+/// jumps, with the number of edges crossed on the way
+fn trace_paths(
+    unit: &Unit,
+    units: &Units,
+    cube_side_length: u32,
+) -> Vec<(Vec<CellCoordinates>, u32)> {
+    // Going around the cube once is the furthest any line can go
+    let max_dist = cube_side_length * 4;
+    let mut output = Vec::new();
+    let mut trace_line = |step: &dyn Fn(CellCoordinates) -> Option<(CellCoordinates, bool)>| {
+        let mut path = Vec::new();
+        let mut edges_crossed = 0;
+        let mut latest_cell = unit.coords;
+        while let Some((next_cell, crossed_edge)) = step(latest_cell) {
+            if next_cell == unit.coords || path.len() as u32 >= max_dist {
+                break;
+            }
+            if crossed_edge {
+                edges_crossed += 1;
+            }
+            path.push(next_cell);
+            output.push((path.clone(), edges_crossed));
+            if units.is_unit_at(next_cell) {
+                break;
+            }
+            latest_cell = next_cell;
+        }
+    };
+
+    for direction in RadialDirection::directions() {
+        trace_line(&|cell: CellCoordinates| {
+            cell.get_cell_in_radial_direction(direction, cube_side_length)
+        });
+    }
+    for diagonal in CartesianDirection::diagonals() {
+        trace_line(&|cell: CellCoordinates| cell.get_diagonal(diagonal, cube_side_length));
+    }
+
+    for direction in RadialDirection::directions() {
+        let normal = unit.coords.normal_direction();
+        let Some(forward) = direction.to_cartesian_direction(normal) else {
+            continue;
+        };
+        let Some(sideways) = forward.get_perpendicular_axis(normal) else {
+            continue;
+        };
+        let Some((forward_one, crossed_1)) =
+            unit.coords.get_cell_in_direction(forward, cube_side_length)
+        else {
+            continue;
+        };
+        let Some((forward_two, crossed_2)) =
+            forward_one.get_cell_in_radial_direction(direction, cube_side_length)
+        else {
+            continue;
+        };
+        for side in [sideways, sideways.opposite()] {
+            let Some((endpoint, crossed_3)) =
+                forward_two.get_cell_in_direction(side, cube_side_length)
+            else {
+                continue;
+            };
+            let edges_crossed = [crossed_1, crossed_2, crossed_3]
+                .into_iter()
+                .filter(|&crossed| crossed)
+                .count() as u32;
+            output.push((vec![forward_one, forward_two, endpoint], edges_crossed));
+        }
+    }
+    output
+}
@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+
+use crate::engine::duel::resolve_moves;
+use crate::engine::movement::{self, GameMove};
+use crate::engine::notation;
+use crate::engine::units::{Team, Unit};
+use crate::gamemanager::{Game, GameEvents, MoveMade, UnitCaptured};
+
+/// Secretly stores the move of the team whose turn it is. Once both teams have picked a move,
+/// they are resolved at the same time. Returns false if the move is illegal.
+pub(crate) fn lock_in_move(game_move: GameMove, game: &mut Game, events: &mut GameEvents) -> bool {
+    if !movement::is_legal(
+        game_move,
+        &game.board,
+        &game.units,
+        game.turn,
+        game.variant.rules(),
+    ) {
+        return false;
+    }
+    game.hidden_moves[game.turn.index()] = Some(game_move);
+    let [Some(white_move), Some(black_move)] = game.hidden_moves else {
+        info!("{:?} has locked in a move", game.turn);
+        return true;
+    };
+    game.hidden_moves = [None; 2];
+
+    let moves = [white_move, black_move];
+    let notations =
+        moves.map(|game_move| notation::write_move(game_move, &game.board, &game.units));
+    let Some(captured_units) = resolve_moves(&mut game.units, moves) else {
+        record_moves(game, moves, notations, &[]);
+        info!("Both moves bounced");
+        return true;
+    };
+    record_moves(game, moves, notations.clone(), &captured_units);
+    for unit in captured_units {
+        events.unit_captured.send(UnitCaptured { unit });
+    }
+    for (game_move, notation) in moves.into_iter().zip(notations) {
+        let Some(unit) = game.units.get_unit(game_move.to) else {
+            continue;
+        };
+        if let Some(entity) = unit.entity {
+            events.move_made.send(MoveMade {
+                game_move,
+                notation,
+                team: unit.team,
+                entity: entity.into(),
+            });
+        }
+    }
+    true
+}
+
+/// Adds the moves of both teams to the history, after they have been resolved
+pub(crate) fn record_moves(
+    game: &mut Game,
+    moves: [GameMove; 2],
+    notations: [String; 2],
+    captured_units: &[Unit],
+) {
+    let teams = [Team::White, Team::Black];
+    for ((team, game_move), notation) in teams.into_iter().zip(moves).zip(notations) {
+        let captured_unit = captured_units
+            .iter()
+            .find(|unit| unit.coords == game_move.to)
+            .cloned();
+        game.history
+            .record_move(team, Some(game_move), notation, captured_unit);
+    }
+}
@@ -0,0 +1,21 @@
+//! The rules of the game, kept free of Bevy so they can be tested, benchmarked and run without
+//! the renderer
+
+pub(crate) mod ai;
+pub(crate) mod analysis;
+pub(crate) mod cell;
+pub(crate) mod cubefen;
+pub(crate) mod direction;
+pub(crate) mod duel;
+pub(crate) mod history;
+pub(crate) mod movement;
+pub(crate) mod notation;
+pub(crate) mod pieces;
+pub(crate) mod puzzles;
+pub(crate) mod rules;
+pub(crate) mod units;
+
+/// Identifies the object that shows a cell or unit, without the rules knowing what it is. The
+/// game stores the bits of a Bevy entity here
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ObjectId(pub(crate) u64);
@@ -0,0 +1,643 @@
+use crate::engine::cell::*;
+use crate::engine::duel;
+use crate::engine::movement::{self, *};
+use crate::engine::units::*;
+
+#[derive(Default, Debug)]
+pub(crate) struct AICache {
+    last_variation: Vec<GameMove>,
+    /// The units that moves are made and unmade on during search. Kept between searches so its
+    /// allocation can be reused
+    search_units: Units,
+}
+
+/// The board is never changed during search, so only the units are copied
+pub(crate) fn next_move(
+    board: &Board,
+    units: &Units,
+    team: Team,
+    depth: u32,
+    variant: Variant,
+    ai_cache: &mut AICache,
+) -> Option<GameMove> {
+    let mut search_units = std::mem::take(&mut ai_cache.search_units);
+    search_units.copy_from(units);
+    let output = next_move_internal(board, &mut search_units, team, depth, variant, ai_cache);
+    ai_cache.search_units = search_units;
+    output
+}
+
+fn next_move_internal(
+    board: &Board,
+    units: &mut Units,
+    team: Team,
+    depth: u32,
+    variant: Variant,
+    ai_cache: &mut AICache,
+) -> Option<GameMove> {
+    let mut stats = (0, 0, 0);
+    let mut variation = eval_recursive(
+        board,
+        units,
+        team,
+        depth,
+        f32::MIN,
+        f32::MAX,
+        &mut stats,
+        true,
+        variant,
+        ai_cache,
+    );
+
+    // The variation is empty if the team has no moves
+    variation.1.pop()
+}
+
+/// How strongly the opponent is assumed to prefer its better looking moves in the duel variant.
+/// Lower means the opponent is assumed to play more like the AI itself.
+const DUEL_TEMPERATURE: f32 = 1.;
+
+/// In the duel variant the move of the opponent isn't known when choosing a move. Every opponent
+/// move is given a probability from how good it looks for them, and the move with the best
+/// expected evaluation over those probabilities is chosen.
+pub(crate) fn next_duel_move(
+    board: &Board,
+    units: &Units,
+    team: Team,
+    ai_cache: &mut AICache,
+) -> Option<GameMove> {
+    let own_moves = get_possible_moves(board, units, team, Variant::Duel);
+    let other_moves = get_possible_moves(board, units, team.opposite(), Variant::Duel);
+    let mut search_units = std::mem::take(&mut ai_cache.search_units);
+
+    let other_evals: Vec<f32> = other_moves
+        .iter()
+        .map(|&other_move| {
+            search_units.copy_from(units);
+            make_move(other_move, board, &mut search_units, Variant::Duel);
+            let eval = evaluation(board, &search_units, Variant::Duel, ai_cache);
+            eval * team.opposite().sign() as f32
+        })
+        .collect();
+    let best_other_eval = other_evals.iter().copied().fold(f32::MIN, f32::max);
+    let weights: Vec<f32> = other_evals
+        .iter()
+        .map(|eval| ((eval - best_other_eval) / DUEL_TEMPERATURE).exp())
+        .collect();
+    let total_weight: f32 = weights.iter().sum();
+
+    let mut best_move = None;
+    let mut best_eval = f32::MIN;
+    for own_move in own_moves {
+        let expected_eval = if other_moves.is_empty() {
+            search_units.copy_from(units);
+            make_move(own_move, board, &mut search_units, Variant::Duel);
+            evaluation(board, &search_units, Variant::Duel, ai_cache) * team.sign() as f32
+        } else {
+            let mut eval_sum = 0.;
+            for (&other_move, weight) in other_moves.iter().zip(&weights) {
+                search_units.copy_from(units);
+                duel::resolve_moves(
+                    &mut search_units,
+                    duel::ordered_moves(team, own_move, other_move),
+                );
+                let eval = evaluation(board, &search_units, Variant::Duel, ai_cache);
+                eval_sum += weight * eval * team.sign() as f32;
+            }
+            eval_sum / total_weight
+        };
+        if expected_eval > best_eval {
+            best_eval = expected_eval;
+            best_move = Some(own_move);
+        }
+    }
+
+    ai_cache.search_units = search_units;
+    best_move
+}
+
+/// How good `game_move` is for `team`, from the material balance after the best reply to it that
+/// is found when searching `depth` moves
+pub(crate) fn move_value(
+    board: &Board,
+    units: &Units,
+    game_move: GameMove,
+    team: Team,
+    depth: u32,
+    variant: Variant,
+    ai_cache: &mut AICache,
+) -> f32 {
+    let mut search_units = std::mem::take(&mut ai_cache.search_units);
+    search_units.copy_from(units);
+    let mut value = f32::MIN;
+    if make_move(game_move, board, &mut search_units, variant).is_some() {
+        let reply = next_move_internal(
+            board,
+            &mut search_units,
+            team.opposite(),
+            depth,
+            variant,
+            ai_cache,
+        );
+        if let Some(reply) = reply {
+            make_move(reply, board, &mut search_units, variant);
+        }
+        value = evaluation(board, &search_units, variant, ai_cache) * team.sign() as f32;
+    }
+    ai_cache.search_units = search_units;
+    value
+}
+
+/// How much the AI values a point of energy in the reinforcements variant, in pawns. Energy buys
+/// material at about its value, but it keeps coming every turn, so it is worth less.
+const ENERGY_VALUE: f32 = 0.5;
+
+/// The summon that is better for `team` than `next_move`, its best move, if any. `summons` are the
+/// units it can summon with their cost. The summons and the move are valued the same way as the
+/// search values a move at `depth`, and the summons less the energy they cost.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn best_summon(
+    board: &Board,
+    units: &Units,
+    summons: &[(Unit, u32)],
+    next_move: Option<GameMove>,
+    team: Team,
+    depth: u32,
+    variant: Variant,
+    ai_cache: &mut AICache,
+) -> Option<Unit> {
+    let depth = depth.saturating_sub(1);
+    let mut search_units = std::mem::take(&mut ai_cache.search_units);
+    let mut best_value = f32::MIN;
+    if let Some(game_move) = next_move {
+        search_units.copy_from(units);
+        if make_move(game_move, board, &mut search_units, variant).is_some() {
+            best_value = value_after(board, &mut search_units, team, depth, variant, ai_cache);
+        }
+    }
+    let mut best_summon = None;
+    for (unit, cost) in summons {
+        search_units.copy_from(units);
+        search_units.add_unit(unit.clone());
+        let value = value_after(board, &mut search_units, team, depth, variant, ai_cache)
+            - *cost as f32 * ENERGY_VALUE;
+        if value > best_value {
+            best_value = value;
+            best_summon = Some(unit.clone());
+        }
+    }
+    ai_cache.search_units = search_units;
+    best_summon
+}
+
+/// How good the position is for `team` after its turn, from searching the turns of the other team
+/// `depth` moves deep
+fn value_after(
+    board: &Board,
+    units: &mut Units,
+    team: Team,
+    depth: u32,
+    variant: Variant,
+    ai_cache: &mut AICache,
+) -> f32 {
+    let (eval, _) = eval_recursive(
+        board,
+        units,
+        team.opposite(),
+        depth,
+        f32::MIN,
+        f32::MAX,
+        &mut (0, 0, 0),
+        false,
+        variant,
+        ai_cache,
+    );
+    -eval
+}
+
+/// Material balance of the position, positive if white is ahead
+pub(crate) fn static_evaluation(board: &Board, units: &Units, variant: Variant) -> f32 {
+    evaluation(board, units, variant, &mut AICache::default())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn eval_recursive(
+    board: &Board,
+    units: &mut Units,
+    team: Team,
+    depth: u32,
+    mut alpha: f32,
+    beta: f32,
+    stats: &mut (u32, u32, u32),
+    og: bool,
+    variant: Variant,
+    ai_cache: &mut AICache,
+) -> (f32, Vec<GameMove>) {
+    let (_, _, ref mut num_nodes) = stats;
+    *num_nodes += 1;
+    if depth == 0 {
+        let eval = evaluation(board, units, variant, ai_cache) * team.sign() as f32;
+        return (eval, Vec::new());
+    }
+
+    let mut eval = f32::MIN;
+    let mut best_variation: Vec<GameMove> = Vec::new();
+    let possible_moves = get_possible_moves(board, units, team, variant);
+    let possible_moves = sort_moves(possible_moves, board, units, team, variant, ai_cache);
+    for game_move in possible_moves {
+        let Some(undo) = make_move(game_move.0, board, units, variant) else {
+            continue;
+        };
+
+        // A full turn is over after black has moved, which is when the neutral units move
+        let (eval_next, best_variation_returned) =
+            if variant.rules().neutral_units_move && team == Team::Black {
+                eval_chance_node(
+                    board,
+                    units,
+                    team.opposite(),
+                    depth - 1,
+                    stats,
+                    variant,
+                    ai_cache,
+                )
+            } else {
+                eval_recursive(
+                    board,
+                    units,
+                    team.opposite(),
+                    depth - 1,
+                    -beta,
+                    -alpha,
+                    stats,
+                    false,
+                    variant,
+                    ai_cache,
+                )
+            };
+        unmake_move(game_move.0, units, undo);
+        // The score of the other team is the opposite of ours
+        let eval_next = -eval_next;
+
+        if eval_next > eval {
+            eval = eval_next;
+            best_variation = best_variation_returned.clone();
+            if og {
+                ai_cache.last_variation = best_variation_returned;
+            }
+            best_variation.push(game_move.0);
+        }
+
+        alpha = alpha.max(eval);
+        if alpha >= beta {
+            let (ref mut a, ref mut b, _) = stats;
+            if team == Team::Black {
+                *a += 1;
+            } else {
+                *b += 1;
+            }
+            break;
+        }
+    }
+    if best_variation.is_empty() {
+        // A team that can't move draws
+        eval = 0.;
+    }
+    (eval, best_variation)
+}
+
+/// The neutral units move randomly, so the position is worth the average of the positions after
+/// each of their possible moves. The average isn't bounded by the window of the move before, so
+/// every outcome is searched with the full window.
+fn eval_chance_node(
+    board: &Board,
+    units: &mut Units,
+    team: Team,
+    depth: u32,
+    stats: &mut (u32, u32, u32),
+    variant: Variant,
+    ai_cache: &mut AICache,
+) -> (f32, Vec<GameMove>) {
+    let mut eval_sum = 0.;
+    let mut num_outcomes = 0;
+    for neutral_move in get_possible_moves(board, units, Team::Neutral, variant) {
+        let Some(undo) = make_move(neutral_move, board, units, variant) else {
+            continue;
+        };
+        let (eval, _) = eval_recursive(
+            board,
+            units,
+            team,
+            depth,
+            f32::MIN,
+            f32::MAX,
+            stats,
+            false,
+            variant,
+            ai_cache,
+        );
+        unmake_move(neutral_move, units, undo);
+        eval_sum += eval;
+        num_outcomes += 1;
+    }
+
+    if num_outcomes == 0 {
+        // The neutral units can't move
+        return eval_recursive(
+            board,
+            units,
+            team,
+            depth,
+            f32::MIN,
+            f32::MAX,
+            stats,
+            false,
+            variant,
+            ai_cache,
+        );
+    }
+    (eval_sum / num_outcomes as f32, Vec::new())
+}
+
+fn sort_moves(
+    possible_moves: Vec<GameMove>,
+    board: &Board,
+    units: &mut Units,
+    team: Team,
+    variant: Variant,
+    ai_cache: &mut AICache,
+) -> Vec<(GameMove, i32, f32)> {
+    let mut output = Vec::new();
+    for possible_move in possible_moves.into_iter() {
+        let Some(undo) = make_move(possible_move, board, units, variant) else {
+            continue;
+        };
+
+        let eval = evaluation(board, units, variant, ai_cache) * team.sign() as f32;
+        unmake_move(possible_move, units, undo);
+
+        if ai_cache
+            .last_variation
+            .last()
+            .is_some_and(|&game_move| game_move == possible_move)
+        {
+            output.push((possible_move, 2, eval));
+            continue;
+        }
+
+        let is_capture = units.is_unit_at(possible_move.to);
+        if is_capture {
+            output.push((possible_move, 1, eval));
+            continue;
+        }
+
+        output.push((possible_move, 0, eval));
+    }
+
+    output.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap());
+    output.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap()); // Sorts list so largest is first
+                                                           // output
+                                                           //     .into_iter()
+                                                           //     .map(|possible_move| possible_move.0)
+                                                           //     .collect()
+    output
+}
+
+pub(crate) fn get_possible_moves(
+    board: &Board,
+    units: &Units,
+    team: Team,
+    variant: Variant,
+) -> Vec<GameMove> {
+    let rules = variant.rules();
+    let mut output = Vec::new();
+    let mut unit_moves = Vec::new();
+    for unit in units.all_units_iter() {
+        if unit.team != team {
+            continue;
+        }
+        unit_moves.clear();
+        movement::get_unit_moves_into(unit, board, units, &mut unit_moves);
+        output.extend(
+            unit_moves
+                .iter()
+                .map(|&move_to| GameMove {
+                    from: unit.coords,
+                    to: move_to,
+                })
+                .filter(|&game_move| rules.allows_move(game_move, board, units)),
+        );
+    }
+    output
+}
+
+fn evaluation(_board: &Board, units: &Units, variant: Variant, _ai_cache: &mut AICache) -> f32 {
+    let rules = variant.rules();
+    let mut white_material = 0.;
+    let mut black_material = 0.;
+
+    for unit in units.all_units_iter() {
+        match unit.team {
+            Team::Black => black_material += rules.unit_value(unit),
+            Team::White => white_material += rules.unit_value(unit),
+            Team::Neutral => {}
+        }
+    }
+
+    white_material - black_material
+}
+
+/// What is needed to take back a move made during search
+struct MoveUndo {
+    captured_unit: Option<Unit>,
+    /// The units blown up by the capture in the atomic variant, the capturing unit among them
+    exploded_units: Vec<Unit>,
+    gained_level: bool,
+}
+
+fn make_move(
+    game_move: GameMove,
+    board: &Board,
+    units: &mut Units,
+    variant: Variant,
+) -> Option<MoveUndo> {
+    let captured_unit = units.remove_unit(game_move.to);
+    let unit = units.get_unit_mut(game_move.from)?;
+    unit.move_unit_to(game_move.to);
+    let rules = variant.rules();
+    let gained_level = rules.capturing_gains_levels && captured_unit.is_some() && unit.gain_level();
+    let exploded_units = if rules.captures_explode && captured_unit.is_some() {
+        movement::explode(game_move.to, board.cube_side_length, units)
+    } else {
+        Vec::new()
+    };
+    Some(MoveUndo {
+        captured_unit,
+        exploded_units,
+        gained_level,
+    })
+}
+
+fn unmake_move(game_move: GameMove, units: &mut Units, undo: MoveUndo) {
+    for exploded_unit in undo.exploded_units {
+        units.add_unit(exploded_unit);
+    }
+    let Some(unit) = units.get_unit_mut(game_move.to) else {
+        panic!("Couldn't undo move: {:?}, units: {:?}", game_move, units);
+    };
+    unit.move_unit_to(game_move.from);
+    if undo.gained_level {
+        unit.level -= 1;
+    }
+    if let Some(captured_unit) = undo.captured_unit {
+        units.add_unit(captured_unit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(units: &[(UnitType, Team, &str)]) -> Units {
+        let mut output = Units::default();
+        for &(unit_type, team, cell) in units {
+            let coords = CellCoordinates::from_display(cell).unwrap();
+            output.add_unit(Unit::new(unit_type, team, coords));
+        }
+        output
+    }
+
+    fn game_move(from: &str, to: &str) -> GameMove {
+        GameMove {
+            from: CellCoordinates::from_display(from).unwrap(),
+            to: CellCoordinates::from_display(to).unwrap(),
+        }
+    }
+
+    fn best_move(units: &Units, team: Team, depth: u32) -> Option<GameMove> {
+        let board = Board::new(4);
+        let mut ai_cache = AICache::default();
+        next_move(&board, units, team, depth, Variant::Standard, &mut ai_cache)
+    }
+
+    #[test]
+    fn hanging_queens_are_taken_and_rooks_are_not_hung() {
+        let board = Board::new(4);
+        let units = position(&[
+            (UnitType::King, Team::White, "Yd4"),
+            (UnitType::Rook, Team::White, "Za1"),
+            (UnitType::King, Team::Black, "zd1"),
+            (UnitType::Queen, Team::Black, "Za4"),
+        ]);
+        for depth in 1..=3 {
+            let chosen = best_move(&units, Team::White, depth);
+            assert_eq!(chosen, Some(game_move("Za1", "Za4")), "depth {}", depth);
+        }
+
+        // The queen attacks the rook, which can't take it back, and it takes a reply to see that
+        let mut units = position(&[
+            (UnitType::King, Team::White, "Yd4"),
+            (UnitType::Rook, Team::White, "Zb1"),
+            (UnitType::King, Team::Black, "zd1"),
+            (UnitType::Queen, Team::Black, "Zd3"),
+        ]);
+        let attacks = get_possible_moves(&board, &units, Team::Black, Variant::Standard);
+        assert!(attacks.contains(&game_move("Zd3", "Zb1")));
+        for depth in 2..=3 {
+            let chosen = best_move(&units, Team::White, depth).unwrap();
+            let undo = make_move(chosen, &board, &mut units, Variant::Standard).unwrap();
+            let rook = chosen.to;
+            let replies = get_possible_moves(&board, &units, Team::Black, Variant::Standard);
+            assert!(
+                replies.iter().all(|reply| reply.to != rook),
+                "depth {} hangs the rook with {:?}",
+                depth,
+                chosen
+            );
+            unmake_move(chosen, &mut units, undo);
+        }
+    }
+
+    #[test]
+    fn black_takes_a_hanging_queen_with_a_comet_on_the_board() {
+        let board = Board::new(4);
+        let units = position(&[
+            (UnitType::King, Team::Black, "Yd4"),
+            (UnitType::Rook, Team::Black, "Za1"),
+            (UnitType::King, Team::White, "zd1"),
+            (UnitType::Queen, Team::White, "Za4"),
+            (UnitType::Comet, Team::Neutral, "xb2"),
+        ]);
+        let mut ai_cache = AICache::default();
+        for depth in 1..=3 {
+            let chosen = next_move(
+                &board,
+                &units,
+                Team::Black,
+                depth,
+                Variant::Wandering,
+                &mut ai_cache,
+            );
+            assert_eq!(chosen, Some(game_move("Za1", "Za4")), "depth {}", depth);
+        }
+    }
+
+    #[test]
+    fn summons_are_compared_to_the_best_move() {
+        let board = Board::new(4);
+        let mut ai_cache = AICache::default();
+        let summon = |cell: &str, unit_type| {
+            let coords = CellCoordinates::from_display(cell).unwrap();
+            Unit::new(unit_type, Team::White, coords)
+        };
+        let units = position(&[
+            (UnitType::King, Team::White, "Yd4"),
+            (UnitType::Rook, Team::White, "Za1"),
+            (UnitType::King, Team::Black, "zd1"),
+            (UnitType::Queen, Team::Black, "Za4"),
+        ]);
+        let summons = [(summon("Yc4", UnitType::Knight), 1)];
+        for depth in 1..=3 {
+            let next_move = Some(game_move("Za1", "Za4"));
+            let chosen = best_summon(
+                &board,
+                &units,
+                &summons,
+                next_move,
+                Team::White,
+                depth,
+                Variant::Reinforcements,
+                &mut ai_cache,
+            );
+            assert!(chosen.is_none(), "depth {}", depth);
+        }
+
+        let units = position(&[
+            (UnitType::King, Team::White, "Yd4"),
+            (UnitType::King, Team::Black, "zd1"),
+        ]);
+        let summons = [(summon("Yc4", UnitType::Rook), 2)];
+        for depth in 1..=3 {
+            let next_move = next_move(
+                &board,
+                &units,
+                Team::White,
+                depth,
+                Variant::Reinforcements,
+                &mut ai_cache,
+            );
+            let chosen = best_summon(
+                &board,
+                &units,
+                &summons,
+                next_move,
+                Team::White,
+                depth,
+                Variant::Reinforcements,
+                &mut ai_cache,
+            );
+            let coords = chosen.map(|unit| unit.coords);
+            assert_eq!(coords, Some(summons[0].0.coords), "depth {}", depth);
+        }
+    }
+}
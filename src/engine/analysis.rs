@@ -0,0 +1,194 @@
+//! Going over the moves of a game with the AI. Every move is compared to the best move the AI
+//! finds in its position, and moves that give up material compared to it are judged by how much.
+
+use crate::engine::ai::{self, AICache};
+use crate::engine::cell::Board;
+use crate::engine::movement::{GameMove, Variant};
+use crate::engine::notation;
+use crate::engine::units::{Team, Units};
+
+/// Searching deeper takes too long for a game of many moves on a big cube
+pub(crate) const ANALYSIS_DEPTH: u32 = 2;
+
+/// How many pawns worse than the best move a move has to be for each judgement
+const INACCURACY_LOSS: f32 = 0.5;
+const MISTAKE_LOSS: f32 = 1.;
+const BLUNDER_LOSS: f32 = 1.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Judgement {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Judgement {
+    fn from_loss(loss: f32) -> Option<Self> {
+        if loss >= BLUNDER_LOSS {
+            Some(Self::Blunder)
+        } else if loss >= MISTAKE_LOSS {
+            Some(Self::Mistake)
+        } else if loss >= INACCURACY_LOSS {
+            Some(Self::Inaccuracy)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Inaccuracy => "inaccuracy",
+            Self::Mistake => "mistake",
+            Self::Blunder => "blunder",
+        }
+    }
+
+    /// The mark put after the move, like in chess annotations
+    pub(crate) fn mark(self) -> &'static str {
+        match self {
+            Self::Inaccuracy => "?!",
+            Self::Mistake => "?",
+            Self::Blunder => "??",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct MoveAnalysis {
+    pub(crate) team: Team,
+    pub(crate) played: String,
+    /// The best move found by the AI, if it isn't the played move
+    pub(crate) best: Option<String>,
+    /// The material balance after the played move and the best reply to it, positive if white is
+    /// ahead
+    pub(crate) evaluation: f32,
+    /// How many pawns the played move is worse than the best move
+    pub(crate) loss: f32,
+    pub(crate) judgement: Option<Judgement>,
+}
+
+/// Only variants where the moves are made one at a time can be analysed, since the moves of
+/// simultaneous turns depend on each other
+pub(crate) fn can_analyse(variant: Variant) -> bool {
+    !variant.rules().simultaneous_moves
+}
+
+/// Compares `played`, the move `team` made with `units` on the board, to the best move found by
+/// the AI
+pub(crate) fn analyse_move(
+    board: &Board,
+    units: &Units,
+    team: Team,
+    played: GameMove,
+    variant: Variant,
+    ai_cache: &mut AICache,
+) -> MoveAnalysis {
+    let best = ai::next_move(board, units, team, ANALYSIS_DEPTH, variant, ai_cache)
+        .filter(|&best| best != played);
+    let mut value = |game_move| {
+        ai::move_value(
+            board,
+            units,
+            game_move,
+            team,
+            ANALYSIS_DEPTH - 1,
+            variant,
+            ai_cache,
+        )
+    };
+    let played_value = value(played);
+    // The search can value its own move lower than the played one, which then counts as the best
+    let loss = match best {
+        Some(best) => (value(best) - played_value).max(0.),
+        None => 0.,
+    };
+    MoveAnalysis {
+        team,
+        played: notation::write_move(played, board, units),
+        best: best.map(|best| notation::write_move(best, board, units)),
+        evaluation: played_value * team.sign() as f32,
+        loss,
+        judgement: Judgement::from_loss(loss),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::engine::cell::CellCoordinates;
+    use crate::engine::puzzles;
+    use crate::engine::units::{Unit, UnitType};
+
+    #[test]
+    fn moves_are_judged_against_the_best_move() {
+        let board = Board::new(3);
+        let mut rng = StdRng::seed_from_u64(5);
+        let starting_units = Units::game_starting_configuration(3);
+        let positions = puzzles::random_game(
+            &board,
+            &starting_units,
+            Team::White,
+            Variant::Standard,
+            20,
+            &mut rng,
+        );
+        let mut ai_cache = AICache::default();
+        let mut blunders = 0;
+        for (units, team) in positions {
+            for game_move in ai::get_possible_moves(&board, &units, team, Variant::Standard) {
+                let analysis = analyse_move(
+                    &board,
+                    &units,
+                    team,
+                    game_move,
+                    Variant::Standard,
+                    &mut ai_cache,
+                );
+                assert_eq!(analysis.judgement, Judgement::from_loss(analysis.loss));
+                if analysis.best.is_none() {
+                    assert_eq!(analysis.judgement, None);
+                }
+                if analysis.judgement == Some(Judgement::Blunder) {
+                    blunders += 1;
+                }
+            }
+        }
+        assert!(blunders > 0, "No blunders among the moves of a random game");
+    }
+
+    #[test]
+    fn quiet_moves_are_valued_after_the_best_reply() {
+        let board = Board::new(4);
+        let mut units = Units::default();
+        for (unit_type, team, cell) in [
+            (UnitType::King, Team::White, "Yd4"),
+            (UnitType::Rook, Team::White, "Za1"),
+            (UnitType::King, Team::Black, "zd1"),
+            (UnitType::Queen, Team::Black, "Za4"),
+        ] {
+            let coords = CellCoordinates::from_display(cell).unwrap();
+            units.add_unit(Unit::new(unit_type, team, coords));
+        }
+        let king = CellCoordinates::from_display("Yd4").unwrap();
+        let king_move = ai::get_possible_moves(&board, &units, Team::White, Variant::Standard)
+            .into_iter()
+            .find(|game_move| game_move.from == king)
+            .unwrap();
+        let mut ai_cache = AICache::default();
+        // The queen takes the rook after any king move
+        let analysis = analyse_move(
+            &board,
+            &units,
+            Team::White,
+            king_move,
+            Variant::Standard,
+            &mut ai_cache,
+        );
+        assert_eq!(analysis.evaluation, -9.);
+        assert_eq!(analysis.judgement, Some(Judgement::Blunder));
+        assert_eq!(analysis.best.as_deref(), Some("♖xZa4"));
+    }
+}
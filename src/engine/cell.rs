@@ -0,0 +1,472 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::direction::{CartesianDirection, RadialDirection, RADIAL_TO_CARTESIAN};
+use crate::engine::ObjectId;
+
+#[derive(Clone, Debug)]
+pub(crate) struct Cell {
+    pub(crate) plane: ObjectId,
+    pub(crate) selected_unit_can_move_to: bool,
+    pub(crate) coords: CellCoordinates,
+    pub(crate) color: CellColor,
+}
+
+impl Cell {
+    pub(crate) fn new(plane: ObjectId, coords: CellCoordinates, cell_color: CellColor) -> Self {
+        Self {
+            plane,
+            coords,
+            selected_unit_can_move_to: false,
+            color: cell_color,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub(crate) enum CellColor {
+    Bright,
+    Mid,
+    Dark,
+}
+
+impl CellColor {
+    pub(crate) const ALL: [CellColor; 3] = [Self::Bright, Self::Mid, Self::Dark];
+
+    /// The position of the color in the colors of a palette
+    pub(crate) fn index(&self) -> usize {
+        match self {
+            Self::Bright => 0,
+            Self::Mid => 1,
+            Self::Dark => 2,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+pub(crate) struct CellCoordinates {
+    x: u32,
+    y: u32,
+    z: u32,
+    normal_is_positive: bool,
+}
+
+impl CellCoordinates {
+    pub(crate) fn new(x: u32, y: u32, z: u32, normal_is_positive: bool) -> Self {
+        CellCoordinates {
+            x,
+            y,
+            z,
+            normal_is_positive,
+        }
+    }
+
+    pub(crate) fn get_adjacent(&self, cube_side_length: u32) -> [CellCoordinates; 4] {
+        let mut output: [CellCoordinates; 4] = Default::default();
+        let mut i = 0;
+        for direction in CartesianDirection::directions() {
+            let adjacent = self.get_cell_in_direction(direction, cube_side_length);
+
+            if adjacent.is_none() {
+                continue;
+            }
+
+            if i >= 4 {
+                // More than 4 directions means there is no zero-field in the coordinates
+                break;
+            }
+
+            output[i] = adjacent.unwrap().0;
+            i += 1;
+        }
+        output
+    }
+
+    /// Returns a tuple where the second element denotes if the new cell is on a different side
+    /// than the first
+    pub(crate) fn get_cell_in_direction(
+        &self,
+        direction: CartesianDirection,
+        cube_side_length: u32,
+    ) -> Option<(CellCoordinates, bool)> {
+        // Directions which would go out of and into the cube have no step
+        let step = STEPS[self.normal_direction().index()][direction.index()]?;
+        Some(self.take_step(step, cube_side_length))
+    }
+
+    pub(crate) fn get_cell_in_radial_direction(
+        &self,
+        radial_direction: RadialDirection,
+        cube_side_length: u32,
+    ) -> Option<(CellCoordinates, bool)> {
+        // The direction is not possible to go in on the sides around its rotation axis
+        let step = RADIAL_STEPS[self.normal_direction().index()][radial_direction.index()]?;
+        Some(self.take_step(step, cube_side_length))
+    }
+
+    fn take_step(&self, step: Step, cube_side_length: u32) -> (CellCoordinates, bool) {
+        let mut adjacent = *self;
+        let relevant_coordinate = adjacent[step.axis] as i32 + step.delta;
+        // We start counting coordinates at 1 since 0 represents on the plane
+        if relevant_coordinate <= 0 || relevant_coordinate > cube_side_length as i32 {
+            let fold = step.fold;
+            adjacent.normal_is_positive = fold.normal_is_positive;
+            adjacent[fold.old_normal_axis] = if fold.to_far_end { cube_side_length } else { 1 };
+            adjacent[step.axis] = 0;
+            return (adjacent, true);
+        }
+        adjacent[step.axis] = relevant_coordinate as u32;
+        (adjacent, false)
+    }
+
+    /// Gets the diagonal that can be reached by walking in the cartesian directions consecutively,
+    /// does not return true neigbors. The second element of the second element denotes if the new
+    /// cell is on a different side than the first
+    pub(crate) fn get_diagonal(
+        &self,
+        diagonal: (CartesianDirection, CartesianDirection),
+        cube_side_length: u32,
+    ) -> Option<(CellCoordinates, bool)> {
+        let cell1 = self.get_cell_in_direction(diagonal.0, cube_side_length)?;
+        let cell2 = cell1
+            .0
+            .get_cell_in_direction(diagonal.1, cube_side_length)?;
+        if cell1.1 && cell2.1 {
+            // The second element tells us if the transformation went over a cube edge, in this
+            // case we are in a corner, which means we have a true neighbor in cell2
+            return None;
+        }
+
+        Some((cell2.0, cell1.1 || cell2.1))
+    }
+
+    pub(crate) fn normal_is_positive(&self) -> bool {
+        self.normal_is_positive
+    }
+
+    pub(crate) fn normal_direction(&self) -> CartesianDirection {
+        if self.z == 0 {
+            if self.normal_is_positive {
+                CartesianDirection::Z
+            } else {
+                CartesianDirection::NegZ
+            }
+        } else if self.y == 0 {
+            if self.normal_is_positive {
+                CartesianDirection::Y
+            } else {
+                CartesianDirection::NegY
+            }
+        } else if self.x == 0 {
+            if self.normal_is_positive {
+                CartesianDirection::X
+            } else {
+                CartesianDirection::NegX
+            }
+        } else {
+            panic!("No zero field on CellCoordinates: {:?}", self);
+        }
+    }
+
+    pub(crate) fn opposite(&self, cube_side_length: u32) -> CellCoordinates {
+        let mut out = *self;
+        out.normal_is_positive = !out.normal_is_positive;
+        if out.x != 0 {
+            out.x = cube_side_length + 1 - out.x;
+        }
+        if out.y != 0 {
+            out.y = cube_side_length + 1 - out.y;
+        }
+        if out.z != 0 {
+            out.z = cube_side_length + 1 - out.z;
+        }
+        out
+    }
+
+    pub(crate) fn display(&self) -> String {
+        let mut output = match self.normal_direction().abs() {
+            CartesianDirection::X => "x",
+            CartesianDirection::Y => "y",
+            CartesianDirection::Z => "z",
+            _ => unreachable!(),
+        }
+        .to_string();
+        if self.normal_is_positive {
+            output = output.to_uppercase();
+        }
+
+        let mut second_axis = false;
+        for i in 0..3 {
+            if self[i] == 0 {
+                continue;
+            }
+            if second_axis {
+                output.push_str(&self[i].to_string());
+            } else {
+                output.push(char::from(b'a' + self[i] as u8 - 1));
+            }
+            second_axis = true;
+        }
+        output
+    }
+
+    /// Reads the text written by [`CellCoordinates::display`]. Doesn't know the size of the cube,
+    /// so the caller has to check that the cell is on it.
+    pub(crate) fn from_display(text: &str) -> Result<Self, CellParseError> {
+        let mut chars = text.chars();
+        let side = chars.next().ok_or(CellParseError::Empty)?;
+        let normal_axis = match side.to_ascii_lowercase() {
+            'x' => 0,
+            'y' => 1,
+            'z' => 2,
+            _ => return Err(CellParseError::Side(side)),
+        };
+        let column = match chars.next() {
+            Some(column @ 'a'..='z') => column as u32 - 'a' as u32 + 1,
+            column => return Err(CellParseError::Column(column)),
+        };
+        let row = chars.as_str();
+        let row = match row.parse() {
+            Ok(row) if row > 0 => row,
+            _ => return Err(CellParseError::Row(row.to_string())),
+        };
+
+        let mut coords = CellCoordinates::new(0, 0, 0, side.is_ascii_uppercase());
+        let mut values = [column, row].into_iter();
+        for axis in (0..3).filter(|&axis| axis != normal_axis) {
+            coords[axis] = values.next().unwrap();
+        }
+        Ok(coords)
+    }
+}
+
+/// A step of a cell along its side, see [`STEPS`]
+#[derive(Clone, Copy)]
+struct Step {
+    /// The coordinate that changes
+    axis: usize,
+    /// -1 or 1
+    delta: i32,
+    /// Where the cell ends up if the step goes over the edge of the side
+    fold: EdgeFold,
+}
+
+/// How a cell is moved to the next side when it steps over an edge. The coordinate along the axis
+/// of the step becomes 0, since that axis is the normal of the new side.
+#[derive(Clone, Copy)]
+struct EdgeFold {
+    /// The axis of the old normal, whose coordinate is 0 on the old side
+    old_normal_axis: usize,
+    /// If the coordinate on the old normal axis becomes the side length rather than 1
+    to_far_end: bool,
+    normal_is_positive: bool,
+}
+
+/// The step in each cartesian direction on each side, indexed by [`CartesianDirection::index`]
+/// of the normal of the side and then of the direction. Directions on the axis of the normal have
+/// no step.
+const STEPS: [[Option<Step>; 6]; 6] = {
+    let mut table = [[None; 6]; 6];
+    let directions = CartesianDirection::directions();
+    let mut i = 0;
+    while i < directions.len() {
+        let normal = directions[i];
+        let mut j = 0;
+        while j < directions.len() {
+            let direction = directions[j];
+            if normal.axis_num() != direction.axis_num() {
+                table[i][j] = Some(Step {
+                    axis: direction.axis_num() as usize,
+                    delta: if direction.is_negative() { -1 } else { 1 },
+                    fold: EdgeFold {
+                        old_normal_axis: normal.axis_num() as usize,
+                        to_far_end: !normal.is_negative(),
+                        normal_is_positive: !direction.is_negative(),
+                    },
+                });
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    table
+};
+
+/// [`STEPS`] for radial directions, indexed by [`CartesianDirection::index`] of the normal of the
+/// side and then [`RadialDirection::index`], since these are taken by every step of sliding units
+const RADIAL_STEPS: [[Option<Step>; 6]; 6] = {
+    let mut table = [[None; 6]; 6];
+    let mut i = 0;
+    while i < table.len() {
+        let mut j = 0;
+        while j < table[i].len() {
+            if let Some(direction) = RADIAL_TO_CARTESIAN[i][j] {
+                table[i][j] = STEPS[i][direction.index()];
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    table
+};
+
+/// Why a cell written like [`CellCoordinates::display`] couldn't be read
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum CellParseError {
+    Empty,
+    /// The side has to be one of x, y and z, in uppercase for the positive side
+    Side(char),
+    /// The column has to be a lowercase letter
+    Column(Option<char>),
+    /// The row has to be a number from 1
+    Row(String),
+}
+
+impl fmt::Display for CellParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "the cell is empty"),
+            Self::Side(side) => write!(f, "{} is not a side, expected x, y or z", side),
+            Self::Column(Some(column)) => write!(f, "{} is not a column", column),
+            Self::Column(None) => write!(f, "the column is missing"),
+            Self::Row(row) if row.is_empty() => write!(f, "the row is missing"),
+            Self::Row(row) => write!(f, "{} is not a row", row),
+        }
+    }
+}
+
+impl Index<usize> for CellCoordinates {
+    type Output = u32;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        match index {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("index out of bounds"),
+        }
+    }
+}
+
+impl IndexMut<usize> for CellCoordinates {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        match index {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            _ => panic!("index out of bounds"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Board {
+    board: BTreeMap<CellCoordinates, Cell>,
+    pub(crate) cube_side_length: u32,
+}
+
+impl Board {
+    pub(crate) fn get_cell(&self, coords: CellCoordinates) -> Option<&Cell> {
+        self.board.get(&coords)
+    }
+    pub(crate) fn get_cell_mut(&mut self, coords: CellCoordinates) -> Option<&mut Cell> {
+        self.board.get_mut(&coords)
+    }
+    pub(crate) fn new(cube_side_length: u32) -> Self {
+        Board {
+            board: BTreeMap::new(),
+            cube_side_length,
+        }
+    }
+
+    pub(crate) fn new_cell(&mut self, coords: CellCoordinates, cell: Cell) {
+        self.board.insert(coords, cell);
+    }
+
+    pub(crate) fn get_all_cells(&self) -> Vec<&Cell> {
+        self.board.values().collect()
+    }
+
+    #[must_use]
+    pub(crate) fn get_all_cells_mut(&mut self) -> Vec<&mut Cell> {
+        self.board.values_mut().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_round_trips() {
+        let cube_side_length = 4;
+        for x in 0..=cube_side_length {
+            for y in 0..=cube_side_length {
+                for z in 0..=cube_side_length {
+                    if [x, y, z].iter().filter(|&&value| value == 0).count() != 1 {
+                        continue;
+                    }
+                    for normal_is_positive in [true, false] {
+                        let coords = CellCoordinates::new(x, y, z, normal_is_positive);
+                        assert_eq!(CellCoordinates::from_display(&coords.display()), Ok(coords));
+                    }
+                }
+            }
+        }
+
+        assert_eq!(
+            CellCoordinates::from_display(""),
+            Err(CellParseError::Empty)
+        );
+        assert_eq!(
+            CellCoordinates::from_display("wa1"),
+            Err(CellParseError::Side('w'))
+        );
+        assert_eq!(
+            CellCoordinates::from_display("XA1"),
+            Err(CellParseError::Column(Some('A')))
+        );
+        assert_eq!(
+            CellCoordinates::from_display("Xb0"),
+            Err(CellParseError::Row("0".to_string()))
+        );
+    }
+
+    #[test]
+    fn steps_fold_over_edges() {
+        let cube_side_length = 3;
+        let coords = CellCoordinates::new(1, 2, 0, true);
+        assert_eq!(
+            coords.get_cell_in_direction(CartesianDirection::X, cube_side_length),
+            Some((CellCoordinates::new(2, 2, 0, true), false))
+        );
+        assert_eq!(
+            coords.get_cell_in_direction(CartesianDirection::NegX, cube_side_length),
+            Some((CellCoordinates::new(0, 2, 3, false), true))
+        );
+        assert_eq!(
+            coords.get_cell_in_direction(CartesianDirection::Z, cube_side_length),
+            None
+        );
+
+        // Going around the cube in a radial direction ends where it started
+        for radial_direction in RadialDirection::directions() {
+            let Some(mut cell) = coords
+                .get_cell_in_radial_direction(radial_direction, cube_side_length)
+                .map(|(cell, _)| cell)
+            else {
+                continue;
+            };
+            for _ in 1..4 * cube_side_length {
+                cell = cell
+                    .get_cell_in_radial_direction(radial_direction, cube_side_length)
+                    .unwrap()
+                    .0;
+            }
+            assert_eq!(cell, coords);
+        }
+    }
+}
@@ -0,0 +1,324 @@
+//! CubeFEN, a one line text form of a position, like FEN is for chess. It is made of fields
+//! separated by spaces:
+//!
+//! 1. The number of cells along each edge of the cube
+//! 2. The units separated by commas, or `-` if there are none. Every unit is written as
+//!    - its letter, uppercase for white and lowercase for black, after a `~` for neutral units.
+//!      The letters are K, Q, R, B, N, P, A for the archbishop, M for the chancellor, E for the
+//!      amazon, W for the wazir, F for the ferz and C for the comet. Units from
+//!      [`crate::engine::pieces`] use the letter they are given there.
+//!    - its x, y and z coordinates as digits, then `+` or `-` for the direction of the normal of the
+//!      side it is on
+//!    - for pawns, the letter of the axis it walks around, uppercase for clockwise and lowercase
+//!      for counterclockwise, then `*` if it has moved
+//!    - `:` and its level, if it has gained any
+//! 3. The team to move, `w` or `b`
+//! 4. The energy of white and black, separated by `/`
+//! 5. The name of the variant
+//!
+//! Only the two kings on opposite corners of the 2-cube, with black to move, is
+//! `2 K202+,k101- b 0/0 standard`. A pawn that has moved could be `P120+Y*`.
+//! The moves secretly picked in the duel variant aren't part of it, so that a shared position
+//! doesn't give them away.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::engine::cell::CellCoordinates;
+use crate::engine::direction::RadialDirection;
+use crate::engine::movement::Variant;
+use crate::engine::pieces;
+use crate::engine::units::{Team, Unit, UnitType, Units};
+
+#[derive(Clone, Debug)]
+pub(crate) struct CubeFen {
+    pub(crate) cube_side_length: u32,
+    pub(crate) units: Units,
+    pub(crate) turn: Team,
+    /// Indexed by [`Team::index`]
+    pub(crate) energy: [u32; 2],
+    pub(crate) variant: Variant,
+}
+
+impl fmt::Display for CubeFen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let units: Vec<String> = self.units.all_units_iter().map(write_unit).collect();
+        let units = match units.is_empty() {
+            true => "-".to_string(),
+            false => units.join(","),
+        };
+        let turn = match self.turn {
+            Team::Black => 'b',
+            _ => 'w',
+        };
+        write!(
+            f,
+            "{} {} {} {}/{} {}",
+            self.cube_side_length,
+            units,
+            turn,
+            self.energy[0],
+            self.energy[1],
+            self.variant.name()
+        )
+    }
+}
+
+impl FromStr for CubeFen {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        let [cube_side_length, units, turn, energy, variant] = fields[..] else {
+            return Err(format!(
+                "A CubeFEN has 5 fields separated by spaces, not {}",
+                fields.len()
+            ));
+        };
+
+        let cube_side_length = match cube_side_length.parse() {
+            // The coordinates are written as single digits
+            Ok(cube_side_length @ 2..=9) => cube_side_length,
+            _ => return Err(format!("{} is not a cube size", cube_side_length)),
+        };
+        let mut parsed_units = Units::default();
+        if units != "-" {
+            for unit in units.split(',') {
+                let unit = read_unit(unit, cube_side_length).ok_or(format!(
+                    "{} is not a unit on a {}-cube",
+                    unit, cube_side_length
+                ))?;
+                if parsed_units.is_unit_at(unit.coords) {
+                    return Err(format!("Two units on {}", unit.coords.display()));
+                }
+                parsed_units.add_unit(unit);
+            }
+        }
+        let turn = match turn {
+            "w" => Team::White,
+            "b" => Team::Black,
+            _ => return Err(format!("{} is not a team, it has to be w or b", turn)),
+        };
+        let energy = match energy.split_once('/') {
+            Some((white, black)) => [white.parse(), black.parse()],
+            None => return Err(format!("{} is not the energy of both teams", energy)),
+        };
+        let [Ok(white_energy), Ok(black_energy)] = energy else {
+            return Err("The energy has to be a number".to_string());
+        };
+        let variant = Variant::from_name(variant).ok_or(format!("Unknown variant {}", variant))?;
+
+        Ok(CubeFen {
+            cube_side_length,
+            units: parsed_units,
+            turn,
+            energy: [white_energy, black_energy],
+            variant,
+        })
+    }
+}
+
+fn write_unit(unit: &Unit) -> String {
+    let letter = match unit.unit_type {
+        UnitType::King => 'K',
+        UnitType::Queen => 'Q',
+        UnitType::Rook => 'R',
+        UnitType::Bishop => 'B',
+        UnitType::Knight => 'N',
+        UnitType::Pawn(_, _) => 'P',
+        UnitType::Comet => 'C',
+        UnitType::Archbishop => 'A',
+        UnitType::Chancellor => 'M',
+        UnitType::Amazon => 'E',
+        UnitType::Wazir => 'W',
+        UnitType::Ferz => 'F',
+        UnitType::Defined(index) => pieces::definition(index).letter,
+    };
+    let mut output = match unit.team {
+        Team::White => letter.to_string(),
+        Team::Black => letter.to_ascii_lowercase().to_string(),
+        Team::Neutral => format!("~{}", letter),
+    };
+    for axis in 0..3 {
+        output.push_str(&unit.coords[axis].to_string());
+    }
+    output.push(match unit.coords.normal_is_positive() {
+        true => '+',
+        false => '-',
+    });
+    if let UnitType::Pawn(direction, has_moved) = unit.unit_type {
+        output.push(direction_letter(direction));
+        if has_moved {
+            output.push('*');
+        }
+    }
+    if unit.level > 0 {
+        output.push_str(&format!(":{}", unit.level));
+    }
+    output
+}
+
+/// Returns None if the text isn't a unit, or if the unit isn't on a cell of the cube
+fn read_unit(text: &str, cube_side_length: u32) -> Option<Unit> {
+    let (text, level) = match text.split_once(':') {
+        Some((text, level)) => (text, level.parse().ok()?),
+        None => (text, 0),
+    };
+    let (text, neutral) = match text.strip_prefix('~') {
+        Some(text) => (text, true),
+        None => (text, false),
+    };
+    let mut chars = text.chars();
+    let letter = chars.next()?;
+    let team = match (neutral, letter.is_ascii_uppercase()) {
+        (true, true) => Team::Neutral,
+        (false, true) => Team::White,
+        (false, false) => Team::Black,
+        (true, false) => return None,
+    };
+
+    let mut coordinates = [0; 3];
+    for coordinate in &mut coordinates {
+        *coordinate = chars.next()?.to_digit(10)?;
+    }
+    let normal_is_positive = match chars.next()? {
+        '+' => true,
+        '-' => false,
+        _ => return None,
+    };
+    let [x, y, z] = coordinates;
+    let on_cube = coordinates
+        .iter()
+        .filter(|&&coordinate| coordinate == 0)
+        .count()
+        == 1
+        && coordinates
+            .iter()
+            .all(|&coordinate| coordinate <= cube_side_length);
+    if !on_cube {
+        return None;
+    }
+
+    let unit_type = match letter.to_ascii_uppercase() {
+        'K' => UnitType::King,
+        'Q' => UnitType::Queen,
+        'R' => UnitType::Rook,
+        'B' => UnitType::Bishop,
+        'N' => UnitType::Knight,
+        'C' => UnitType::Comet,
+        'A' => UnitType::Archbishop,
+        'M' => UnitType::Chancellor,
+        'E' => UnitType::Amazon,
+        'W' => UnitType::Wazir,
+        'F' => UnitType::Ferz,
+        'P' => {
+            let direction = letter_direction(chars.next()?)?;
+            let has_moved = chars.as_str() == "*";
+            if has_moved {
+                chars.next();
+            }
+            UnitType::Pawn(direction, has_moved)
+        }
+        letter => pieces::unit_type_of_letter(letter)?,
+    };
+    if chars.next().is_some() {
+        return None;
+    }
+
+    let mut unit = Unit::new(
+        unit_type,
+        team,
+        CellCoordinates::new(x, y, z, normal_is_positive),
+    );
+    unit.level = level;
+    Some(unit)
+}
+
+/// Uppercase for clockwise and lowercase for counterclockwise
+fn direction_letter(direction: RadialDirection) -> char {
+    match direction {
+        RadialDirection::ClockwiseX => 'X',
+        RadialDirection::CounterX => 'x',
+        RadialDirection::ClockwiseY => 'Y',
+        RadialDirection::CounterY => 'y',
+        RadialDirection::ClockwiseZ => 'Z',
+        RadialDirection::CounterZ => 'z',
+    }
+}
+
+fn letter_direction(letter: char) -> Option<RadialDirection> {
+    match letter {
+        'X' => Some(RadialDirection::ClockwiseX),
+        'x' => Some(RadialDirection::CounterX),
+        'Y' => Some(RadialDirection::ClockwiseY),
+        'y' => Some(RadialDirection::CounterY),
+        'Z' => Some(RadialDirection::ClockwiseZ),
+        'z' => Some(RadialDirection::CounterZ),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CubeFen;
+    use crate::engine::cell::CellCoordinates;
+    use crate::engine::movement::Variant;
+    use crate::engine::units::{Team, Unit, UnitType, Units};
+
+    #[test]
+    fn cubefen_round_trips() {
+        let mut units = Units::game_starting_configuration(4);
+        if let Some(UnitType::Pawn(_, has_moved)) = units
+            .all_units_iter_mut()
+            .find(|unit| matches!(unit.unit_type, UnitType::Pawn(_, _)))
+            .map(|unit| &mut unit.unit_type)
+        {
+            *has_moved = true;
+        }
+        let mut comet = Unit::new(
+            UnitType::Comet,
+            Team::Neutral,
+            CellCoordinates::new(1, 1, 0, true),
+        );
+        comet.level = 2;
+        units.add_unit(comet);
+        for (unit_type, team, y) in [
+            (UnitType::Archbishop, Team::White, 1),
+            (UnitType::Chancellor, Team::Black, 2),
+        ] {
+            units.add_unit(Unit::new(
+                unit_type,
+                team,
+                CellCoordinates::new(2, y, 0, true),
+            ));
+        }
+        let position = CubeFen {
+            cube_side_length: 4,
+            units,
+            turn: Team::Black,
+            energy: [3, 12],
+            variant: Variant::Wandering,
+        };
+
+        let text = position.to_string();
+        let parsed: CubeFen = text.parse().unwrap();
+        assert_eq!(parsed.to_string(), text);
+        assert_eq!(parsed.turn, Team::Black);
+        assert_eq!(parsed.energy, [3, 12]);
+        assert_eq!(parsed.variant, Variant::Wandering);
+        for (unit, parsed_unit) in position
+            .units
+            .all_units_iter()
+            .zip(parsed.units.all_units_iter())
+        {
+            assert_eq!(unit.unit_type, parsed_unit.unit_type);
+            assert_eq!(unit.team, parsed_unit.team);
+            assert_eq!(unit.coords, parsed_unit.coords);
+            assert_eq!(unit.level, parsed_unit.level);
+        }
+
+        assert!("4 K404+ w 0/0".parse::<CubeFen>().is_err());
+        assert!("4 K444+ w 0/0 standard".parse::<CubeFen>().is_err());
+        assert!("4 K404+,k404+ w 0/0 standard".parse::<CubeFen>().is_err());
+    }
+}
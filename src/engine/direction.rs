@@ -0,0 +1,256 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum RadialDirection {
+    ClockwiseX,
+    CounterX,
+    ClockwiseY,
+    CounterY,
+    ClockwiseZ,
+    CounterZ,
+}
+
+impl RadialDirection {
+    /// When on a side that has normal vector on the same axis as one of the elements, start
+    /// walking toward negative coordinates to continue walking in the same radial direction
+    const fn negate_movement_on(&self) -> [CartesianDirection; 2] {
+        match self {
+            Self::ClockwiseX | Self::CounterX => [CartesianDirection::Y, CartesianDirection::NegZ],
+            Self::ClockwiseY | Self::CounterY => [CartesianDirection::NegX, CartesianDirection::Z],
+            Self::ClockwiseZ | Self::CounterZ => [CartesianDirection::X, CartesianDirection::NegY],
+        }
+    }
+    const fn is_counterclockwise(&self) -> bool {
+        match self {
+            Self::ClockwiseX | Self::ClockwiseY | Self::ClockwiseZ => false,
+            Self::CounterX | Self::CounterY | Self::CounterZ => true,
+        }
+    }
+
+    pub(crate) const fn rotation_axis(&self) -> CartesianDirection {
+        match self {
+            Self::ClockwiseX => CartesianDirection::X,
+            Self::CounterX => CartesianDirection::NegX,
+            Self::ClockwiseY => CartesianDirection::Y,
+            Self::CounterY => CartesianDirection::NegY,
+            Self::ClockwiseZ => CartesianDirection::Z,
+            Self::CounterZ => CartesianDirection::NegZ,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn opposite(&self) -> RadialDirection {
+        match self {
+            Self::ClockwiseX => Self::CounterX,
+            Self::CounterX => Self::ClockwiseX,
+            Self::ClockwiseY => Self::CounterY,
+            Self::CounterY => Self::ClockwiseY,
+            Self::ClockwiseZ => Self::CounterZ,
+            Self::CounterZ => Self::ClockwiseZ,
+        }
+    }
+
+    /// Looked up in [`RADIAL_TO_CARTESIAN`], since this runs for every step of sliding units.
+    /// Returns None if the normal is on the same axis as the rotation axis
+    pub(crate) fn to_cartesian_direction(
+        self,
+        normal: CartesianDirection,
+    ) -> Option<CartesianDirection> {
+        RADIAL_TO_CARTESIAN[normal.index()][self.index()]
+    }
+
+    const fn compute_cartesian_direction(
+        self,
+        normal: CartesianDirection,
+    ) -> Option<CartesianDirection> {
+        let rotation_axis_num = self.rotation_axis().axis_num();
+        if normal.axis_num() == rotation_axis_num {
+            return None;
+        }
+
+        let mut negate = false;
+        let negate_movement_on_axes = self.negate_movement_on();
+        let mut i = 0;
+        while i < negate_movement_on_axes.len() {
+            if normal.is_same_as(negate_movement_on_axes[i]) {
+                negate = true;
+                break;
+            }
+            i += 1;
+        }
+
+        if self.is_counterclockwise() {
+            negate = !negate
+        }
+
+        // The direction is on the axis that is neither the normal nor the rotation axis
+        let axis_num = 3 - normal.axis_num() - rotation_axis_num;
+        Some(CartesianDirection::from_axis_num(axis_num, !negate))
+    }
+
+    /// The position of the direction in [`Self::directions`]
+    pub(crate) const fn index(&self) -> usize {
+        match self {
+            Self::ClockwiseX => 0,
+            Self::CounterX => 1,
+            Self::ClockwiseY => 2,
+            Self::CounterY => 3,
+            Self::ClockwiseZ => 4,
+            Self::CounterZ => 5,
+        }
+    }
+
+    pub(crate) const fn directions() -> [RadialDirection; 6] {
+        [
+            Self::ClockwiseX,
+            Self::CounterX,
+            Self::ClockwiseY,
+            Self::CounterY,
+            Self::ClockwiseZ,
+            Self::CounterZ,
+        ]
+    }
+}
+
+/// The cartesian direction that each radial direction goes in on each side, indexed by
+/// [`CartesianDirection::index`] of the normal of the side and then [`RadialDirection::index`]
+pub(crate) const RADIAL_TO_CARTESIAN: [[Option<CartesianDirection>; 6]; 6] = {
+    let mut table = [[None; 6]; 6];
+    let normals = CartesianDirection::directions();
+    let radial_directions = RadialDirection::directions();
+    let mut i = 0;
+    while i < normals.len() {
+        let mut j = 0;
+        while j < radial_directions.len() {
+            table[i][j] = radial_directions[j].compute_cartesian_direction(normals[i]);
+            j += 1;
+        }
+        i += 1;
+    }
+    table
+};
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub(crate) enum CartesianDirection {
+    X,
+    NegX,
+    Y,
+    NegY,
+    Z,
+    NegZ,
+}
+
+impl CartesianDirection {
+    pub(crate) const fn from_axis_num(axis_num: u32, is_positive: bool) -> Self {
+        let mut output = match axis_num {
+            0 => Self::X,
+            1 => Self::Y,
+            2 => Self::Z,
+            _ => unreachable!(),
+        };
+
+        if !is_positive {
+            output = output.opposite();
+        }
+        output
+    }
+
+    pub(crate) const fn is_negative(&self) -> bool {
+        match self {
+            Self::X | Self::Y | Self::Z => false,
+            Self::NegX | Self::NegY | Self::NegZ => true,
+        }
+    }
+
+    pub(crate) fn abs(&self) -> CartesianDirection {
+        match self {
+            Self::X | Self::NegX => Self::X,
+            Self::Y | Self::NegY => Self::Y,
+            Self::Z | Self::NegZ => Self::Z,
+        }
+    }
+
+    pub(crate) const fn axis_num(&self) -> u32 {
+        match self {
+            Self::X | Self::NegX => 0,
+            Self::Y | Self::NegY => 1,
+            Self::Z | Self::NegZ => 2,
+        }
+    }
+
+    /// Same as `==`, but usable in const functions
+    const fn is_same_as(&self, other: CartesianDirection) -> bool {
+        self.axis_num() == other.axis_num() && self.is_negative() == other.is_negative()
+    }
+
+    /// The position of the direction in [`Self::directions`]
+    pub(crate) const fn index(&self) -> usize {
+        match self {
+            Self::X => 0,
+            Self::NegX => 1,
+            Self::Y => 2,
+            Self::NegY => 3,
+            Self::Z => 4,
+            Self::NegZ => 5,
+        }
+    }
+
+    #[must_use]
+    pub(crate) const fn opposite(&self) -> CartesianDirection {
+        match self {
+            Self::X => Self::NegX,
+            Self::NegX => Self::X,
+            Self::Y => Self::NegY,
+            Self::NegY => Self::Y,
+            Self::Z => Self::NegZ,
+            Self::NegZ => Self::Z,
+        }
+    }
+
+    /// Returns the positive direction whose axis that is perpendicular to the two others. Returns
+    /// None if the two directions are on the same axis
+    pub(crate) fn get_perpendicular_axis(
+        &self,
+        other: CartesianDirection,
+    ) -> Option<CartesianDirection> {
+        if self.abs() == other.abs() {
+            // Both are on same axis, so there are two perpendiculars
+            return None;
+        }
+        for axis_num in 0..3 {
+            if self.axis_num() != axis_num && other.axis_num() != axis_num {
+                return Some(CartesianDirection::from_axis_num(axis_num, true));
+            }
+        }
+        None
+    }
+
+    pub(crate) const fn directions() -> [CartesianDirection; 6] {
+        [
+            Self::X,
+            Self::NegX,
+            Self::Y,
+            Self::NegY,
+            Self::Z,
+            Self::NegZ,
+        ]
+    }
+
+    pub(crate) fn diagonals() -> [(Self, Self); 12] {
+        let mut out = [(Self::X, Self::X); 12];
+        let mut i = 0;
+        for dir in Self::directions() {
+            for dir2 in Self::directions() {
+                if dir.abs() == dir2.abs()
+                    || out
+                        .iter()
+                        .any(|&diagonal| diagonal == (dir, dir2) || diagonal == (dir2, dir))
+                {
+                    continue;
+                } else {
+                    out[i] = (dir, dir2);
+                    i += 1;
+                }
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,41 @@
+use crate::engine::movement::GameMove;
+use crate::engine::units::{Team, Unit, UnitType, Units};
+
+/// Makes the white and the black move at the same time. A unit that moves away can't be captured
+/// on the cell it left. If both units go to the same cell, or swap places, they bounce back and
+/// neither move is made, which is signaled by returning None. Otherwise the captured units are
+/// returned.
+pub(crate) fn resolve_moves(units: &mut Units, moves: [GameMove; 2]) -> Option<Vec<Unit>> {
+    let [white_move, black_move] = moves;
+    if white_move.to == black_move.to
+        || (white_move.to == black_move.from && black_move.to == white_move.from)
+    {
+        return None;
+    }
+
+    // Lift both moving units first so that a unit leaving a cell isn't captured there
+    let moving_units = moves.map(|game_move| units.remove_unit(game_move.from));
+    let mut captured_units = Vec::new();
+    for (game_move, moving_unit) in moves.iter().zip(moving_units) {
+        let Some(mut moving_unit) = moving_unit else {
+            continue;
+        };
+        if let Some(captured_unit) = units.remove_unit(game_move.to) {
+            captured_units.push(captured_unit);
+        }
+        moving_unit.move_unit_to(game_move.to);
+        if let UnitType::Pawn(_, ref mut has_moved) = moving_unit.unit_type {
+            *has_moved = true;
+        }
+        units.add_unit(moving_unit);
+    }
+    Some(captured_units)
+}
+
+/// The moves in the order [`resolve_moves`] takes them
+pub(crate) fn ordered_moves(team: Team, own_move: GameMove, other_move: GameMove) -> [GameMove; 2] {
+    match team {
+        Team::Black => [other_move, own_move],
+        _ => [own_move, other_move],
+    }
+}
@@ -0,0 +1,173 @@
+//! Every move made in a game, grouped by the turn they were made in. The position at the start of
+//! every turn is kept as well, so turns can be undone and redone.
+
+use std::mem;
+use std::time::{Duration, Instant};
+
+use crate::engine::movement::GameMove;
+use crate::engine::units::{Team, Unit, Units};
+
+/// Everything that changes during a turn. Captured units and pawns that have moved are part of the
+/// units.
+#[derive(Clone, Debug)]
+pub(crate) struct Position {
+    pub(crate) units: Units,
+    pub(crate) turn: Team,
+    pub(crate) energy: [u32; 2],
+    pub(crate) hidden_moves: [Option<GameMove>; 2],
+    /// The moves made in the turn that led to this position
+    moves: Vec<RecordedMove>,
+}
+
+impl Position {
+    pub(crate) fn new(
+        units: Units,
+        turn: Team,
+        energy: [u32; 2],
+        hidden_moves: [Option<GameMove>; 2],
+    ) -> Self {
+        Position {
+            units,
+            turn,
+            energy,
+            hidden_moves,
+            moves: Vec::new(),
+        }
+    }
+}
+
+/// A move or summon as it was made in the game
+#[derive(Clone, Debug)]
+pub(crate) struct RecordedMove {
+    pub(crate) team: Team,
+    /// None for a unit summoned in the reinforcements variant
+    pub(crate) game_move: Option<GameMove>,
+    /// See [`crate::engine::notation`]
+    pub(crate) notation: String,
+    pub(crate) captured_unit: Option<Unit>,
+    /// The units blown up by the capture in the atomic variant, the capturing unit among them
+    pub(crate) exploded_units: Vec<Unit>,
+    /// If the unit moved over an edge of the cube onto another side
+    pub(crate) crossed_edge: bool,
+    /// When the move was made, counted from the start of the game. The moves of a loaded game get
+    /// the time they were loaded at.
+    pub(crate) time: Duration,
+}
+
+#[derive(Debug)]
+pub(crate) struct MoveHistory {
+    /// The position at the start of every turn so far, the last one being the current position
+    positions: Vec<Position>,
+    /// Positions that have been undone, the last one being the next to redo
+    undone: Vec<Position>,
+    /// The moves made since the last position
+    moves_this_turn: Vec<RecordedMove>,
+    started: Instant,
+}
+
+impl MoveHistory {
+    pub(crate) fn new(starting_position: Position) -> Self {
+        MoveHistory {
+            positions: vec![starting_position],
+            undone: Vec::new(),
+            moves_this_turn: Vec::new(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Ends the turn with the moves made since the last position. The undone positions can't be
+    /// redone after a new turn has been played.
+    pub(crate) fn record_position(&mut self, mut position: Position) {
+        position.moves = mem::take(&mut self.moves_this_turn);
+        self.positions.push(position);
+        self.undone.clear();
+    }
+
+    pub(crate) fn record_move(
+        &mut self,
+        team: Team,
+        game_move: Option<GameMove>,
+        notation: String,
+        captured_unit: Option<Unit>,
+    ) {
+        let crossed_edge = matches!(game_move, Some(game_move)
+            if game_move.from.normal_direction() != game_move.to.normal_direction());
+        self.moves_this_turn.push(RecordedMove {
+            team,
+            game_move,
+            notation,
+            captured_unit,
+            exploded_units: Vec::new(),
+            crossed_edge,
+            time: self.started.elapsed(),
+        });
+    }
+
+    /// Adds the units blown up by the last recorded move in the atomic variant to it
+    pub(crate) fn record_explosion(&mut self, exploded_units: Vec<Unit>) {
+        if let Some(recorded_move) = self.moves_this_turn.last_mut() {
+            recorded_move.exploded_units = exploded_units;
+        }
+    }
+
+    /// Goes back to the start of the previous turn, returning the position there. The moves made
+    /// since the last position are dropped.
+    pub(crate) fn undo(&mut self) -> Option<&Position> {
+        if self.positions.len() < 2 {
+            return None;
+        }
+        let current = self.positions.pop().unwrap();
+        self.undone.push(current);
+        self.moves_this_turn.clear();
+        self.positions.last()
+    }
+
+    /// Plays the last undone turn again, returning the position after it
+    pub(crate) fn redo(&mut self) -> Option<&Position> {
+        let position = self.undone.pop()?;
+        self.positions.push(position);
+        self.moves_this_turn.clear();
+        self.positions.last()
+    }
+
+    /// The turns played so far, which doesn't count the undone turns
+    pub(crate) fn turns_played(&self) -> usize {
+        self.positions.len() - 1
+    }
+
+    /// The turns that have been undone and can be redone
+    pub(crate) fn turns_undone(&self) -> usize {
+        self.undone.len()
+    }
+
+    /// The moves made in the turn that led to the current position
+    pub(crate) fn last_turn_moves(&self) -> &[RecordedMove] {
+        &self.positions.last().unwrap().moves
+    }
+
+    /// The moves of every turn played, including the undone turns that can still be redone
+    pub(crate) fn turns(&self) -> impl Iterator<Item = &[RecordedMove]> {
+        self.positions[1..]
+            .iter()
+            .chain(self.undone.iter().rev())
+            .map(|position| position.moves.as_slice())
+    }
+
+    /// Every move made so far, leaving out the undone ones
+    pub(crate) fn moves(&self) -> impl Iterator<Item = &RecordedMove> {
+        self.positions
+            .iter()
+            .flat_map(|position| &position.moves)
+            .chain(&self.moves_this_turn)
+    }
+
+    /// The position at the start of every turn played, the last one being the current position
+    pub(crate) fn positions(&self) -> &[Position] {
+        &self.positions
+    }
+
+    /// The position the game started from
+    pub(crate) fn starting_position(&self) -> &Position {
+        &self.positions[0]
+    }
+}
@@ -0,0 +1,1127 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::cell::{Board, CellCoordinates};
+use crate::engine::direction::{CartesianDirection, RadialDirection};
+use crate::engine::pieces::{self, MovePart};
+use crate::engine::rules::Ruleset;
+use crate::engine::units::*;
+
+/// Rule variants that can be played instead of standard rules
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[allow(unused)]
+pub(crate) enum Variant {
+    Standard,
+    /// Experimental: Units gain a level when capturing, unlocking extra movement
+    Experience,
+    /// Teams gain energy every turn, which can be spent to summon units on their home face
+    Reinforcements,
+    /// A neutral comet makes a random move after every full turn
+    Wandering,
+    /// Experimental: Both teams secretly pick a move, then the moves are made at the same time
+    Duel,
+    /// Every capture blows up the capturing unit and the units next to the captured one, see
+    /// [`explode`]
+    Atomic,
+}
+
+impl Variant {
+    pub(crate) const ALL: [Variant; 6] = [
+        Variant::Standard,
+        Variant::Experience,
+        Variant::Reinforcements,
+        Variant::Wandering,
+        Variant::Duel,
+        Variant::Atomic,
+    ];
+
+    /// The name used on the command line and in CubeFEN
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Variant::Standard => "standard",
+            Variant::Experience => "experience",
+            Variant::Reinforcements => "reinforcements",
+            Variant::Wandering => "wandering",
+            Variant::Duel => "duel",
+            Variant::Atomic => "atomic",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|variant| variant.name() == name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct GameMove {
+    pub(crate) from: CellCoordinates,
+    pub(crate) to: CellCoordinates,
+}
+
+/// The one place that decides if a move follows the rules. Moves from the player, the AI and
+/// anywhere else are checked here, so that they all agree.
+pub(crate) fn is_legal(
+    game_move: GameMove,
+    board: &Board,
+    units: &Units,
+    team: Team,
+    rules: Ruleset,
+) -> bool {
+    let Some(unit) = units.get_unit(game_move.from) else {
+        return false;
+    };
+    unit.team == team && get_legal_moves(unit, board, units, rules).contains(&game_move.to)
+}
+
+/// Moves a unit without checking that the move is legal, and returns the captured unit
+pub(crate) fn apply_move(game_move: GameMove, units: &mut Units, rules: Ruleset) -> Option<Unit> {
+    if !units.is_unit_at(game_move.from) {
+        return None;
+    }
+    let mut captured_unit = None;
+    if let Some(unit) = units.get_unit_mut(game_move.to) {
+        unit.dead = true;
+        captured_unit = Some(unit.clone());
+        units.remove_dead_units();
+    }
+
+    let unit = units.get_unit_mut(game_move.from).unwrap();
+    unit.move_unit_to(game_move.to);
+    if rules.capturing_gains_levels && captured_unit.is_some() {
+        unit.gain_level();
+    }
+    if let UnitType::Pawn(_, ref mut has_moved) = unit.unit_type {
+        *has_moved = true;
+    }
+    captured_unit
+}
+
+/// The explosion of a capture at `coords` in the atomic variant. It takes the capturing unit, which
+/// stands on `coords` after the move, and every unit on the cells next to it, also over edges, off
+/// the board. Only pawns survive it. Returns the units that were blown up.
+pub(crate) fn explode(
+    coords: CellCoordinates,
+    cube_side_length: u32,
+    units: &mut Units,
+) -> Vec<Unit> {
+    let mut exploded: Vec<Unit> = units.remove_unit(coords).into_iter().collect();
+    for adjacent in coords.get_adjacent(cube_side_length) {
+        let is_pawn = matches!(
+            units.get_unit(adjacent),
+            Some(Unit {
+                unit_type: UnitType::Pawn(..),
+                ..
+            })
+        );
+        if !is_pawn {
+            exploded.extend(units.remove_unit(adjacent));
+        }
+    }
+    exploded
+}
+
+/// The units a pawn can be promoted to, in the order they are offered
+pub(crate) const PROMOTIONS: [UnitType; 5] = [
+    UnitType::Queen,
+    UnitType::Rook,
+    UnitType::Bishop,
+    UnitType::Knight,
+    UnitType::Amazon,
+];
+
+/// The face each team starts with its king on, indexed by [`Team::index`]
+pub(crate) fn home_faces(cube_side_length: u32) -> [CartesianDirection; 2] {
+    let units = Units::game_starting_configuration(cube_side_length);
+    let home_face = |team: Team| {
+        units
+            .all_units_iter()
+            .find(|unit| unit.team == team && unit.unit_type == UnitType::King)
+            .map_or(CartesianDirection::Y, |king| king.coords.normal_direction())
+    };
+    [home_face(Team::White), home_face(Team::Black)]
+}
+
+/// If the move takes a pawn onto the home face of the other team, where it is promoted. Pawns
+/// aren't promoted in the duel variant, where both moves are made at the same time.
+pub(crate) fn promotes(
+    game_move: GameMove,
+    units: &Units,
+    variant: Variant,
+    home_faces: [CartesianDirection; 2],
+) -> bool {
+    let Some(unit) = units.get_unit(game_move.from) else {
+        return false;
+    };
+    variant.rules().pawns_promote
+        && unit.team != Team::Neutral
+        && matches!(unit.unit_type, UnitType::Pawn(_, _))
+        && game_move.to.normal_direction() == home_faces[unit.team.opposite().index()]
+}
+
+/// The moves of a unit that the rules of the variant allow, see [`Ruleset::allows_move`]
+pub(crate) fn get_legal_moves(
+    unit: &Unit,
+    board: &Board,
+    units: &Units,
+    rules: Ruleset,
+) -> Vec<CellCoordinates> {
+    let mut moves = get_unit_moves(unit, board, units);
+    moves.retain(|&to| {
+        rules.allows_move(
+            GameMove {
+                from: unit.coords,
+                to,
+            },
+            board,
+            units,
+        )
+    });
+    moves
+}
+
+pub(crate) fn get_unit_moves(unit: &Unit, board: &Board, units: &Units) -> Vec<CellCoordinates> {
+    let mut moves = Vec::new();
+    get_unit_moves_into(unit, board, units, &mut moves);
+    moves
+}
+
+/// Same as [`get_unit_moves`], but appends the moves to `output` instead of allocating a new
+/// vector, so that a buffer can be reused when generating moves for many units
+pub(crate) fn get_unit_moves_into(
+    unit: &Unit,
+    board: &Board,
+    units: &Units,
+    output: &mut Vec<CellCoordinates>,
+) {
+    let start = output.len();
+    match unit.unit_type {
+        UnitType::Rook => rook_movement(unit.coords, board, units, output),
+        UnitType::Bishop => bishop_movement(unit.coords, board, units, output),
+        UnitType::King => king_movement(unit.coords, board, units, output),
+        UnitType::Pawn(direction, has_moved) => {
+            pawn_movement(unit.coords, board, units, direction, has_moved, output)
+        }
+        UnitType::Knight => knight_movement(unit.coords, board, units, output),
+        UnitType::Queen => queen_movement(unit.coords, board, units, output),
+        UnitType::Comet => comet_movement(unit.coords, board, units, output),
+        UnitType::Archbishop => bishop_movement(unit.coords, board, units, output),
+        UnitType::Chancellor => rook_movement(unit.coords, board, units, output),
+        UnitType::Amazon => queen_movement(unit.coords, board, units, output),
+        UnitType::Wazir => wazir_movement(unit.coords, board, units, output),
+        UnitType::Ferz => ferz_movement(unit.coords, board, units, output),
+        UnitType::Defined(index) => defined_movement(index, unit.coords, board, units, output),
+    };
+    if unit.has_level_ability() {
+        for extra_move in level_ability_moves(unit, board, units) {
+            if !output[start..].contains(&extra_move) {
+                output.push(extra_move);
+            }
+        }
+    }
+
+    retain_from(output, start, |move_to| {
+        if move_to.normal_direction() == unit.coords.normal_direction()
+            || unit.unit_type.can_capture_over_edge()
+        {
+            units
+                .get_unit(move_to)
+                .is_none_or(|other_unit| other_unit.team != unit.team)
+        } else {
+            !units.is_unit_at(move_to)
+        }
+    });
+
+    // Added after the filter above, since knight moves can capture over an edge
+    let mut knight_moves = Vec::new();
+    extra_knight_moves(unit, board, &mut knight_moves);
+    for knight_move in knight_moves {
+        let own_unit = units
+            .get_unit(knight_move)
+            .filter(|other_unit| other_unit.team == unit.team);
+        if own_unit.is_none() && !output[start..].contains(&knight_move) {
+            output.push(knight_move);
+        }
+    }
+}
+
+/// The knight moves of units that make them on top of their other moves
+fn extra_knight_moves(unit: &Unit, board: &Board, output: &mut Vec<CellCoordinates>) {
+    let cube_side_length = board.cube_side_length;
+    if unit.unit_type.also_moves_like_knight() {
+        parts::get_knight_moves(unit.coords, 1, cube_side_length, output);
+    }
+    if let UnitType::Defined(index) = unit.unit_type {
+        for part in &pieces::definition(index).moves {
+            if let MovePart::Leaper { max_edge_crossings } = *part {
+                parts::get_knight_moves(unit.coords, max_edge_crossings, cube_side_length, output);
+            }
+        }
+    }
+}
+
+/// Like [`Vec::retain`], but only considers the elements from index `start` and onward
+fn retain_from(
+    output: &mut Vec<CellCoordinates>,
+    start: usize,
+    mut keep: impl FnMut(CellCoordinates) -> bool,
+) {
+    let mut write_index = start;
+    for read_index in start..output.len() {
+        if keep(output[read_index]) {
+            output[write_index] = output[read_index];
+            write_index += 1;
+        }
+    }
+    output.truncate(write_index);
+}
+
+fn king_movement(
+    unit_coords: CellCoordinates,
+    board: &Board,
+    units: &Units,
+    output: &mut Vec<CellCoordinates>,
+) {
+    parts::get_straight(unit_coords, 1, 0, board.cube_side_length, units, output);
+    parts::get_diagonals(unit_coords, 1, 0, board.cube_side_length, units, output);
+}
+
+fn bishop_movement(
+    unit_coords: CellCoordinates,
+    board: &Board,
+    units: &Units,
+    output: &mut Vec<CellCoordinates>,
+) {
+    parts::get_diagonals(
+        unit_coords,
+        u32::MAX,
+        1,
+        board.cube_side_length,
+        units,
+        output,
+    );
+}
+
+fn rook_movement(
+    unit_coords: CellCoordinates,
+    board: &Board,
+    units: &Units,
+    output: &mut Vec<CellCoordinates>,
+) {
+    parts::get_straight(
+        unit_coords,
+        u32::MAX,
+        1,
+        board.cube_side_length,
+        units,
+        output,
+    );
+}
+
+fn queen_movement(
+    unit_coords: CellCoordinates,
+    board: &Board,
+    units: &Units,
+    output: &mut Vec<CellCoordinates>,
+) {
+    parts::get_straight(
+        unit_coords,
+        u32::MAX,
+        1,
+        board.cube_side_length,
+        units,
+        output,
+    );
+    parts::get_diagonals(
+        unit_coords,
+        u32::MAX,
+        1,
+        board.cube_side_length,
+        units,
+        output,
+    );
+}
+
+fn pawn_movement(
+    unit_coords: CellCoordinates,
+    board: &Board,
+    units: &Units,
+    direction: RadialDirection,
+    has_moved: bool,
+    output: &mut Vec<CellCoordinates>,
+) {
+    if direction
+        .to_cartesian_direction(unit_coords.normal_direction())
+        .is_none()
+    {
+        // The pawn has a direction that can't be walked in on this side
+        return;
+    }
+    parts::get_cells_in_direction(
+        unit_coords,
+        if has_moved { 1 } else { 2 },
+        2,
+        board.cube_side_length,
+        units,
+        direction,
+        false,
+        output,
+    );
+
+    // Diagonal capture moves
+    // The filter for only capturing on same side is elsewhere
+    let start = output.len();
+    pawn_attacks(unit_coords, board, direction, output);
+    retain_from(output, start, |diagonal_coords| {
+        units.is_unit_at(diagonal_coords)
+    });
+}
+
+/// The cells a pawn walking in `direction` attacks, regardless of whether there is a unit there
+fn pawn_attacks(
+    unit_coords: CellCoordinates,
+    board: &Board,
+    direction: RadialDirection,
+    output: &mut Vec<CellCoordinates>,
+) {
+    let Some(forward) = direction.to_cartesian_direction(unit_coords.normal_direction()) else {
+        return;
+    };
+
+    for &diagonal in CartesianDirection::diagonals()
+        .iter()
+        .filter(|diag| diag.0 == forward || diag.1 == forward)
+    {
+        let Some(diagonal_coords) = unit_coords.get_diagonal(diagonal, board.cube_side_length)
+        else {
+            continue;
+        };
+        output.push(diagonal_coords.0);
+    }
+}
+
+/// All cells that units of `team` could capture on if an enemy unit stood there. Cells occupied
+/// by units of `team` itself are included, meaning they are defended.
+pub(crate) fn get_attacked_cells(
+    board: &Board,
+    units: &Units,
+    team: Team,
+) -> BTreeSet<CellCoordinates> {
+    let mut output = BTreeSet::new();
+    let mut attacked = Vec::new();
+    for unit in units.all_units_iter().filter(|unit| unit.team == team) {
+        attacked.clear();
+        match unit.unit_type {
+            UnitType::Pawn(direction, _) => {
+                pawn_attacks(unit.coords, board, direction, &mut attacked)
+            }
+            UnitType::Rook => rook_movement(unit.coords, board, units, &mut attacked),
+            UnitType::Bishop => bishop_movement(unit.coords, board, units, &mut attacked),
+            UnitType::King => king_movement(unit.coords, board, units, &mut attacked),
+            UnitType::Knight => knight_movement(unit.coords, board, units, &mut attacked),
+            UnitType::Queen => queen_movement(unit.coords, board, units, &mut attacked),
+            UnitType::Comet => comet_movement(unit.coords, board, units, &mut attacked),
+            UnitType::Archbishop => bishop_movement(unit.coords, board, units, &mut attacked),
+            UnitType::Chancellor => rook_movement(unit.coords, board, units, &mut attacked),
+            UnitType::Amazon => queen_movement(unit.coords, board, units, &mut attacked),
+            UnitType::Wazir => wazir_movement(unit.coords, board, units, &mut attacked),
+            UnitType::Ferz => ferz_movement(unit.coords, board, units, &mut attacked),
+            UnitType::Defined(index) => {
+                defined_movement(index, unit.coords, board, units, &mut attacked)
+            }
+        };
+        output.extend(attacked.iter().filter(|coords| {
+            // Only knights can capture over an edge
+            unit.unit_type.can_capture_over_edge()
+                || coords.normal_direction() == unit.coords.normal_direction()
+        }));
+        attacked.clear();
+        extra_knight_moves(unit, board, &mut attacked);
+        output.extend(attacked.iter());
+    }
+    output
+}
+
+fn knight_movement(
+    unit_coords: CellCoordinates,
+    board: &Board,
+    _units: &Units,
+    output: &mut Vec<CellCoordinates>,
+) {
+    parts::get_knight_moves(unit_coords, 1, board.cube_side_length, output);
+}
+
+fn wazir_movement(
+    unit_coords: CellCoordinates,
+    board: &Board,
+    units: &Units,
+    output: &mut Vec<CellCoordinates>,
+) {
+    parts::get_straight(unit_coords, 1, 1, board.cube_side_length, units, output);
+}
+
+fn ferz_movement(
+    unit_coords: CellCoordinates,
+    board: &Board,
+    units: &Units,
+    output: &mut Vec<CellCoordinates>,
+) {
+    parts::get_diagonals(unit_coords, 1, 1, board.cube_side_length, units, output);
+}
+
+/// The moves of a unit from the pieces file, except for its leaps, which are added by
+/// [`extra_knight_moves`]
+fn defined_movement(
+    index: u8,
+    unit_coords: CellCoordinates,
+    board: &Board,
+    units: &Units,
+    output: &mut Vec<CellCoordinates>,
+) {
+    let cube_side_length = board.cube_side_length;
+    for part in &pieces::definition(index).moves {
+        match *part {
+            MovePart::Straight {
+                max_distance,
+                max_edge_crossings,
+            } => parts::get_straight(
+                unit_coords,
+                max_distance.unwrap_or(u32::MAX),
+                max_edge_crossings,
+                cube_side_length,
+                units,
+                output,
+            ),
+            MovePart::Diagonal {
+                max_distance,
+                max_edge_crossings,
+            } => parts::get_diagonals(
+                unit_coords,
+                max_distance.unwrap_or(u32::MAX),
+                max_edge_crossings,
+                cube_side_length,
+                units,
+                output,
+            ),
+            MovePart::Leaper { .. } => {}
+        }
+    }
+}
+
+fn comet_movement(
+    unit_coords: CellCoordinates,
+    board: &Board,
+    units: &Units,
+    output: &mut Vec<CellCoordinates>,
+) {
+    parts::get_straight(unit_coords, 1, 1, board.cube_side_length, units, output);
+    parts::get_diagonals(unit_coords, 1, 1, board.cube_side_length, units, output);
+}
+
+/// The extra movement a unit unlocks by gaining levels in the experience variant. None of these
+/// moves cross an edge.
+fn level_ability_moves(unit: &Unit, board: &Board, units: &Units) -> Vec<CellCoordinates> {
+    let cube_side_length = board.cube_side_length;
+    let mut extra_moves = Vec::new();
+    match unit.unit_type {
+        UnitType::Pawn(direction, _) => {
+            // Step sideways, without capturing
+            let normal = unit.coords.normal_direction();
+            let Some(sideways) = direction
+                .to_cartesian_direction(normal)
+                .and_then(|forward| forward.get_perpendicular_axis(normal))
+            else {
+                return extra_moves;
+            };
+            for side in [sideways, sideways.opposite()] {
+                let Some((cell, crossed_edge)) =
+                    unit.coords.get_cell_in_direction(side, cube_side_length)
+                else {
+                    continue;
+                };
+                if !crossed_edge && !units.is_unit_at(cell) {
+                    extra_moves.push(cell);
+                }
+            }
+        }
+        UnitType::Rook | UnitType::Chancellor | UnitType::Wazir => {
+            parts::get_diagonals(unit.coords, 1, 0, cube_side_length, units, &mut extra_moves)
+        }
+        UnitType::Bishop | UnitType::Knight | UnitType::Archbishop | UnitType::Ferz => {
+            parts::get_straight(unit.coords, 1, 0, cube_side_length, units, &mut extra_moves)
+        }
+        UnitType::Queen => {
+            parts::get_knight_moves(unit.coords, 0, cube_side_length, &mut extra_moves)
+        }
+        UnitType::King => {
+            parts::get_straight(unit.coords, 2, 0, cube_side_length, units, &mut extra_moves)
+        }
+        UnitType::Comet | UnitType::Defined(_) => {}
+        // The amazon already makes the moves of a knight
+        UnitType::Amazon => {}
+    }
+    extra_moves
+}
+
+/// Node counts from [`perft`]
+#[derive(Debug, Default)]
+pub(crate) struct PerftResult {
+    /// Number of move sequences of the full depth
+    pub(crate) nodes: u64,
+    /// The nodes after each of the first moves
+    pub(crate) per_move: Vec<(GameMove, u64)>,
+}
+
+/// Counts every sequence of `depth` moves, starting with `team` and then alternating. Comparing
+/// the counts against recorded ones shows if a change to the movement rules changed which moves
+/// are generated, and timing it benchmarks move generation.
+#[allow(unused)]
+pub(crate) fn perft(board: &Board, units: &Units, team: Team, depth: u32) -> PerftResult {
+    let mut units = units.clone();
+    let mut output = PerftResult::default();
+    if depth == 0 {
+        output.nodes = 1;
+        return output;
+    }
+    for game_move in perft_moves(board, &units, team) {
+        let undo = perft_make_move(game_move, &mut units);
+        let nodes = perft_recursive(board, &mut units, team.opposite(), depth - 1);
+        perft_unmake_move(game_move, &mut units, undo);
+        output.nodes += nodes;
+        output.per_move.push((game_move, nodes));
+    }
+    output
+}
+
+fn perft_recursive(board: &Board, units: &mut Units, team: Team, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let mut nodes = 0;
+    for game_move in perft_moves(board, units, team) {
+        let undo = perft_make_move(game_move, units);
+        nodes += perft_recursive(board, units, team.opposite(), depth - 1);
+        perft_unmake_move(game_move, units, undo);
+    }
+    nodes
+}
+
+fn perft_moves(board: &Board, units: &Units, team: Team) -> Vec<GameMove> {
+    let mut output = Vec::new();
+    let mut unit_moves = Vec::new();
+    for unit in units.all_units_iter().filter(|unit| unit.team == team) {
+        unit_moves.clear();
+        get_unit_moves_into(unit, board, units, &mut unit_moves);
+        output.extend(unit_moves.iter().map(|&to| GameMove {
+            from: unit.coords,
+            to,
+        }));
+    }
+    output
+}
+
+/// Returns the unit type before the move, since pawns change when moving, and the captured unit
+fn perft_make_move(game_move: GameMove, units: &mut Units) -> (UnitType, Option<Unit>) {
+    let captured_unit = units.remove_unit(game_move.to);
+    let unit = units.get_unit_mut(game_move.from).unwrap();
+    let unit_type = unit.unit_type;
+    unit.move_unit_to(game_move.to);
+    if let UnitType::Pawn(_, ref mut has_moved) = unit.unit_type {
+        *has_moved = true;
+    }
+    (unit_type, captured_unit)
+}
+
+fn perft_unmake_move(game_move: GameMove, units: &mut Units, undo: (UnitType, Option<Unit>)) {
+    let unit = units.get_unit_mut(game_move.to).unwrap();
+    unit.move_unit_to(game_move.from);
+    unit.unit_type = undo.0;
+    if let Some(captured_unit) = undo.1 {
+        units.add_unit(captured_unit);
+    }
+}
+
+/// Parts to create full movement patterns with
+mod parts {
+    use std::collections::VecDeque;
+
+    use crate::engine::cell::{Board, CellCoordinates};
+    use crate::engine::direction::{CartesianDirection, RadialDirection};
+    use crate::engine::units::Units;
+
+    pub(crate) fn get_straight(
+        coords: CellCoordinates,
+        max_dist: u32,
+        max_edge_crossings: u32,
+        cube_side_length: u32,
+        units: &Units,
+        output: &mut Vec<CellCoordinates>,
+    ) {
+        for direction in RadialDirection::directions() {
+            get_cells_in_direction(
+                coords,
+                max_dist,
+                max_edge_crossings,
+                cube_side_length,
+                units,
+                direction,
+                true,
+                output,
+            )
+        }
+    }
+
+    #[allow(unused)]
+    fn all_cells_on_same_side(coords: CellCoordinates, board: &Board) -> Vec<CellCoordinates> {
+        let mut output = Vec::new();
+        for cell in board.get_all_cells() {
+            if cell.coords.normal_direction() == coords.normal_direction() {
+                output.push(cell.coords);
+            }
+        }
+        output
+    }
+
+    #[allow(unused)]
+    pub(crate) fn get_cells_max_dist(
+        coords: CellCoordinates,
+        max_dist: u32,
+        board: &Board,
+    ) -> Vec<CellCoordinates> {
+        let mut output = Vec::new();
+        let mut queue = VecDeque::new();
+        queue.push_back((coords, 0));
+        while !queue.is_empty() {
+            let entry = queue.pop_front().unwrap();
+            if entry.1 > max_dist {
+                break;
+            }
+            output.push(entry.0);
+
+            for adjacent in entry.0.get_adjacent(board.cube_side_length) {
+                if !output.contains(&entry.0) {
+                    continue;
+                }
+                queue.push_back((adjacent, entry.1 + 1));
+            }
+        }
+        output
+    }
+
+    // TODO: Use two RadialDirection to represent a radial diagonal
+    pub(crate) fn get_diagonals(
+        coords: CellCoordinates,
+        max_dist: u32,
+        max_edge_crossings: u32,
+        cube_side_length: u32,
+        units: &Units,
+        output: &mut Vec<CellCoordinates>,
+    ) {
+        let start = output.len();
+        for diagonal in CartesianDirection::diagonals() {
+            let mut latest_cell = coords;
+            let mut dist = 0;
+            let mut edge_crossings = 0;
+            while let Some(next_cell) = latest_cell.get_diagonal(diagonal, cube_side_length) {
+                if output[start..].contains(&next_cell.0) {
+                    break;
+                }
+
+                dist += 1;
+                if next_cell.1 {
+                    edge_crossings += 1;
+                }
+
+                if dist > max_dist || edge_crossings > max_edge_crossings {
+                    break;
+                }
+
+                output.push(next_cell.0);
+
+                if units.is_unit_at(next_cell.0) {
+                    break;
+                }
+
+                latest_cell = next_cell.0;
+            }
+        }
+    }
+
+    pub(crate) fn get_knight_moves(
+        coords: CellCoordinates,
+        max_edge_crossings: u32,
+        cube_side_length: u32,
+        output: &mut Vec<CellCoordinates>,
+    ) {
+        for radial_direction in RadialDirection::directions() {
+            let Some(mut forward_two) =
+                coords.get_cell_in_radial_direction(radial_direction, cube_side_length)
+            else {
+                continue;
+            };
+            let mut edge_crossings = 0;
+
+            if forward_two.1 {
+                edge_crossings += 1;
+            }
+            // If we didn't get a None the first time, we are guaranteed to still be on the same
+            // ring after the first transformation => Safe to unwrap
+            forward_two = forward_two
+                .0
+                .get_cell_in_radial_direction(radial_direction, cube_side_length)
+                .unwrap();
+
+            if forward_two.1 {
+                edge_crossings += 1;
+            }
+
+            // Gets the left/right axis
+            let left_right_axis = radial_direction
+                .to_cartesian_direction(coords.normal_direction())
+                .unwrap()
+                .get_perpendicular_axis(coords.normal_direction())
+                .unwrap();
+
+            if edge_crossings > max_edge_crossings {
+                continue;
+            }
+
+            for direction_2 in [left_right_axis, left_right_axis.opposite()] {
+                let endpoint = forward_two
+                    .0
+                    .get_cell_in_direction(direction_2, cube_side_length)
+                    .unwrap();
+                if endpoint.1 && edge_crossings + 1 > max_edge_crossings {
+                    // Will go over the max if add this one
+                    continue;
+                }
+                output.push(endpoint.0);
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn get_cells_in_direction(
+        coords: CellCoordinates,
+        max_dist: u32,
+        max_edge_crossings: u32,
+        cube_side_length: u32,
+        units: &Units,
+        direction: RadialDirection,
+        include_other_unit_cells: bool,
+        output: &mut Vec<CellCoordinates>,
+    ) {
+        let start = output.len();
+        let mut latest_cell = coords;
+        let mut dist = 0;
+        let mut edge_crossings = 0;
+        loop {
+            let next_cell = latest_cell.get_cell_in_radial_direction(direction, cube_side_length);
+            if next_cell.is_none() {
+                break;
+            }
+            let next_cell = next_cell.unwrap();
+
+            if output[start..].contains(&next_cell.0) {
+                break;
+            }
+
+            dist += 1;
+            if next_cell.1 {
+                edge_crossings += 1;
+            }
+
+            if dist > max_dist || edge_crossings > max_edge_crossings {
+                break;
+            }
+
+            if !include_other_unit_cells && units.is_unit_at(next_cell.0) {
+                break;
+            }
+
+            output.push(next_cell.0);
+
+            if units.is_unit_at(next_cell.0) {
+                break;
+            }
+
+            latest_cell = next_cell.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameMove, Variant};
+    use crate::engine::cell::{Board, CellCoordinates};
+    use crate::engine::units::{Team, Unit, UnitType, Units};
+
+    #[test]
+    fn generated_moves_are_legal() {
+        let board = Board::new(4);
+        let units = Units::game_starting_configuration(4);
+        let rules = Variant::Standard.rules();
+        for game_move in super::perft_moves(&board, &units, Team::White) {
+            assert!(super::is_legal(
+                game_move,
+                &board,
+                &units,
+                Team::White,
+                rules
+            ));
+            assert!(!super::is_legal(
+                game_move,
+                &board,
+                &units,
+                Team::Black,
+                rules
+            ));
+        }
+
+        // The white king can't capture the white knight next to it
+        let own_capture = GameMove {
+            from: CellCoordinates::new(4, 0, 4, true),
+            to: CellCoordinates::new(3, 0, 3, true),
+        };
+        assert!(!super::is_legal(
+            own_capture,
+            &board,
+            &units,
+            Team::White,
+            rules
+        ));
+    }
+
+    #[test]
+    fn pawns_are_promoted_on_the_home_face_of_the_other_team() {
+        let units = Units::game_starting_configuration(4);
+        let home_faces = super::home_faces(4);
+        let find = |team: Team, is_type: fn(&UnitType) -> bool| {
+            units
+                .all_units_iter()
+                .find(|unit| unit.team == team && is_type(&unit.unit_type))
+                .unwrap()
+                .coords
+        };
+        let pawn = find(Team::White, |unit_type| {
+            matches!(unit_type, UnitType::Pawn(..))
+        });
+        let knight = find(Team::White, |unit_type| *unit_type == UnitType::Knight);
+        let own_king = find(Team::White, |unit_type| *unit_type == UnitType::King);
+        let other_king = find(Team::Black, |unit_type| *unit_type == UnitType::King);
+        let promotes = |game_move, variant| super::promotes(game_move, &units, variant, home_faces);
+
+        let onto_other_face = GameMove {
+            from: pawn,
+            to: other_king,
+        };
+        assert!(promotes(onto_other_face, Variant::Standard));
+        assert!(!promotes(onto_other_face, Variant::Duel));
+        let onto_own_face = GameMove {
+            from: pawn,
+            to: own_king,
+        };
+        assert!(!promotes(onto_own_face, Variant::Standard));
+        let knight_move = GameMove {
+            from: knight,
+            to: other_king,
+        };
+        assert!(!promotes(knight_move, Variant::Standard));
+    }
+
+    #[test]
+    fn explosions_spare_pawns() {
+        let mut units = Units::game_starting_configuration(4);
+        let king = units
+            .all_units_iter()
+            .find(|unit| unit.team == Team::White && unit.unit_type == UnitType::King)
+            .unwrap()
+            .coords;
+        let adjacent = king.get_adjacent(4);
+        let is_pawn = |unit: &Unit| matches!(unit.unit_type, UnitType::Pawn(..));
+        let pawns_before = units.all_units_iter().filter(|unit| is_pawn(unit)).count();
+
+        let exploded = super::explode(king, 4, &mut units);
+        assert!(exploded.iter().any(|unit| unit.coords == king));
+        assert!(exploded.iter().all(|unit| !is_pawn(unit)));
+        assert!(exploded.len() > 1);
+        assert!(!units.is_unit_at(king));
+        for coords in adjacent {
+            let survivor = units.get_unit(coords);
+            assert!(survivor.filter(|unit| !is_pawn(unit)).is_none());
+        }
+        let pawns_after = units.all_units_iter().filter(|unit| is_pawn(unit)).count();
+        assert_eq!(pawns_before, pawns_after);
+    }
+
+    #[test]
+    fn atomic_captures_spare_the_own_king() {
+        let board = Board::new(4);
+        let mut units = Units::default();
+        let king = CellCoordinates::new(2, 2, 0, true);
+        let rook = CellCoordinates::new(3, 4, 0, true);
+        let next_to_king = CellCoordinates::new(3, 2, 0, true);
+        let away_from_king = CellCoordinates::new(1, 4, 0, true);
+        units.add_unit(Unit::new(UnitType::King, Team::White, king));
+        units.add_unit(Unit::new(UnitType::Rook, Team::White, rook));
+        for enemy in [next_to_king, away_from_king] {
+            units.add_unit(Unit::new(UnitType::Rook, Team::Black, enemy));
+        }
+        let is_legal = |from, to, variant: Variant| {
+            super::is_legal(
+                GameMove { from, to },
+                &board,
+                &units,
+                Team::White,
+                variant.rules(),
+            )
+        };
+
+        // Kings can't capture, and captures can't blow up the own king
+        assert!(!is_legal(king, next_to_king, Variant::Atomic));
+        assert!(!is_legal(rook, next_to_king, Variant::Atomic));
+        assert!(is_legal(rook, away_from_king, Variant::Atomic));
+        assert!(is_legal(king, next_to_king, Variant::Standard));
+        assert!(is_legal(rook, next_to_king, Variant::Standard));
+    }
+
+    #[test]
+    fn starting_configuration_fits_every_size() {
+        let mut army_size = 0;
+        for cube_side_length in 2..=8 {
+            let units = Units::game_starting_configuration(cube_side_length);
+            // Wider cubes never start with fewer units
+            assert!(units.all_units_iter().count() >= army_size);
+            army_size = units.all_units_iter().count();
+            let coords: Vec<_> = units.all_units_iter().map(|unit| unit.coords).collect();
+            for (i, cell) in coords.iter().enumerate() {
+                assert!(!coords[..i].contains(cell), "Two units on {:?}", cell);
+                let zeros = (0..3).filter(|&axis| cell[axis] == 0).count();
+                assert_eq!(zeros, 1, "Not a cell: {:?}", cell);
+                assert!((0..3).all(|axis| cell[axis] <= cube_side_length));
+            }
+            // Small cubes get a wazir and a ferz instead of the knight and the queen
+            let has_ferz = units
+                .all_units_iter()
+                .any(|unit| unit.unit_type == UnitType::Ferz);
+            assert_eq!(has_ferz, cube_side_length <= 3);
+            let board = Board::new(cube_side_length);
+            assert!(!super::perft_moves(&board, &units, Team::White).is_empty());
+        }
+    }
+
+    #[test]
+    fn shuffled_setups_keep_the_kings_home() {
+        let unit_types = |units: &Units| -> Vec<_> {
+            units
+                .all_units_iter()
+                .map(|unit| (unit.coords, unit.team, unit.unit_type))
+                .collect()
+        };
+        let setups = [4, 7]
+            .into_iter()
+            .flat_map(|n| (0..20).map(move |seed| (n, seed)));
+        for (cube_side_length, seed) in setups {
+            let board = Board::new(cube_side_length);
+            let home_faces = super::home_faces(cube_side_length);
+            let units = Units::shuffled_starting_configuration(cube_side_length, seed);
+            let again = Units::shuffled_starting_configuration(cube_side_length, seed);
+            assert_eq!(unit_types(&units), unit_types(&again));
+            for team in [Team::White, Team::Black] {
+                let king = units
+                    .all_units_iter()
+                    .find(|unit| unit.team == team && unit.unit_type == UnitType::King)
+                    .unwrap();
+                assert_eq!(king.coords.normal_direction(), home_faces[team.index()]);
+            }
+            assert!(!super::perft_moves(&board, &units, Team::White).is_empty());
+        }
+    }
+
+    #[test]
+    fn compound_units_move_like_both_parts() {
+        let board = Board::new(4);
+        let mut units = Units::default();
+        // On an edge, with enemy units on the face next to it that only a knight can capture
+        let coords = CellCoordinates::new(4, 1, 0, true);
+        for enemy in [
+            CellCoordinates::new(0, 2, 2, true),
+            CellCoordinates::new(3, 0, 1, true),
+        ] {
+            units.add_unit(Unit::new(UnitType::Rook, Team::Black, enemy));
+        }
+        let moves = |unit_type| {
+            let unit = Unit::new(unit_type, Team::White, coords);
+            let mut moves = super::get_unit_moves(&unit, &board, &units);
+            moves.sort();
+            moves.dedup();
+            moves
+        };
+        let attacked = |unit_type| {
+            let mut units = units.clone();
+            units.add_unit(Unit::new(unit_type, Team::White, coords));
+            super::get_attacked_cells(&board, &units, Team::White)
+        };
+        for (unit_type, part) in [
+            (UnitType::Archbishop, UnitType::Bishop),
+            (UnitType::Chancellor, UnitType::Rook),
+            (UnitType::Amazon, UnitType::Queen),
+        ] {
+            let mut expected = moves(part);
+            expected.extend(moves(UnitType::Knight));
+            expected.sort();
+            expected.dedup();
+            assert_eq!(moves(unit_type), expected, "Unit: {:?}", unit_type);
+
+            let mut expected = attacked(part);
+            expected.extend(attacked(UnitType::Knight));
+            assert_eq!(attacked(unit_type), expected, "Unit: {:?}", unit_type);
+        }
+    }
+
+    // The recorded counts only need updating when a change to the movement rules is intended
+
+    #[test]
+    fn perft_starting_position() {
+        let units = Units::game_starting_configuration(4);
+        for (depth, expected) in [(1, 21), (2, 441), (3, 10782)] {
+            let result = super::perft(&Board::new(4), &units, Team::White, depth);
+            assert_eq!(result.nodes, expected, "Depth: {}", depth);
+            assert_eq!(
+                result.per_move.iter().map(|(_, nodes)| nodes).sum::<u64>(),
+                result.nodes
+            );
+        }
+    }
+
+    #[test]
+    fn perft_over_edges() {
+        let positions = [
+            (UnitType::Queen, [29, 615, 17298]),
+            (UnitType::Knight, [6, 125, 948]),
+            (UnitType::Bishop, [7, 147, 1031]),
+        ];
+        for (unit_type, expected) in positions {
+            // The unit is on an edge, with an enemy rook on the face next to it
+            let mut units = Units::default();
+            units.add_unit(Unit::new(
+                unit_type,
+                Team::White,
+                CellCoordinates::new(4, 1, 0, true),
+            ));
+            units.add_unit(Unit::new(
+                UnitType::Rook,
+                Team::Black,
+                CellCoordinates::new(0, 2, 2, true),
+            ));
+            for (depth, expected) in (1..).zip(expected) {
+                let result = super::perft(&Board::new(4), &units, Team::White, depth);
+                assert_eq!(
+                    result.nodes, expected,
+                    "Unit: {:?}, depth: {}",
+                    unit_type, depth
+                );
+            }
+        }
+    }
+}
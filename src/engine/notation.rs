@@ -0,0 +1,309 @@
+//! Moves written like the algebraic notation of chess: the symbol of the unit followed by the cell
+//! it moves to, with
+//! - the side it moves from when another unit of the same kind and team could move to the same
+//!   cell, followed by the column or the row it moves from, or both, when a unit on the same side
+//!   could too, like the file and rank in chess
+//! - `x` before the cell if it captures
+//! - `^` before the cell if it crosses an edge of the cube onto another side
+//! - `=` and the symbol of the unit a pawn is promoted to after the cell
+//! - `+` after the move if it attacks the king of the other team, and `#` if it captures it. Kings
+//!   may be left attacked, so the game only ends when one is captured.
+//!
+//! A knight capturing on the other side of an edge while attacking the king is `♘x^Zb3+`.
+//! Summoning a unit in the reinforcements variant is written as its symbol, `@` and the cell.
+
+use std::mem;
+
+use crate::engine::cell::{Board, CellCoordinates};
+use crate::engine::movement::{self, GameMove};
+use crate::engine::pieces;
+use crate::engine::units::{Team, UnitType, Units};
+
+/// Writes a move that hasn't been made yet, `units` being the position before it
+pub(crate) fn write_move(game_move: GameMove, board: &Board, units: &Units) -> String {
+    write_promoting_move(game_move, None, board, units)
+}
+
+/// Writes a move like [`write_move`], with the unit the pawn is promoted to if it is promoted
+pub(crate) fn write_promoting_move(
+    game_move: GameMove,
+    promotion: Option<UnitType>,
+    board: &Board,
+    units: &Units,
+) -> String {
+    let Some(unit) = units.get_unit(game_move.from) else {
+        return format!("{}-{}", game_move.from.display(), game_move.to.display());
+    };
+    let mut output = unit.unit_type.symbol().to_string();
+    output.push_str(&disambiguation(game_move, board, units));
+    let captured = units.get_unit(game_move.to);
+    if captured.is_some() {
+        output.push('x');
+    }
+    if game_move.from.normal_direction() != game_move.to.normal_direction() {
+        output.push('^');
+    }
+    output.push_str(&game_move.to.display());
+    if let Some(promotion) = promotion {
+        output.push('=');
+        output.push(promotion.symbol());
+    }
+
+    if matches!(captured, Some(captured) if captured.unit_type == UnitType::King) {
+        output.push('#');
+    } else if unit.team != Team::Neutral
+        && attacks_king(game_move, promotion, board, units, unit.team)
+    {
+        output.push('+');
+    }
+    output
+}
+
+pub(crate) fn write_summon(unit_type: UnitType, coords: CellCoordinates) -> String {
+    format!("{}@{}", unit_type.symbol(), coords.display())
+}
+
+/// Empty if no other unit of the same kind and team can move to the same cell
+fn disambiguation(game_move: GameMove, board: &Board, units: &Units) -> String {
+    let Some(unit) = units.get_unit(game_move.from) else {
+        return String::new();
+    };
+    let others: Vec<CellCoordinates> = units
+        .all_units_iter()
+        .filter(|other| {
+            other.coords != unit.coords
+                && other.team == unit.team
+                && mem::discriminant(&other.unit_type) == mem::discriminant(&unit.unit_type)
+                && movement::get_unit_moves(other, board, units).contains(&game_move.to)
+        })
+        .map(|other| other.coords)
+        .collect();
+    if others.is_empty() {
+        return String::new();
+    }
+    let from = game_move.from.display();
+    let (side, column, row) = (&from[..1], &from[1..2], &from[2..]);
+    let same_side: Vec<String> = others
+        .iter()
+        .filter(|other| other.normal_direction() == game_move.from.normal_direction())
+        .map(CellCoordinates::display)
+        .collect();
+    if same_side.is_empty() {
+        side.to_string()
+    } else if same_side.iter().all(|other| &other[1..2] != column) {
+        format!("{}{}", side, column)
+    } else if same_side.iter().all(|other| &other[2..] != row) {
+        format!("{}{}", side, row)
+    } else {
+        from
+    }
+}
+
+/// If a player who typed `typed` meant the move. Besides the notation of [`write_promoting_move`],
+/// letters can be typed for the symbols, like `N` for `♘`, and `x`, `^`, `+` and `#` can be left
+/// out, as can the symbol of a pawn. The move can also be typed as the cells it goes from and to,
+/// like `Nzc2-Zb3`. A pawn that is promoted without saying to what becomes a queen.
+pub(crate) fn is_typed_move(
+    typed: &str,
+    game_move: GameMove,
+    promotion: Option<UnitType>,
+    board: &Board,
+    units: &Units,
+) -> bool {
+    let Some(unit) = units.get_unit(game_move.from) else {
+        return false;
+    };
+    let typed: String = typed
+        .trim()
+        .trim_end_matches(['+', '#'])
+        .chars()
+        .map(symbol_of_letter)
+        .collect();
+    let promotions = match promotion {
+        Some(UnitType::Queen) => vec![format!("={}", UnitType::Queen.symbol()), String::new()],
+        Some(unit_type) => vec![format!("={}", unit_type.symbol())],
+        None => vec![String::new()],
+    };
+    let written = write_move(game_move, board, units);
+    let symbol = unit.unit_type.symbol().to_string();
+    let disambiguation = disambiguation(game_move, board, units);
+    let (from, to) = (game_move.from.display(), game_move.to.display());
+    let without_symbol = [
+        written[symbol.len()..]
+            .trim_end_matches(['+', '#'])
+            .to_string(),
+        format!("{}{}", disambiguation, to),
+        format!("{}-{}", from, to),
+    ];
+    let is_pawn = matches!(unit.unit_type, UnitType::Pawn(_, _));
+    without_symbol.iter().any(|form| {
+        promotions.iter().any(|promoted| {
+            typed == format!("{}{}{}", symbol, form, promoted)
+                || (is_pawn || form.contains('-')) && typed == format!("{}{}", form, promoted)
+        })
+    })
+}
+
+/// The symbol of the unit that has the letter in English notation, or the character itself
+fn symbol_of_letter(character: char) -> char {
+    match character {
+        'K' => UnitType::King.symbol(),
+        'Q' => UnitType::Queen.symbol(),
+        'R' => UnitType::Rook.symbol(),
+        'B' => UnitType::Bishop.symbol(),
+        'N' => UnitType::Knight.symbol(),
+        'A' => UnitType::Archbishop.symbol(),
+        'M' => UnitType::Chancellor.symbol(),
+        'E' => UnitType::Amazon.symbol(),
+        'W' => UnitType::Wazir.symbol(),
+        'F' => UnitType::Ferz.symbol(),
+        'P' => '♙',
+        character => {
+            pieces::unit_type_of_letter(character).map_or(character, |unit_type| unit_type.symbol())
+        }
+    }
+}
+
+/// The unit a pawn is promoted to in a written move, if it is promoted
+pub(crate) fn promotion(written: &str) -> Option<UnitType> {
+    let (_, promotion) = written.split_once('=')?;
+    let symbol = promotion.chars().next()?;
+    promotion_from_symbol(&symbol.to_string())
+}
+
+/// The unit a pawn can be promoted to that has the symbol
+pub(crate) fn promotion_from_symbol(symbol: &str) -> Option<UnitType> {
+    movement::PROMOTIONS
+        .into_iter()
+        .find(|unit_type| unit_type.symbol().to_string() == symbol)
+}
+
+/// If `team` could capture the king of the other team after making the move
+fn attacks_king(
+    game_move: GameMove,
+    promotion: Option<UnitType>,
+    board: &Board,
+    units: &Units,
+    team: Team,
+) -> bool {
+    let mut units = units.clone();
+    units.remove_unit(game_move.to);
+    let Some(unit) = units.get_unit_mut(game_move.from) else {
+        return false;
+    };
+    unit.move_unit_to(game_move.to);
+    if let Some(promotion) = promotion {
+        unit.unit_type = promotion;
+    } else if let UnitType::Pawn(_, ref mut has_moved) = unit.unit_type {
+        *has_moved = true;
+    }
+
+    let Some(king) = units
+        .all_units_iter()
+        .find(|unit| unit.team == team.opposite() && unit.unit_type == UnitType::King)
+    else {
+        return false;
+    };
+    units
+        .all_units_iter()
+        .filter(|unit| unit.team == team)
+        .any(|unit| movement::get_unit_moves(unit, board, &units).contains(&king.coords))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::units::Unit;
+
+    fn cell(text: &str) -> CellCoordinates {
+        CellCoordinates::from_display(text).unwrap()
+    }
+
+    /// The units of a 4 cube, written as the unit type, the team and the cell
+    fn position(units: &[(UnitType, Team, &str)]) -> (Board, Units) {
+        let mut output = Units::default();
+        for &(unit_type, team, coords) in units {
+            output.add_unit(Unit::new(unit_type, team, cell(coords)));
+        }
+        (Board::new(4), output)
+    }
+
+    fn game_move(from: &str, to: &str) -> GameMove {
+        GameMove {
+            from: cell(from),
+            to: cell(to),
+        }
+    }
+
+    #[test]
+    fn moves_are_disambiguated_by_side_column_and_row() {
+        let write = |units: &[(UnitType, Team, &str)], from, to| {
+            let (board, units) = position(units);
+            write_move(game_move(from, to), &board, &units)
+        };
+        let rook = |coords| (UnitType::Rook, Team::White, coords);
+        let queen = |coords| (UnitType::Queen, Team::White, coords);
+
+        assert_eq!(write(&[rook("Xa1")], "Xa1", "Xa2"), "♖Xa2");
+        // The other rook is on another side
+        assert_eq!(write(&[rook("Xd4"), rook("Zd1")], "Xd4", "Zd4"), "♖X^Zd4");
+        // On the same side and row
+        assert_eq!(write(&[rook("Xa2"), rook("Xc2")], "Xa2", "Xb2"), "♖XaXb2");
+        // On the same side and column
+        assert_eq!(write(&[rook("Xa1"), rook("Xa3")], "Xa1", "Xa2"), "♖X1Xa2");
+        // One queen shares the column and another the row
+        let queens = [queen("Xb1"), queen("Xb3"), queen("Xa1")];
+        assert_eq!(write(&queens, "Xb1", "Xb2"), "♕Xb1Xb2");
+        // Units of the other team don't need to be told apart
+        let rooks = [rook("Xa1"), (UnitType::Rook, Team::Black, "Xa4")];
+        assert_eq!(write(&rooks, "Xa1", "Xa2"), "♖Xa2");
+    }
+
+    #[test]
+    fn captures_and_attacks_on_the_king_are_marked() {
+        let (board, units) = position(&[
+            (UnitType::Rook, Team::White, "Xa1"),
+            (UnitType::Knight, Team::Black, "Xa3"),
+            (UnitType::King, Team::Black, "Xd2"),
+        ]);
+        let write = |from, to| write_move(game_move(from, to), &board, &units);
+        assert_eq!(write("Xa1", "Xa3"), "♖xXa3");
+        assert_eq!(write("Xa1", "Xa2"), "♖Xa2+");
+        assert_eq!(write("Xa1", "Xb1"), "♖Xb1");
+
+        let (board, units) = position(&[
+            (UnitType::Rook, Team::White, "Xa2"),
+            (UnitType::King, Team::Black, "Xd2"),
+        ]);
+        assert_eq!(
+            write_move(game_move("Xa2", "Xd2"), &board, &units),
+            "♖xXd2#"
+        );
+    }
+
+    #[test]
+    fn typed_moves_are_recognised() {
+        let (board, units) = position(&[
+            (UnitType::Rook, Team::White, "Xa1"),
+            (UnitType::Rook, Team::White, "Xa3"),
+            (UnitType::Knight, Team::Black, "Xc1"),
+        ]);
+        let typed =
+            |typed, from, to| is_typed_move(typed, game_move(from, to), None, &board, &units);
+        for accepted in ["♖xXc1", "RxXc1", "RXc1", "Xa1-Xc1", "RXa1-Xc1", "RxXc1+"] {
+            assert!(
+                typed(accepted, "Xa1", "Xc1"),
+                "{} wasn't accepted",
+                accepted
+            );
+        }
+        for rejected in ["Xc1", "QxXc1", "RxXc2", "Xa3-Xc1", ""] {
+            assert!(!typed(rejected, "Xa1", "Xc1"), "{} was accepted", rejected);
+        }
+        // Both rooks can go to Xa2
+        assert!(typed("RX1Xa2", "Xa1", "Xa2"));
+        assert!(typed("RX3Xa2", "Xa3", "Xa2"));
+        assert!(!typed("RXa2", "Xa1", "Xa2"));
+        assert!(!typed("RX1Xa2", "Xa3", "Xa2"));
+    }
+}
@@ -0,0 +1,150 @@
+//! Units described in `assets/pieces.ron` instead of in code, so that new fairy pieces can be
+//! added without changing the game. Every piece moves with a list of [`MovePart`]s and becomes a
+//! [`UnitType::Defined`], which the movement code, CubeFEN, notation and the AI look up here.
+//!
+//! The file is read once at startup. A missing file defines no pieces, and a piece that doesn't
+//! fit with the others is skipped with an error.
+
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+use crate::engine::units::UnitType;
+
+/// The name of the file in the assets folder
+pub(crate) const PIECES_FILE: &str = "pieces.ron";
+
+/// Letters taken by the built-in units in CubeFEN, and by the faces of cells in typed moves
+const RESERVED_LETTERS: &str = "KQRBNPCAMEWFXYZ";
+
+static DEFINITIONS: OnceLock<Vec<PieceDefinition>> = OnceLock::new();
+
+/// One way a piece can move. Like the built-in units, only leaps can capture over an edge.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub(crate) enum MovePart {
+    /// Along the rows and columns, like a rook. Without a `max_distance` it goes until it is
+    /// blocked.
+    Straight {
+        #[serde(default)]
+        max_distance: Option<u32>,
+        max_edge_crossings: u32,
+    },
+    /// Along the diagonals, like a bishop
+    Diagonal {
+        #[serde(default)]
+        max_distance: Option<u32>,
+        max_edge_crossings: u32,
+    },
+    /// The leap of a knight, over any units in the way
+    Leaper { max_edge_crossings: u32 },
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct PieceDefinition {
+    pub(crate) name: String,
+    /// Written uppercase for white and lowercase for black in CubeFEN, and typed in moves
+    pub(crate) letter: char,
+    /// Written in moves and shown in the move list
+    pub(crate) symbol: char,
+    /// The model in `assets/models`, without the `.glb`
+    pub(crate) model: String,
+    /// How much the unit is worth to the AI, with a pawn worth 1
+    pub(crate) value: f32,
+    pub(crate) moves: Vec<MovePart>,
+}
+
+#[derive(Deserialize)]
+struct PiecesFile {
+    pieces: Vec<PieceDefinition>,
+}
+
+/// Reads the pieces from `path` into the definitions used by the rest of the game. Only the first
+/// call has an effect. Returns why pieces were skipped, or why the file couldn't be read.
+pub(crate) fn load(path: &Path) -> Vec<String> {
+    let text = fs::read_to_string(path).unwrap_or_default();
+    let (definitions, errors) = match text.trim().is_empty() {
+        true => (Vec::new(), Vec::new()),
+        false => parse(&text),
+    };
+    let errors = errors
+        .into_iter()
+        .map(|err| format!("{}: {}", path.display(), err))
+        .collect();
+    let _ = DEFINITIONS.set(definitions);
+    errors
+}
+
+/// The pieces in the text of a pieces file that can be played, and why the others can't
+fn parse(text: &str) -> (Vec<PieceDefinition>, Vec<String>) {
+    let file: PiecesFile = match ron::from_str(text) {
+        Ok(file) => file,
+        Err(err) => return (Vec::new(), vec![format!("it is corrupted ({})", err)]),
+    };
+    let mut definitions: Vec<PieceDefinition> = Vec::new();
+    let mut errors = Vec::new();
+    for definition in file.pieces {
+        let (letter, symbol) = (definition.letter, definition.symbol);
+        let problem = if !letter.is_ascii_uppercase() || RESERVED_LETTERS.contains(letter) {
+            Some(format!("{} can't be used as a letter", letter))
+        } else if definitions.iter().any(|other| other.letter == letter) {
+            Some(format!("another piece has the letter {}", letter))
+        } else if definitions.iter().any(|other| other.symbol == symbol) {
+            Some(format!("another piece has the symbol {}", symbol))
+        } else if definition.moves.is_empty() {
+            Some("it has no moves".to_string())
+        } else if definitions.len() > u8::MAX as usize {
+            Some("there are too many pieces".to_string())
+        } else {
+            None
+        };
+        match problem {
+            Some(problem) => errors.push(format!("Skipping {}, {}", definition.name, problem)),
+            None => definitions.push(definition),
+        }
+    }
+    (definitions, errors)
+}
+
+/// The loaded pieces, in the order of [`UnitType::Defined`]
+pub(crate) fn definitions() -> &'static [PieceDefinition] {
+    DEFINITIONS.get().map_or(&[], Vec::as_slice)
+}
+
+pub(crate) fn definition(index: u8) -> &'static PieceDefinition {
+    &definitions()[index as usize]
+}
+
+/// The loaded piece that is written with the uppercase `letter`
+pub(crate) fn unit_type_of_letter(letter: char) -> Option<UnitType> {
+    definitions()
+        .iter()
+        .position(|definition| definition.letter == letter)
+        .map(|index| UnitType::Defined(index as u8))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn bundled_pieces_can_be_played() {
+        let (definitions, errors) = super::parse(include_str!("../../assets/pieces.ron"));
+        assert_eq!(errors, Vec::<String>::new());
+        assert!(!definitions.is_empty());
+    }
+
+    #[test]
+    fn pieces_with_taken_letters_are_skipped() {
+        let text = "(pieces: [
+            (name: \"Guard\", letter: 'G', symbol: 'g', model: \"king\", value: 3.,
+                moves: [Straight(max_distance: Some(1), max_edge_crossings: 1)]),
+            (name: \"Other guard\", letter: 'G', symbol: 'o', model: \"king\", value: 3.,
+                moves: [Leaper(max_edge_crossings: 1)]),
+            (name: \"Zebra\", letter: 'Z', symbol: 'z', model: \"knight\", value: 3.,
+                moves: [Leaper(max_edge_crossings: 1)]),
+        ])";
+        let (definitions, errors) = super::parse(text);
+        assert_eq!(definitions.len(), 1);
+        assert_eq!(errors.len(), 2);
+    }
+}
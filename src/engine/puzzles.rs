@@ -0,0 +1,455 @@
+//! Mate puzzles, positions where the team to move can force the capture of the other king. A
+//! puzzle is a mate in N when after N moves of the solver the king can't escape anymore, whatever
+//! the other team answers. Puzzles are found by searching the positions of played games, or of
+//! games of random moves, see [`find_puzzle`], and read from the puzzle packs in
+//! `assets/puzzles`, see [`load_packs`].
+
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::engine::ai;
+use crate::engine::cell::Board;
+use crate::engine::cubefen::CubeFen;
+use crate::engine::movement::{GameMove, Variant};
+use crate::engine::notation;
+use crate::engine::units::{Team, UnitType, Units};
+
+/// Longer mates take too long to search for
+pub(crate) const MAX_MATE_IN: u32 = 2;
+
+#[derive(Clone, Debug)]
+pub(crate) struct Puzzle {
+    /// The team to move is the one that mates
+    pub(crate) position: CubeFen,
+    pub(crate) mate_in: u32,
+    /// The moves of both teams until the king can't escape, starting with the first move of the
+    /// solver. The other team holds out as long as it can.
+    pub(crate) solution: Vec<GameMove>,
+    /// What the puzzle is about, like the unit that mates or the trick it takes
+    pub(crate) themes: Vec<String>,
+}
+
+impl Puzzle {
+    /// The moves of the solution in the notation of [`notation`], separated by spaces
+    pub(crate) fn written_solution(&self) -> String {
+        let board = Board::new(self.position.cube_side_length);
+        let mut units = self.position.units.clone();
+        let mut written = Vec::new();
+        for &game_move in &self.solution {
+            written.push(notation::write_move(game_move, &board, &units));
+            units = after_move(game_move, &board, &units, self.position.variant);
+        }
+        written.join(" ")
+    }
+}
+
+/// Only variants where every move is known and made one at a time have puzzles, and captures
+/// that blow up units aren't searched
+pub(crate) fn has_puzzles(variant: Variant) -> bool {
+    let rules = variant.rules();
+    !(rules.simultaneous_moves
+        || rules.neutral_units_move
+        || rules.summons
+        || rules.captures_explode)
+}
+
+fn after_move(game_move: GameMove, board: &Board, units: &Units, variant: Variant) -> Units {
+    let mut units = units.clone();
+    variant.rules().apply_move(game_move, board, &mut units);
+    units
+}
+
+/// If `team` can take the king of the other team right away
+pub(crate) fn can_take_king(board: &Board, units: &Units, team: Team, variant: Variant) -> bool {
+    let Some(king) = units
+        .all_units_iter()
+        .find(|unit| unit.team == team.opposite() && unit.unit_type == UnitType::King)
+    else {
+        return false;
+    };
+    ai::get_possible_moves(board, units, team, variant)
+        .iter()
+        .any(|game_move| game_move.to == king.coords)
+}
+
+/// If `game_move` of `team` forces the capture of the other king after at most `moves` moves of
+/// `team`, counting this one. A move that takes the king forces it right away.
+pub(crate) fn forces_mate(
+    board: &Board,
+    units: &Units,
+    team: Team,
+    game_move: GameMove,
+    moves: u32,
+    variant: Variant,
+) -> bool {
+    let units = after_move(game_move, board, units, variant);
+    if variant.rules().winner(&units).is_some() {
+        return true;
+    }
+    let replies = ai::get_possible_moves(board, &units, team.opposite(), variant);
+    // The game is a draw if the other team can't move
+    !replies.is_empty()
+        && replies
+            .into_iter()
+            .all(|reply| !escapes(board, &units, team, reply, moves, variant))
+}
+
+/// If the reply of the other team keeps `team` from taking the king with the `moves` that are
+/// left, see [`forces_mate`]
+fn escapes(
+    board: &Board,
+    units: &Units,
+    team: Team,
+    reply: GameMove,
+    moves: u32,
+    variant: Variant,
+) -> bool {
+    mate_length(board, units, team, reply, moves - 1, variant).is_none()
+}
+
+/// The fewest moves `team` needs to mate after the reply of the other team, with 0 if it can take
+/// the king right away. None if it takes more than `max_moves`, or if the reply took the king of
+/// `team`.
+fn mate_length(
+    board: &Board,
+    units: &Units,
+    team: Team,
+    reply: GameMove,
+    max_moves: u32,
+    variant: Variant,
+) -> Option<u32> {
+    let units = after_move(reply, board, units, variant);
+    if variant.rules().winner(&units).is_some() {
+        return None;
+    }
+    if can_take_king(board, &units, team, variant) {
+        return Some(0);
+    }
+    (1..=max_moves).find(|&moves| mating_move(board, &units, team, moves, variant).is_some())
+}
+
+/// A move of `team` that forces mate in `moves`, see [`forces_mate`]
+pub(crate) fn mating_move(
+    board: &Board,
+    units: &Units,
+    team: Team,
+    moves: u32,
+    variant: Variant,
+) -> Option<GameMove> {
+    ai::get_possible_moves(board, units, team, variant)
+        .into_iter()
+        .find(|&game_move| forces_mate(board, units, team, game_move, moves, variant))
+}
+
+/// The reply to a move of `team` that forces mate in `moves` which puts off the mate the longest.
+/// `units` are the units after the move.
+pub(crate) fn longest_defence(
+    board: &Board,
+    units: &Units,
+    team: Team,
+    moves: u32,
+    variant: Variant,
+) -> Option<GameMove> {
+    ai::get_possible_moves(board, units, team.opposite(), variant)
+        .into_iter()
+        .max_by_key(|&reply| mate_length(board, units, team, reply, moves - 1, variant))
+}
+
+/// The reply to a move of `team` that doesn't force mate in `moves`, after which there is no mate
+/// in the moves that are left. The reply of the AI is picked if it is one of them. `units` are
+/// the units after the move.
+pub(crate) fn refutation(
+    board: &Board,
+    units: &Units,
+    team: Team,
+    moves: u32,
+    variant: Variant,
+) -> Option<GameMove> {
+    let refutes = |reply| escapes(board, units, team, reply, moves, variant);
+    let mut ai_cache = ai::AICache::default();
+    ai::next_move(board, units, team.opposite(), 2, variant, &mut ai_cache)
+        .filter(|&reply| refutes(reply))
+        .or_else(|| {
+            ai::get_possible_moves(board, units, team.opposite(), variant)
+                .into_iter()
+                .find(|&reply| refutes(reply))
+        })
+}
+
+/// The shortest mate of the team to move, if it is at most [`MAX_MATE_IN`] moves. Positions where
+/// a king can already be taken aren't puzzles.
+pub(crate) fn find_mate(
+    board: &Board,
+    units: &Units,
+    team: Team,
+    variant: Variant,
+) -> Option<Puzzle> {
+    if !has_puzzles(variant)
+        || can_take_king(board, units, team, variant)
+        || can_take_king(board, units, team.opposite(), variant)
+    {
+        return None;
+    }
+    let mate_in = (1..=MAX_MATE_IN)
+        .find(|&moves| mating_move(board, units, team, moves, variant).is_some())?;
+
+    let mut solution = Vec::new();
+    let mut line_units = units.clone();
+    let mut moves = mate_in;
+    loop {
+        // The shortest mate, so that the line ends with a move of the solver
+        let (game_move, shortest) = (1..=moves).find_map(|shortest| {
+            let game_move = mating_move(board, &line_units, team, shortest, variant)?;
+            Some((game_move, shortest))
+        })?;
+        solution.push(game_move);
+        line_units = after_move(game_move, board, &line_units, variant);
+        if shortest == 1 {
+            break;
+        }
+        let reply = longest_defence(board, &line_units, team, shortest, variant)?;
+        solution.push(reply);
+        line_units = after_move(reply, board, &line_units, variant);
+        moves = shortest - 1;
+    }
+    let mut units = units.clone();
+    for unit in units.all_units_iter_mut() {
+        unit.entity = None;
+    }
+    Some(Puzzle {
+        position: CubeFen {
+            cube_side_length: board.cube_side_length,
+            units,
+            turn: team,
+            energy: [0; 2],
+            variant,
+        },
+        mate_in,
+        solution,
+        themes: Vec::new(),
+    })
+}
+
+/// The first of `positions` that is a puzzle for the team to move in it. Gives up once `deadline`
+/// has passed.
+pub(crate) fn find_puzzle(
+    board: &Board,
+    variant: Variant,
+    positions: impl IntoIterator<Item = (Units, Team)>,
+    deadline: Instant,
+) -> Option<Puzzle> {
+    positions
+        .into_iter()
+        .take_while(|_| Instant::now() < deadline)
+        .find_map(|(units, team)| find_mate(board, &units, team, variant))
+}
+
+/// The positions of a game of random moves from `units`, which ends once a king is taken or after
+/// `max_moves` moves
+pub(crate) fn random_game(
+    board: &Board,
+    units: &Units,
+    team: Team,
+    variant: Variant,
+    max_moves: u32,
+    rng: &mut impl Rng,
+) -> Vec<(Units, Team)> {
+    let mut positions = vec![(units.clone(), team)];
+    let mut units = units.clone();
+    let mut team = team;
+    for _ in 0..max_moves {
+        let Some(&game_move) = ai::get_possible_moves(board, &units, team, variant).choose(rng)
+        else {
+            break;
+        };
+        units = after_move(game_move, board, &units, variant);
+        if variant.rules().winner(&units).is_some() {
+            break;
+        }
+        team = team.opposite();
+        positions.push((units.clone(), team));
+    }
+    positions
+}
+
+/// A file of puzzles in `assets/puzzles`
+pub(crate) struct PuzzlePack {
+    pub(crate) name: String,
+    pub(crate) puzzles: Vec<Puzzle>,
+}
+
+#[derive(Deserialize)]
+struct PackFile {
+    name: String,
+    puzzles: Vec<WrittenPuzzle>,
+}
+
+/// A puzzle as it is written in a pack. The solution is the moves of both teams separated by
+/// spaces, typed like in [`notation::is_typed_move`], and ends with the move of the solver after
+/// which the king can't escape.
+#[derive(Deserialize)]
+struct WrittenPuzzle {
+    position: String,
+    solution: String,
+    #[serde(default)]
+    themes: Vec<String>,
+}
+
+/// Reads the packs in `dir`, which are the `.ron` files in it, sorted by file name. Returns the
+/// packs, and why packs or puzzles in them were skipped.
+pub(crate) fn load_packs(dir: &Path) -> (Vec<PuzzlePack>, Vec<String>) {
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "ron"))
+        .collect();
+    paths.sort();
+    let mut packs = Vec::new();
+    let mut errors = Vec::new();
+    for path in paths {
+        let text = fs::read_to_string(&path).unwrap_or_default();
+        match parse_pack(&text) {
+            Ok((pack, pack_errors)) => {
+                let pack_errors = pack_errors
+                    .into_iter()
+                    .map(|err| format!("{}: {}", path.display(), err));
+                errors.extend(pack_errors);
+                packs.push(pack);
+            }
+            Err(err) => errors.push(format!("{}: {}", path.display(), err)),
+        }
+    }
+    (packs, errors)
+}
+
+/// The puzzles in the text of a pack that can be played, and why the others can't
+fn parse_pack(text: &str) -> Result<(PuzzlePack, Vec<String>), String> {
+    let file: PackFile = ron::from_str(text).map_err(|err| format!("it is corrupted ({})", err))?;
+    let mut puzzles = Vec::new();
+    let mut errors = Vec::new();
+    for (number, written) in file.puzzles.into_iter().enumerate() {
+        match read_puzzle(written) {
+            Ok(puzzle) => puzzles.push(puzzle),
+            Err(err) => errors.push(format!("Skipping puzzle {}, {}", number + 1, err)),
+        }
+    }
+    let pack = PuzzlePack {
+        name: file.name,
+        puzzles,
+    };
+    Ok((pack, errors))
+}
+
+fn read_puzzle(written: WrittenPuzzle) -> Result<Puzzle, String> {
+    let position: CubeFen = written.position.parse()?;
+    if !has_puzzles(position.variant) {
+        return Err(format!("{} has no puzzles", position.variant.name()));
+    }
+    let board = Board::new(position.cube_side_length);
+    let mut units = position.units.clone();
+    let mut team = position.turn;
+    let mut solution = Vec::new();
+    for typed in written.solution.split_whitespace() {
+        let game_move = ai::get_possible_moves(&board, &units, team, position.variant)
+            .into_iter()
+            .find(|&game_move| notation::is_typed_move(typed, game_move, None, &board, &units))
+            .ok_or(format!("{} is not a legal move of {:?}", typed, team))?;
+        solution.push(game_move);
+        units = after_move(game_move, &board, &units, position.variant);
+        team = team.opposite();
+    }
+    // Every move of the solver but the last one is answered
+    if solution.len() % 2 == 0 {
+        return Err("the solution has to end with a move of the team to move".to_string());
+    }
+    Ok(Puzzle {
+        mate_in: (solution.len() as u32).div_ceil(2),
+        position,
+        solution,
+        themes: written.themes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn solutions_of_found_puzzles_mate() {
+        let board = Board::new(3);
+        let mut rng = StdRng::seed_from_u64(3);
+        let starting_units = Units::game_starting_configuration(3);
+        let puzzle = (0..50)
+            .flat_map(|_| {
+                random_game(
+                    &board,
+                    &starting_units,
+                    Team::White,
+                    Variant::Standard,
+                    60,
+                    &mut rng,
+                )
+            })
+            .find_map(|(units, team)| find_mate(&board, &units, team, Variant::Standard))
+            .expect("No puzzle in the random games");
+
+        let team = puzzle.position.turn;
+        let mut units = puzzle.position.units.clone();
+        let first_move = puzzle.solution[0];
+        assert!(forces_mate(
+            &board,
+            &units,
+            team,
+            first_move,
+            puzzle.mate_in,
+            Variant::Standard
+        ));
+        for &game_move in &puzzle.solution {
+            units = after_move(game_move, &board, &units, Variant::Standard);
+        }
+        let replies = ai::get_possible_moves(&board, &units, team.opposite(), Variant::Standard);
+        assert!(replies.into_iter().all(|reply| {
+            let units = after_move(reply, &board, &units, Variant::Standard);
+            can_take_king(&board, &units, team, Variant::Standard)
+        }));
+    }
+
+    #[test]
+    fn bundled_puzzles_are_mates() {
+        let (pack, errors) =
+            parse_pack(include_str!("../../assets/puzzles/first_mates.ron")).unwrap();
+        assert_eq!(errors, Vec::<String>::new());
+        for puzzle in pack.puzzles {
+            let position = &puzzle.position;
+            assert!(forces_mate(
+                &Board::new(position.cube_side_length),
+                &position.units,
+                position.turn,
+                puzzle.solution[0],
+                puzzle.mate_in,
+                position.variant
+            ));
+        }
+    }
+
+    #[test]
+    fn solutions_have_to_end_with_the_solver() {
+        let text = "(name: \"Pack\", puzzles: [
+            (position: \"2 K202+,k101- w 0/0 standard\", solution: \"Kyb2 Kya1\"),
+            (position: \"2 K202+,k101- w 0/0 duel\", solution: \"Kyb2\"),
+        ])";
+        let (pack, errors) = parse_pack(text).unwrap();
+        assert!(pack.puzzles.is_empty());
+        assert_eq!(errors.len(), 2);
+    }
+}
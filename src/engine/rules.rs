@@ -0,0 +1,133 @@
+//! The rules that set the variants apart. The game, the movement code and the AI ask the
+//! [`Ruleset`] of the variant what to do instead of checking which variant is played, so a new
+//! variant only has to pick its rules in [`Variant::rules`].
+
+use crate::engine::cell::Board;
+use crate::engine::movement::{self, GameMove, Variant};
+use crate::engine::units::{Team, Unit, UnitType, Units};
+
+/// The rules that differ between variants, everything else is the same in all of them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Ruleset {
+    /// Units gain a level when capturing, which unlocks extra movement
+    pub(crate) capturing_gains_levels: bool,
+    /// Captures blow up the units around them, see [`movement::explode`]
+    pub(crate) captures_explode: bool,
+    /// Pawns are promoted on the home face of the other team
+    pub(crate) pawns_promote: bool,
+    /// A neutral comet starts on the board and makes a random move after every full turn
+    pub(crate) neutral_units_move: bool,
+    /// Teams gain energy every turn, which can be spent to summon units on their home face
+    pub(crate) summons: bool,
+    /// Both teams secretly pick a move, then the moves are made at the same time
+    pub(crate) simultaneous_moves: bool,
+}
+
+impl Ruleset {
+    const STANDARD: Ruleset = Ruleset {
+        capturing_gains_levels: false,
+        captures_explode: false,
+        pawns_promote: true,
+        neutral_units_move: false,
+        summons: false,
+        simultaneous_moves: false,
+    };
+
+    /// Moves a unit without checking that the move is legal, and returns the captured unit and the
+    /// units blown up by the capture
+    pub(crate) fn apply_move(
+        &self,
+        game_move: GameMove,
+        board: &Board,
+        units: &mut Units,
+    ) -> (Option<Unit>, Vec<Unit>) {
+        let captured_unit = movement::apply_move(game_move, units, *self);
+        let exploded_units = if self.captures_explode && captured_unit.is_some() {
+            movement::explode(game_move.to, board.cube_side_length, units)
+        } else {
+            Vec::new()
+        };
+        (captured_unit, exploded_units)
+    }
+
+    /// If the rules allow the move. Only captures in the atomic variant can be forbidden: kings
+    /// can't capture, and no capture may blow up the king of the team that makes it.
+    pub(crate) fn allows_move(&self, game_move: GameMove, board: &Board, units: &Units) -> bool {
+        if !self.captures_explode || !units.is_unit_at(game_move.to) {
+            return true;
+        }
+        let Some(unit) = units.get_unit(game_move.from) else {
+            return false;
+        };
+        if unit.unit_type == UnitType::King {
+            return false;
+        }
+        !game_move
+            .to
+            .get_adjacent(board.cube_side_length)
+            .into_iter()
+            .filter_map(|adjacent| units.get_unit(adjacent))
+            .any(|other| other.team == unit.team && other.unit_type == UnitType::King)
+    }
+
+    /// The team that has won by taking the king of the other team, if any
+    pub(crate) fn winner(&self, units: &Units) -> Option<Team> {
+        let has_king = |team: Team| {
+            units
+                .all_units_iter()
+                .any(|unit| unit.team == team && unit.unit_type == UnitType::King)
+        };
+        if !has_king(Team::White) {
+            Some(Team::Black)
+        } else if !has_king(Team::Black) {
+            Some(Team::White)
+        } else {
+            None
+        }
+    }
+
+    /// How much a unit is worth to the AI
+    pub(crate) fn unit_value(&self, unit: &Unit) -> f32 {
+        let mut value = unit.unit_type.material_value();
+        // Units that have gained levels are worth a bit more, and even more once they have
+        // unlocked their extra movement
+        if self.capturing_gains_levels {
+            value += unit.level as f32 * 0.25;
+            if unit.has_level_ability() {
+                value += 0.5;
+            }
+        }
+        value
+    }
+}
+
+impl Variant {
+    pub(crate) fn rules(self) -> Ruleset {
+        let standard = Ruleset::STANDARD;
+        match self {
+            Variant::Standard => standard,
+            Variant::Experience => Ruleset {
+                capturing_gains_levels: true,
+                ..standard
+            },
+            Variant::Reinforcements => Ruleset {
+                summons: true,
+                ..standard
+            },
+            Variant::Wandering => Ruleset {
+                neutral_units_move: true,
+                ..standard
+            },
+            // Pawns aren't promoted when both moves are made at the same time
+            Variant::Duel => Ruleset {
+                simultaneous_moves: true,
+                pawns_promote: false,
+                ..standard
+            },
+            Variant::Atomic => Ruleset {
+                captures_explode: true,
+                ..standard
+            },
+        }
+    }
+}
@@ -0,0 +1,455 @@
+use std::slice::{Iter, IterMut};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::cell::CellCoordinates;
+use crate::engine::direction::{CartesianDirection, RadialDirection};
+use crate::engine::pieces;
+use crate::engine::ObjectId;
+
+/// The highest level a unit can reach in the experience variant
+pub(crate) const MAX_LEVEL: u32 = 3;
+
+/// The units behind the pawns of the standard setup, in the order of the cells they start on. The
+/// first two cells are on the home face.
+const BACK_UNITS: [UnitType; 4] = [
+    UnitType::King,
+    UnitType::Knight,
+    UnitType::Queen,
+    UnitType::Rook,
+];
+
+/// The units behind the pawns on cubes at most [`SMALL_CUBE`] cells wide, where a knight or a queen
+/// reaches too much of the cube
+const SMALL_CUBE_BACK_UNITS: [UnitType; 4] = [
+    UnitType::King,
+    UnitType::Ferz,
+    UnitType::Wazir,
+    UnitType::Rook,
+];
+
+/// The widest cube that starts with [`SMALL_CUBE_BACK_UNITS`]
+const SMALL_CUBE: u32 = 3;
+
+/// The widest cube that starts with [`BACK_UNITS`], wider cubes start with [`WIDE_CUBE_UNITS`]
+const STANDARD_CUBE: u32 = 4;
+
+/// The cells of a home face, as offsets from the corner of the cube, in the order of the units of
+/// [`WIDE_CUBE_UNITS`]. Every slot is a diagonal cell or a pair of cells mirrored in the diagonal.
+/// The first slot is ring 0, the next two ring 1 and the last three ring 2.
+const SLOTS: [&[(u32, u32)]; 6] = [
+    &[(0, 0)],
+    &[(1, 1)],
+    &[(1, 0), (0, 1)],
+    &[(2, 2)],
+    &[(2, 1), (1, 2)],
+    &[(2, 0), (0, 2)],
+];
+
+/// The units behind the pawns on cubes wider than [`STANDARD_CUBE`], for every home face of the
+/// team slot by slot, see [`SLOTS`]. The face of the king comes first.
+const WIDE_CUBE_UNITS: [[UnitType; 6]; 3] = [
+    [
+        UnitType::King,
+        UnitType::Knight,
+        UnitType::Bishop,
+        UnitType::Archbishop,
+        UnitType::Knight,
+        UnitType::Rook,
+    ],
+    [
+        UnitType::Queen,
+        UnitType::Knight,
+        UnitType::Rook,
+        UnitType::Chancellor,
+        UnitType::Bishop,
+        UnitType::Knight,
+    ],
+    [
+        UnitType::Rook,
+        UnitType::Knight,
+        UnitType::Bishop,
+        UnitType::Queen,
+        UnitType::Knight,
+        UnitType::Bishop,
+    ],
+];
+
+/// The units behind the pawns for the size of the cube, with the cells of white they start on. The
+/// king comes first, followed by the others on its face. Returns those units together with how
+/// many of them are on the face of the king.
+fn back_units(cube_side_length: u32) -> (Vec<(UnitType, CellCoordinates)>, usize) {
+    let n = cube_side_length;
+    if n <= STANDARD_CUBE {
+        let units = match n <= SMALL_CUBE {
+            true => SMALL_CUBE_BACK_UNITS,
+            false => BACK_UNITS,
+        };
+        let offsets = [(0, (0, 0)), (0, (1, 1)), (1, (0, 0)), (2, (0, 0))];
+        let cells = offsets.map(|(face, offset)| home_face_cell(face, offset, n));
+        return (units.into_iter().zip(cells).collect(), 2);
+    }
+    let slots = &SLOTS[..slot_count(piece_rings(n))];
+    let mut units = Vec::new();
+    for (face, face_units) in WIDE_CUBE_UNITS.iter().enumerate() {
+        for (&unit_type, slot) in face_units.iter().zip(slots) {
+            for &offset in slot.iter() {
+                units.push((unit_type, home_face_cell(face, offset, n)));
+            }
+        }
+    }
+    let on_king_face = slots.iter().map(|slot| slot.len()).sum();
+    (units, on_king_face)
+}
+
+/// How many rings of cells around the corner are filled with units behind the pawns on cubes
+/// wider than [`STANDARD_CUBE`], which leaves the middle of the faces between the teams empty. At
+/// most the three rings of [`SLOTS`].
+fn piece_rings(cube_side_length: u32) -> u32 {
+    ((cube_side_length - 1) / 2).min(3)
+}
+
+/// How many of [`SLOTS`] make up the first `rings` rings
+fn slot_count(rings: u32) -> usize {
+    (1..=rings as usize).sum()
+}
+
+/// The cells the pawns of white start on, with the way they walk. The pawns stand on the ring
+/// around the units behind them, apart from its diagonal cells, and walk away from the corner.
+fn pawns(cube_side_length: u32) -> Vec<(CellCoordinates, RadialDirection)> {
+    let n = cube_side_length;
+    let ring = match n <= STANDARD_CUBE {
+        true => 1,
+        false => piece_rings(n),
+    };
+    let mut pawns = Vec::new();
+    for face in 0..3 {
+        for offset in (0..ring).flat_map(|other| [(ring, other), (other, ring)]) {
+            let coords = home_face_cell(face, offset, n);
+            let further = match offset.0 > offset.1 {
+                true => (offset.0 + 1, offset.1),
+                false => (offset.0, offset.1 + 1),
+            };
+            let next = home_face_cell(face, further, n);
+            let axis = (0..3).find(|&axis| coords[axis] != next[axis]).unwrap();
+            let walk = CartesianDirection::from_axis_num(axis as u32, false);
+            let direction = RadialDirection::directions()
+                .into_iter()
+                .find(|direction| {
+                    direction.to_cartesian_direction(coords.normal_direction()) == Some(walk)
+                })
+                .unwrap();
+            pawns.push((coords, direction));
+        }
+    }
+    pawns
+}
+
+/// A cell on one of the three faces white starts on, around the corner of the cube where all
+/// coordinates are highest. The faces are +Y, where the king starts, +Z and +X, and `offset` is
+/// how many cells the cell is from the corner along the two axes of the face.
+fn home_face_cell(face: usize, offset: (u32, u32), cube_side_length: u32) -> CellCoordinates {
+    let (a, b) = (cube_side_length - offset.0, cube_side_length - offset.1);
+    match face {
+        0 => CellCoordinates::new(a, 0, b, true),
+        1 => CellCoordinates::new(a, b, 0, true),
+        _ => CellCoordinates::new(0, a, b, true),
+    }
+}
+
+/// The unit that starts where `unit_type` starts on bigger cubes, which is another unit on small
+/// cubes
+pub(crate) fn starting_counterpart(unit_type: UnitType, cube_side_length: u32) -> UnitType {
+    if cube_side_length > SMALL_CUBE {
+        return unit_type;
+    }
+    BACK_UNITS
+        .iter()
+        .position(|&back_unit| back_unit == unit_type)
+        .map_or(unit_type, |index| SMALL_CUBE_BACK_UNITS[index])
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Unit {
+    pub(crate) unit_type: UnitType,
+    pub(crate) coords: CellCoordinates,
+    /// The entity that represents this unit on the board
+    pub(crate) entity: Option<ObjectId>,
+    pub(crate) team: Team,
+    pub(crate) dead: bool,
+    /// Number of levels gained by capturing in the experience variant
+    pub(crate) level: u32,
+}
+
+impl Unit {
+    pub(crate) fn new(unit_type: UnitType, team: Team, coords: CellCoordinates) -> Self {
+        Unit {
+            unit_type,
+            coords,
+            entity: None,
+            team,
+            dead: false,
+            level: 0,
+        }
+    }
+
+    pub(crate) fn set_entity(&mut self, entity: ObjectId) {
+        self.entity = Some(entity);
+    }
+
+    pub(crate) fn move_unit_to(&mut self, coords: CellCoordinates) {
+        self.coords = coords
+    }
+
+    /// Returns false if the unit already is at the max level
+    pub(crate) fn gain_level(&mut self) -> bool {
+        if self.level >= MAX_LEVEL {
+            return false;
+        }
+        self.level += 1;
+        true
+    }
+
+    /// If the unit has gained enough levels to unlock its extra movement
+    pub(crate) fn has_level_ability(&self) -> bool {
+        self.level >= self.unit_type.level_for_ability()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[allow(unused)]
+pub(crate) enum UnitType {
+    Rook,
+    Bishop,
+    King,
+    /// (The direction that the pawn moves in, if the pawn has moved before)
+    Pawn(RadialDirection, bool),
+    Knight,
+    Queen,
+    /// Neutral unit in the wandering variant. Moves one step in any direction, also over edges
+    Comet,
+    /// Moves like a bishop or a knight
+    Archbishop,
+    /// Moves like a rook or a knight
+    Chancellor,
+    /// Moves like a queen or a knight
+    Amazon,
+    /// Moves one step straight, also over an edge
+    Wazir,
+    /// Moves one step diagonally, also over an edge
+    Ferz,
+    /// A unit from the pieces file, by its index in [`pieces::definitions`]
+    Defined(u8),
+}
+
+impl UnitType {
+    pub(crate) fn model_name(&self) -> &str {
+        match self {
+            UnitType::Rook => "rook",
+            UnitType::Bishop => "bishop",
+            UnitType::King => "king",
+            UnitType::Pawn(_, _) => "pawn",
+            UnitType::Knight => "knight",
+            UnitType::Queen => "queen",
+            UnitType::Comet => "laser",
+            UnitType::Archbishop => "melee",
+            UnitType::Chancellor => "laser",
+            UnitType::Amazon => "melee",
+            UnitType::Wazir => "laser",
+            UnitType::Ferz => "melee",
+            UnitType::Defined(index) => &pieces::definition(*index).model,
+        }
+    }
+
+    pub(crate) fn can_capture_over_edge(&self) -> bool {
+        matches!(self, Self::Knight | Self::Comet)
+    }
+
+    /// If the unit can also make the moves of a knight, which can capture over an edge even if the
+    /// rest of its moves can't
+    pub(crate) fn also_moves_like_knight(&self) -> bool {
+        matches!(self, Self::Archbishop | Self::Chancellor | Self::Amazon)
+    }
+
+    /// The level needed to unlock the extra movement of the experience variant
+    pub(crate) fn level_for_ability(&self) -> u32 {
+        match self {
+            UnitType::Pawn(_, _) | UnitType::King => 2,
+            _ => 1,
+        }
+    }
+
+    pub(crate) fn material_value(&self) -> f32 {
+        match self {
+            UnitType::Rook => 5.,
+            UnitType::Bishop => 3.5,
+            UnitType::King => 1000.,
+            UnitType::Pawn(_, _) => 1.,
+            UnitType::Knight => 3.,
+            UnitType::Queen => 9.,
+            UnitType::Comet => 2.,
+            UnitType::Archbishop => 7.,
+            UnitType::Chancellor => 8.,
+            UnitType::Amazon => 12.,
+            UnitType::Wazir => 1.5,
+            UnitType::Ferz => 1.5,
+            UnitType::Defined(index) => pieces::definition(*index).value,
+        }
+    }
+
+    #[allow(unused)]
+    pub(crate) fn symbol(&self) -> char {
+        match self {
+            UnitType::Rook => '♖',
+            UnitType::Bishop => '♗',
+            UnitType::King => '♔',
+            UnitType::Pawn(_, _) => '♙',
+            UnitType::Knight => '♘',
+            UnitType::Queen => '♕',
+            UnitType::Comet => '☄',
+            UnitType::Archbishop => '\u{1FA50}',
+            UnitType::Chancellor => '\u{1FA4F}',
+            UnitType::Amazon => '\u{1FA4E}',
+            UnitType::Wazir => '\u{1FA20}',
+            UnitType::Ferz => '\u{1FA21}',
+            UnitType::Defined(index) => pieces::definition(*index).symbol,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Units {
+    units: Vec<Unit>,
+}
+
+impl Units {
+    pub(crate) fn get_unit(&self, coords: CellCoordinates) -> Option<&Unit> {
+        self.units.iter().find(|unit| unit.coords == coords)
+    }
+
+    pub(crate) fn get_unit_mut(&mut self, coords: CellCoordinates) -> Option<&mut Unit> {
+        self.units.iter_mut().find(|unit| unit.coords == coords)
+    }
+
+    pub(crate) fn get_unit_from_entity(&self, entity: ObjectId) -> Option<&Unit> {
+        self.units.iter().find(|unit| {
+            if let Some(unit_entity) = unit.entity {
+                unit_entity == entity
+            } else {
+                false
+            }
+        })
+    }
+
+    pub(crate) fn is_unit_at(&self, coords: CellCoordinates) -> bool {
+        self.units.iter().any(|unit| unit.coords == coords)
+    }
+
+    pub(crate) fn remove_dead_units(&mut self) {
+        self.units.retain(|unit| !unit.dead)
+    }
+
+    pub(crate) fn add_unit(&mut self, unit: Unit) {
+        self.units.push(unit)
+    }
+
+    /// Overwrites these units with a copy of `other`, reusing the existing allocation
+    pub(crate) fn copy_from(&mut self, other: &Units) {
+        self.units.clone_from(&other.units)
+    }
+
+    /// White starts around the corner of the cube where all coordinates are highest, and black around
+    /// the opposite corner. Fits on every cube at least 2 cells wide. Small cubes get a wazir and a
+    /// ferz instead of the knight and the queen, and wide cubes get more rings of units around the
+    /// corner.
+    pub(crate) fn game_starting_configuration(cube_side_length: u32) -> Units {
+        let (back_units, _) = back_units(cube_side_length);
+        Self::starting_configuration_with(cube_side_length, back_units)
+    }
+
+    /// The starting configuration with the units behind the pawns shuffled, like in Chess960. The
+    /// same `seed` always gives the same setup. The king stays on its home face, so the pawns are
+    /// still promoted where the other king starts, and black gets the mirrored setup of white.
+    pub(crate) fn shuffled_starting_configuration(cube_side_length: u32, seed: u64) -> Units {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (mut back_units, on_king_face) = back_units(cube_side_length);
+        let mut unit_types: Vec<_> = back_units.iter().map(|&(unit_type, _)| unit_type).collect();
+        unit_types[1..].shuffle(&mut rng);
+        unit_types.swap(0, rng.gen_range(0..on_king_face));
+        for ((unit_type, _), shuffled) in back_units.iter_mut().zip(unit_types) {
+            *unit_type = shuffled;
+        }
+        Self::starting_configuration_with(cube_side_length, back_units)
+    }
+
+    /// Puts the units of white on their cells, and the units of black on the mirrored cells
+    fn starting_configuration_with(
+        cube_side_length: u32,
+        back_units: Vec<(UnitType, CellCoordinates)>,
+    ) -> Units {
+        let pawns = pawns(cube_side_length)
+            .into_iter()
+            .map(|(coords, direction)| (UnitType::Pawn(direction, false), coords));
+        let mut output = Units::default();
+        for (unit_type, coords) in back_units.into_iter().chain(pawns) {
+            output.add_unit(Unit::new(unit_type, Team::White, coords));
+            output.add_unit(Unit::new(
+                unit_type,
+                Team::Black,
+                coords.opposite(cube_side_length),
+            ));
+        }
+        output
+    }
+
+    pub(crate) fn all_units_iter_mut(&mut self) -> IterMut<'_, Unit> {
+        self.units.iter_mut()
+    }
+
+    pub(crate) fn all_units_iter(&self) -> Iter<'_, Unit> {
+        self.units.iter()
+    }
+
+    pub(crate) fn remove_unit(&mut self, coords: CellCoordinates) -> Option<Unit> {
+        let index = self.units.iter().position(|unit| unit.coords == coords)?;
+
+        Some(self.units.swap_remove(index))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Team {
+    Black,
+    White,
+    /// Units that belong to neither player, like the comet in the wandering variant
+    Neutral,
+}
+impl Team {
+    pub(crate) fn opposite(&self) -> Self {
+        match self {
+            Team::Black => Team::White,
+            Team::White => Team::Black,
+            Team::Neutral => Team::Neutral,
+        }
+    }
+
+    pub(crate) fn sign(&self) -> i32 {
+        match self {
+            Team::Black => -1,
+            Team::White => 1,
+            Team::Neutral => 0,
+        }
+    }
+
+    /// For storing per-team values in arrays
+    pub(crate) fn index(&self) -> usize {
+        match self {
+            Team::White => 0,
+            Team::Black => 1,
+            Team::Neutral => panic!("The neutral team has no per-team values"),
+        }
+    }
+}
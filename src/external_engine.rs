@@ -0,0 +1,238 @@
+//! Lets an external engine play the team of the AI, instead of the built-in AI. The engine is a
+//! program that is sent commands on stdin and answers on stdout, one per line, much like UCI:
+//!
+//! - `cci` is sent once the engine has started. The engine answers with `id name <name>`, and then
+//!   `cciok` once it is ready.
+//! - `position cubefen <cubefen> moves <move> <move>...` is the position the game started from,
+//!   followed by every move made since in the notation of [`crate::engine::notation`]. Summons are
+//!   written like `♘@zc3`.
+//! - `go depth <moves>` asks for a move, with the depth the built-in AI would search. The engine
+//!   answers with `bestmove <move>`.
+//! - `quit` is sent when the game closes.
+//!
+//! Other lines from the engine are ignored. If the engine exits or picks an illegal move, the
+//! built-in AI takes over.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Mutex;
+use std::thread;
+
+use bevy::prelude::*;
+
+use crate::duel;
+use crate::engine::cell::CellCoordinates;
+use crate::game_record;
+use crate::gamemanager::{self, Game, GameEvents};
+use crate::reinforcements;
+use crate::rng::GameRng;
+use crate::AppState;
+
+/// Runs the engine started with the command, if there is one
+pub(crate) struct ExternalEnginePlugin(pub(crate) Option<String>);
+
+impl Plugin for ExternalEnginePlugin {
+    fn build(&self, app: &mut App) {
+        let Some(command) = &self.0 else {
+            return;
+        };
+        match ExternalEngine::launch(command) {
+            Ok(engine) => {
+                app.insert_resource(engine).add_system(
+                    engine_play
+                        .run_if(resource_exists::<ExternalEngine>())
+                        .in_set(OnUpdate(AppState::InGame)),
+                );
+            }
+            Err(err) => eprintln!("Couldn't start the engine {}: {}", command, err),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub(crate) struct ExternalEngine {
+    child: Child,
+    stdin: ChildStdin,
+    /// The lines the engine has written, read on another thread so that waiting for the engine
+    /// doesn't hold up the game
+    lines: Mutex<Receiver<String>>,
+    name: Option<String>,
+    /// Set once the engine has answered `cciok`
+    ready: bool,
+    /// The number of moves in the history of the game when the engine was asked for a move, until
+    /// it answers. An answer for a position that has changed since, like by undoing a turn, is
+    /// ignored.
+    asked_at: Option<usize>,
+}
+
+impl ExternalEngine {
+    /// Starts the engine. The command is split on whitespace into the program and its arguments.
+    fn launch(command: &str) -> io::Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words.next().ok_or(io::ErrorKind::InvalidInput)?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child
+            .stdin
+            .take()
+            .expect("The input of the engine is piped");
+        let stdout = child
+            .stdout
+            .take()
+            .expect("The output of the engine is piped");
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut engine = ExternalEngine {
+            child,
+            stdin,
+            lines: Mutex::new(receiver),
+            name: None,
+            ready: false,
+            asked_at: None,
+        };
+        engine.send("cci")?;
+        Ok(engine)
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()
+    }
+
+    /// The lines the engine has written since the last call. Fails once the engine has exited.
+    fn receive(&self) -> Result<Vec<String>, TryRecvError> {
+        let lines = self.lines.lock().unwrap();
+        let mut received = Vec::new();
+        loop {
+            match lines.try_recv() {
+                Ok(line) => received.push(line),
+                Err(TryRecvError::Empty) => return Ok(received),
+                Err(TryRecvError::Disconnected) if !received.is_empty() => return Ok(received),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Asks for a move in the current position of the game
+    fn ask_for_move(&mut self, game: &Game) -> io::Result<()> {
+        let moves: Vec<&str> = game
+            .history
+            .moves()
+            .map(|recorded_move| recorded_move.notation.as_str())
+            .collect();
+        let mut position = format!("position cubefen {}", game.starting_cubefen());
+        if !moves.is_empty() {
+            position.push_str(" moves ");
+            position.push_str(&moves.join(" "));
+        }
+        self.send(&position)?;
+        self.send(&format!("go depth {}", game.ai_depth))?;
+        self.asked_at = Some(moves.len());
+        Ok(())
+    }
+}
+
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        if self.send("quit").is_err() {
+            let _ = self.child.kill();
+        }
+    }
+}
+
+/// Asks the engine for a move whenever it is the turn of the AI, and makes the move it picks
+fn engine_play(
+    mut commands: Commands,
+    mut engine: ResMut<ExternalEngine>,
+    mut game: ResMut<Game>,
+    mut events: GameEvents,
+    mut rng: ResMut<GameRng>,
+) {
+    let lines = match engine.receive() {
+        Ok(lines) => lines,
+        Err(_) => {
+            warn!("The engine has exited, the built-in AI plays instead");
+            commands.remove_resource::<ExternalEngine>();
+            return;
+        }
+    };
+    let mut best_move = None;
+    for line in lines {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix("id name ") {
+            engine.name = Some(name.to_string());
+        } else if line == "cciok" {
+            engine.ready = true;
+        } else if let Some(written) = line.strip_prefix("bestmove ") {
+            // Answers to positions that have changed since are dropped
+            if engine.asked_at.take() == Some(game.history.moves().count()) {
+                best_move = Some(written.trim().to_string());
+            }
+        }
+    }
+
+    let game = &mut *game;
+    if !engine.ready || game.ai_playing != Some(game.turn) || game.result.is_some() {
+        return;
+    }
+    let result = match best_move {
+        Some(written) => make_engine_move(&written, game, &mut events, &mut rng),
+        None if engine.asked_at.is_none() => engine
+            .ask_for_move(game)
+            .map_err(|err| format!("Couldn't ask for a move: {}", err)),
+        None => Ok(()),
+    };
+    if let Err(err) = result {
+        let name = engine.name.as_deref().unwrap_or("The engine");
+        warn!("{}: {}, the built-in AI plays instead", name, err);
+        commands.remove_resource::<ExternalEngine>();
+    }
+}
+
+/// Makes a move or summon written in notation, checking it like a move made by a player
+fn make_engine_move(
+    written: &str,
+    game: &mut Game,
+    events: &mut GameEvents,
+    rng: &mut GameRng,
+) -> Result<(), String> {
+    let illegal = || format!("picked the illegal move {}", written);
+    if let Some((symbol, cell)) = written.split_once('@') {
+        let coords = CellCoordinates::from_display(cell).map_err(|_| illegal())?;
+        if !(0..3).all(|axis| coords[axis] <= game.board.cube_side_length) {
+            return Err(illegal());
+        }
+        game.pending_summon = reinforcements::summonable_from_symbol(symbol);
+        if !reinforcements::try_summon(game, coords) {
+            return Err(illegal());
+        }
+    } else {
+        let (game_move, promotion) =
+            game_record::find_move(game, written, game.turn).map_err(|_| illegal())?;
+        let made = if game.variant.rules().simultaneous_moves {
+            duel::lock_in_move(game_move, game, events)
+        } else if let Some(promotion) = promotion {
+            gamemanager::make_promoting_move(game_move, promotion, game, events)
+        } else {
+            gamemanager::make_move(game_move, game, events)
+        };
+        if !made {
+            return Err(illegal());
+        }
+    }
+    gamemanager::end_turn(game, events, rng);
+    Ok(())
+}
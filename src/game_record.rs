@@ -0,0 +1,538 @@
+//! Finished games saved in a format like PGN, the format chess games are shared in, and loaded
+//! back to be played on
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+
+use crate::duel;
+use crate::engine::cell::CellCoordinates;
+use crate::engine::duel::resolve_moves;
+use crate::engine::movement::{self, GameMove};
+use crate::engine::notation;
+use crate::engine::units::{Team, UnitType};
+use crate::gamemanager::{Game, GameEnded, GameResult};
+use crate::match_play::Match;
+use crate::presets::{Handicap, Odds};
+use crate::reinforcements;
+use crate::settings::{self, Settings};
+
+/// Finished games are saved in this folder in the config dir, one file per game
+const GAMES_FOLDER: &str = "games";
+
+/// The unfinished game is saved to this file in the config dir after every turn, so that it can be
+/// continued from the main menu. It is removed when the game ends.
+const AUTOSAVE_PATH: &str = "autosave.pgn";
+
+/// Saves every finished game. The moves are written in the notation of [`notation`], and the
+/// starting position is written as a CubeFEN so that games that didn't start from the starting
+/// position can be replayed too.
+pub(crate) fn export_finished_game(
+    mut game_ended: EventReader<GameEnded>,
+    game: Res<Game>,
+    current_match: Option<Res<Match>>,
+) {
+    for game_ended in game_ended.iter() {
+        let players = players(&game, current_match.as_deref());
+        let timestamp = timestamp();
+        let text = game_record(&game, Some(game_ended.result), players, &timestamp);
+        let dir = settings::config_dir().join(GAMES_FOLDER);
+        let path = dir.join(format!("{}.pgn", timestamp.replace([':', ' '], "-")));
+        let written = fs::create_dir_all(&dir).and_then(|()| fs::write(&path, text));
+        match written {
+            Ok(()) => println!("Saved the game to {}", path.display()),
+            Err(err) => warn!("Couldn't write {}: {}", path.display(), err),
+        }
+        // The finished game can't be continued
+        let _ = fs::remove_file(settings::config_dir().join(AUTOSAVE_PATH));
+    }
+}
+
+/// Saves the game whenever a turn has been played or undone
+pub(crate) fn autosave(
+    game: Res<Game>,
+    current_match: Option<Res<Match>>,
+    mut saved_turns: Local<Option<usize>>,
+) {
+    if *saved_turns == Some(game.history.turns_played()) {
+        return;
+    }
+    *saved_turns = Some(game.history.turns_played());
+    let players = players(&game, current_match.as_deref());
+    let text = game_record(&game, None, players, &timestamp());
+    let path = settings::config_dir().join(AUTOSAVE_PATH);
+    let written = fs::create_dir_all(settings::config_dir()).and_then(|()| fs::write(&path, text));
+    if let Err(err) = written {
+        warn!("Couldn't write {}: {}", path.display(), err);
+    }
+}
+
+pub(crate) fn has_autosave() -> bool {
+    settings::config_dir().join(AUTOSAVE_PATH).exists()
+}
+
+/// The game saved by [`autosave`]
+pub(crate) fn load_autosave(settings: &Settings) -> Result<Game, String> {
+    load_game(&settings::config_dir().join(AUTOSAVE_PATH), settings)
+}
+
+/// The finished games saved by [`export_finished_game`]. Games that can't be loaded anymore are
+/// left out.
+pub(crate) fn saved_games(settings: &Settings) -> Vec<Game> {
+    let dir = settings::config_dir().join(GAMES_FOLDER);
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| load_game(&entry.path(), settings).ok())
+        .collect()
+}
+
+/// The unfinished game written like a saved game, which is how spectators of online games are
+/// sent the moves made before they joined
+pub(crate) fn write_game(game: &Game) -> String {
+    game_record(game, None, players(game, None), &timestamp())
+}
+
+/// The names of the white and the black player
+fn players(game: &Game, current_match: Option<&Match>) -> [&'static str; 2] {
+    if let Some(current_match) = current_match {
+        let [first, second] = current_match.player_names();
+        return match current_match.first_player_team() {
+            Team::White => [first, second],
+            _ => [second, first],
+        };
+    }
+    match game.ai_playing {
+        Some(Team::White) => ["AI", "You"],
+        Some(_) => ["You", "AI"],
+        None => ["Player 1", "Player 2"],
+    }
+}
+
+/// The result is None for a game that hasn't ended
+fn game_record(
+    game: &Game,
+    result: Option<GameResult>,
+    players: [&str; 2],
+    timestamp: &str,
+) -> String {
+    let result = match result {
+        Some(GameResult::Win(Team::White)) => "1-0",
+        Some(GameResult::Win(_)) => "0-1",
+        Some(GameResult::Draw) => "1/2-1/2",
+        None => "*",
+    };
+    let (date, time) = timestamp.split_once(' ').unwrap_or_default();
+    // The units of the odds of a handicap are missing from the CubeFEN already
+    let extra_moves = game.handicap.and_then(|handicap| {
+        let every = handicap.extra_move_every?;
+        Some(format!("{:?} {}", handicap.team.opposite(), every))
+    });
+    let tag = |name: &str, value: &str| format!("[{} \"{}\"]\n", name, value);
+    let mut tags = [
+        ("Event", "Chess on a cube"),
+        ("UTCDate", &date.replace('-', ".")),
+        ("UTCTime", time),
+        ("White", players[0]),
+        ("Black", players[1]),
+        ("Result", result),
+        ("CubeSize", &game.board.cube_side_length.to_string()),
+        ("Variant", game.variant.name()),
+        ("CubeFEN", &game.starting_cubefen()),
+    ]
+    .map(|(name, value)| tag(name, value))
+    .to_vec();
+    if let Some(extra_moves) = extra_moves {
+        tags.push(tag("ExtraMoves", &extra_moves));
+    }
+
+    format!("{}\n{}{}\n", tags.concat(), movetext(game), result)
+}
+
+/// The moves numbered like in PGN, with a number before every move of white. The moves of the
+/// comet in the wandering variant are written as comments.
+fn movetext(game: &Game) -> String {
+    let mut output = String::new();
+    let mut number = 0;
+    let mut black_needs_number = true;
+    for recorded_move in game.history.moves() {
+        match recorded_move.team {
+            Team::White => {
+                number += 1;
+                output.push_str(&format!("{}. ", number));
+                black_needs_number = false;
+            }
+            Team::Black if black_needs_number => {
+                number += 1;
+                output.push_str(&format!("{}... ", number));
+            }
+            Team::Black => black_needs_number = true,
+            Team::Neutral => {
+                output.push_str(&format!("{{{}}} ", recorded_move.notation));
+                black_needs_number = true;
+                continue;
+            }
+        }
+        output.push_str(&recorded_move.notation);
+        output.push(' ');
+    }
+    output
+}
+
+/// The current date and time in UTC, as `YYYY-MM-DD HH:MM:SS`
+fn timestamp() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, seconds_of_day) = (seconds / 86400, seconds % 86400);
+
+    // Converts days since 1970 to a date, see http://howardhinnant.github.io/date_algorithms.html
+    let days = days as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60
+    )
+}
+
+/// Reads a game saved by [`export_finished_game`], replaying its moves from the starting position.
+/// Every move is checked to be legal. The game continues from the last position, and the moves can
+/// be undone one turn at a time.
+pub(crate) fn load_game(path: &Path, settings: &Settings) -> Result<Game, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| format!("Couldn't read {}: {}", path.display(), err))?;
+    read_game(&text, settings).map_err(|err| format!("Couldn't load {}: {}", path.display(), err))
+}
+
+/// Reads a game written by [`write_game`] or saved by the game or the dedicated server
+pub(crate) fn read_game(text: &str, settings: &Settings) -> Result<Game, String> {
+    let mut tags = HashMap::new();
+    let mut movetext = String::new();
+    for line in text.lines().map(str::trim) {
+        match line.strip_prefix('[').and_then(|tag| tag.strip_suffix(']')) {
+            Some(tag) => {
+                if let Some((name, value)) = tag.split_once(' ') {
+                    tags.insert(name, value.trim_matches('"'));
+                }
+            }
+            None => {
+                movetext.push_str(line);
+                movetext.push(' ');
+            }
+        }
+    }
+    let start = tags
+        .get("CubeFEN")
+        .ok_or("the starting position is missing")?;
+    let mut game = Game::from_cubefen(start, settings)?;
+    game.ai_playing = match (tags.get("White"), tags.get("Black")) {
+        (Some(&"AI"), _) => Some(Team::White),
+        (_, Some(&"AI")) => Some(Team::Black),
+        _ => None,
+    };
+    if let Some(extra_moves) = tags.get("ExtraMoves") {
+        game.handicap = Some(read_extra_moves(extra_moves)?);
+    }
+
+    let mut tokens = movetext
+        .split_whitespace()
+        .filter(|token| !is_move_number(token) && !["1-0", "0-1", "1/2-1/2", "*"].contains(token))
+        .peekable();
+    while let Some(token) = tokens.next() {
+        if game.variant.rules().simultaneous_moves {
+            let black_move = tokens.next().ok_or("the last move of black is missing")?;
+            replay_duel_moves(&mut game, [token, black_move])?;
+            game.next_player_turn();
+        } else {
+            let team = game.turn;
+            replay_move(&mut game, token, team)?;
+        }
+        game.next_player_turn();
+        // The comet moves after both teams have moved
+        let comet_move = tokens
+            .peek()
+            .and_then(|token| token.strip_prefix('{')?.strip_suffix('}'));
+        if let Some(comet_move) = comet_move {
+            replay_move(&mut game, comet_move, Team::Neutral)?;
+            tokens.next();
+        }
+        game.record_position();
+    }
+    Ok(game)
+}
+
+/// The team that gets extra moves and how often, written like `Black 3`
+fn read_extra_moves(text: &str) -> Result<Handicap, String> {
+    let invalid = || format!("{} are not extra moves", text);
+    let (team, every) = text.split_once(' ').ok_or_else(invalid)?;
+    let team = match team {
+        "White" => Team::White,
+        "Black" => Team::Black,
+        _ => return Err(invalid()),
+    };
+    Ok(Handicap {
+        team: team.opposite(),
+        odds: Odds::None,
+        extra_move_every: Some(every.parse().map_err(|_| invalid())?),
+    })
+}
+
+/// Move numbers are written like `12.` before a move of white, and `12...` before a move of black
+fn is_move_number(token: &str) -> bool {
+    token.ends_with('.') && token.trim_end_matches('.').parse::<u32>().is_ok()
+}
+
+fn replay_move(game: &mut Game, written: &str, team: Team) -> Result<(), String> {
+    if let Some((symbol, cell)) = written.split_once('@') {
+        return replay_summon(game, symbol, cell);
+    }
+    let (game_move, promotion) = find_move(game, written, team)?;
+    let (captured_unit, exploded_units) = game.apply_move(game_move);
+    if let Some(unit_type) = promotion {
+        game.promote(game_move.to, unit_type);
+    }
+    game.history
+        .record_move(team, Some(game_move), written.to_string(), captured_unit);
+    if !exploded_units.is_empty() {
+        game.history.record_explosion(exploded_units);
+    }
+    Ok(())
+}
+
+/// The legal move of `team` that is written as `written` in the current position, with the unit a
+/// pawn is promoted to by it
+pub(crate) fn find_move(
+    game: &Game,
+    written: &str,
+    team: Team,
+) -> Result<(GameMove, Option<UnitType>), String> {
+    legal_moves(game, team)
+        .find(|&(game_move, promotion)| {
+            notation::write_promoting_move(game_move, promotion, &game.board, &game.units)
+                == written
+        })
+        .ok_or(format!("{} is not a legal move of {:?}", written, team))
+}
+
+/// The legal move of `team` that a player typed, see [`notation::is_typed_move`]
+pub(crate) fn find_typed_move(
+    game: &Game,
+    typed: &str,
+    team: Team,
+) -> Result<(GameMove, Option<UnitType>), String> {
+    legal_moves(game, team)
+        .find(|&(game_move, promotion)| {
+            notation::is_typed_move(typed, game_move, promotion, &game.board, &game.units)
+        })
+        .ok_or(format!("{} is not a legal move", typed.trim()))
+}
+
+/// Every legal move of `team`, once for every unit a pawn can be promoted to by it
+fn legal_moves(game: &Game, team: Team) -> impl Iterator<Item = (GameMove, Option<UnitType>)> + '_ {
+    game.units
+        .all_units_iter()
+        .filter(move |unit| unit.team == team)
+        .flat_map(|unit| {
+            movement::get_legal_moves(unit, &game.board, &game.units, game.variant.rules())
+                .into_iter()
+                .map(|to| GameMove {
+                    from: unit.coords,
+                    to,
+                })
+        })
+        .flat_map(|game_move| {
+            let promotions = match game.promotes(game_move) {
+                true => movement::PROMOTIONS.map(Some).to_vec(),
+                false => vec![None],
+            };
+            promotions
+                .into_iter()
+                .map(move |promotion| (game_move, promotion))
+        })
+}
+
+fn replay_summon(game: &mut Game, symbol: &str, cell: &str) -> Result<(), String> {
+    let team = game.turn;
+    let invalid = || format!("{}@{} is not a legal summon of {:?}", symbol, cell, team);
+    let unit_type = reinforcements::summonable_from_symbol(symbol);
+    let coords = CellCoordinates::from_display(cell)
+        .map_err(|err| format!("{}@{}: {}", symbol, cell, err))?;
+    let on_cube = (0..3).all(|axis| coords[axis] <= game.board.cube_side_length);
+    let (Some(unit_type), true) = (unit_type, on_cube) else {
+        return Err(invalid());
+    };
+    game.pending_summon = Some(unit_type);
+    if !reinforcements::try_summon(game, coords) {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// Both moves are made at the same time in the duel variant
+fn replay_duel_moves(game: &mut Game, written: [&str; 2]) -> Result<(), String> {
+    let moves = [
+        find_move(game, written[0], Team::White)?.0,
+        find_move(game, written[1], Team::Black)?.0,
+    ];
+    let captured_units = resolve_moves(&mut game.units, moves).unwrap_or_default();
+    duel::record_moves(game, moves, written.map(str::to_string), &captured_units);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::movement::Variant;
+
+    /// Plays the first legal move of every turn like [`read_game`] replays it, summoning a pawn
+    /// next to the king on every third turn of a variant with summons, and moving the comet after
+    /// every move of black
+    fn play(game: &mut Game, turns: usize) {
+        for turn in 0..turns {
+            let team = game.turn;
+            let king = game
+                .units
+                .all_units_iter()
+                .find(|unit| unit.team == team && unit.unit_type == UnitType::King)
+                .unwrap()
+                .coords;
+            let free_home_cell = king
+                .get_adjacent(game.board.cube_side_length)
+                .into_iter()
+                .find(|&coords| {
+                    coords.normal_direction() == game.home_face(team)
+                        && !game.units.is_unit_at(coords)
+                });
+            match free_home_cell {
+                Some(coords) if game.variant.rules().summons && turn % 3 == 1 => {
+                    replay_summon(game, "♙", &coords.display()).unwrap()
+                }
+                _ => {
+                    let (game_move, promotion) = legal_moves(game, team).next().unwrap();
+                    let written = notation::write_promoting_move(
+                        game_move,
+                        promotion,
+                        &game.board,
+                        &game.units,
+                    );
+                    replay_move(game, &written, team).unwrap();
+                }
+            }
+            game.next_player_turn();
+            if game.variant.rules().neutral_units_move && team == Team::Black {
+                let (game_move, _) = legal_moves(game, Team::Neutral).next().unwrap();
+                let written = notation::write_move(game_move, &game.board, &game.units);
+                replay_move(game, &written, Team::Neutral).unwrap();
+            }
+            game.record_position();
+        }
+    }
+
+    #[test]
+    fn written_games_are_read_back() {
+        let settings = Settings::default();
+        let black_first = Game::new(4, Variant::Standard, &settings)
+            .to_cubefen()
+            .replace(" w ", " b ");
+        let games = [
+            Game::from_cubefen(&black_first, &settings).unwrap(),
+            Game::new(4, Variant::Wandering, &settings),
+            Game::new(4, Variant::Reinforcements, &settings),
+        ];
+        let mut texts = Vec::new();
+        for mut game in games {
+            play(&mut game, 6);
+            let text = write_game(&game);
+            let read = read_game(&text, &settings).unwrap();
+            assert_eq!(read.to_cubefen(), game.to_cubefen());
+            assert_eq!(read.turn, game.turn);
+            assert_eq!(movetext(&read), movetext(&game));
+            texts.push(movetext(&game));
+        }
+        // Black moved first, so the first move number is written for black
+        assert!(texts[0].starts_with("1... "), "{}", texts[0]);
+        assert!(texts[0].contains(" 2. "), "{}", texts[0]);
+        assert!(!texts[0].contains("2... "), "{}", texts[0]);
+        // The comet moves after black, and white numbers the next move again
+        assert!(texts[1].contains("} 2. "), "{}", texts[1]);
+        assert!(texts[1].contains("{☄"), "{}", texts[1]);
+        assert!(texts[2].contains("♙@"), "{}", texts[2]);
+    }
+
+    #[test]
+    fn extra_moves_are_read_back() {
+        let settings = Settings::default();
+        let mut game = Game::new(4, Variant::Standard, &settings);
+        game.handicap = Some(Handicap {
+            team: Team::Black,
+            odds: Odds::None,
+            extra_move_every: Some(2),
+        });
+        play(&mut game, 7);
+        let read = read_game(&write_game(&game), &settings).unwrap();
+        assert_eq!(read.handicap, game.handicap);
+        assert_eq!(read.to_cubefen(), game.to_cubefen());
+        assert_eq!(movetext(&read), movetext(&game));
+    }
+
+    #[test]
+    fn malformed_games_are_refused() {
+        let settings = Settings::default();
+        let tag = |variant| {
+            let start = Game::new(3, variant, &settings).to_cubefen();
+            format!("[CubeFEN \"{}\"]", start)
+        };
+        let first_move = |variant, team| {
+            let game = Game::new(3, variant, &settings);
+            let (game_move, _) = legal_moves(&game, team).next().unwrap();
+            notation::write_move(game_move, &game.board, &game.units)
+        };
+        let standard = tag(Variant::Standard);
+        let texts = [
+            String::new(),
+            "[Event \"Chess on a cube\"]\n\n*".to_string(),
+            "[CubeFEN \"3 - w 0/0 nonsense\"]\n\n*".to_string(),
+            format!("{}\n\n1. ♖Xa9 *", standard),
+            format!(
+                "{}\n\n1. {} *",
+                standard,
+                first_move(Variant::Standard, Team::Black)
+            ),
+            format!("{}\n[ExtraMoves \"Purple 3\"]\n\n*", standard),
+            format!("{}\n[ExtraMoves \"Black often\"]\n\n*", standard),
+            format!("{}\n\n1. ♙@Xa9 *", tag(Variant::Reinforcements)),
+            // Both moves of a duel turn are needed
+            format!(
+                "{}\n\n1. {} *",
+                tag(Variant::Duel),
+                first_move(Variant::Duel, Team::White)
+            ),
+        ];
+        for text in texts {
+            assert!(read_game(&text, &settings).is_err(), "{} was read", text);
+        }
+    }
+}
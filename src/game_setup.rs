@@ -0,0 +1,382 @@
+use bevy::prelude::*;
+
+use crate::cli::LaunchGame;
+use crate::engine::movement::Variant;
+use crate::engine::units::Team;
+use crate::gamemanager::Palette;
+use crate::match_play::Match;
+use crate::presets::{
+    AiSettings, Handicap, Odds, SetupPreset, SetupPresets, TimeControl, CUBE_SIDE_LENGTHS,
+};
+use crate::puzzles::PuzzleBrowser;
+use crate::settings::Settings;
+use crate::settings_menu::SettingsMenu;
+use crate::ui::{self, MenuRoot};
+use crate::AppState;
+
+/// The depths the AI can be set to search, from easiest to hardest
+pub(crate) const AI_DEPTHS: [u32; 5] = [1, 2, 3, 4, 5];
+
+const TIME_CONTROLS: [(u32, u32); 6] = [(1, 0), (3, 2), (5, 3), (10, 0), (15, 10), (30, 0)];
+
+const DELAYS: [u32; 4] = [0, 2, 3, 5];
+
+/// How many moves of its own the other team makes before each extra move of a handicap
+const EXTRA_MOVES_EVERY: [Option<u32>; 4] = [None, Some(2), Some(3), Some(5)];
+
+/// The settings on the setup menu, in the order they are shown
+#[derive(Clone, Copy, PartialEq)]
+enum SetupRow {
+    CubeSize,
+    Variant,
+    Setup,
+    Ai,
+    Difficulty,
+    Palette,
+    TimeControl,
+    Delay,
+    Handicap,
+    Odds,
+    ExtraMoves,
+}
+
+impl SetupRow {
+    const ALL: [SetupRow; 11] = [
+        SetupRow::CubeSize,
+        SetupRow::Variant,
+        SetupRow::Setup,
+        SetupRow::Ai,
+        SetupRow::Difficulty,
+        SetupRow::Palette,
+        SetupRow::TimeControl,
+        SetupRow::Delay,
+        SetupRow::Handicap,
+        SetupRow::Odds,
+        SetupRow::ExtraMoves,
+    ];
+}
+
+/// The game being set up on the setup menu. It exists only while the menu is open, which also
+/// keeps the game from starting when the main menu is left for the setup menu.
+#[derive(Resource)]
+pub(crate) struct GameSetup {
+    preset: SetupPreset,
+    row: usize,
+}
+
+impl GameSetup {
+    /// Starts from the chosen preset, on the starting position
+    pub(crate) fn new(presets: &SetupPresets) -> Self {
+        let mut preset = presets.chosen().clone();
+        preset.name = "Custom game".to_string();
+        preset.position = None;
+        GameSetup { preset, row: 0 }
+    }
+
+    /// Changes the setting on the current row to the next or the previous choice
+    fn change(&mut self, forward: bool) {
+        let preset = &mut self.preset;
+        match SetupRow::ALL[self.row] {
+            SetupRow::CubeSize => {
+                let choices: Vec<u32> = CUBE_SIDE_LENGTHS.collect();
+                preset.cube_side_length = cycle(&choices, preset.cube_side_length, forward);
+            }
+            SetupRow::Variant => preset.variant = cycle(&Variant::ALL, preset.variant, forward),
+            SetupRow::Setup => {
+                preset.shuffled = !preset.shuffled;
+                preset.setup_seed = None;
+            }
+            SetupRow::Ai => {
+                let team = cycle(
+                    &[None, Some(Team::Black), Some(Team::White)],
+                    preset.ai.map(|ai| ai.team),
+                    forward,
+                );
+                let depth = preset.ai.and_then(|ai| ai.depth);
+                preset.ai = team.map(|team| AiSettings { team, depth });
+            }
+            SetupRow::Difficulty => {
+                if let Some(ai) = &mut preset.ai {
+                    let choices: Vec<Option<u32>> =
+                        [None].into_iter().chain(AI_DEPTHS.map(Some)).collect();
+                    ai.depth = cycle(&choices, ai.depth, forward);
+                }
+            }
+            SetupRow::Palette => {
+                let choices = [None, Some(Palette::Filippa), Some(Palette::Pinkish)];
+                preset.palette = cycle(&choices, preset.palette, forward);
+            }
+            SetupRow::TimeControl => {
+                let delay_seconds = preset.time_control.map_or(0, |time| time.delay_seconds);
+                let choices: Vec<Option<TimeControl>> = [None]
+                    .into_iter()
+                    .chain(TIME_CONTROLS.map(|(minutes, increment_seconds)| {
+                        Some(TimeControl {
+                            minutes,
+                            increment_seconds,
+                            delay_seconds,
+                        })
+                    }))
+                    .collect();
+                preset.time_control = cycle(&choices, preset.time_control, forward);
+            }
+            SetupRow::Delay => {
+                if let Some(time_control) = &mut preset.time_control {
+                    time_control.delay_seconds =
+                        cycle(&DELAYS, time_control.delay_seconds, forward);
+                }
+            }
+            SetupRow::Handicap => {
+                let team = cycle(
+                    &[None, Some(Team::White), Some(Team::Black)],
+                    preset.handicap.map(|handicap| handicap.team),
+                    forward,
+                );
+                let odds = preset.handicap.map_or(Odds::None, |handicap| handicap.odds);
+                let extra_move_every = preset
+                    .handicap
+                    .and_then(|handicap| handicap.extra_move_every);
+                preset.handicap = team.map(|team| Handicap {
+                    team,
+                    odds,
+                    extra_move_every,
+                });
+            }
+            SetupRow::Odds => {
+                if let Some(handicap) = &mut preset.handicap {
+                    handicap.odds = cycle(&Odds::ALL, handicap.odds, forward);
+                }
+            }
+            SetupRow::ExtraMoves => {
+                if let Some(handicap) = &mut preset.handicap {
+                    handicap.extra_move_every =
+                        cycle(&EXTRA_MOVES_EVERY, handicap.extra_move_every, forward);
+                }
+            }
+        }
+    }
+
+    fn display_row(&self, row: SetupRow, settings: &Settings) -> String {
+        let preset = &self.preset;
+        match row {
+            SetupRow::CubeSize => format!("Cube size: {}", preset.cube_side_length),
+            SetupRow::Variant => format!("Variant: {}", preset.variant.name()),
+            SetupRow::Setup => match (preset.shuffled, preset.setup_seed) {
+                (true, Some(seed)) => format!("Setup: shuffled with seed {}", seed),
+                (true, None) => "Setup: shuffled".to_string(),
+                (false, _) => "Setup: standard".to_string(),
+            },
+            SetupRow::Ai => match preset.ai {
+                Some(ai) => format!("AI plays: {:?}", ai.team),
+                None => "AI plays: nobody, two players".to_string(),
+            },
+            SetupRow::Difficulty => match preset.ai {
+                Some(AiSettings {
+                    depth: Some(depth), ..
+                }) => format!("AI depth: {}", depth),
+                Some(_) => format!("AI depth: {} (from the settings)", settings.ai_depth),
+                None => "AI depth: -".to_string(),
+            },
+            SetupRow::Palette => match preset.palette {
+                Some(palette) => format!("Palette: {:?}", palette),
+                None => format!("Palette: {:?} (from the settings)", settings.palette),
+            },
+            SetupRow::TimeControl => match preset.time_control {
+                Some(time_control) => format!(
+                    "Time control: {}+{}",
+                    time_control.minutes, time_control.increment_seconds
+                ),
+                None => "Time control: none".to_string(),
+            },
+            SetupRow::Delay => match preset.time_control {
+                Some(time_control) => format!("Delay: {}s", time_control.delay_seconds),
+                None => "Delay: -".to_string(),
+            },
+            SetupRow::Handicap => match preset.handicap {
+                Some(handicap) => format!("Handicap given by: {:?}", handicap.team),
+                None => "Handicap given by: nobody".to_string(),
+            },
+            SetupRow::Odds => match preset.handicap {
+                Some(handicap) => format!("Starts without: {}", handicap.odds.display()),
+                None => "Starts without: -".to_string(),
+            },
+            SetupRow::ExtraMoves => match preset.handicap {
+                Some(Handicap {
+                    team,
+                    extra_move_every: Some(every),
+                    ..
+                }) => format!("Extra move: {:?} every {} moves", team.opposite(), every),
+                Some(_) => "Extra move: never".to_string(),
+                None => "Extra move: -".to_string(),
+            },
+        }
+    }
+}
+
+/// The choice after or before `current`, wrapping around. A current value that isn't one of the
+/// choices goes to the first one.
+pub(crate) fn cycle<T: Copy + PartialEq>(choices: &[T], current: T, forward: bool) -> T {
+    let Some(index) = choices.iter().position(|choice| *choice == current) else {
+        return choices[0];
+    };
+    let index = if forward {
+        (index + 1) % choices.len()
+    } else {
+        (index + choices.len() - 1) % choices.len()
+    };
+    choices[index]
+}
+
+pub(crate) fn spawn_setup_menu(
+    mut commands: Commands,
+    setup: Res<GameSetup>,
+    settings: Res<Settings>,
+) {
+    spawn_setup_menu_for(&mut commands, &setup, &settings);
+}
+
+fn spawn_setup_menu_for(commands: &mut Commands, setup: &GameSetup, settings: &Settings) {
+    let mut hint = String::new();
+    for (i, row) in SetupRow::ALL.into_iter().enumerate() {
+        let marker = if i == setup.row { "> " } else { "  " };
+        hint.push_str(&format!("{}{}\n", marker, setup.display_row(row, settings)));
+    }
+    hint.push_str(
+        "\nUp and Down pick a setting, Left and Right change it\n\
+         Press Enter to play, or Escape to go back",
+    );
+    ui::spawn_menu(commands, "New game", &hint, &[]);
+}
+
+/// Enter starts the game that was set up, and Escape goes back to the main menu without starting
+/// one
+pub(crate) fn setup_menu_input(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    mut setup: ResMut<GameSetup>,
+    settings: Res<Settings>,
+    mut next_state: ResMut<NextState<AppState>>,
+    menu_query: Query<Entity, With<MenuRoot>>,
+) {
+    if input.just_pressed(KeyCode::Return) {
+        let game = setup.preset.new_game(&settings);
+        commands.insert_resource(LaunchGame(Some(game)));
+        commands.remove_resource::<Match>();
+        commands.remove_resource::<GameSetup>();
+        next_state.set(AppState::InGame);
+        return;
+    }
+    if input.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::MainMenu);
+        return;
+    }
+
+    let rows = SetupRow::ALL.len();
+    if input.just_pressed(KeyCode::Down) {
+        setup.row = (setup.row + 1) % rows;
+    } else if input.just_pressed(KeyCode::Up) {
+        setup.row = (setup.row + rows - 1) % rows;
+    } else if input.any_just_pressed([KeyCode::Left, KeyCode::Right]) {
+        setup.change(input.just_pressed(KeyCode::Right));
+    } else {
+        return;
+    }
+    for entity in &menu_query {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_setup_menu_for(&mut commands, &setup, &settings);
+}
+
+/// False while the setup menu, the settings menu or the puzzle browser is open, so that the game
+/// doesn't start when the main menu is left for it
+pub(crate) fn is_game_picked(
+    setup: Option<Res<GameSetup>>,
+    settings_menu: Option<Res<SettingsMenu>>,
+    puzzle_browser: Option<Res<PuzzleBrowser>>,
+) -> bool {
+    setup.is_none() && settings_menu.is_none() && puzzle_browser.is_none()
+}
+
+/// Forgets a setup that was left with Escape
+pub(crate) fn forget_game_setup(mut commands: Commands) {
+    commands.remove_resource::<GameSetup>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::ai::{self, AICache};
+    use crate::engine::cell::{Board, CellCoordinates};
+    use crate::engine::movement::GameMove;
+    use crate::engine::units::{Unit, UnitType, Units};
+
+    fn position(units: &[(UnitType, Team, &str)]) -> Units {
+        let mut output = Units::default();
+        for &(unit_type, team, cell) in units {
+            let coords = CellCoordinates::from_display(cell).unwrap();
+            output.add_unit(Unit::new(unit_type, team, coords));
+        }
+        output
+    }
+
+    #[test]
+    fn every_difficulty_takes_a_hanging_queen_without_hanging_a_rook() {
+        let board = Board::new(4);
+        let mut ai_cache = AICache::default();
+        let units = position(&[
+            (UnitType::King, Team::White, "Yd4"),
+            (UnitType::Rook, Team::White, "Za1"),
+            (UnitType::King, Team::Black, "zd1"),
+            (UnitType::Queen, Team::Black, "Za4"),
+        ]);
+        let capture = GameMove {
+            from: CellCoordinates::from_display("Za1").unwrap(),
+            to: CellCoordinates::from_display("Za4").unwrap(),
+        };
+        for depth in AI_DEPTHS {
+            let chosen = ai::next_move(
+                &board,
+                &units,
+                Team::White,
+                depth,
+                Variant::Standard,
+                &mut ai_cache,
+            );
+            assert_eq!(chosen, Some(capture), "depth {}", depth);
+        }
+
+        // The queen attacks the rook, which only the easiest difficulty doesn't look far enough
+        // ahead to see
+        let mut units = position(&[
+            (UnitType::King, Team::White, "Yd4"),
+            (UnitType::Rook, Team::White, "Zb1"),
+            (UnitType::King, Team::Black, "zd1"),
+            (UnitType::Queen, Team::Black, "Zd3"),
+        ]);
+        for depth in AI_DEPTHS.into_iter().skip(1) {
+            let chosen = ai::next_move(
+                &board,
+                &units,
+                Team::White,
+                depth,
+                Variant::Standard,
+                &mut ai_cache,
+            )
+            .unwrap();
+            let unit = units.remove_unit(chosen.from).unwrap();
+            units.add_unit(Unit {
+                coords: chosen.to,
+                ..unit
+            });
+            let hanging = ai::get_possible_moves(&board, &units, Team::Black, Variant::Standard)
+                .into_iter()
+                .any(|reply| units.is_unit_at(reply.to));
+            assert!(!hanging, "depth {} hangs a unit with {:?}", depth, chosen);
+            let moved = units.remove_unit(chosen.to).unwrap();
+            units.add_unit(Unit {
+                coords: chosen.from,
+                ..moved
+            });
+        }
+    }
+}
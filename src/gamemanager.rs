@@ -1,45 +1,315 @@
-use crate::ai::AICache;
-use crate::movement::GameMove;
-use crate::{ai, movement, units::*};
+use crate::bug_report::GameLog;
+use crate::cube_rotation::FaceCell;
+use crate::engine::ai::{self, AICache};
+use crate::engine::cubefen::CubeFen;
+use crate::engine::direction::CartesianDirection;
+use crate::engine::history::{MoveHistory, Position};
+use crate::engine::movement::{self, GameMove, Variant};
+use crate::engine::notation;
+use crate::engine::units::*;
+use crate::input_state::InputState;
+use crate::piece_sets::PieceSet;
+use crate::presets::{Handicap, TimeControl};
+use crate::rng::GameRng;
+use crate::{duel, reinforcements, AppState};
 
-use crate::cell::*;
+use crate::engine::cell::*;
+use crate::puzzles::PuzzleMode;
 use crate::scene::{self, MainCube, SceneChild};
+use crate::settings::Settings;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy_mod_picking::prelude::*;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
 
 #[derive(Resource, Debug)]
 pub(crate) struct Game {
     pub(crate) board: Board,
     pub(crate) units: Units,
     pub(crate) selected_cell: Option<CellCoordinates>,
+    /// The cell under the cursor, or the cell of the unit under it
+    pub(crate) hovered_cell: Option<CellCoordinates>,
     pub(crate) phase: GamePhase,
     pub(crate) stored_units: Vec<Unit>,
     pub(crate) turn: Team,
     pub(crate) entities_to_move: Vec<(Entity, CellCoordinates)>,
     pub(crate) palette: Palette,
     pub(crate) ai_playing: Option<Team>,
+    /// The team played on another computer, see [`crate::network`]
+    pub(crate) remote_team: Option<Team>,
+    /// Set when watching an online game, which turns off input
+    pub(crate) spectating: bool,
+    /// How many moves ahead the AI searches
+    pub(crate) ai_depth: u32,
+    pub(crate) variant: Variant,
+    /// Energy of each team in the reinforcements variant, indexed by [`Team::index`]
+    pub(crate) energy: [u32; 2],
+    /// The unit that will be summoned on the next click in the reinforcements variant
+    pub(crate) pending_summon: Option<UnitType>,
+    /// The move of a pawn that is promoted once the player has chosen what it is promoted to, see
+    /// [`crate::promotion`]
+    pub(crate) pending_promotion: Option<GameMove>,
+    /// The moves each team has secretly picked in the duel variant, indexed by [`Team::index`]
+    pub(crate) hidden_moves: [Option<GameMove>; 2],
+    /// The face each team starts with its king on, indexed by [`Team::index`]
+    home_faces: [CartesianDirection; 2],
+    /// The seed of the shuffled setup the game started from, None for the standard setup
+    pub(crate) setup_seed: Option<u64>,
+    pub(crate) handicap: Option<Handicap>,
+    /// Played on the clocks of [`crate::chess_clock`]. Online games use the clocks of the host
+    /// instead.
+    pub(crate) time_control: Option<TimeControl>,
+    pub(crate) result: Option<GameResult>,
+    pub(crate) end_reason: Option<EndReason>,
+    pub(crate) history: MoveHistory,
+    ai_cache: AICache,
 }
+
 impl Game {
-    pub(crate) fn new(cube_side_length: u32) -> Self {
+    pub(crate) fn new(cube_side_length: u32, variant: Variant, settings: &Settings) -> Self {
+        let mut units = Units::game_starting_configuration(cube_side_length);
+        if variant.rules().neutral_units_move {
+            units.add_unit(Unit::new(
+                UnitType::Comet,
+                Team::Neutral,
+                CellCoordinates::new(1, 1, 0, true),
+            ));
+        }
+        let history =
+            MoveHistory::new(Position::new(units.clone(), Team::White, [0; 2], [None; 2]));
         Game {
             board: Board::new(cube_side_length),
-            units: Units::game_starting_configuration(cube_side_length),
+            units,
             selected_cell: None,
+            hovered_cell: None,
             phase: GamePhase::PlaceUnits,
             stored_units: vec![],
             turn: Team::White,
             entities_to_move: Vec::new(),
-            palette: Palette::Pinkish,
+            palette: settings.palette,
             ai_playing: Some(Team::Black),
+            remote_team: None,
+            spectating: false,
+            ai_depth: settings.ai_depth,
+            variant,
+            energy: [0; 2],
+            pending_summon: None,
+            pending_promotion: None,
+            hidden_moves: [None; 2],
+            home_faces: movement::home_faces(cube_side_length),
+            setup_seed: None,
+            handicap: None,
+            time_control: None,
+            result: None,
+            end_reason: None,
+            history,
+            ai_cache: AICache::default(),
+        }
+    }
+
+    /// Passes the turn to the other team, unless the team whose turn it is gets an extra move from
+    /// the handicap of the other team
+    pub(crate) fn next_player_turn(&mut self) {
+        if !self.takes_extra_move() {
+            self.turn = self.turn.opposite();
+        }
+        if self.variant.rules().summons {
+            let energy = &mut self.energy[self.turn.index()];
+            *energy = (*energy + reinforcements::ENERGY_PER_TURN).min(reinforcements::MAX_ENERGY);
+        }
+    }
+
+    /// The team that is given extra moves makes one after every `extra_move_every` moves of its own,
+    /// counting the extra moves. Both teams move at the same time in some variants, where there
+    /// are no extra moves.
+    fn takes_extra_move(&self) -> bool {
+        let Some(Handicap {
+            team,
+            extra_move_every: Some(every),
+            ..
+        }) = self.handicap
+        else {
+            return false;
+        };
+        if self.turn != team.opposite() || self.variant.rules().simultaneous_moves {
+            return false;
+        }
+        // Every move would be followed by another one if it was every move
+        let every = every.max(2) as usize;
+        let moves = self
+            .history
+            .moves()
+            .filter(|recorded_move| recorded_move.team == self.turn)
+            .count();
+        moves > 0 && moves % every == 0
+    }
+
+    pub(crate) fn energy(&self, team: Team) -> u32 {
+        self.energy[team.index()]
+    }
+
+    pub(crate) fn home_face(&self, team: Team) -> CartesianDirection {
+        self.home_faces[team.index()]
+    }
+
+    /// The current position in CubeFEN, see [`CubeFen`]
+    pub(crate) fn to_cubefen(&self) -> String {
+        CubeFen {
+            cube_side_length: self.board.cube_side_length,
+            units: self.units.clone(),
+            turn: self.turn,
+            energy: self.energy,
+            variant: self.variant,
+        }
+        .to_string()
+    }
+
+    /// A game that starts from the position in `text`, with the rest set up like [`Game::new`].
+    /// The turns before the position can't be undone.
+    pub(crate) fn from_cubefen(text: &str, settings: &Settings) -> Result<Self, String> {
+        let position: CubeFen = text.parse()?;
+        let mut game = Game::new(position.cube_side_length, position.variant, settings);
+        game.units = position.units;
+        game.turn = position.turn;
+        game.energy = position.energy;
+        game.history = MoveHistory::new(game.position());
+        Ok(game)
+    }
+
+    /// Starts from the shuffled setup of `seed` instead of the standard setup, see
+    /// [`Units::shuffled_starting_configuration`]. Neutral units are kept where they are.
+    pub(crate) fn shuffle_setup(&mut self, seed: u64) {
+        let mut units = Units::shuffled_starting_configuration(self.board.cube_side_length, seed);
+        for unit in self.units.all_units_iter() {
+            if unit.team == Team::Neutral {
+                units.add_unit(unit.clone());
+            }
+        }
+        self.units = units;
+        self.history = MoveHistory::new(self.position());
+        self.setup_seed = Some(seed);
+    }
+
+    /// Takes the units of the odds off the board of the team that gives the handicap, and starts
+    /// the history over from there
+    pub(crate) fn give_handicap(&mut self, handicap: Handicap) {
+        for &unit_type in handicap.odds.removed_units() {
+            let unit_type = starting_counterpart(unit_type, self.board.cube_side_length);
+            let coords = self
+                .units
+                .all_units_iter()
+                .find(|unit| unit.team == handicap.team && unit.unit_type == unit_type)
+                .map(|unit| unit.coords);
+            if let Some(coords) = coords {
+                self.units.remove_unit(coords);
+            }
         }
+        self.history = MoveHistory::new(self.position());
+        self.handicap = Some(handicap);
     }
 
-    fn next_player_turn(&mut self) {
-        self.turn = self.turn.opposite()
+    /// The same game again from its starting position, with the AI playing the other team
+    pub(crate) fn rematch(&self, settings: &Settings) -> Game {
+        let mut rematch = Game::from_cubefen(&self.starting_cubefen(), settings)
+            .unwrap_or_else(|_| Game::new(self.board.cube_side_length, self.variant, settings));
+        rematch.ai_playing = self.ai_playing.map(|team| team.opposite());
+        rematch.ai_depth = self.ai_depth;
+        rematch.palette = self.palette;
+        rematch.time_control = self.time_control;
+        rematch.setup_seed = self.setup_seed;
+        // The units of the odds are already gone from the starting position
+        rematch.handicap = self.handicap;
+        rematch
+    }
+
+    /// If the team whose turn it is is played on this computer, by a player or the AI
+    pub(crate) fn is_played_here(&self) -> bool {
+        !self.spectating && self.remote_team != Some(self.turn)
+    }
+
+    /// If both teams are played by people taking turns on this computer
+    pub(crate) fn is_hot_seat(&self) -> bool {
+        !self.spectating && self.ai_playing.is_none() && self.remote_team.is_none()
+    }
+
+    fn position(&self) -> Position {
+        Position::new(
+            self.units.clone(),
+            self.turn,
+            self.energy,
+            self.hidden_moves,
+        )
+    }
+
+    /// Saves the current position to the history, see [`MoveHistory::record_position`]
+    pub(crate) fn record_position(&mut self) {
+        self.history.record_position(self.position());
+    }
+
+    /// Moves a unit without checking that the move is legal or sending any events, see
+    /// [`crate::engine::rules::Ruleset::apply_move`]
+    pub(crate) fn apply_move(&mut self, game_move: GameMove) -> (Option<Unit>, Vec<Unit>) {
+        let rules = self.variant.rules();
+        rules.apply_move(game_move, &self.board, &mut self.units)
+    }
+
+    /// If the move takes a pawn onto the home face of the other team, see [`movement::promotes`]
+    pub(crate) fn promotes(&self, game_move: GameMove) -> bool {
+        movement::promotes(game_move, &self.units, self.variant, self.home_faces)
+    }
+
+    /// Turns the unit on `coords` into another kind of unit, without sending any events
+    pub(crate) fn promote(&mut self, coords: CellCoordinates, unit_type: UnitType) {
+        if let Some(unit) = self.units.get_unit_mut(coords) {
+            unit.unit_type = unit_type;
+        }
+    }
+
+    /// The position the game started from, in CubeFEN
+    pub(crate) fn starting_cubefen(&self) -> String {
+        let start = self.history.starting_position();
+        CubeFen {
+            cube_side_length: self.board.cube_side_length,
+            units: start.units.clone(),
+            turn: start.turn,
+            energy: start.energy,
+            variant: self.variant,
+        }
+        .to_string()
+    }
+
+    fn restore_position(&mut self, position: Position) {
+        self.units = position.units;
+        self.turn = position.turn;
+        self.energy = position.energy;
+        self.hidden_moves = position.hidden_moves;
+        self.selected_cell = None;
+        self.pending_summon = None;
+        self.pending_promotion = None;
+        reset_cells_new_selection(self);
+    }
+
+    /// Goes back to the start of the previous turn. Returns false if there is nothing to undo.
+    pub(crate) fn undo(&mut self) -> bool {
+        let Some(position) = self.history.undo().cloned() else {
+            return false;
+        };
+        self.restore_position(position);
+        true
+    }
+
+    /// Plays the last undone turn again. Returns false if there is nothing to redo.
+    pub(crate) fn redo(&mut self) -> bool {
+        let Some(position) = self.history.redo().cloned() else {
+            return false;
+        };
+        self.restore_position(position);
+        true
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[allow(unused)]
 pub(crate) enum Palette {
     Filippa,
@@ -61,33 +331,124 @@ impl Palette {
         }
         output
     }
+
+    pub(crate) fn cell_color(&self, cell_color: CellColor) -> Color {
+        self.get_colors()[cell_color.index()]
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub(crate) enum Team {
-    Black,
-    White,
+pub(crate) enum GameResult {
+    Win(Team),
+    Draw,
+}
+
+/// Why the game ended, shown on the game over screen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum EndReason {
+    KingCaptured,
+    /// The team to move had no moves left
+    NoMoves,
+    Resignation,
+    /// A team ran out of time on its clock
+    Time,
+    /// The players agreed to a draw
+    Agreement,
 }
-impl Team {
-    pub(crate) fn color(&self) -> Color {
-        match self {
-            Self::Black => Color::DARK_GRAY,
-            Self::White => Color::BISQUE,
-        }
-    }
 
-    pub(crate) fn opposite(&self) -> Self {
+impl EndReason {
+    pub(crate) fn display(&self) -> &'static str {
         match self {
-            Team::Black => Team::White,
-            Team::White => Team::Black,
+            EndReason::KingCaptured => "The king was captured",
+            EndReason::NoMoves => "No moves left",
+            EndReason::Resignation => "By resignation",
+            EndReason::Time => "On time",
+            EndReason::Agreement => "By agreement",
         }
     }
+}
 
-    pub(crate) fn sign(&self) -> i32 {
-        match self {
-            Team::Black => -1,
-            Team::White => 1,
-        }
+/// A unit has been moved on the board
+#[derive(Debug)]
+pub(crate) struct MoveMade {
+    pub(crate) game_move: GameMove,
+    /// Written in the position before the move, see [`notation`]
+    pub(crate) notation: String,
+    pub(crate) team: Team,
+    pub(crate) entity: Entity,
+}
+
+/// A unit has been captured and removed from the board
+#[derive(Debug)]
+pub(crate) struct UnitCaptured {
+    pub(crate) unit: Unit,
+}
+
+/// A pawn has been promoted to another kind of unit, so its entity shows the wrong unit
+#[derive(Debug)]
+pub(crate) struct UnitPromoted {
+    pub(crate) coords: CellCoordinates,
+    pub(crate) unit_type: UnitType,
+    pub(crate) entity: Entity,
+}
+
+#[derive(Debug)]
+pub(crate) struct TurnChanged {
+    pub(crate) turn: Team,
+}
+
+#[derive(Debug)]
+pub(crate) struct GameEnded {
+    pub(crate) result: GameResult,
+}
+
+/// What happens in the game is sent as events, so that the scene, and anything else that wants to
+/// follow the game, can react to it without the game logic knowing about them
+#[derive(SystemParam)]
+pub(crate) struct GameEvents<'w> {
+    pub(crate) move_made: EventWriter<'w, MoveMade>,
+    pub(crate) unit_captured: EventWriter<'w, UnitCaptured>,
+    pub(crate) unit_promoted: EventWriter<'w, UnitPromoted>,
+    pub(crate) turn_changed: EventWriter<'w, TurnChanged>,
+    pub(crate) game_ended: EventWriter<'w, GameEnded>,
+}
+
+/// Logs the game events, and keeps them in the [`GameLog`] for bug reports
+pub(crate) fn log_game_events(
+    mut moves_made: EventReader<MoveMade>,
+    mut units_captured: EventReader<UnitCaptured>,
+    mut units_promoted: EventReader<UnitPromoted>,
+    mut turns_changed: EventReader<TurnChanged>,
+    mut games_ended: EventReader<GameEnded>,
+    mut game_log: ResMut<GameLog>,
+    game: Res<Game>,
+) {
+    // The lines of turns that were taken back go with them
+    let turns_played = game.history.turns_played();
+    game_log.truncate(turns_played);
+    let mut lines = Vec::new();
+    for move_made in moves_made.iter() {
+        lines.push(format!("{:?} moved {}", move_made.team, move_made.notation));
+    }
+    for unit_captured in units_captured.iter() {
+        lines.push(format!("{:?} was captured", unit_captured.unit));
+    }
+    for unit_promoted in units_promoted.iter() {
+        lines.push(format!(
+            "The pawn on {} was promoted to {:?}",
+            unit_promoted.coords.display(),
+            unit_promoted.unit_type
+        ));
+    }
+    for turn_changed in turns_changed.iter() {
+        lines.push(format!("It is now {:?}'s turn", turn_changed.turn));
+    }
+    for game_ended in games_ended.iter() {
+        lines.push(format!("The game ended: {:?}", game_ended.result));
+    }
+    for line in lines {
+        info!("{}", line);
+        game_log.push_line(turns_played, line);
     }
 }
 
@@ -97,20 +458,123 @@ pub(crate) enum GamePhase {
     Play,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn on_cell_clicked(
     In(click): In<ListenedEvent<Click>>,
     mut query: Query<(Option<&MainCube>, &mut Transform)>,
+    camera_query: Query<&GlobalTransform>,
     mut game: ResMut<Game>,
-    commands: Commands,
+    state: Res<State<AppState>>,
+    events: GameEvents,
+    mut rng: ResMut<GameRng>,
+    settings: Res<Settings>,
+    mut face_cell_events: EventWriter<FaceCell>,
+    input_state: Res<InputState>,
 ) -> Bubble {
+    if !state.0.is_playing() || !input_state.can_select() {
+        return Bubble::Up;
+    }
     let game = &mut *game;
-    match game.phase {
-        GamePhase::Play => on_cell_clicked_play_phase(click.target, &mut query, game, commands),
-        GamePhase::PlaceUnits => on_cell_clicked_place_units_phase(click.target, &mut query, game),
+    // Near edges the plane of the neighboring face can get the click
+    let target = clicked_plane(&click, &camera_query, game).unwrap_or(click.target);
+    if let Some(coords) = far_face_cell(target, &click, &query, &camera_query, &settings) {
+        face_cell_events.send(FaceCell(coords));
+        return Bubble::Up;
+    }
+    choose_cell(target, &mut query, game, events, &mut rng);
+    Bubble::Up
+}
+
+/// Large cubes have one mesh per face instead of per cell, so the clicked cell is found from where
+/// the face was hit
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn on_face_clicked(
+    In(click): In<ListenedEvent<Click>>,
+    mut query: Query<(Option<&MainCube>, &mut Transform)>,
+    camera_query: Query<&GlobalTransform>,
+    mut game: ResMut<Game>,
+    state: Res<State<AppState>>,
+    events: GameEvents,
+    mut rng: ResMut<GameRng>,
+    settings: Res<Settings>,
+    mut face_cell_events: EventWriter<FaceCell>,
+    input_state: Res<InputState>,
+) -> Bubble {
+    if !state.0.is_playing() || !input_state.can_select() {
+        return Bubble::Up;
     }
+    let game = &mut *game;
+    let Some(plane) = clicked_plane(&click, &camera_query, game) else {
+        return Bubble::Up;
+    };
+    if let Some(coords) = far_face_cell(plane, &click, &query, &camera_query, &settings) {
+        face_cell_events.send(FaceCell(coords));
+        return Bubble::Up;
+    }
+    choose_cell(plane, &mut query, game, events, &mut rng);
     Bubble::Up
 }
 
+/// Does what clicking the cell of `plane` does, also used by [`crate::cell_cursor`]
+pub(crate) fn choose_cell(
+    plane: Entity,
+    query: &mut Query<(Option<&MainCube>, &mut Transform)>,
+    game: &mut Game,
+    events: GameEvents,
+    rng: &mut GameRng,
+) {
+    match game.phase {
+        GamePhase::Play => on_cell_clicked_play_phase(plane, query, game, events, rng),
+        GamePhase::PlaceUnits => on_cell_clicked_place_units_phase(plane, query, game),
+    }
+}
+
+/// Takes back the selection of a unit
+pub(crate) fn deselect(game: &mut Game) {
+    game.selected_cell = None;
+    reset_cells_new_selection(game);
+}
+
+/// The plane of the cell that the player meant to click, found from where the cube was hit. The
+/// cube never moves, it is the camera that rotates around it.
+fn clicked_plane(
+    click: &ListenedEvent<Click>,
+    camera_query: &Query<&GlobalTransform>,
+    game: &Game,
+) -> Option<Entity> {
+    let position = click.hit.position?;
+    let camera_transform = camera_query.get(click.hit.camera).ok()?;
+    let coords = scene::coords_at_cube_position(
+        position,
+        position - camera_transform.translation(),
+        game.board.cube_side_length,
+    );
+    Some(game.board.get_cell(coords)?.plane.into())
+}
+
+/// The cell of `plane` if its face points so far away from the camera that it was hardly seen,
+/// in which case the click turns the cube toward it instead of selecting it. The free camera is
+/// never turned for the player.
+fn far_face_cell(
+    plane: Entity,
+    click: &ListenedEvent<Click>,
+    query: &Query<(Option<&MainCube>, &mut Transform)>,
+    camera_query: &Query<&GlobalTransform>,
+    settings: &Settings,
+) -> Option<CellCoordinates> {
+    if settings.orbit_camera {
+        return None;
+    }
+    let (Some(main_cube), transform) = query.get(plane).ok()? else {
+        return None;
+    };
+    let camera_transform = camera_query.get(click.hit.camera).ok()?;
+    let to_camera = camera_transform.translation() - transform.translation;
+    let normal = main_cube.coords.normal_direction().as_vec3();
+    let angle = normal.angle_between(to_camera).to_degrees();
+    (angle > settings.far_face_angle).then_some(main_cube.coords)
+}
+
 fn on_cell_clicked_place_units_phase(
     target: Entity,
     query: &mut Query<(Option<&MainCube>, &mut Transform)>,
@@ -144,8 +608,12 @@ fn on_cell_clicked_play_phase(
     target: Entity,
     query: &mut Query<(Option<&MainCube>, &mut Transform)>,
     game: &mut Game,
-    mut commands: Commands,
+    mut events: GameEvents,
+    rng: &mut GameRng,
 ) {
+    if !game.is_played_here() {
+        return;
+    }
     let cell_clicked = query.get(target);
     let clicked_coords;
     if let Ok(cell_clicked) = cell_clicked {
@@ -160,22 +628,54 @@ fn on_cell_clicked_play_phase(
         return;
     }
 
+    // The player is choosing what a pawn is promoted to
+    if game.pending_promotion.is_some() {
+        return;
+    }
+
+    if game.pending_summon.is_some() {
+        if reinforcements::try_summon(game, clicked_coords) {
+            end_turn(game, &mut events, rng);
+        }
+        game.selected_cell = None;
+        reset_cells_new_selection(game);
+        return;
+    }
+
     let old_selected_cell = game.selected_cell;
     game.selected_cell = Some(clicked_coords);
 
-    let clicked_cell = game.board.get_cell_mut(clicked_coords).unwrap();
-
-    if clicked_cell.selected_unit_can_move_to {
+    if let Some(from) = old_selected_cell {
+        let game_move = GameMove {
+            from,
+            to: clicked_coords,
+        };
         // Move selected unit
-        if let Some(from) = old_selected_cell {
-            let game_move = GameMove {
-                from,
-                to: clicked_coords,
-            };
-            if make_move(game_move, game, &mut commands)
-                && game.units.get_unit_mut(clicked_coords).is_some()
-            {
-                game.next_player_turn();
+        if movement::is_legal(
+            game_move,
+            &game.board,
+            &game.units,
+            game.turn,
+            game.variant.rules(),
+        ) {
+            if game.variant.rules().simultaneous_moves {
+                if duel::lock_in_move(game_move, game, &mut events) {
+                    end_turn(game, &mut events, rng);
+                }
+                // Don't show anything about the picked move to the other player
+                game.selected_cell = None;
+                reset_cells_new_selection(game);
+                return;
+            }
+            if game.promotes(game_move) {
+                // The move is made once the player has chosen what the pawn is promoted to
+                game.pending_promotion = Some(game_move);
+                game.selected_cell = None;
+                reset_cells_new_selection(game);
+                return;
+            }
+            if make_move(game_move, game, &mut events) {
+                end_turn(game, &mut events, rng);
             }
         }
     }
@@ -187,56 +687,130 @@ fn on_cell_clicked_play_phase(
         return;
     }
     // Mark which cells the selected unit can go to
-    let unit_moves = movement::get_unit_moves(unit, &game.board, &game.units);
+    let unit_moves =
+        movement::get_legal_moves(unit, &game.board, &game.units, game.variant.rules());
     for unit_move in unit_moves {
-        let cell = game.board.get_cell_mut(unit_move);
-        match cell {
+        match game.board.get_cell_mut(unit_move) {
             None => {
                 warn!("Cell {:?} doesn't exist", unit_move);
             }
-            Some(cell) => {
-                let unit_at_destination = game.units.get_unit(unit_move);
-                // Check so normal pieces can't capture over edge
-                if (unit.unit_type.can_capture_over_edge()
-                    || unit_at_destination.is_none()
-                    || unit.coords.normal_direction() == unit_move.normal_direction())
-                // Prevent taking units on same team
-                    && unit_at_destination.map_or(true, |unit_at_d| unit.team != unit_at_d.team)
-                {
-                    cell.selected_unit_can_move_to = true;
-                }
-            }
+            Some(cell) => cell.selected_unit_can_move_to = true,
         }
     }
 }
 
-pub(crate) fn make_move(game_move: GameMove, game: &mut Game, commands: &mut Commands) -> bool {
-    let captured_unit = game.units.get_unit_mut(game_move.to);
-    if let Some(captured_unit) = captured_unit {
-        if captured_unit.team == game.turn {
-            return false;
-        }
-        if let Some(entity) = captured_unit.entity {
-            scene::kill_unit(commands, entity);
-        };
-        captured_unit.dead = true;
-        game.units.remove_dead_units();
-    }
+/// Makes a move of the team whose turn it is. A pawn that is promoted by the move becomes a queen.
+pub(crate) fn make_move(game_move: GameMove, game: &mut Game, events: &mut GameEvents) -> bool {
+    make_move_as(game_move, game.turn, None, game, events)
+}
 
-    let Some(unit) = game.units.get_unit_mut(game_move.from) else {return false};
-    if unit.team != game.turn {
+/// Makes a move like [`make_move`], promoting a pawn that is promoted by the move to `promotion`
+pub(crate) fn make_promoting_move(
+    game_move: GameMove,
+    promotion: UnitType,
+    game: &mut Game,
+    events: &mut GameEvents,
+) -> bool {
+    make_move_as(game_move, game.turn, Some(promotion), game, events)
+}
+
+/// Makes a move with a unit of `team`, which doesn't have to be the team whose turn it is.
+/// Returns false without changing anything if the move is illegal.
+fn make_move_as(
+    game_move: GameMove,
+    team: Team,
+    promotion: Option<UnitType>,
+    game: &mut Game,
+    events: &mut GameEvents,
+) -> bool {
+    if !movement::is_legal(
+        game_move,
+        &game.board,
+        &game.units,
+        team,
+        game.variant.rules(),
+    ) {
+        return false;
+    }
+    let promotion = game
+        .promotes(game_move)
+        .then(|| promotion.unwrap_or(UnitType::Queen));
+    let notation = notation::write_promoting_move(game_move, promotion, &game.board, &game.units);
+    // The unit may be blown up by its own capture in the atomic variant
+    let Some(entity) = game
+        .units
+        .get_unit(game_move.from)
+        .and_then(|unit| unit.entity)
+    else {
+        warn!("Unit entity was None");
         return false;
+    };
+    let (captured_unit, exploded_units) = game.apply_move(game_move);
+    if let Some(unit_type) = promotion {
+        game.promote(game_move.to, unit_type);
+    }
+    game.history.record_move(
+        team,
+        Some(game_move),
+        notation.clone(),
+        captured_unit.clone(),
+    );
+    if !exploded_units.is_empty() {
+        game.history.record_explosion(exploded_units.clone());
+    }
+    for unit in captured_unit.into_iter().chain(exploded_units) {
+        events.unit_captured.send(UnitCaptured { unit });
     }
 
-    unit.move_unit_to(game_move.to);
-    let Some(entity) = unit.entity else {warn!("Unit entity was None");return false;};
-    game.entities_to_move.push((entity, game_move.to));
-    if let UnitType::Pawn(_, ref mut has_moved) = unit.unit_type {
-        *has_moved = true;
+    events.move_made.send(MoveMade {
+        game_move,
+        notation,
+        team,
+        entity: entity.into(),
+    });
+    if let Some(unit_type) = promotion.filter(|_| game.units.is_unit_at(game_move.to)) {
+        events.unit_promoted.send(UnitPromoted {
+            coords: game_move.to,
+            unit_type,
+            entity: entity.into(),
+        });
     }
     true
 }
 
+/// Passes the turn to the other team, and lets the neutral units move once both teams have moved
+pub(crate) fn end_turn(game: &mut Game, events: &mut GameEvents, rng: &mut GameRng) {
+    let mover = game.turn;
+    game.next_player_turn();
+    // Not after the extra moves of a handicap
+    let both_moved = mover == Team::Black && game.turn == Team::White;
+    if game.variant.rules().neutral_units_move && both_moved {
+        move_neutral_units(game, events, rng);
+    }
+    events.turn_changed.send(TurnChanged { turn: game.turn });
+    game.record_position();
+}
+
+/// Neutral units make a random move
+fn move_neutral_units(game: &mut Game, events: &mut GameEvents, rng: &mut GameRng) {
+    let mut possible_moves = Vec::new();
+    for unit in game
+        .units
+        .all_units_iter()
+        .filter(|unit| unit.team == Team::Neutral)
+    {
+        for to in movement::get_unit_moves(unit, &game.board, &game.units) {
+            possible_moves.push(GameMove {
+                from: unit.coords,
+                to,
+            });
+        }
+    }
+    if let Some(&game_move) = possible_moves.choose(rng.rng()) {
+        make_move_as(game_move, Team::Neutral, None, game, events);
+    }
+}
+
 fn reset_cells_new_selection(game: &mut Game) {
     for cell in game.board.get_all_cells_mut() {
         cell.selected_unit_can_move_to = false;
@@ -248,29 +822,37 @@ pub(crate) fn spawn_unit_entity(
     unit: &mut Unit,
     entities_to_move: &mut Vec<(Entity, CellCoordinates)>,
     asset_server: &AssetServer,
+    piece_set: &PieceSet,
 ) {
-    let model_name = unit.unit_type.model_name();
-    let entity = scene::spawn_unit(commands, asset_server, model_name);
+    let entity = scene::spawn_unit(commands, asset_server, piece_set, unit.unit_type);
     entities_to_move.push((entity, unit.coords));
-    unit.set_entity(entity);
+    unit.set_entity(entity.into());
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn on_unit_clicked(
     In(click): In<ListenedEvent<Click>>,
     mut query: Query<(Option<&MainCube>, &mut Transform)>,
     scene_child_query: Query<&SceneChild>,
     mut game: ResMut<Game>,
-    commands: Commands,
+    state: Res<State<AppState>>,
+    events: GameEvents,
+    mut rng: ResMut<GameRng>,
+    input_state: Res<InputState>,
 ) -> Bubble {
     let game = &mut *game;
-    if game.phase == GamePhase::Play {
+    if game.phase == GamePhase::Play && state.0.is_playing() && input_state.can_select() {
         let Ok(scene_child) = scene_child_query.get(click.target) else {
             warn!("Err when getting scene_child");
             return Bubble::Up;
         };
-        if let Some(unit) = game.units.get_unit_from_entity(scene_child.parent_entity) {
+        if let Some(unit) = game
+            .units
+            .get_unit_from_entity(scene_child.parent_entity.into())
+        {
             if let Some(cell) = game.board.get_cell(unit.coords) {
-                on_cell_clicked_play_phase(cell.plane, &mut query, game, commands);
+                let plane = cell.plane.into();
+                on_cell_clicked_play_phase(plane, &mut query, game, events, &mut rng);
             } else {
                 warn!("Cell is None");
             }
@@ -281,18 +863,218 @@ pub(crate) fn on_unit_clicked(
     Bubble::Burst
 }
 
-pub(crate) fn ai_play(
+pub(crate) fn on_cell_hovered(
+    In(over): In<ListenedEvent<Over>>,
+    query: Query<&MainCube>,
+    mut game: ResMut<Game>,
+    state: Res<State<AppState>>,
+) -> Bubble {
+    if let (Ok(cell), true) = (query.get(over.target), state.0.hovers_cells()) {
+        game.hovered_cell = Some(cell.coords);
+    }
+    Bubble::Up
+}
+
+pub(crate) fn on_cell_unhovered(
+    In(out): In<ListenedEvent<Out>>,
+    query: Query<&MainCube>,
+    mut game: ResMut<Game>,
+) -> Bubble {
+    if let Ok(cell) = query.get(out.target) {
+        unhover(&mut game, cell.coords);
+    }
+    Bubble::Up
+}
+
+/// A hovered unit highlights the cell it is on, since clicking it selects that cell
+pub(crate) fn on_unit_hovered(
+    In(over): In<ListenedEvent<Over>>,
+    scene_child_query: Query<&SceneChild>,
     mut game: ResMut<Game>,
+    state: Res<State<AppState>>,
+) -> Bubble {
+    if let (Some(coords), true) = (
+        hovered_unit_coords(over.target, &scene_child_query, &game),
+        state.0.hovers_cells(),
+    ) {
+        game.hovered_cell = Some(coords);
+    }
+    Bubble::Burst
+}
+
+pub(crate) fn on_unit_unhovered(
+    In(out): In<ListenedEvent<Out>>,
+    scene_child_query: Query<&SceneChild>,
+    mut game: ResMut<Game>,
+) -> Bubble {
+    if let Some(coords) = hovered_unit_coords(out.target, &scene_child_query, &game) {
+        unhover(&mut game, coords);
+    }
+    Bubble::Burst
+}
+
+fn hovered_unit_coords(
+    target: Entity,
+    scene_child_query: &Query<&SceneChild>,
+    game: &Game,
+) -> Option<CellCoordinates> {
+    let scene_child = scene_child_query.get(target).ok()?;
+    let unit = game
+        .units
+        .get_unit_from_entity(scene_child.parent_entity.into())?;
+    Some(unit.coords)
+}
+
+/// The cursor can be over the next cell before it is out of the last one, so only the cell it left
+/// stops being hovered
+fn unhover(game: &mut Game, coords: CellCoordinates) {
+    if game.hovered_cell == Some(coords) {
+        game.hovered_cell = None;
+    }
+}
+
+/// Ctrl+Z takes back the last turn of the player together with the answer of the AI, and Ctrl+Y
+/// plays them again
+pub(crate) fn undo_redo(
     mut commands: Commands,
-    mut ai_cache: Local<AICache>,
+    input: Res<Input<KeyCode>>,
+    mut game: ResMut<Game>,
+    puzzle: Option<Res<PuzzleMode>>,
+    settings: Res<Settings>,
+) {
+    // Turns can't be taken back from the other player of an online game, or while watching one.
+    // Puzzles take back wrong moves by themselves.
+    if !input.any_pressed([KeyCode::LControl, KeyCode::RControl])
+        || puzzle.is_some()
+        || game.remote_team.is_some()
+        || game.spectating
+    {
+        return;
+    }
+    let key_bindings = &settings.key_bindings;
+    let step = if input.just_pressed(key_bindings.undo) {
+        Game::undo
+    } else if input.just_pressed(key_bindings.redo) {
+        Game::redo
+    } else {
+        return;
+    };
+    let game = &mut *game;
+    let previous_units = game.units.clone();
+    if !step(game) {
+        return;
+    }
+    // The AI would answer again right away, so its turns are stepped over
+    while game.ai_playing == Some(game.turn) && step(game) {}
+    scene::resync_unit_entities(&mut commands, game, &previous_units);
+}
+
+/// The game is over when a king has been captured, or when the team to move has no moves
+pub(crate) fn check_game_over(
+    mut game: ResMut<Game>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut events: GameEvents,
 ) {
+    if game.phase != GamePhase::Play || !game.is_changed() {
+        return;
+    }
+    let (result, reason) = if let Some(winner) = game.variant.rules().winner(&game.units) {
+        (GameResult::Win(winner), EndReason::KingCaptured)
+    } else if game
+        .units
+        .all_units_iter()
+        .filter(|unit| unit.team == game.turn)
+        .all(|unit| {
+            movement::get_legal_moves(unit, &game.board, &game.units, game.variant.rules())
+                .is_empty()
+        })
+    {
+        (GameResult::Draw, EndReason::NoMoves)
+    } else {
+        return;
+    };
+    end_game(&mut game, &mut events, &mut next_state, result, reason);
+}
+
+/// Ends the game, even if both kings are still on the board
+pub(crate) fn end_game(
+    game: &mut Game,
+    events: &mut GameEvents,
+    next_state: &mut NextState<AppState>,
+    result: GameResult,
+    reason: EndReason,
+) {
+    game.result = Some(result);
+    game.end_reason = Some(reason);
+    events.game_ended.send(GameEnded { result });
+    next_state.set(AppState::GameOver);
+}
+
+/// The AI waits for one frame at the start of its turn, so that the turn indicator shows that it is
+/// thinking before the search holds up the game
+pub(crate) fn ai_play(
+    mut game: ResMut<Game>,
+    mut events: GameEvents,
+    mut rng: ResMut<GameRng>,
+    mut waited: Local<bool>,
+) {
+    let game = &mut *game;
     if game
         .ai_playing
         .map_or(false, |ai_playing| ai_playing == game.turn)
     {
+        if !*waited {
+            *waited = true;
+            return;
+        }
+        *waited = false;
         // It is AI's turn
-        let next_move = ai::next_move(&game.board, &game.units, game.turn, 3, &mut ai_cache);
-        make_move(next_move, &mut game, &mut commands);
-        game.next_player_turn();
+        let next_move = if game.variant.rules().simultaneous_moves {
+            ai::next_duel_move(&game.board, &game.units, game.turn, &mut game.ai_cache)
+        } else {
+            ai::next_move(
+                &game.board,
+                &game.units,
+                game.turn,
+                game.ai_depth,
+                game.variant,
+                &mut game.ai_cache,
+            )
+        };
+        // In the reinforcements variant the turn can be spent on a summon instead
+        if game.variant.rules().summons {
+            let summons = reinforcements::possible_summons(game, game.turn);
+            let summon = ai::best_summon(
+                &game.board,
+                &game.units,
+                &summons,
+                next_move,
+                game.turn,
+                game.ai_depth,
+                game.variant,
+                &mut game.ai_cache,
+            );
+            if let Some(unit) = summon {
+                game.pending_summon = Some(unit.unit_type);
+                if reinforcements::try_summon(game, unit.coords) {
+                    end_turn(game, &mut events, &mut rng);
+                    return;
+                }
+            }
+        }
+        let Some(next_move) = next_move else {
+            warn!("AI has no moves to make");
+            return;
+        };
+        let move_made = if game.variant.rules().simultaneous_moves {
+            duel::lock_in_move(next_move, game, &mut events)
+        } else {
+            make_move(next_move, game, &mut events)
+        };
+        if !move_made {
+            warn!("AI picked an illegal move: {:?}", next_move);
+            return;
+        }
+        end_turn(game, &mut events, &mut rng);
     }
 }
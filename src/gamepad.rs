@@ -0,0 +1,156 @@
+//! Playing with a gamepad. The shoulder buttons and the right stick turn the cube, the north
+//! button turns the camera, the d-pad and the left stick move the cell cursor of
+//! [`crate::cell_cursor`], the south button does what clicking the cell under the cursor does and
+//! the east button takes back the selection.
+
+use bevy::prelude::*;
+
+use crate::cell_cursor::{self, CellCursor};
+use crate::cube_rotation::{RevealCell, RotationButtons};
+use crate::gamemanager::{self, Game, GameEvents};
+use crate::input_state::InputState;
+use crate::rng::GameRng;
+use crate::scene::MainCube;
+use crate::{AppState, MainCamera};
+
+/// How far a stick has to be pushed to count as pressed
+const STICK_THRESHOLD: f32 = 0.6;
+
+/// Seconds between steps of the cursor while the d-pad or the left stick is held
+const CURSOR_REPEAT: f32 = 0.2;
+
+fn any_pressed(
+    gamepads: &Gamepads,
+    buttons: &Input<GamepadButton>,
+    button_type: GamepadButtonType,
+) -> bool {
+    gamepads
+        .iter()
+        .any(|gamepad| buttons.pressed(GamepadButton::new(gamepad, button_type)))
+}
+
+fn any_just_pressed(
+    gamepads: &Gamepads,
+    buttons: &Input<GamepadButton>,
+    button_type: GamepadButtonType,
+) -> bool {
+    gamepads
+        .iter()
+        .any(|gamepad| buttons.just_pressed(GamepadButton::new(gamepad, button_type)))
+}
+
+/// The direction a stick is pushed in, with sticks that are barely pushed counting as centered
+fn stick(
+    gamepads: &Gamepads,
+    axes: &Axis<GamepadAxis>,
+    x_axis: GamepadAxisType,
+    y_axis: GamepadAxisType,
+) -> Vec2 {
+    gamepads
+        .iter()
+        .map(|gamepad| {
+            let value = |axis_type| axes.get(GamepadAxis::new(gamepad, axis_type));
+            Vec2::new(value(x_axis).unwrap_or(0.), value(y_axis).unwrap_or(0.))
+        })
+        .find(|direction| direction.length() > STICK_THRESHOLD)
+        .unwrap_or(Vec2::ZERO)
+}
+
+/// Passes the rotations pressed on gamepads to [`crate::cube_rotation::rotate`]
+pub(crate) fn gamepad_rotation(
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut rotation_buttons: ResMut<RotationButtons>,
+) {
+    let right_stick = stick(
+        &gamepads,
+        &axes,
+        GamepadAxisType::RightStickX,
+        GamepadAxisType::RightStickY,
+    );
+    let pressed = [
+        any_pressed(&gamepads, &buttons, GamepadButtonType::LeftTrigger) || right_stick.x < 0.,
+        any_pressed(&gamepads, &buttons, GamepadButtonType::RightTrigger) || right_stick.x > 0.,
+        right_stick.y < 0.,
+        right_stick.y > 0.,
+    ];
+    for (i, pressed) in pressed.into_iter().enumerate() {
+        rotation_buttons.just_pressed[i] = pressed && !rotation_buttons.pressed[i];
+        rotation_buttons.pressed[i] = pressed;
+    }
+    rotation_buttons.turn_camera = any_just_pressed(&gamepads, &buttons, GamepadButtonType::North);
+}
+
+/// Moves the cell cursor, and selects and deselects with it
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn gamepad_cursor(
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    time: Res<Time>,
+    state: Res<State<AppState>>,
+    camera_query: Query<&GlobalTransform, (With<MainCamera>, With<Camera>)>,
+    mut query: Query<(Option<&MainCube>, &mut Transform)>,
+    mut game: ResMut<Game>,
+    events: GameEvents,
+    mut rng: ResMut<GameRng>,
+    mut reveal_cell_events: EventWriter<RevealCell>,
+    mut cursor: ResMut<CellCursor>,
+    input_state: Res<InputState>,
+    mut until_repeat: Local<f32>,
+) {
+    if !state.0.is_playing() {
+        return;
+    }
+    let game = &mut *game;
+    let d_pad_axis = |positive, negative| match (
+        any_pressed(&gamepads, &buttons, positive),
+        any_pressed(&gamepads, &buttons, negative),
+    ) {
+        (true, false) => 1.,
+        (false, true) => -1.,
+        _ => 0.,
+    };
+    let d_pad = Vec2::new(
+        d_pad_axis(GamepadButtonType::DPadRight, GamepadButtonType::DPadLeft),
+        d_pad_axis(GamepadButtonType::DPadUp, GamepadButtonType::DPadDown),
+    );
+    let left_stick = stick(
+        &gamepads,
+        &axes,
+        GamepadAxisType::LeftStickX,
+        GamepadAxisType::LeftStickY,
+    );
+    let direction = if d_pad != Vec2::ZERO {
+        d_pad
+    } else {
+        left_stick
+    };
+
+    *until_repeat -= time.delta_seconds();
+    if direction == Vec2::ZERO {
+        *until_repeat = 0.;
+    } else if *until_repeat <= 0. {
+        *until_repeat = CURSOR_REPEAT;
+        if let Ok(camera_transform) = camera_query.get_single() {
+            cell_cursor::move_cursor(
+                game,
+                direction,
+                camera_transform,
+                &query,
+                &mut reveal_cell_events,
+                &mut cursor,
+            );
+        }
+    }
+
+    if !input_state.can_select() {
+        return;
+    }
+    if any_just_pressed(&gamepads, &buttons, GamepadButtonType::South) {
+        cell_cursor::choose_cursor_cell(game, &mut query, events, &mut rng);
+    } else if any_just_pressed(&gamepads, &buttons, GamepadButtonType::East) {
+        gamemanager::deselect(game);
+    }
+}
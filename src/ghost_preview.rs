@@ -0,0 +1,124 @@
+//! Shows a see-through copy of the selected unit on the cell under the cursor when the unit can
+//! move there, so that the player can see the move before making it.
+
+use bevy::prelude::*;
+use bevy::scene::SceneInstance;
+
+use crate::engine::cell::CellCoordinates;
+use crate::engine::units::{Team, Unit, UnitType};
+use crate::gamemanager::Game;
+use crate::piece_sets::PieceSets;
+use crate::scene;
+use crate::settings::Settings;
+
+/// How see-through the copy is, from 0 for invisible to 1 for solid
+const GHOST_ALPHA: f32 = 0.4;
+
+#[derive(Component)]
+pub(crate) struct GhostUnit {
+    team: Team,
+}
+
+/// Marks a copy whose model hasn't been made see-through yet, like [`scene::PrepareUnit`]
+#[derive(Component)]
+pub(crate) struct PrepareGhost;
+
+/// Moves the copy to the hovered cell, or removes it once no move is hovered
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn update_ghost_preview(
+    mut commands: Commands,
+    game: Res<Game>,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    piece_sets: Res<PieceSets>,
+    ghost_query: Query<Entity, With<GhostUnit>>,
+    cell_query: Query<&Transform, Without<GhostUnit>>,
+    mut shown: Local<Option<(CellCoordinates, UnitType, Team)>>,
+) {
+    let previewed = hovered_move(&game);
+    if *shown == previewed && ghost_query.iter().count() == usize::from(shown.is_some()) {
+        return;
+    }
+    for entity in &ghost_query {
+        commands.entity(entity).despawn_recursive();
+    }
+    *shown = None;
+
+    let Some((coords, unit_type, team)) = previewed else {
+        return;
+    };
+    let Some(cell) = game.board.get_cell(coords) else {
+        return;
+    };
+    // Tried again on the next frame if the cell hasn't been spawned yet
+    let Ok(cell_transform) = cell_query.get(cell.plane.into()) else {
+        return;
+    };
+    commands.spawn((
+        SceneBundle {
+            scene: asset_server.load(piece_sets.get(&settings.piece_set).scene_path(unit_type)),
+            transform: scene::unit_transform(
+                cell_transform.translation,
+                coords,
+                game.board.cube_side_length,
+                scene::unit_facing(
+                    &Unit::new(unit_type, team, coords),
+                    &game.units,
+                    game.board.cube_side_length,
+                ),
+            ),
+            ..default()
+        },
+        GhostUnit { team },
+        PrepareGhost,
+    ));
+    *shown = previewed;
+}
+
+/// The hovered cell together with the selected unit, if that unit can move to the cell
+fn hovered_move(game: &Game) -> Option<(CellCoordinates, UnitType, Team)> {
+    let hovered = game.hovered_cell?;
+    let can_move = game.board.get_cell(hovered)?.selected_unit_can_move_to;
+    if !can_move || game.pending_promotion.is_some() {
+        return None;
+    }
+    let unit = game.units.get_unit(game.selected_cell?)?;
+    Some((hovered, unit.unit_type, unit.team))
+}
+
+/// Gives the copy its own see-through materials once its model has been loaded
+pub(crate) fn prepare_ghost(
+    mut commands: Commands,
+    ghost_query: Query<(Entity, &SceneInstance, &GhostUnit), With<PrepareGhost>>,
+    mut material_query: Query<&mut Handle<StandardMaterial>>,
+    scene_manager: Res<SceneSpawner>,
+    settings: Res<Settings>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (entity, instance, ghost) in &ghost_query {
+        if !scene_manager.instance_is_ready(**instance) {
+            continue;
+        }
+        commands.entity(entity).remove::<PrepareGhost>();
+
+        for child in scene_manager.iter_instance_entities(**instance) {
+            let Ok(mut material_handle) = material_query.get_mut(child) else {
+                continue;
+            };
+            let Some(material) = materials.get(&material_handle) else {
+                continue;
+            };
+            let mut material = material.clone();
+            settings.team_material(ghost.team).apply(&mut material);
+            material.base_color.set_a(GHOST_ALPHA);
+            material.alpha_mode = AlphaMode::Blend;
+            *material_handle = materials.add(material);
+        }
+    }
+}
+
+pub(crate) fn despawn_ghost_preview(mut commands: Commands, query: Query<Entity, With<GhostUnit>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
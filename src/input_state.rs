@@ -0,0 +1,60 @@
+//! What the input of the player can do at the moment. Clicks, the cell cursor, typed moves and
+//! turning the cube all happen in their own systems and callbacks, so they are checked against one
+//! state instead of each other. That way a unit can't be selected while a move is still being
+//! animated or while the cube turns under the mouse, and nothing can be moved while the AI
+//! decides on its move.
+
+use bevy::prelude::*;
+
+use crate::cube_rotation::CubeTurning;
+use crate::gamemanager::Game;
+use crate::scene::UnitMovement;
+
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum InputState {
+    #[default]
+    Idle,
+    UnitSelected,
+    /// A unit is sliding to its new cell, see [`crate::scene::animate_unit_movement`]
+    AnimatingMove,
+    /// It is the turn of the AI, which makes its move on the next frames
+    AiThinking,
+    Rotating,
+}
+
+impl InputState {
+    /// If cells and units can be chosen, by clicking or otherwise
+    pub(crate) fn can_select(self) -> bool {
+        matches!(self, Self::Idle | Self::UnitSelected)
+    }
+
+    /// If the player can start turning the cube. Turns are kept out of move animations, which may
+    /// turn the cube themselves to show the move, see [`crate::cube_rotation::reveal_ai_moves`].
+    pub(crate) fn can_rotate(self) -> bool {
+        self != Self::AnimatingMove
+    }
+}
+
+/// The turn of the AI comes first, since nothing the player does can be made while it lasts
+pub(crate) fn update_input_state(
+    game: Res<Game>,
+    cube_turning: Res<CubeTurning>,
+    movement_query: Query<(), With<UnitMovement>>,
+    mut input_state: ResMut<InputState>,
+) {
+    let state = if game.ai_playing == Some(game.turn) {
+        InputState::AiThinking
+    } else if !movement_query.is_empty() {
+        InputState::AnimatingMove
+    } else if cube_turning.0 {
+        InputState::Rotating
+    } else if game.selected_cell.is_some() {
+        InputState::UnitSelected
+    } else {
+        InputState::Idle
+    };
+    // Only set when needed to not trigger change detection every frame
+    if *input_state != state {
+        *input_state = state;
+    }
+}
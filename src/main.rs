@@ -1,11 +1,52 @@
-mod ai;
-mod cell;
+mod analysis;
+mod annotations;
+mod board_editor;
+mod board_theme;
+mod bug_report;
+mod captures;
+mod caster;
+mod cell_cursor;
+mod chess_clock;
+mod cli;
+mod clipboard;
+mod coordinate_labels;
 mod cube_rotation;
+mod debug;
+mod duel;
+mod engine;
+mod external_engine;
+mod game_record;
+mod game_setup;
 mod gamemanager;
+mod gamepad;
+mod ghost_preview;
+mod input_state;
+mod match_play;
 mod materials;
-mod movement;
+mod move_list;
+mod move_markers;
+mod move_prompt;
+mod music;
+mod network;
+mod peek;
+mod piece_guide;
+mod piece_sets;
+mod presets;
+mod promotion;
+mod protocol;
+mod puzzles;
+mod reinforcements;
+mod replay;
+mod rng;
 mod scene;
-mod units;
+mod settings;
+mod settings_menu;
+mod team_looks;
+mod threat_overlay;
+mod training;
+mod turn_indicator;
+mod tutorial;
+mod ui;
 mod utils;
 
 use bevy::log::*;
@@ -13,6 +54,53 @@ use bevy::prelude::*;
 use bevy_mod_picking::prelude::*;
 
 fn main() {
+    // Read before the command line, since positions given there can have these pieces
+    let assets = bevy::asset::FileAssetIo::get_base_path().join("assets");
+    let piece_errors = engine::pieces::load(&assets.join(engine::pieces::PIECES_FILE));
+    let options = match cli::LaunchOptions::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+    let mut load_errors = settings::LoadErrors(piece_errors);
+    let settings = settings::Settings::load(&mut load_errors);
+    let presets = presets::SetupPresets::load(&mut load_errors);
+    let puzzle_packs = puzzles::PuzzlePacks::load(&assets, &mut load_errors);
+    let solved_puzzles = puzzles::SolvedPuzzles::load(&mut load_errors);
+    let blunder_deck = training::BlunderDeck::load(&mut load_errors);
+    let launch_preset = options.preset(presets.chosen());
+    if let Some(moves) = options.benchmark_moves {
+        let preset = launch_preset.as_ref().unwrap_or(presets.chosen());
+        cli::run_benchmark(preset, &settings, moves);
+        return;
+    }
+    let launch_game = match &options.load {
+        Some(path) => match game_record::load_game(path, &settings) {
+            Ok(game) => Some(game),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        },
+        None => launch_preset.map(|preset| preset.new_game(&settings)),
+    };
+    let initial_state = if settings.seen_onboarding || launch_game.is_some() {
+        AppState::MainMenu
+    } else {
+        AppState::Onboarding
+    };
+    // Going straight from the main menu to the game starts the game set up on the command line
+    let next_state = match (&launch_game, options.replay) {
+        (Some(_), true) => Some(AppState::Replay),
+        (Some(_), false) => Some(AppState::InGame),
+        (None, _) => None,
+    };
     App::new()
         .add_plugins(
             DefaultPlugins
@@ -27,42 +115,326 @@ fn main() {
                 .build()
                 .disable::<DefaultHighlightingPlugin>(),
         )
-        .insert_resource(gamemanager::Game::new(4))
+        .insert_resource(presets.chosen().new_game(&settings))
+        .insert_resource(rng::GameRng::new(options.seed.or(settings.seed)))
+        .add_plugin(external_engine::ExternalEnginePlugin(
+            options.engine.clone().or(settings.engine.clone()),
+        ))
+        .insert_resource(presets)
+        .insert_resource(piece_sets::PieceSets::find(&assets.join("models")))
+        .insert_resource(music::Music::find(&assets))
+        .insert_resource(puzzle_packs)
+        .insert_resource(solved_puzzles)
+        .init_resource::<puzzles::LaunchPuzzle>()
+        .insert_resource(settings)
+        .insert_resource(load_errors)
+        .add_startup_system(ui::spawn_load_error_dialog)
+        .add_system(ui::close_load_error_dialog)
+        .add_system(settings::save_settings)
+        .add_state::<AppState>()
+        .insert_resource(State(initial_state))
+        .insert_resource(NextState(next_state))
+        .insert_resource(cli::LaunchGame(launch_game))
+        .add_event::<gamemanager::MoveMade>()
+        .add_event::<cube_rotation::FaceCell>()
+        .add_event::<cube_rotation::RevealCell>()
+        .add_event::<gamemanager::UnitCaptured>()
+        .add_event::<gamemanager::UnitPromoted>()
+        .add_event::<gamemanager::TurnChanged>()
+        .add_event::<gamemanager::GameEnded>()
         .add_startup_system(setup)
-        .add_system(cube_rotation::rotate)
-        .add_system(scene::update_cell_colors)
+        .add_systems(start_game_systems().in_schedule(OnExit(AppState::MainMenu)))
+        .add_systems(start_game_systems().in_schedule(OnExit(AppState::Setup)))
+        .add_systems(start_game_systems().in_schedule(OnExit(AppState::Puzzles)))
+        .add_systems(
+            (
+                scene::despawn_cube,
+                reinforcements::despawn_energy_meter,
+                match_play::despawn_match_score,
+                match_play::forget_finished_match,
+                game_setup::forget_game_setup,
+                move_list::despawn_move_list,
+                captures::despawn_captures_tray,
+                turn_indicator::despawn_turn_indicator,
+                chess_clock::despawn_chess_clock,
+                move_markers::despawn_move_markers,
+                threat_overlay::despawn_threat_overlay,
+                ghost_preview::despawn_ghost_preview,
+                cell_cursor::despawn_cursor_frame,
+                annotations::forget_annotations,
+                puzzles::forget_puzzle,
+            )
+                .in_schedule(OnEnter(AppState::MainMenu)),
+        )
+        .add_system(ui::spawn_main_menu.in_schedule(OnEnter(AppState::MainMenu)))
+        .add_system(ui::launch_waiting_game.in_schedule(OnEnter(AppState::MainMenu)))
+        .add_system(ui::despawn_menu.in_schedule(OnExit(AppState::MainMenu)))
+        .add_system(
+            ui::main_menu_input
+                .run_if(not(resource_exists::<network::JoinPrompt>()))
+                .in_set(OnUpdate(AppState::MainMenu)),
+        )
+        .add_system(game_setup::spawn_setup_menu.in_schedule(OnEnter(AppState::Setup)))
+        .add_system(ui::despawn_menu.in_schedule(OnExit(AppState::Setup)))
+        .add_system(game_setup::setup_menu_input.in_set(OnUpdate(AppState::Setup)))
+        .add_system(puzzles::spawn_puzzle_browser.in_schedule(OnEnter(AppState::Puzzles)))
+        .add_system(ui::despawn_menu.in_schedule(OnExit(AppState::Puzzles)))
+        .add_system(puzzles::puzzle_browser_input.in_set(OnUpdate(AppState::Puzzles)))
+        .add_system(settings_menu::spawn_settings_menu.in_schedule(OnEnter(AppState::Settings)))
+        .add_system(ui::despawn_menu.in_schedule(OnExit(AppState::Settings)))
+        .add_system(settings_menu::settings_menu_input.in_set(OnUpdate(AppState::Settings)))
+        .add_system(settings_menu::settings_key)
+        .add_system(ui::spawn_pause_menu.in_schedule(OnEnter(AppState::Paused)))
+        .add_system(ui::despawn_menu.in_schedule(OnExit(AppState::Paused)))
+        .add_system(ui::toggle_pause.before(reinforcements::choose_summon))
+        .add_system(ui::button_system)
+        .add_system(ui::new_game_input)
+        .init_resource::<bug_report::GameLog>()
+        .add_system(bug_report::export_bug_report.in_set(OnUpdate(AppState::Paused)))
+        .add_system(clipboard::copy_position.in_set(OnUpdate(AppState::Paused)))
+        .add_system(ui::spawn_game_over_screen.in_schedule(OnEnter(AppState::GameOver)))
+        .add_system(ui::despawn_menu.in_schedule(OnExit(AppState::GameOver)))
+        .add_system(
+            ui::rematch_input
+                .run_if(not(resource_exists::<network::Network>()))
+                .in_set(OnUpdate(AppState::GameOver)),
+        )
+        .add_system(replay::enter_replay.in_set(OnUpdate(AppState::GameOver)))
+        .add_system(analysis::enter_analysis.in_set(OnUpdate(AppState::GameOver)))
+        .init_resource::<analysis::GameAnalysis>()
+        .add_system(analysis::forget_analysis.in_schedule(OnEnter(AppState::MainMenu)))
+        .add_system(analysis::spawn_analysis_report.in_schedule(OnEnter(AppState::Analysis)))
+        .add_system(analysis::despawn_analysis_report.in_schedule(OnExit(AppState::Analysis)))
+        .add_systems(
+            (
+                analysis::analyse_next_turn,
+                analysis::update_analysis_report.after(analysis::analyse_next_turn),
+                analysis::analysis_input,
+            )
+                .in_set(OnUpdate(AppState::Analysis)),
+        )
+        .add_system(replay::start_replay.in_schedule(OnEnter(AppState::Replay)))
+        .add_system(replay::replay_input.in_set(OnUpdate(AppState::Replay)))
+        .add_system(replay::update_replay_panel.in_set(OnUpdate(AppState::Replay)))
+        .add_system(move_list::update_move_list)
+        .add_system(move_list::scroll_move_list)
+        .add_system(move_list::click_move_list)
+        .add_system(replay::end_replay.in_schedule(OnExit(AppState::Replay)))
+        .add_system(board_editor::spawn_editor_panel.in_schedule(OnEnter(AppState::Editor)))
+        .add_systems(
+            (
+                board_editor::editor_input,
+                board_editor::update_editor_panel,
+            )
+                .chain()
+                .in_set(OnUpdate(AppState::Editor)),
+        )
+        .add_system(board_editor::close_board_editor.in_schedule(OnExit(AppState::Editor)))
+        .add_system(piece_guide::spawn_piece_guide_text.in_schedule(OnEnter(AppState::PieceGuide)))
+        .add_systems(
+            (
+                piece_guide::piece_guide_input,
+                piece_guide::update_piece_guide_text.after(piece_guide::piece_guide_input),
+            )
+                .in_set(OnUpdate(AppState::PieceGuide)),
+        )
+        .add_system(piece_guide::close_piece_guide.in_schedule(OnExit(AppState::PieceGuide)))
+        .add_system(tutorial::start_onboarding.in_schedule(OnEnter(AppState::Onboarding)))
+        .add_system(tutorial::advance_tutorial.in_set(OnUpdate(AppState::Onboarding)))
+        .add_system(
+            tutorial::update_tutorial_text
+                .after(tutorial::advance_tutorial)
+                .in_set(OnUpdate(AppState::Onboarding)),
+        )
+        .add_system(tutorial::end_onboarding.in_schedule(OnExit(AppState::Onboarding)))
+        .init_resource::<cube_rotation::RotationButtons>()
+        .init_resource::<cube_rotation::CubeTurning>()
+        .init_resource::<input_state::InputState>()
+        .add_system(input_state::update_input_state.before(cube_rotation::rotate))
+        .add_system(gamepad::gamepad_rotation.before(cube_rotation::rotate))
+        .add_system(gamepad::gamepad_cursor)
+        .init_resource::<cell_cursor::CellCursor>()
+        .add_system(cell_cursor::keyboard_cursor.in_set(OnUpdate(AppState::InGame)))
+        .add_system(
+            cell_cursor::update_cursor_frame
+                .after(cell_cursor::keyboard_cursor)
+                .after(gamepad::gamepad_cursor),
+        )
+        .add_system(cube_rotation::rotate.run_if(can_rotate))
+        .add_system(cube_rotation::orbit.run_if(can_rotate))
+        .add_system(
+            cube_rotation::face_king
+                .before(cube_rotation::rotate)
+                .run_if(can_rotate),
+        )
+        .add_system(cube_rotation::reveal_ai_moves.before(cube_rotation::rotate))
+        .add_system(cube_rotation::face_king_on_turn_change.before(cube_rotation::rotate))
+        .add_system(cube_rotation::toggle_idle_rotation)
+        .init_resource::<peek::Peek>()
+        .add_system(peek::update_peek.before(scene::update_cell_colors))
+        .add_system(scene::update_cell_colors.run_if(resource_exists::<materials::CellMaterials>()))
         .add_system(scene::prepare_unit_entity.run_if(any_with_component::<scene::PrepareUnit>()))
-        .add_system(scene::move_unit_entities)
-        .add_system(scene::spawn_missing_unit_entities)
-        .add_system(gamemanager::ai_play)
+        .add_system(scene::apply_game_events.before(scene::move_unit_entities))
+        .add_system(gamemanager::log_game_events)
+        .add_system(scene::move_unit_entities.run_if(resource_exists::<materials::CellMaterials>()))
+        .add_system(scene::animate_unit_movement.after(scene::move_unit_entities))
+        .add_system(scene::lift_selected_unit.after(scene::animate_unit_movement))
+        .add_system(scene::animate_captures)
+        .add_system(
+            scene::spawn_missing_unit_entities
+                .run_if(resource_exists::<materials::CellMaterials>()),
+        )
+        .add_system(
+            piece_sets::respawn_units_on_set_change.before(scene::spawn_missing_unit_entities),
+        )
+        .add_system(team_looks::recolor_units_on_look_change)
+        .add_system(scene::update_level_badges)
+        .add_system(move_markers::update_move_markers)
+        .init_resource::<annotations::Annotations>()
+        .add_system(annotations::draw_annotations.run_if(can_rotate))
+        .add_system(annotations::clear_annotations_on_move)
+        .add_system(
+            annotations::update_annotation_marks
+                .after(annotations::draw_annotations)
+                .after(annotations::clear_annotations_on_move),
+        )
+        .add_system(ghost_preview::update_ghost_preview)
+        .add_system(coordinate_labels::update_coordinate_labels)
+        .add_system(
+            coordinate_labels::place_coordinate_labels
+                .after(coordinate_labels::update_coordinate_labels),
+        )
+        .add_system(
+            ghost_preview::prepare_ghost
+                .run_if(any_with_component::<ghost_preview::PrepareGhost>()),
+        )
+        .add_system(threat_overlay::toggle_threat_overlay)
+        .add_system(
+            threat_overlay::update_threat_overlay.after(threat_overlay::toggle_threat_overlay),
+        )
+        .add_system(
+            match_play::record_match_game
+                .after(gamemanager::check_game_over)
+                .run_if(resource_exists::<match_play::Match>()),
+        )
+        .add_system(
+            game_record::export_finished_game
+                .after(gamemanager::check_game_over)
+                .before(match_play::record_match_game),
+        )
+        .add_system(match_play::update_match_score.run_if(resource_exists::<match_play::Match>()))
+        .add_system(reinforcements::choose_summon.in_set(OnUpdate(AppState::InGame)))
+        .add_systems(
+            (
+                promotion::update_promotion_chooser,
+                promotion::place_promotion_chooser,
+                promotion::choose_promotion.after(ui::toggle_pause),
+            )
+                .in_set(OnUpdate(AppState::InGame)),
+        )
+        .add_system(promotion::despawn_promotion_chooser.in_schedule(OnExit(AppState::InGame)))
+        .add_system(reinforcements::update_energy_meter)
+        .add_system(captures::update_captures_tray)
+        .add_system(turn_indicator::update_turn_indicator.after(gamemanager::ai_play))
+        .add_system(
+            gamemanager::ai_play
+                .run_if(not(resource_exists::<external_engine::ExternalEngine>()))
+                .in_set(OnUpdate(AppState::InGame)),
+        )
+        .add_system(gamemanager::check_game_over.in_set(OnUpdate(AppState::InGame)))
+        .add_system(
+            chess_clock::run_chess_clock
+                .before(gamemanager::ai_play)
+                .run_if(resource_exists::<chess_clock::ChessClock>())
+                .in_set(OnUpdate(AppState::InGame)),
+        )
+        .add_system(
+            chess_clock::update_clock_display.run_if(resource_exists::<chess_clock::ChessClock>()),
+        )
+        .add_system(
+            game_record::autosave
+                .before(gamemanager::check_game_over)
+                .in_set(OnUpdate(AppState::InGame)),
+        )
+        .add_system(gamemanager::undo_redo.in_set(OnUpdate(AppState::InGame)))
+        .init_resource::<caster::CasterMode>()
+        .add_system(caster::toggle_caster_mode)
+        .add_system(
+            caster::follow_main_camera
+                .after(cube_rotation::rotate)
+                .after(cube_rotation::orbit),
+        )
+        .add_system(caster::update_caster_overlay)
+        .insert_resource(blunder_deck)
+        .add_system(training::flag_blunders)
+        .add_system(
+            puzzles::play_puzzle
+                .before(gamemanager::check_game_over)
+                .in_set(OnUpdate(AppState::InGame)),
+        )
+        .add_startup_system(puzzles::setup_puzzle_prompt)
+        .add_startup_system(music::start_music)
+        .add_system(music::update_music_volume)
+        .add_system(puzzles::update_puzzle_prompt)
+        .add_system(puzzles::change_pack_puzzle)
+        .add_system(debug::dump_selected_cell_moves)
+        .add_plugin(network::NetworkPlugin)
+        .add_plugin(move_prompt::MovePromptPlugin)
         .run();
 }
 
+#[derive(States, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub(crate) enum AppState {
+    #[default]
+    MainMenu,
+    /// Shown instead of the main menu on the first launch
+    Onboarding,
+    /// Picking the cube size, the AI and the rest before a game, instead of playing a preset
+    Setup,
+    /// Changing the settings, from the main menu or the pause menu
+    Settings,
+    InGame,
+    Paused,
+    GameOver,
+    /// Stepping through the turns of the finished game
+    Replay,
+    /// The report of the moves of the finished game, see [`analysis`]
+    Analysis,
+    /// Setting up a position by hand, see [`board_editor`]
+    Editor,
+    /// Picking a mate puzzle, see [`puzzles`]
+    Puzzles,
+    /// Showing how every unit moves, see [`piece_guide`]
+    PieceGuide,
+}
+
+impl AppState {
+    /// If the player can move units
+    pub(crate) fn is_playing(&self) -> bool {
+        matches!(self, Self::InGame | Self::Onboarding)
+    }
+
+    /// The cube can be rotated during a replay, in the editor and in the piece guide too
+    pub(crate) fn can_rotate(&self) -> bool {
+        self.is_playing() || matches!(self, Self::Replay | Self::Editor | Self::PieceGuide)
+    }
+
+    /// If the cell under the mouse is highlighted, since clicking it moves or places a unit
+    pub(crate) fn hovers_cells(&self) -> bool {
+        self.is_playing() || *self == Self::Editor
+    }
+}
+
+fn can_rotate(state: Res<State<AppState>>) -> bool {
+    state.0.can_rotate()
+}
+
 #[derive(Component)]
 struct MainCamera {
     start_coords: Vec3,
 }
 
-fn setup(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mut game: ResMut<gamemanager::Game>,
-) {
-    let material = StandardMaterial {
-        base_color: Color::ANTIQUE_WHITE,
-        ..default()
-    };
-
-    scene::construct_cube(
-        game.board.cube_side_length,
-        &mut meshes,
-        &mut commands,
-        &mut materials,
-        &material,
-        &mut game,
-    );
-
+fn setup(mut commands: Commands) {
     commands.spawn((
         PointLightBundle {
             point_light: PointLight {
@@ -90,3 +462,44 @@ fn setup(
         },
     ));
 }
+
+/// Starts the game picked on the main menu or the setup menu, when it is left for the game
+fn start_game_systems() -> bevy::ecs::schedule::SystemConfigs {
+    (
+        start_game,
+        reinforcements::setup_energy_meter.after(start_game),
+        match_play::setup_match_score,
+        move_list::spawn_move_list,
+        captures::setup_captures_tray,
+        turn_indicator::setup_turn_indicator,
+        chess_clock::setup_chess_clock.after(start_game),
+        puzzles::begin_puzzle,
+    )
+        .distributive_run_if(game_setup::is_game_picked)
+}
+
+/// The cube is built once a preset has been picked on the main menu, since the preset decides its
+/// size and colors. The cube of the previous game is removed when entering the main menu.
+#[allow(clippy::too_many_arguments)]
+fn start_game(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut game: ResMut<gamemanager::Game>,
+    presets: Res<presets::SetupPresets>,
+    current_match: Option<Res<match_play::Match>>,
+    mut launch_game: ResMut<cli::LaunchGame>,
+    settings: Res<settings::Settings>,
+    mut game_log: ResMut<bug_report::GameLog>,
+    mut rng: ResMut<rng::GameRng>,
+) {
+    // Later games are picked on the main menu
+    *game = match (current_match, launch_game.0.take()) {
+        (Some(current_match), _) => current_match.new_game(&settings),
+        (None, Some(launch_game)) => launch_game,
+        (None, None) => presets.chosen().new_game(&settings),
+    };
+    game_log.clear();
+    rng.restart();
+    scene::build_cube(&mut commands, &mut meshes, &mut materials, &mut game);
+}
@@ -0,0 +1,195 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::units::Team;
+use crate::gamemanager::{Game, GameEnded, GameResult};
+use crate::presets::SetupPreset;
+use crate::settings::{self, Settings};
+
+/// Finished matches are added to the end of this file
+const RESULTS_PATH: &str = "match-results.ron";
+
+/// Several games between the same two players, who swap colors every game. The first player to
+/// reach the target points wins the match.
+#[derive(Resource, Debug)]
+pub(crate) struct Match {
+    pub(crate) preset: SetupPreset,
+    target_points: u32,
+    /// Points are counted in halves, since a draw gives half a point to each player. The first
+    /// player has white in the first game.
+    half_points: [u32; 2],
+    games_played: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MatchResult {
+    preset: String,
+    players: [String; 2],
+    half_points: [u32; 2],
+    games_played: u32,
+}
+
+impl Match {
+    pub(crate) fn new(preset: SetupPreset, target_points: u32) -> Self {
+        Self {
+            preset,
+            target_points,
+            half_points: [0; 2],
+            games_played: 0,
+        }
+    }
+
+    /// Against the AI, the first player is the one at the computer
+    pub(crate) fn player_names(&self) -> [&'static str; 2] {
+        if self.preset.ai.is_some() {
+            ["You", "AI"]
+        } else {
+            ["Player 1", "Player 2"]
+        }
+    }
+
+    /// The color of the first player in the current game
+    pub(crate) fn first_player_team(&self) -> Team {
+        match self.games_played % 2 {
+            0 => Team::White,
+            _ => Team::Black,
+        }
+    }
+
+    /// The game of the next round, with the colors swapped from the previous one
+    pub(crate) fn new_game(&self, settings: &Settings) -> Game {
+        let mut game = self.preset.new_game(settings);
+        if self.first_player_team() == Team::Black {
+            game.ai_playing = game.ai_playing.map(|team| team.opposite());
+        }
+        game
+    }
+
+    fn record(&mut self, result: GameResult) {
+        match result {
+            GameResult::Win(team) if team == self.first_player_team() => self.half_points[0] += 2,
+            GameResult::Win(_) => self.half_points[1] += 2,
+            GameResult::Draw => {
+                self.half_points[0] += 1;
+                self.half_points[1] += 1;
+            }
+        }
+        self.games_played += 1;
+    }
+
+    /// The index of the player that has won the match, if any
+    pub(crate) fn winner(&self) -> Option<usize> {
+        (0..2).find(|&player| self.half_points[player] >= 2 * self.target_points)
+    }
+
+    pub(crate) fn display_score(&self) -> String {
+        let [first, second] = self.player_names();
+        let score = match self.winner() {
+            Some(winner) => format!("{} won the match", self.player_names()[winner]),
+            None => format!("first to {}", self.target_points),
+        };
+        format!(
+            "{} {} - {} {} ({})",
+            first,
+            display_points(self.half_points[0]),
+            display_points(self.half_points[1]),
+            second,
+            score
+        )
+    }
+}
+
+fn display_points(half_points: u32) -> String {
+    match (half_points / 2, half_points % 2) {
+        (0, 1) => "½".to_string(),
+        (points, 1) => format!("{}½", points),
+        (points, _) => points.to_string(),
+    }
+}
+
+/// Adds the result of a finished game to the match, and saves the match once it is won
+pub(crate) fn record_match_game(
+    mut game_ended: EventReader<GameEnded>,
+    mut current_match: ResMut<Match>,
+) {
+    for game_ended in game_ended.iter() {
+        current_match.record(game_ended.result);
+        if current_match.winner().is_some() {
+            save_result(&current_match);
+        }
+    }
+}
+
+fn save_result(finished: &Match) {
+    // A corrupted file has been moved aside, so the results start over
+    let mut results: Vec<MatchResult> = match settings::read_ron(RESULTS_PATH) {
+        Ok(results) => results.unwrap_or_default(),
+        Err(err) => {
+            warn!("{}", err);
+            Vec::new()
+        }
+    };
+    results.push(MatchResult {
+        preset: finished.preset.name.clone(),
+        players: finished.player_names().map(str::to_string),
+        half_points: finished.half_points,
+        games_played: finished.games_played,
+    });
+    settings::save_ron(RESULTS_PATH, &results);
+}
+
+/// A won match is over once the player leaves the game over screen
+pub(crate) fn forget_finished_match(mut commands: Commands, current_match: Option<Res<Match>>) {
+    if matches!(current_match, Some(current_match) if current_match.winner().is_some()) {
+        commands.remove_resource::<Match>();
+    }
+}
+
+#[derive(Component)]
+pub(crate) struct MatchScore;
+
+pub(crate) fn setup_match_score(mut commands: Commands, current_match: Option<Res<Match>>) {
+    if current_match.is_none() {
+        return;
+    }
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(10.),
+                right: Val::Px(10.),
+                ..default()
+            },
+            ..default()
+        }),
+        MatchScore,
+    ));
+}
+
+pub(crate) fn update_match_score(
+    current_match: Res<Match>,
+    mut query: Query<&mut Text, With<MatchScore>>,
+) {
+    for mut text in &mut query {
+        text.sections[0].value = format!(
+            "{}\n{:?}: {}",
+            current_match.display_score(),
+            current_match.first_player_team(),
+            current_match.player_names()[0]
+        );
+    }
+}
+
+pub(crate) fn despawn_match_score(mut commands: Commands, query: Query<Entity, With<MatchScore>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
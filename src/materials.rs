@@ -1,13 +1,169 @@
-use crate::cell::CellColor;
+use crate::board_theme::{self, BoardTheme, ThemeTextures};
+use crate::engine::cell::CellColor;
 use crate::gamemanager::Palette;
 use bevy::prelude::*;
+use bevy::render::render_resource::Face;
+
+/// How much of the far side shows through the faces in front while peeking
+const PEEK_ALPHA: f32 = 0.3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CellState {
+    Normal,
+    Selected,
+    CanGo,
+    /// Under the cursor, see [`crate::gamemanager::on_cell_hovered`]
+    Hovered,
+}
+
+impl CellState {
+    const ALL: [CellState; 4] = [Self::Normal, Self::Selected, Self::CanGo, Self::Hovered];
+
+    fn apply(&self, material: &mut StandardMaterial, palette: Palette, color: CellColor) {
+        match self {
+            Self::Normal => normal_cell_material(material, palette, color),
+            Self::Selected => select_cell_material(material, palette, color),
+            Self::CanGo => can_go_cell_material(material, palette, color),
+            Self::Hovered => hovered_cell_material(material, palette, color),
+        }
+    }
+}
+
+/// How a cell is drawn, which only changes while the player peeks through the cube, see
+/// [`crate::peek`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CellView {
+    Solid,
+    /// On a face in front, which the far side of the cube can be seen through
+    SeeThrough,
+    /// On a face at the back, which is seen from inside the cube through the faces in front
+    FromInside,
+}
+
+impl CellView {
+    const ALL: [CellView; 3] = [Self::Solid, Self::SeeThrough, Self::FromInside];
+
+    /// Applied after the color of the cell has been set
+    fn apply(&self, material: &mut StandardMaterial) {
+        if *self == Self::SeeThrough {
+            material.base_color.set_a(PEEK_ALPHA);
+            material.alpha_mode = AlphaMode::Blend;
+        } else {
+            material.alpha_mode = AlphaMode::Opaque;
+        }
+        // The cells are planes, which are only drawn from the front unless they are double sided
+        let from_inside = *self == Self::FromInside;
+        material.double_sided = from_inside;
+        material.cull_mode = (!from_inside).then_some(Face::Back);
+    }
+}
+
+/// One material per combination of cell color, state and view, shared by all cells. Cells change look
+/// by swapping which of these handles they use.
+#[derive(Resource)]
+pub(crate) struct CellMaterials {
+    palette: Palette,
+    /// Starts flat, the theme of the settings is put on by [`CellMaterials::set_look`]
+    theme: BoardTheme,
+    textures: Option<ThemeTextures>,
+    handles: Vec<(CellColor, CellState, CellView, Handle<StandardMaterial>)>,
+    /// Used by the merged face meshes of large cubes, which get their colors from the vertices
+    vertex_colored: Vec<(CellView, Handle<StandardMaterial>)>,
+}
+
+impl CellMaterials {
+    pub(crate) fn new(palette: Palette, materials: &mut Assets<StandardMaterial>) -> Self {
+        let mut handles = Vec::new();
+        for color in CellColor::ALL {
+            for state in CellState::ALL {
+                for view in CellView::ALL {
+                    let mut material = StandardMaterial::default();
+                    state.apply(&mut material, palette, color);
+                    view.apply(&mut material);
+                    handles.push((color, state, view, materials.add(material)));
+                }
+            }
+        }
+        let vertex_colored = CellView::ALL
+            .into_iter()
+            .map(|view| {
+                let mut material = StandardMaterial::default();
+                view.apply(&mut material);
+                (view, materials.add(material))
+            })
+            .collect();
+        CellMaterials {
+            palette,
+            theme: BoardTheme::Flat,
+            textures: None,
+            handles,
+            vertex_colored,
+        }
+    }
+
+    pub(crate) fn get(
+        &self,
+        color: CellColor,
+        state: CellState,
+        view: CellView,
+    ) -> &Handle<StandardMaterial> {
+        self.handles
+            .iter()
+            .find(|(c, s, v, _)| *c == color && *s == state && *v == view)
+            .map(|(_, _, _, handle)| handle)
+            .unwrap()
+    }
+
+    pub(crate) fn vertex_colored(&self, view: CellView) -> &Handle<StandardMaterial> {
+        self.vertex_colored
+            .iter()
+            .find(|(v, _)| *v == view)
+            .map(|(_, handle)| handle)
+            .unwrap()
+    }
+
+    /// Recolors and retextures the shared materials, which updates every cell at once
+    pub(crate) fn set_look(
+        &mut self,
+        palette: Palette,
+        theme: BoardTheme,
+        materials: &mut Assets<StandardMaterial>,
+        images: &mut Assets<Image>,
+    ) {
+        if theme != self.theme {
+            self.theme = theme;
+            self.textures = theme.textures(images);
+        }
+        self.palette = palette;
+        for (color, state, view, handle) in &self.handles {
+            if let Some(material) = materials.get_mut(handle) {
+                state.apply(material, palette, *color);
+                view.apply(material);
+                board_theme::apply_textures(material, self.textures.as_ref());
+            }
+        }
+        for (_, handle) in &self.vertex_colored {
+            if let Some(material) = materials.get_mut(handle) {
+                board_theme::apply_textures(material, self.textures.as_ref());
+            }
+        }
+    }
+
+    pub(crate) fn palette(&self) -> Palette {
+        self.palette
+    }
+
+    pub(crate) fn theme(&self) -> BoardTheme {
+        self.theme
+    }
+}
 
 pub(crate) fn select_cell_material(
     material: &mut StandardMaterial,
     palette: Palette,
     color: CellColor,
 ) {
-    material.base_color = blend_colors(color.base_color(palette), Color::YELLOW, 0.3);
+    material.base_color = blend_colors(palette.cell_color(color), Color::YELLOW, 0.3);
 }
 
 pub(crate) fn normal_cell_material(
@@ -15,7 +171,7 @@ pub(crate) fn normal_cell_material(
     palette: Palette,
     color: CellColor,
 ) {
-    material.base_color = color.base_color(palette);
+    material.base_color = palette.cell_color(color);
 }
 
 pub(crate) fn can_go_cell_material(
@@ -23,7 +179,16 @@ pub(crate) fn can_go_cell_material(
     palette: Palette,
     color: CellColor,
 ) {
-    material.base_color = blend_colors(color.base_color(palette), Color::LIME_GREEN, 0.3);
+    material.base_color = blend_colors(palette.cell_color(color), Color::LIME_GREEN, 0.3);
+}
+
+/// Only slightly lighter than a normal cell, so that it doesn't look selected
+pub(crate) fn hovered_cell_material(
+    material: &mut StandardMaterial,
+    palette: Palette,
+    color: CellColor,
+) {
+    material.base_color = blend_colors(palette.cell_color(color), Color::WHITE, 0.8);
 }
 
 fn blend_colors(c1: Color, c2: Color, fac: f32) -> Color {
@@ -0,0 +1,208 @@
+//! A panel at the side of the board listing every turn of the game, with the turn that led to the
+//! position on the board highlighted. Clicking a turn on the game over screen or during the replay
+//! shows the board as it was after that turn.
+
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+
+use crate::gamemanager::Game;
+use crate::replay::{self, ReplayFrom};
+use crate::AppState;
+
+const ROW_COLOR: Color = Color::NONE;
+const CURRENT_ROW_COLOR: Color = Color::rgba(1., 1., 1., 0.25);
+/// How far one line of the mouse wheel scrolls the list
+const LINE_HEIGHT: f32 = 24.;
+
+/// The visible part of the list, which the list scrolls inside
+#[derive(Component)]
+pub(crate) struct MoveListPanel;
+
+#[derive(Component, Default)]
+pub(crate) struct MoveList {
+    /// The rows as they are shown, so that they are only spawned again when they change
+    rows: Vec<String>,
+    current: usize,
+    /// How far the list is scrolled down, in pixels
+    offset: f32,
+    /// Keeps the list scrolled to the end as turns are played, until it is scrolled up
+    follow: bool,
+}
+
+/// A row of the list, with the number of turns that have been played after its turn
+#[derive(Component)]
+pub(crate) struct MoveListRow(usize);
+
+pub(crate) fn spawn_move_list(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        bottom: Val::Px(10.),
+                        right: Val::Px(10.),
+                        ..default()
+                    },
+                    size: Size::new(Val::Px(260.), Val::Percent(45.)),
+                    flex_direction: FlexDirection::Column,
+                    overflow: Overflow::Hidden,
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.4).into(),
+                ..default()
+            },
+            Interaction::default(),
+            MoveListPanel,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        flex_shrink: 0.,
+                        ..default()
+                    },
+                    ..default()
+                },
+                MoveList {
+                    follow: true,
+                    ..default()
+                },
+            ));
+        });
+}
+
+pub(crate) fn despawn_move_list(mut commands: Commands, query: Query<Entity, With<MoveListPanel>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Spawns the rows again whenever a turn is played, undone or redone
+pub(crate) fn update_move_list(
+    mut commands: Commands,
+    game: Res<Game>,
+    mut query: Query<(Entity, &mut MoveList)>,
+) {
+    if !game.is_changed() {
+        return;
+    }
+    let rows: Vec<String> = game
+        .history
+        .turns()
+        .enumerate()
+        .map(|(i, moves)| {
+            let moves: Vec<String> = moves
+                .iter()
+                .map(|recorded_move| format!("{:?} {}", recorded_move.team, recorded_move.notation))
+                .collect();
+            format!("{}. {}", i + 1, moves.join(", "))
+        })
+        .collect();
+    let current = game.history.turns_played();
+    for (entity, mut list) in &mut query {
+        if list.rows == rows && list.current == current {
+            continue;
+        }
+        commands.entity(entity).despawn_descendants();
+        commands.entity(entity).with_children(|parent| {
+            for (i, row) in rows.iter().enumerate() {
+                spawn_row(parent, row, i + 1, i + 1 == current);
+            }
+        });
+        list.rows = rows.clone();
+        list.current = current;
+    }
+}
+
+fn spawn_row(parent: &mut ChildBuilder, row: &str, turn: usize, current: bool) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    padding: UiRect::all(Val::Px(4.)),
+                    ..default()
+                },
+                background_color: match current {
+                    true => CURRENT_ROW_COLOR,
+                    false => ROW_COLOR,
+                }
+                .into(),
+                // Lets the panel under the row know that it is hovered, for scrolling
+                focus_policy: FocusPolicy::Pass,
+                ..default()
+            },
+            MoveListRow(turn),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                row,
+                TextStyle {
+                    font_size: 20.,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// Scrolls the list with the mouse wheel while the mouse is over it
+pub(crate) fn scroll_move_list(
+    mut wheel_events: EventReader<MouseWheel>,
+    panel_query: Query<(&Interaction, &Node, &Children), With<MoveListPanel>>,
+    mut list_query: Query<(&mut MoveList, &mut Style, &Node)>,
+) {
+    let scrolled: f32 = wheel_events
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y * LINE_HEIGHT,
+            MouseScrollUnit::Pixel => event.y,
+        })
+        .sum();
+    for (interaction, panel_node, children) in &panel_query {
+        for &child in children {
+            let Ok((mut list, mut style, list_node)) = list_query.get_mut(child) else {
+                continue;
+            };
+            let max_offset = (list_node.size().y - panel_node.size().y).max(0.);
+            if *interaction != Interaction::None && scrolled != 0. {
+                list.offset = (list.offset - scrolled).clamp(0., max_offset);
+                list.follow = list.offset == max_offset;
+            } else if list.follow {
+                list.offset = max_offset;
+            } else {
+                list.offset = list.offset.min(max_offset);
+            }
+            let top = Val::Px(-list.offset);
+            if style.position.top != top {
+                style.position.top = top;
+            }
+        }
+    }
+}
+
+/// Clicking a turn on the game over screen starts the replay there, and clicking one during the
+/// replay jumps to it
+pub(crate) fn click_move_list(
+    mut commands: Commands,
+    query: Query<(&Interaction, &MoveListRow), Changed<Interaction>>,
+    state: Res<State<AppState>>,
+    mut game: ResMut<Game>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for (interaction, row) in &query {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match state.0 {
+            AppState::Replay => replay::jump(&mut commands, &mut game, row.0),
+            AppState::GameOver => {
+                commands.insert_resource(ReplayFrom(row.0));
+                next_state.set(AppState::Replay);
+            }
+            _ => {}
+        }
+    }
+}
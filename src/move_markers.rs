@@ -0,0 +1,115 @@
+//! Marks the cells the selected unit can move to, on top of their color: a dot on an empty cell
+//! and a ring around a unit that would be captured. The marks are easier to see than the color
+//! alone on every palette.
+
+use bevy::prelude::*;
+
+use crate::engine::cell::CellCoordinates;
+use crate::gamemanager::Game;
+
+const MARKER_COLOR: Color = Color::rgba(0.05, 0.05, 0.05, 0.6);
+
+/// How far above the cell the markers are, relative to the size of a cell, so that they aren't
+/// hidden in it
+const MARKER_LIFT: f32 = 0.01;
+
+#[derive(Component)]
+pub(crate) struct MoveMarker;
+
+/// The meshes of the markers, which are sized for a cell 1 wide
+pub(crate) struct MarkerAssets {
+    dot: Handle<Mesh>,
+    ring: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+/// Spawns the markers again when the cells the selected unit can move to change
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn update_move_markers(
+    mut commands: Commands,
+    game: Res<Game>,
+    marker_query: Query<Entity, With<MoveMarker>>,
+    cell_query: Query<&Transform, Without<MoveMarker>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut assets: Local<Option<MarkerAssets>>,
+    mut shown: Local<Vec<CellCoordinates>>,
+) {
+    let destinations: Vec<CellCoordinates> = game
+        .board
+        .get_all_cells()
+        .into_iter()
+        .filter(|cell| cell.selected_unit_can_move_to)
+        .map(|cell| cell.coords)
+        .collect();
+    if *shown == destinations {
+        return;
+    }
+    for entity in &marker_query {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let assets = assets.get_or_insert_with(|| MarkerAssets {
+        dot: meshes.add(
+            shape::Cylinder {
+                radius: 0.15,
+                height: 0.02,
+                ..default()
+            }
+            .into(),
+        ),
+        ring: meshes.add(
+            shape::Torus {
+                radius: 0.4,
+                ring_radius: 0.03,
+                ..default()
+            }
+            .into(),
+        ),
+        material: materials.add(StandardMaterial {
+            base_color: MARKER_COLOR,
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+    });
+    let mut spawned = Vec::new();
+    for &coords in &destinations {
+        let Some(cell) = game.board.get_cell(coords) else {
+            continue;
+        };
+        let Ok(cell_transform) = cell_query.get(cell.plane.into()) else {
+            continue;
+        };
+        let normal = coords.normal_direction().as_vec3();
+        let size = cell_transform.scale.x;
+        let mesh = match game.units.is_unit_at(coords) {
+            true => assets.ring.clone(),
+            false => assets.dot.clone(),
+        };
+        commands.spawn((
+            PbrBundle {
+                mesh,
+                material: assets.material.clone(),
+                transform: Transform {
+                    translation: cell_transform.translation + normal * MARKER_LIFT * size,
+                    rotation: Quat::from_rotation_arc(Vec3::Y, normal),
+                    scale: Vec3::splat(size),
+                },
+                ..default()
+            },
+            MoveMarker,
+        ));
+        spawned.push(coords);
+    }
+    // Cells whose entities haven't been spawned yet are tried again on the next frame
+    if spawned == destinations {
+        *shown = destinations;
+    }
+}
+
+pub(crate) fn despawn_move_markers(mut commands: Commands, query: Query<Entity, With<MoveMarker>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,159 @@
+//! Typing moves instead of clicking them, in the notation of [`crate::engine::notation`], which
+//! also makes it possible to play blindfolded. The prompt is opened with a key binding and the
+//! move is made with Enter, if it is legal.
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+
+use crate::game_record;
+use crate::gamemanager::{self, Game, GameEvents, GamePhase};
+use crate::input_state::InputState;
+use crate::rng::GameRng;
+use crate::settings::Settings;
+use crate::{duel, AppState};
+
+pub(crate) struct MovePromptPlugin;
+
+impl Plugin for MovePromptPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_move_prompt_text)
+            .add_system(type_move.in_base_set(CoreSet::PreUpdate).after(InputSystem))
+            .add_system(update_move_prompt_text);
+    }
+}
+
+/// The move being typed, and why the last one that was entered couldn't be made
+#[derive(Resource, Default)]
+pub(crate) struct MovePrompt {
+    typed: String,
+    error: Option<String>,
+}
+
+/// Runs right after the keyboard is read and clears it while the prompt is open, so that the keys
+/// typed into it don't also turn the cube or pause the game. The characters are read even without
+/// a prompt, so the key that opened it isn't typed into it.
+#[allow(clippy::too_many_arguments)]
+fn type_move(
+    mut commands: Commands,
+    prompt: Option<ResMut<MovePrompt>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut input: ResMut<Input<KeyCode>>,
+    settings: Res<Settings>,
+    state: Res<State<AppState>>,
+    mut game: ResMut<Game>,
+    events: GameEvents,
+    mut rng: ResMut<GameRng>,
+    input_state: Res<InputState>,
+) {
+    let typed: String = characters
+        .iter()
+        .map(|character| character.char)
+        .filter(|character| !character.is_control())
+        .collect();
+    let Some(mut prompt) = prompt else {
+        if state.0.is_playing() && input.just_pressed(settings.key_bindings.type_move) {
+            commands.init_resource::<MovePrompt>();
+            input.reset_all();
+        }
+        return;
+    };
+    if !state.0.is_playing() {
+        commands.remove_resource::<MovePrompt>();
+        return;
+    }
+    prompt.typed.push_str(&typed);
+    if input.just_pressed(KeyCode::Back) {
+        prompt.typed.pop();
+    }
+    if input.just_pressed(KeyCode::Escape) {
+        commands.remove_resource::<MovePrompt>();
+    } else if input.just_pressed(KeyCode::Return) && !input_state.can_select() {
+        prompt.error = Some("Wait for the move or turn that is going on".to_string());
+    } else if input.just_pressed(KeyCode::Return) {
+        match make_typed_move(&prompt.typed, &mut game, events, &mut rng) {
+            Ok(()) => commands.remove_resource::<MovePrompt>(),
+            Err(err) => prompt.error = Some(err),
+        }
+    }
+    input.reset_all();
+}
+
+/// Makes the move like it would be made by clicking it
+fn make_typed_move(
+    typed: &str,
+    game: &mut Game,
+    mut events: GameEvents,
+    rng: &mut GameRng,
+) -> Result<(), String> {
+    if !game.is_played_here() || game.phase != GamePhase::Play {
+        return Err("It isn't your turn".to_string());
+    }
+    if game.pending_promotion.is_some() || game.pending_summon.is_some() {
+        return Err("Finish the move you started first".to_string());
+    }
+    let (game_move, promotion) = game_record::find_typed_move(game, typed, game.turn)?;
+    gamemanager::deselect(game);
+    let move_made = match promotion {
+        _ if game.variant.rules().simultaneous_moves => {
+            duel::lock_in_move(game_move, game, &mut events)
+        }
+        Some(promotion) => {
+            gamemanager::make_promoting_move(game_move, promotion, game, &mut events)
+        }
+        None => gamemanager::make_move(game_move, game, &mut events),
+    };
+    if !move_made {
+        return Err(format!("{} can't be made", typed.trim()));
+    }
+    gamemanager::end_turn(game, &mut events, rng);
+    Ok(())
+}
+
+#[derive(Component)]
+struct MovePromptText;
+
+fn setup_move_prompt_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: Val::Px(10.),
+                left: Val::Percent(40.),
+                ..default()
+            },
+            ..default()
+        }),
+        ZIndex::Global(1),
+        MovePromptText,
+    ));
+}
+
+fn update_move_prompt_text(
+    prompt: Option<Res<MovePrompt>>,
+    mut query: Query<&mut Text, With<MovePromptText>>,
+) {
+    let text = match prompt {
+        Some(prompt) => format!(
+            "Move: {}_\n{}",
+            prompt.typed,
+            prompt
+                .error
+                .as_deref()
+                .unwrap_or("Enter to make the move, Escape to cancel")
+        ),
+        None => String::new(),
+    };
+    for mut query_text in &mut query {
+        if query_text.sections[0].value != text {
+            query_text.sections[0].value = text.clone();
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Background music, played on a loop from the first `.ogg` file in `assets/music` by file name.
+//! There is no music without such a file. The music fades out on the game over screen and back in
+//! once it is left, and its volume is picked in the settings.
+
+use std::fs;
+use std::path::Path;
+
+use bevy::prelude::*;
+
+use crate::settings::Settings;
+use crate::AppState;
+
+const MUSIC_FOLDER: &str = "music";
+/// Seconds the music takes to fade out or in
+const FADE_TIME: f32 = 2.;
+
+#[derive(Resource)]
+pub(crate) struct Music {
+    /// The track that is played, for the asset server
+    track: Option<String>,
+    sink: Option<Handle<AudioSink>>,
+    /// How loud the music is from silent at 0 to the volume of the settings at 1
+    fade: f32,
+}
+
+impl Music {
+    /// Looks for the track in the music folder of `assets_dir`
+    pub(crate) fn find(assets_dir: &Path) -> Self {
+        let mut tracks: Vec<String> = fs::read_dir(assets_dir.join(MUSIC_FOLDER))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                let is_track = path.extension().is_some_and(|extension| extension == "ogg");
+                Some(path.file_name()?.to_str()?.to_string()).filter(|_| is_track)
+            })
+            .collect();
+        tracks.sort();
+        Music {
+            track: tracks
+                .first()
+                .map(|track| format!("{}/{}", MUSIC_FOLDER, track)),
+            sink: None,
+            fade: 1.,
+        }
+    }
+}
+
+pub(crate) fn start_music(
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    sinks: Res<Assets<AudioSink>>,
+    settings: Res<Settings>,
+    mut music: ResMut<Music>,
+) {
+    let Some(track) = &music.track else {
+        return;
+    };
+    let playing = audio.play_with_settings(
+        asset_server.load(track.as_str()),
+        PlaybackSettings::LOOP.with_volume(settings.music_volume),
+    );
+    // The handle from playing is weak, and the sink would be dropped with it
+    music.sink = Some(sinks.get_handle(playing));
+}
+
+/// Fades the music out on the game over screen and in everywhere else, and follows the volume of
+/// the settings
+pub(crate) fn update_music_volume(
+    time: Res<Time>,
+    state: Res<State<AppState>>,
+    settings: Res<Settings>,
+    sinks: Res<Assets<AudioSink>>,
+    mut music: ResMut<Music>,
+) {
+    let Some(sink) = music.sink.as_ref().and_then(|sink| sinks.get(sink)) else {
+        return;
+    };
+    let target = match state.0 {
+        AppState::GameOver => 0.,
+        _ => 1.,
+    };
+    let step = time.delta_seconds() / FADE_TIME;
+    let fade = if music.fade < target {
+        (music.fade + step).min(target)
+    } else {
+        (music.fade - step).max(target)
+    };
+    let volume = settings.music_volume * fade;
+    if sink.volume() != volume {
+        sink.set_volume(volume);
+    }
+    if music.fade != fade {
+        music.fade = fade;
+    }
+}
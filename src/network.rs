@@ -0,0 +1,1000 @@
+//! Playing against someone on another computer. One player hosts the game and the other joins it by
+//! address, or both join the dedicated server. The players send each other their moves as
+//! [`Message`]s, and each computer checks the moves of the other player with the same rules as its
+//! own moves. If the positions drift apart anyway, the position of the host wins.
+//!
+//! A player who loses the connection has [`REJOIN_TIME`] to rejoin, with the key the host gave
+//! them. The game they rejoin is sent in full and replaces their own.
+//!
+//! Once a game is over, the players can agree to a rematch with the colours swapped.
+//!
+//! Others can watch a game by joining it as spectators. They are sent the game so far and then
+//! every move, but can't make moves themselves.
+//!
+//! The comet of the wandering variant moves randomly and the duel variant keeps the moves secret,
+//! and neither is sent over the network, so those variants can't be played online.
+//!
+//! Games are only played over TCP, which a browser can't open. There is no browser build, so there
+//! are no room codes and no WebRTC connections either.
+
+use std::io::{self, ErrorKind};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+
+use crate::chess_clock;
+use crate::cli::LaunchGame;
+use crate::engine::cell::CellCoordinates;
+use crate::engine::cubefen::CubeFen;
+use crate::engine::history::RecordedMove;
+use crate::engine::movement::GameMove;
+use crate::engine::notation;
+use crate::engine::units::Team;
+use crate::game_record;
+use crate::gamemanager::{self, EndReason, Game, GameEvents, GameResult};
+use crate::match_play::Match;
+use crate::presets::SetupPreset;
+use crate::protocol::{self, Clocks, Connection, Greeting, Message, Newcomers, REJOIN_TIME};
+use crate::reinforcements;
+use crate::rng::GameRng;
+use crate::scene;
+use crate::settings::Settings;
+use crate::AppState;
+
+/// How long joining waits for the host to answer
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub(crate) struct NetworkPlugin;
+
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_network_status)
+            .add_system(update_network_status)
+            .add_system(type_join_address.in_set(OnUpdate(AppState::MainMenu)))
+            .add_system(leave_online_game.in_schedule(OnEnter(AppState::MainMenu)))
+            .add_system(accept_connections.run_if(resource_exists::<Network>()))
+            .add_system(serve_spectators.run_if(resource_exists::<Network>()))
+            .add_system(sync_game.run_if(resource_exists::<Network>()))
+            .add_system(wait_for_rejoin.run_if(resource_exists::<Network>()))
+            .add_system(flag_on_time.run_if(resource_exists::<Network>()))
+            .add_system(
+                online_game_input
+                    .run_if(resource_exists::<Network>())
+                    .in_set(OnUpdate(AppState::Paused)),
+            )
+            .add_system(
+                rematch_input
+                    .run_if(resource_exists::<Network>())
+                    .in_set(OnUpdate(AppState::GameOver)),
+            );
+    }
+}
+
+/// An online game that is being hosted, joined or played
+#[derive(Resource)]
+pub(crate) struct Network {
+    /// Only the host listens, for the other player and for spectators
+    listener: Option<TcpListener>,
+    /// Those who have connected to the host but not said yet if they play or watch
+    newcomers: Newcomers,
+    /// The connection to the other player, or to the host when watching
+    connection: Option<Connection>,
+    /// Those watching the game of the host
+    spectators: Vec<Connection>,
+    host: bool,
+    spectating: bool,
+    /// The address of the host, to rejoin it
+    address: String,
+    /// Lets the joining player rejoin, see [`Message::Rejoin`]
+    key: u64,
+    /// When the connection to the other player was lost, until they have rejoined
+    connection_lost: Option<Instant>,
+    /// Connects to the host again in the background after losing the connection
+    rejoining: Option<JoinHandle<io::Result<Connection>>>,
+    /// The moves in the history of the game that have been sent or received already
+    synced_moves: usize,
+    /// The team that has offered a draw that hasn't been answered
+    draw_offer: Option<Team>,
+    /// The team that has offered a rematch that hasn't been answered
+    rematch_offer: Option<Team>,
+    /// Set while going by the main menu to start a rematch, which doesn't leave the online game
+    starting_rematch: bool,
+    /// The clocks of a timed game. Those of the host decide when a team has run out of time, see
+    /// [`Clocks`].
+    clocks: Option<Clocks>,
+    /// Shown in the corner of the screen
+    status: String,
+}
+
+impl Network {
+    /// Starts listening for the other player, who plays black in the returned game
+    pub(crate) fn host(preset: &SetupPreset, settings: &Settings) -> Result<(Self, Game), String> {
+        let mut game = preset.new_game(settings);
+        let rules = game.variant.rules();
+        if rules.neutral_units_move || rules.simultaneous_moves {
+            return Err(format!(
+                "The {} variant can't be played online",
+                game.variant.name()
+            ));
+        }
+        if matches!(game.handicap, Some(handicap) if handicap.extra_move_every.is_some()) {
+            return Err("Extra moves of a handicap can't be given online".to_string());
+        }
+        game.ai_playing = None;
+        game.remote_team = Some(Team::Black);
+
+        let port = settings.online_port;
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .and_then(|listener| {
+                listener.set_nonblocking(true)?;
+                Ok(listener)
+            })
+            .map_err(|err| format!("Couldn't host on port {}: {}", port, err))?;
+        let network = Network {
+            listener: Some(listener),
+            newcomers: Newcomers::default(),
+            connection: None,
+            spectators: Vec::new(),
+            host: true,
+            spectating: false,
+            address: String::new(),
+            key: rand::random(),
+            connection_lost: None,
+            rejoining: None,
+            synced_moves: 0,
+            draw_offer: None,
+            rematch_offer: None,
+            starting_rematch: false,
+            clocks: settings
+                .online_minutes
+                .map(|minutes| Clocks::new(Duration::from_secs(minutes * 60))),
+            status: format!("Waiting for a player to join on port {}", port),
+        };
+        Ok((network, game))
+    }
+
+    /// Connects to the host at `address`, to play or watch. The game starts once the host has
+    /// answered.
+    pub(crate) fn join(
+        address: &str,
+        greeting: Greeting,
+        settings: &Settings,
+    ) -> Result<Self, String> {
+        let address = match address.contains(':') {
+            true => address.to_string(),
+            false => format!("{}:{}", address, settings.online_port),
+        };
+        let connection = connect(&address, greeting)
+            .map_err(|err| format!("Couldn't join {}: {}", address, err))?;
+        Ok(Network {
+            listener: None,
+            newcomers: Newcomers::default(),
+            connection: Some(connection),
+            spectators: Vec::new(),
+            host: false,
+            spectating: greeting == Greeting::Watch,
+            address: address.clone(),
+            key: 0,
+            connection_lost: None,
+            rejoining: None,
+            synced_moves: 0,
+            draw_offer: None,
+            rematch_offer: None,
+            starting_rematch: false,
+            clocks: None,
+            status: format!("Joining {}", address),
+        })
+    }
+
+    /// If the other player of the game is connected
+    pub(crate) fn is_playing(&self) -> bool {
+        self.connection.is_some() && !self.spectating
+    }
+
+    fn send(&mut self, message: &Message) {
+        let Some(connection) = &mut self.connection else {
+            return;
+        };
+        if let Err(err) = connection.send(message) {
+            warn!("Couldn't send {:?}: {}", message, err);
+        }
+    }
+
+    fn send_to_spectators(&mut self, message: &Message) {
+        protocol::send_to_spectators(&mut self.spectators, message);
+    }
+
+    /// Ends the turn of `team` on the clocks of a timed game, and returns the clocks to stamp on
+    /// its move. `stamp` is the clocks the other player stamped on the move.
+    fn end_turn(&mut self, team: Team, stamp: Option<[u64; 2]>) -> Option<[u64; 2]> {
+        let clocks = self.clocks.as_mut()?;
+        clocks.end_turn(team, stamp);
+        Some(clocks.stamp(team.opposite()))
+    }
+
+    /// The clocks of a timed game, to send to someone who has just joined
+    fn clock_message(&self, turn: Team) -> Option<Message> {
+        let clocks = self.clocks.as_ref()?;
+        Some(Message::Clock {
+            remaining: clocks.stamp(turn),
+        })
+    }
+
+    /// The status, with whose turn it is when watching a game and the clocks once the game has
+    /// started
+    fn status(&self, game: &Game) -> String {
+        if game.result.is_some() || self.connection.is_none() {
+            return self.status.clone();
+        }
+        let mut status = self.status.clone();
+        if self.spectating {
+            status.push_str(&format!("\n{:?} to move", game.turn));
+        }
+        if let Some(clocks) = &self.clocks {
+            let [white, black] = clocks.remaining(game.turn).map(chess_clock::display_time);
+            status.push_str(&format!("\nWhite {}  Black {}", white, black));
+        }
+        status
+    }
+
+    /// Starts a game sent by the host, or a rematch the host has started. A rematch goes by the
+    /// main menu, which cleans up the last game.
+    fn launch_game(
+        &mut self,
+        game: Game,
+        commands: &mut Commands,
+        next_state: &mut NextState<AppState>,
+        state: &State<AppState>,
+    ) {
+        self.synced_moves = game.history.moves().count();
+        self.draw_offer = None;
+        self.rematch_offer = None;
+        commands.insert_resource(LaunchGame(Some(game)));
+        commands.remove_resource::<Match>();
+        if state.0 == AppState::MainMenu {
+            next_state.set(AppState::InGame);
+        } else {
+            self.starting_rematch = true;
+            next_state.set(AppState::MainMenu);
+        }
+    }
+
+    /// Sets up the next game on the same cube with the colours swapped, and sends it to the other
+    /// player and the spectators
+    fn start_rematch(
+        &mut self,
+        game: &Game,
+        settings: &Settings,
+        commands: &mut Commands,
+        next_state: &mut NextState<AppState>,
+        state: &State<AppState>,
+    ) {
+        let Some(team) = game.remote_team.map(|team| team.opposite()) else {
+            return;
+        };
+        let mut rematch = Game::new(game.board.cube_side_length, game.variant, settings);
+        rematch.ai_playing = None;
+        rematch.remote_team = Some(team);
+        self.clocks = settings
+            .online_minutes
+            .map(|minutes| Clocks::new(Duration::from_secs(minutes * 60)));
+        self.send(&Message::Welcome {
+            team,
+            position: rematch.to_cubefen(),
+            key: self.key,
+        });
+        self.send_to_spectators(&Message::Watching {
+            record: game_record::write_game(&rematch),
+        });
+        if let Some(message) = self.clock_message(rematch.turn) {
+            self.send(&message);
+            self.send_to_spectators(&message);
+        }
+        self.launch_game(rematch, commands, next_state, state);
+        self.status = format!("Rematch, playing online against {:?}", team);
+    }
+
+    /// Connects to the host again and asks to play again, trying until [`REJOIN_TIME`] is up
+    fn rejoin(&mut self, team: Team) {
+        let address = self.address.clone();
+        let greeting = Greeting::Rejoin {
+            team,
+            key: self.key,
+        };
+        self.rejoining = Some(thread::spawn(move || {
+            let started = Instant::now();
+            loop {
+                match connect(&address, greeting) {
+                    Ok(connection) => return Ok(connection),
+                    Err(err) if started.elapsed() >= REJOIN_TIME => return Err(err),
+                    Err(_) => thread::sleep(Duration::from_secs(1)),
+                }
+            }
+        }));
+    }
+
+    fn disconnect(&mut self, status: String) {
+        self.connection = None;
+        self.status = status;
+    }
+}
+
+/// Connects to the host and says what for
+fn connect(address: &str, greeting: Greeting) -> io::Result<Connection> {
+    let socket_address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or(io::Error::from(ErrorKind::NotFound))?;
+    let mut connection = Connection::new(TcpStream::connect_timeout(
+        &socket_address,
+        CONNECT_TIMEOUT,
+    )?)?;
+    let version = env!("CARGO_PKG_VERSION").to_string();
+    connection.send(&match greeting {
+        Greeting::Play => Message::Join { version },
+        Greeting::Watch => Message::Spectate { version },
+        Greeting::Rejoin { team, key } => Message::Rejoin { version, team, key },
+    })?;
+    Ok(connection)
+}
+
+/// The host listens during the whole game, so spectators can join at any time. Only one player
+/// can join.
+fn accept_connections(mut network: ResMut<Network>, game: Res<Game>) {
+    let network = &mut *network;
+    let Some(listener) = &network.listener else {
+        return;
+    };
+    match listener
+        .accept()
+        .and_then(|(stream, _)| Connection::new(stream))
+    {
+        Ok(connection) => network.newcomers.add(connection),
+        Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+        Err(err) => warn!("Couldn't accept a connection: {}", err),
+    }
+    for greeted in network.newcomers.greeted() {
+        let (mut connection, greeting) = match greeted {
+            Ok(greeted) => greeted,
+            Err(err) => {
+                warn!("{}", err);
+                continue;
+            }
+        };
+        // The place of a player who has lost the connection is kept for them
+        let free = network.connection.is_none() && network.connection_lost.is_none();
+        let answered = match (greeting, game.remote_team) {
+            (Greeting::Play, Some(team)) if free => {
+                if let Some(clocks) = &mut network.clocks {
+                    clocks.restart();
+                }
+                let answered = connection
+                    .send(&Message::Welcome {
+                        team,
+                        position: game.to_cubefen(),
+                        key: network.key,
+                    })
+                    .and_then(|()| send_clocks(&mut connection, network, game.turn));
+                network.connection = Some(connection);
+                network.synced_moves = game.history.moves().count();
+                network.status = format!("Playing online against {:?}", team);
+                answered
+            }
+            (Greeting::Play, _) => connection.send(&Message::Refused {
+                reason: "Someone else is playing the game already".to_string(),
+            }),
+            (Greeting::Rejoin { team, key }, Some(remote_team))
+                if team == remote_team
+                    && key == network.key
+                    && network.connection.is_none()
+                    && network.connection_lost.is_some() =>
+            {
+                let answered = connection
+                    .send(&Message::Rejoined {
+                        record: game_record::write_game(&game),
+                    })
+                    .and_then(|()| send_clocks(&mut connection, network, game.turn));
+                network.connection = Some(connection);
+                network.connection_lost = None;
+                network.synced_moves = game.history.moves().count();
+                network.status = format!("Playing online against {:?}", team);
+                answered
+            }
+            (Greeting::Rejoin { .. }, _) => connection.send(&Message::Refused {
+                reason: "The game can't be rejoined".to_string(),
+            }),
+            (Greeting::Watch, _) => {
+                let answered = connection
+                    .send(&Message::Watching {
+                        record: game_record::write_game(&game),
+                    })
+                    .and_then(|()| send_clocks(&mut connection, network, game.turn));
+                network.spectators.push(connection);
+                answered
+            }
+        };
+        if let Err(err) = answered {
+            warn!("Couldn't answer {:?}: {}", greeting, err);
+        }
+    }
+}
+
+fn send_clocks(connection: &mut Connection, network: &Network, turn: Team) -> io::Result<()> {
+    match network.clock_message(turn) {
+        Some(message) => connection.send(&message),
+        None => Ok(()),
+    }
+}
+
+/// See [`protocol::serve_spectators`]
+fn serve_spectators(mut network: ResMut<Network>, game: Res<Game>) {
+    protocol::serve_spectators(&mut network.spectators, || game.to_cubefen());
+}
+
+/// Makes the moves of the other player, and sends the moves made here
+#[allow(clippy::too_many_arguments)]
+fn sync_game(
+    mut commands: Commands,
+    mut network: ResMut<Network>,
+    mut game: ResMut<Game>,
+    mut events: GameEvents,
+    mut rng: ResMut<GameRng>,
+    mut next_state: ResMut<NextState<AppState>>,
+    state: Res<State<AppState>>,
+    settings: Res<Settings>,
+) {
+    let Some(connection) = &mut network.connection else {
+        return;
+    };
+    let messages = match connection.receive() {
+        Ok(messages) => messages,
+        Err(err) if network.spectating => {
+            network.disconnect(format!("Lost the connection to the game ({})", err));
+            return;
+        }
+        Err(err) if game.result.is_some() => {
+            network.disconnect(format!("The other player has left ({})", err));
+            return;
+        }
+        Err(err) => {
+            network.connection_lost = Some(Instant::now());
+            if network.host {
+                network.disconnect(format!(
+                    "The other player lost the connection ({}), waiting for them to rejoin",
+                    err
+                ));
+            } else {
+                network.disconnect(format!("Lost the connection ({}), rejoining", err));
+                if let Some(remote_team) = game.remote_team {
+                    network.rejoin(remote_team.opposite());
+                }
+            }
+            return;
+        }
+    };
+    let game = &mut *game;
+    for message in messages {
+        let message = match message {
+            Ok(message) => message,
+            Err(err) => {
+                warn!("{}", err);
+                continue;
+            }
+        };
+        match message {
+            Message::Welcome {
+                team,
+                position,
+                key,
+            } => match Game::from_cubefen(&position, &settings) {
+                Ok(mut new_game) => {
+                    new_game.ai_playing = None;
+                    new_game.remote_team = Some(team.opposite());
+                    network.launch_game(new_game, &mut commands, &mut next_state, &state);
+                    network.key = key;
+                    network.status = format!("Playing online as {:?}", team);
+                }
+                Err(err) => network.disconnect(format!("The host sent a bad position: {}", err)),
+            },
+            Message::Rejoined { record } => match game_record::read_game(&record, &settings) {
+                Ok(rejoined) => {
+                    let previous_units = game.units.clone();
+                    game.units = rejoined.units;
+                    game.turn = rejoined.turn;
+                    game.energy = rejoined.energy;
+                    game.history = rejoined.history;
+                    scene::resync_unit_entities(&mut commands, game, &previous_units);
+                    network.synced_moves = game.history.moves().count();
+                    network.connection_lost = None;
+                    network.status = "Rejoined the game".to_string();
+                }
+                Err(err) => network.disconnect(format!("The host sent a bad game: {}", err)),
+            },
+            Message::Watching { record } => match game_record::read_game(&record, &settings) {
+                Ok(mut new_game) => {
+                    new_game.ai_playing = None;
+                    new_game.spectating = true;
+                    network.launch_game(new_game, &mut commands, &mut next_state, &state);
+                    network.status = "Watching an online game".to_string();
+                }
+                Err(err) => network.disconnect(format!("The host sent a bad game: {}", err)),
+            },
+            Message::Refused { reason } => network.disconnect(format!("Refused: {}", reason)),
+            Message::Move { clocks, .. } | Message::Summon { clocks, .. } => {
+                let team = game.turn;
+                if make_remote_move(&message, game, &mut events, &mut rng) {
+                    network.synced_moves += 1;
+                    network.draw_offer = None;
+                    if network.host {
+                        // The clocks of the host replace the clocks of the player
+                        let stamp = network.end_turn(team, clocks);
+                        network.send_to_spectators(&stamp_move(message, stamp));
+                    } else if let Some(clocks) = clocks {
+                        network.clocks = Some(Clocks::from_stamp(clocks));
+                    }
+                } else if network.host {
+                    warn!("The other player made an illegal move: {:?}", message);
+                    network.send(&Message::Resync {
+                        position: game.to_cubefen(),
+                    });
+                } else {
+                    warn!("The host made a move that isn't legal here: {:?}", message);
+                    network.send(&Message::RequestResync);
+                }
+            }
+            Message::Flagged { team } => {
+                if game.result.is_none() {
+                    let result = GameResult::Win(team.opposite());
+                    gamemanager::end_game(
+                        game,
+                        &mut events,
+                        &mut next_state,
+                        result,
+                        EndReason::Time,
+                    );
+                }
+            }
+            Message::Resign => {
+                if let Some(remote_team) = game.remote_team.filter(|_| game.result.is_none()) {
+                    let winner = remote_team.opposite();
+                    network.send_to_spectators(&Message::Ended {
+                        winner: Some(winner),
+                    });
+                    let result = GameResult::Win(winner);
+                    gamemanager::end_game(
+                        game,
+                        &mut events,
+                        &mut next_state,
+                        result,
+                        EndReason::Resignation,
+                    );
+                }
+            }
+            Message::OfferDraw => {
+                network.draw_offer = game.remote_team;
+                network.status =
+                    "The other player offers a draw, accept it on the pause menu".to_string();
+            }
+            Message::AcceptDraw => {
+                let offered =
+                    network.draw_offer.is_some() && network.draw_offer != game.remote_team;
+                if offered && game.result.is_none() {
+                    network.send_to_spectators(&Message::Ended { winner: None });
+                    gamemanager::end_game(
+                        game,
+                        &mut events,
+                        &mut next_state,
+                        GameResult::Draw,
+                        EndReason::Agreement,
+                    );
+                }
+            }
+            Message::OfferRematch => {
+                network.rematch_offer = game.remote_team;
+                network.status = "The other player offers a rematch, press T to accept".to_string();
+            }
+            Message::AcceptRematch => {
+                let offered =
+                    network.rematch_offer.is_some() && network.rematch_offer != game.remote_team;
+                if offered && network.host {
+                    network.start_rematch(game, &settings, &mut commands, &mut next_state, &state);
+                }
+            }
+            Message::Clock { remaining } => {
+                network.clocks = Some(Clocks::from_stamp(remaining));
+            }
+            Message::Ended { winner } => {
+                if game.result.is_none() {
+                    // The other ways a game ends can be seen in the moves or are sent on their own
+                    let (result, reason) = match winner {
+                        Some(winner) => (GameResult::Win(winner), EndReason::Resignation),
+                        None => (GameResult::Draw, EndReason::Agreement),
+                    };
+                    gamemanager::end_game(game, &mut events, &mut next_state, result, reason);
+                }
+            }
+            Message::RequestResync => network.send(&Message::Resync {
+                position: game.to_cubefen(),
+            }),
+            Message::Resync { position } => match position.parse::<CubeFen>() {
+                Ok(position) => {
+                    let previous_units = game.units.clone();
+                    game.units = position.units;
+                    game.turn = position.turn;
+                    game.energy = position.energy;
+                    game.record_position();
+                    scene::resync_unit_entities(&mut commands, game, &previous_units);
+                    network.synced_moves = game.history.moves().count();
+                }
+                Err(err) => warn!("The host sent a bad position: {}", err),
+            },
+            // Only those who have just connected send these, see accept_connections
+            Message::Join { .. } | Message::Spectate { .. } | Message::Rejoin { .. } => {}
+        }
+    }
+
+    // Only moves of the game that is being played online or watched are sent
+    if game.remote_team.is_none() && network.spectators.is_empty() || state.0 != AppState::InGame {
+        return;
+    }
+    let new_moves: Vec<RecordedMove> = game
+        .history
+        .moves()
+        .skip(network.synced_moves)
+        .cloned()
+        .collect();
+    network.synced_moves = game.history.moves().count();
+    for recorded_move in new_moves {
+        let team = recorded_move.team;
+        let clocks = network.end_turn(team, None);
+        let Some(message) = move_message(&recorded_move, clocks) else {
+            continue;
+        };
+        if Some(team) != game.remote_team {
+            network.send(&message);
+            // Moving declines the draw offered by the other player
+            network.draw_offer = None;
+        }
+        network.send_to_spectators(&message);
+    }
+}
+
+fn move_message(recorded_move: &RecordedMove, clocks: Option<[u64; 2]>) -> Option<Message> {
+    match recorded_move.game_move {
+        Some(game_move) => Some(Message::Move {
+            from: game_move.from.display(),
+            to: game_move.to.display(),
+            promotion: notation::promotion(&recorded_move.notation)
+                .map(|unit_type| unit_type.symbol().to_string()),
+            clocks,
+        }),
+        None => {
+            let (unit, cell) = recorded_move.notation.split_once('@')?;
+            Some(Message::Summon {
+                unit: unit.to_string(),
+                cell: cell.to_string(),
+                clocks,
+            })
+        }
+    }
+}
+
+/// The move with other clocks stamped on it
+fn stamp_move(message: Message, clocks: Option<[u64; 2]>) -> Message {
+    match message {
+        Message::Move {
+            from,
+            to,
+            promotion,
+            ..
+        } => Message::Move {
+            from,
+            to,
+            promotion,
+            clocks,
+        },
+        Message::Summon { unit, cell, .. } => Message::Summon { unit, cell, clocks },
+        message => message,
+    }
+}
+
+/// The host decides when a team of a timed game has run out of time, once the other player has
+/// joined
+fn flag_on_time(
+    mut network: ResMut<Network>,
+    mut game: ResMut<Game>,
+    mut events: GameEvents,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let joined = network.connection.is_some() || network.connection_lost.is_some();
+    if !network.host || !joined || game.remote_team.is_none() || game.result.is_some() {
+        return;
+    }
+    let team = game.turn;
+    if !matches!(&network.clocks, Some(clocks) if clocks.has_flagged(team)) {
+        return;
+    }
+    network.send(&Message::Flagged { team });
+    network.send_to_spectators(&Message::Flagged { team });
+    let result = GameResult::Win(team.opposite());
+    gamemanager::end_game(
+        &mut game,
+        &mut events,
+        &mut next_state,
+        result,
+        EndReason::Time,
+    );
+}
+
+/// Picks up the connection once rejoining has connected. The game goes on with both teams played
+/// here if the other player doesn't rejoin in time, or rejoining fails.
+fn wait_for_rejoin(mut network: ResMut<Network>, mut game: ResMut<Game>) {
+    let Some(connection_lost) = network.connection_lost else {
+        return;
+    };
+    if matches!(&network.rejoining, Some(rejoining) if rejoining.is_finished()) {
+        match network.rejoining.take().unwrap().join() {
+            Ok(Ok(connection)) => {
+                // The game is replaced once the host has answered
+                network.connection = Some(connection);
+                return;
+            }
+            Ok(Err(err)) => warn!("Couldn't rejoin {}: {}", network.address, err),
+            Err(_) => warn!("Rejoining {} failed", network.address),
+        }
+    }
+    let waiting = network.connection.is_none() && network.rejoining.is_none();
+    if waiting && connection_lost.elapsed() >= REJOIN_TIME {
+        network.connection_lost = None;
+        game.remote_team = None;
+        network.status = match network.host {
+            true => "The other player didn't rejoin".to_string(),
+            false => "Couldn't rejoin the game".to_string(),
+        };
+    }
+}
+
+/// Makes a move or summon of the other player, checking it like a move made here. Returns false
+/// if it isn't legal.
+fn make_remote_move(
+    message: &Message,
+    game: &mut Game,
+    events: &mut GameEvents,
+    rng: &mut GameRng,
+) -> bool {
+    if game.result.is_some() || game.is_played_here() {
+        return false;
+    }
+    let on_board = |cell: &str| {
+        CellCoordinates::from_display(cell)
+            .ok()
+            .filter(|&coords| game.board.get_cell(coords).is_some())
+    };
+    match message {
+        Message::Move {
+            from,
+            to,
+            promotion,
+            ..
+        } => {
+            let (Some(from), Some(to)) = (on_board(from), on_board(to)) else {
+                return false;
+            };
+            let game_move = GameMove { from, to };
+            let made = match promotion.as_deref().map(notation::promotion_from_symbol) {
+                Some(Some(promotion)) => {
+                    gamemanager::make_promoting_move(game_move, promotion, game, events)
+                }
+                Some(None) => false,
+                None => gamemanager::make_move(game_move, game, events),
+            };
+            if !made {
+                return false;
+            }
+        }
+        Message::Summon { unit, cell, .. } => {
+            let Some(coords) = on_board(cell) else {
+                return false;
+            };
+            game.pending_summon = reinforcements::summonable_from_symbol(unit);
+            if !reinforcements::try_summon(game, coords) {
+                return false;
+            }
+        }
+        _ => return false,
+    }
+    gamemanager::end_turn(game, events, rng);
+    true
+}
+
+/// On the pause menu of an online game, D offers or accepts a draw and X resigns
+fn online_game_input(
+    input: Res<Input<KeyCode>>,
+    mut network: ResMut<Network>,
+    mut game: ResMut<Game>,
+    mut events: GameEvents,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let Some(remote_team) = game.remote_team else {
+        return;
+    };
+    if !network.is_playing() || game.result.is_some() {
+        return;
+    }
+    if input.just_pressed(KeyCode::D) {
+        if network.draw_offer == Some(remote_team) {
+            network.send(&Message::AcceptDraw);
+            network.send_to_spectators(&Message::Ended { winner: None });
+            gamemanager::end_game(
+                &mut game,
+                &mut events,
+                &mut next_state,
+                GameResult::Draw,
+                EndReason::Agreement,
+            );
+        } else if network.draw_offer.is_none() {
+            network.send(&Message::OfferDraw);
+            network.draw_offer = Some(remote_team.opposite());
+            network.status = "Offered a draw".to_string();
+        }
+    } else if input.just_pressed(KeyCode::X) {
+        network.send(&Message::Resign);
+        network.send_to_spectators(&Message::Ended {
+            winner: Some(remote_team),
+        });
+        let result = GameResult::Win(remote_team);
+        gamemanager::end_game(
+            &mut game,
+            &mut events,
+            &mut next_state,
+            result,
+            EndReason::Resignation,
+        );
+    }
+}
+
+/// On the game over screen of an online game, T offers or accepts a rematch
+fn rematch_input(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    mut network: ResMut<Network>,
+    game: Res<Game>,
+    settings: Res<Settings>,
+    mut next_state: ResMut<NextState<AppState>>,
+    state: Res<State<AppState>>,
+) {
+    let Some(remote_team) = game.remote_team else {
+        return;
+    };
+    if !network.is_playing() || !input.just_pressed(KeyCode::T) {
+        return;
+    }
+    if network.rematch_offer == Some(remote_team) {
+        network.send(&Message::AcceptRematch);
+        if network.host {
+            network.start_rematch(&game, &settings, &mut commands, &mut next_state, &state);
+        } else {
+            network.status = "Waiting for the host to start the rematch".to_string();
+        }
+    } else if network.rematch_offer.is_none() {
+        network.send(&Message::OfferRematch);
+        network.rematch_offer = Some(remote_team.opposite());
+        network.status = "Offered a rematch".to_string();
+    }
+}
+
+/// Going back to the main menu leaves the online game, unless it is on the way to a rematch
+fn leave_online_game(
+    mut commands: Commands,
+    network: Option<ResMut<Network>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if let Some(mut network) = network.filter(|network| network.starting_rematch) {
+        network.starting_rematch = false;
+        next_state.set(AppState::InGame);
+        return;
+    }
+    commands.remove_resource::<Network>();
+    commands.remove_resource::<JoinPrompt>();
+}
+
+/// The address being typed on the main menu to join an online game, to play or to watch it
+#[derive(Resource)]
+pub(crate) struct JoinPrompt {
+    address: String,
+    greeting: Greeting,
+}
+
+impl JoinPrompt {
+    pub(crate) fn new(greeting: Greeting) -> Self {
+        JoinPrompt {
+            address: String::new(),
+            greeting,
+        }
+    }
+}
+
+/// The characters are read even without a prompt, so the key that opened it isn't typed into it
+fn type_join_address(
+    mut commands: Commands,
+    prompt: Option<ResMut<JoinPrompt>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    input: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+) {
+    let typed: String = characters
+        .iter()
+        .map(|character| character.char)
+        .filter(|character| !character.is_control())
+        .collect();
+    let Some(mut prompt) = prompt else {
+        return;
+    };
+    prompt.address.push_str(&typed);
+    if input.just_pressed(KeyCode::Back) {
+        prompt.address.pop();
+    }
+    if input.just_pressed(KeyCode::Escape) {
+        commands.remove_resource::<JoinPrompt>();
+    } else if input.just_pressed(KeyCode::Return) {
+        commands.remove_resource::<JoinPrompt>();
+        match Network::join(prompt.address.trim(), prompt.greeting, &settings) {
+            Ok(network) => commands.insert_resource(network),
+            Err(err) => warn!("{}", err),
+        }
+    }
+}
+
+#[derive(Component)]
+struct NetworkStatus;
+
+fn setup_network_status(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(10.),
+                right: Val::Px(10.),
+                ..default()
+            },
+            ..default()
+        }),
+        ZIndex::Global(1),
+        NetworkStatus,
+    ));
+}
+
+fn update_network_status(
+    network: Option<Res<Network>>,
+    prompt: Option<Res<JoinPrompt>>,
+    game: Res<Game>,
+    mut query: Query<&mut Text, With<NetworkStatus>>,
+) {
+    let status = match (prompt, network) {
+        (Some(prompt), _) => format!(
+            "Address to {}: {}_\nEnter to join, Escape to cancel",
+            match prompt.greeting {
+                Greeting::Watch => "watch",
+                _ => "join",
+            },
+            prompt.address
+        ),
+        (None, Some(network)) => network.status(&game),
+        (None, None) => String::new(),
+    };
+    for mut text in &mut query {
+        if text.sections[0].value != status {
+            text.sections[0].value = status.clone();
+        }
+    }
+}
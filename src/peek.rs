@@ -0,0 +1,46 @@
+//! Looking through the cube at the units on its far side without turning it. While the key is
+//! held, the faces in front of the cube are drawn see-through and the faces at the back are drawn
+//! from the inside, see [`CellView`].
+
+use bevy::prelude::*;
+
+use crate::engine::direction::CartesianDirection;
+use crate::materials::CellView;
+use crate::settings::Settings;
+use crate::{AppState, MainCamera};
+
+#[derive(Resource, Default)]
+pub(crate) struct Peek {
+    /// Where the camera is while the player peeks
+    camera: Option<Vec3>,
+}
+
+impl Peek {
+    /// How the cells on the face with the normal are drawn
+    pub(crate) fn view(&self, normal: CartesianDirection) -> CellView {
+        match self.camera {
+            None => CellView::Solid,
+            // The cube is 1 wide around the origin, so a face is seen from beyond half a unit
+            Some(camera) if normal.as_vec3().dot(camera) > 0.5 => CellView::SeeThrough,
+            Some(_) => CellView::FromInside,
+        }
+    }
+}
+
+pub(crate) fn update_peek(
+    input: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    state: Res<State<AppState>>,
+    camera_query: Query<&GlobalTransform, (With<MainCamera>, With<Camera>)>,
+    mut peek: ResMut<Peek>,
+) {
+    let camera = camera_query
+        .get_single()
+        .ok()
+        .map(|transform| transform.translation())
+        .filter(|_| state.0.can_rotate() && input.pressed(settings.key_bindings.peek));
+    // Only set when it changes, so the cells aren't recolored every frame
+    if peek.camera != camera {
+        peek.camera = camera;
+    }
+}
@@ -0,0 +1,242 @@
+//! The "How pieces move" screen, opened from the main menu. Every unit of the board editor is put
+//! alone on a small cube, with the cells it can go to marked like for a selected unit. Each unit is
+//! shown from the middle of a face and from the cell where most of its moves go over an edge,
+//! switching between the two every few seconds. The cells are picked with the movement code of the
+//! game, so the screen shows the pieces as they really move.
+
+use bevy::prelude::*;
+
+use crate::board_editor;
+use crate::cli::LaunchGame;
+use crate::cube_rotation::FaceCell;
+use crate::engine::cell::CellCoordinates;
+use crate::engine::movement::{self, Variant};
+use crate::engine::units::{Team, Unit, UnitType, Units};
+use crate::gamemanager::{self, Game};
+use crate::match_play::Match;
+use crate::reinforcements;
+use crate::scene;
+use crate::settings::Settings;
+use crate::AppState;
+
+/// Big enough for a knight in the middle of a face to stay on it
+const GUIDE_CUBE_SIZE: u32 = 4;
+/// Seconds each example is shown before the next one
+const EXAMPLE_TIME: f32 = 3.;
+
+/// A cell to show the moves of a unit from
+struct Example {
+    label: &'static str,
+    coords: CellCoordinates,
+    moves: Vec<CellCoordinates>,
+}
+
+/// Exists while the screen is open
+#[derive(Resource)]
+pub(crate) struct PieceGuide {
+    /// The index in [`board_editor::palette`] of the shown unit
+    piece: usize,
+    examples: Vec<Example>,
+    example: usize,
+    /// When the example was put on the cube, in seconds since startup
+    shown_at: f32,
+}
+
+pub(crate) fn open_piece_guide(
+    commands: &mut Commands,
+    settings: &Settings,
+    next_state: &mut NextState<AppState>,
+) {
+    let mut game = Game::new(GUIDE_CUBE_SIZE, Variant::Standard, settings);
+    game.units = Units::default();
+    game.ai_playing = None;
+    commands.insert_resource(LaunchGame(Some(game)));
+    commands.remove_resource::<Match>();
+    commands.insert_resource(PieceGuide {
+        piece: 0,
+        examples: Vec::new(),
+        example: 0,
+        shown_at: f32::MIN,
+    });
+    next_state.set(AppState::PieceGuide);
+}
+
+/// The unit of `unit_type` that is put on the cell. Pawns walk toward the nearest edge of their
+/// face, like in the board editor.
+fn guide_unit(unit_type: UnitType, coords: CellCoordinates) -> Unit {
+    match unit_type {
+        UnitType::Pawn(_, _) => {
+            let direction = reinforcements::pawn_direction(coords, GUIDE_CUBE_SIZE);
+            Unit::new(UnitType::Pawn(direction, false), Team::White, coords)
+        }
+        UnitType::Comet => Unit::new(unit_type, Team::Neutral, coords),
+        _ => Unit::new(unit_type, Team::White, coords),
+    }
+}
+
+/// The cell with the most moves that stay on its face, and the cell with the most moves that go
+/// over an edge, if the unit can go over one
+fn find_examples(game: &Game, unit_type: UnitType) -> Vec<Example> {
+    let mut middle: Option<(usize, Example)> = None;
+    let mut over_edge: Option<(usize, Example)> = None;
+    for cell in game.board.get_all_cells() {
+        let unit = guide_unit(unit_type, cell.coords);
+        let mut units = Units::default();
+        units.add_unit(unit.clone());
+        let moves = movement::get_unit_moves(&unit, &game.board, &units);
+        let normal = cell.coords.normal_direction();
+        let crossing = moves
+            .iter()
+            .filter(|to| to.normal_direction() != normal)
+            .count();
+        let staying = moves.len() - crossing;
+        let example = |label| Example {
+            label,
+            coords: cell.coords,
+            moves: moves.clone(),
+        };
+        if beats(staying, &middle) {
+            middle = Some((staying, example("From the middle of a face")));
+        }
+        if crossing > 0 && beats(crossing, &over_edge) {
+            over_edge = Some((crossing, example("Over the edge")));
+        }
+    }
+    [middle, over_edge]
+        .into_iter()
+        .flatten()
+        .map(|(_, example)| example)
+        .collect()
+}
+
+/// If `count` moves are more than the moves of the example found so far
+fn beats(count: usize, found: &Option<(usize, Example)>) -> bool {
+    match found {
+        Some((best, _)) => count > *best,
+        None => true,
+    }
+}
+
+/// Puts the unit of the example alone on the cube, with its moves marked
+fn show_example(commands: &mut Commands, game: &mut Game, unit_type: UnitType, example: &Example) {
+    for unit in game.units.all_units_iter() {
+        if let Some(entity) = unit.entity {
+            scene::kill_unit(commands, entity.into());
+        }
+    }
+    game.units = Units::default();
+    game.entities_to_move.clear();
+    game.units.add_unit(guide_unit(unit_type, example.coords));
+    gamemanager::deselect(game);
+    game.selected_cell = Some(example.coords);
+    for &to in &example.moves {
+        if let Some(cell) = game.board.get_cell_mut(to) {
+            cell.selected_unit_can_move_to = true;
+        }
+    }
+}
+
+/// Left and Right pick the unit, Up and Down the example, and Escape goes back to the main menu.
+/// The examples take turns on their own too.
+pub(crate) fn piece_guide_input(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    mut guide: ResMut<PieceGuide>,
+    mut game: ResMut<Game>,
+    mut face_events: EventWriter<FaceCell>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if input.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::MainMenu);
+        return;
+    }
+    let guide = &mut *guide;
+    let palette = board_editor::palette();
+    let now = time.elapsed_seconds();
+    if input.any_just_pressed([KeyCode::Left, KeyCode::Right]) || guide.examples.is_empty() {
+        if input.just_pressed(KeyCode::Right) {
+            guide.piece = (guide.piece + 1) % palette.len();
+        } else if input.just_pressed(KeyCode::Left) {
+            guide.piece = (guide.piece + palette.len() - 1) % palette.len();
+        }
+        guide.examples = find_examples(&game, palette[guide.piece]);
+        guide.example = 0;
+    } else if input.any_just_pressed([KeyCode::Up, KeyCode::Down])
+        || now - guide.shown_at >= EXAMPLE_TIME
+    {
+        guide.example = (guide.example + 1) % guide.examples.len();
+    } else {
+        return;
+    }
+    guide.shown_at = now;
+    let example = &guide.examples[guide.example];
+    show_example(&mut commands, &mut game, palette[guide.piece], example);
+    face_events.send(FaceCell(example.coords));
+}
+
+/// The text at the side of the cube, with the name of the unit and the keys of the screen
+#[derive(Component)]
+pub(crate) struct PieceGuideText;
+
+pub(crate) fn spawn_piece_guide_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(10.),
+                left: Val::Px(10.),
+                ..default()
+            },
+            ..default()
+        }),
+        PieceGuideText,
+    ));
+}
+
+pub(crate) fn update_piece_guide_text(
+    guide: Res<PieceGuide>,
+    mut query: Query<&mut Text, With<PieceGuideText>>,
+) {
+    if !guide.is_changed() {
+        return;
+    }
+    let Some(example) = guide.examples.get(guide.example) else {
+        return;
+    };
+    let palette = board_editor::palette();
+    let unit_type = palette[guide.piece];
+    let value = format!(
+        "How pieces move\n\n{} {} ({}/{})\n{}: {} moves\n\n\
+         Left and Right: another piece\n\
+         Up and Down: another example\n\
+         Escape: back to the main menu",
+        unit_type.symbol(),
+        board_editor::display_unit_type(unit_type),
+        guide.piece + 1,
+        palette.len(),
+        example.label,
+        example.moves.len()
+    );
+    for mut text in &mut query {
+        text.sections[0].value = value.clone();
+    }
+}
+
+pub(crate) fn close_piece_guide(
+    mut commands: Commands,
+    query: Query<Entity, With<PieceGuideText>>,
+) {
+    commands.remove_resource::<PieceGuide>();
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
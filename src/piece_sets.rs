@@ -0,0 +1,102 @@
+//! Piece sets, which give the units other models. The classic set is the models in
+//! `assets/models`, and every folder in there is another set with models named like the classic
+//! ones. A set doesn't need a model for every unit, the classic model is used for the ones it
+//! lacks. The set is picked in the settings, and the units on the board change right away.
+
+use std::fs;
+use std::path::Path;
+
+use bevy::prelude::*;
+
+use crate::engine::units::UnitType;
+use crate::gamemanager::Game;
+use crate::scene;
+use crate::settings::Settings;
+
+/// The name of the set made of the models that aren't in a folder
+pub(crate) const CLASSIC: &str = "Classic";
+
+pub(crate) struct PieceSet {
+    pub(crate) name: String,
+    /// The models the set has, without `.glb`
+    models: Vec<String>,
+}
+
+impl PieceSet {
+    /// The scene of the model of the unit type, for the asset server
+    pub(crate) fn scene_path(&self, unit_type: UnitType) -> String {
+        let model_name = unit_type.model_name();
+        if self.name == CLASSIC || !self.models.iter().any(|model| model == model_name) {
+            format!("models/{}.glb#Scene0", model_name)
+        } else {
+            format!("models/{}/{}.glb#Scene0", self.name, model_name)
+        }
+    }
+}
+
+/// The piece sets that were found at startup, starting with the classic one
+#[derive(Resource)]
+pub(crate) struct PieceSets(Vec<PieceSet>);
+
+impl PieceSets {
+    /// Looks for sets in the folders of `models_dir`
+    pub(crate) fn find(models_dir: &Path) -> Self {
+        let mut sets = vec![PieceSet {
+            name: CLASSIC.to_string(),
+            models: Vec::new(),
+        }];
+        let folders = fs::read_dir(models_dir).into_iter().flatten().flatten();
+        for folder in folders.filter(|entry| entry.path().is_dir()) {
+            let models = fs::read_dir(folder.path())
+                .into_iter()
+                .flatten()
+                .flatten()
+                .filter_map(|entry| {
+                    let path = entry.path();
+                    let is_model = path.extension().is_some_and(|extension| extension == "glb");
+                    Some(path.file_stem()?.to_str()?.to_string()).filter(|_| is_model)
+                })
+                .collect();
+            sets.push(PieceSet {
+                name: folder.file_name().to_string_lossy().into_owned(),
+                models,
+            });
+        }
+        sets[1..].sort_by(|a, b| a.name.cmp(&b.name));
+        PieceSets(sets)
+    }
+
+    pub(crate) fn names(&self) -> Vec<&str> {
+        self.0.iter().map(|set| set.name.as_str()).collect()
+    }
+
+    /// The set with the name, or the classic set if there is no such set anymore
+    pub(crate) fn get(&self, name: &str) -> &PieceSet {
+        self.0
+            .iter()
+            .find(|set| set.name == name)
+            .unwrap_or(&self.0[0])
+    }
+}
+
+/// Gives the units on the board new entities with the models of the set that was just picked
+pub(crate) fn respawn_units_on_set_change(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut game: ResMut<Game>,
+    mut shown: Local<Option<String>>,
+) {
+    if !settings.is_changed() || shown.as_deref() == Some(settings.piece_set.as_str()) {
+        return;
+    }
+    // The units of the first game are spawned with the set already
+    if shown.replace(settings.piece_set.clone()).is_none() {
+        return;
+    }
+    for unit in game.units.all_units_iter_mut() {
+        if let Some(entity) = unit.entity.take() {
+            scene::kill_unit(&mut commands, entity.into());
+        }
+    }
+    game.entities_to_move.clear();
+}
@@ -0,0 +1,296 @@
+use std::ops::RangeInclusive;
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::movement::Variant;
+use crate::engine::units::{Team, UnitType};
+use crate::gamemanager::{Game, Palette};
+use crate::settings::{self, LoadErrors, Settings};
+
+const PRESETS_PATH: &str = "presets.ron";
+
+/// The starting configuration doesn't fit on smaller cubes, and bigger ones are too slow for the AI
+pub(crate) const CUBE_SIDE_LENGTHS: RangeInclusive<u32> = 2..=8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct TimeControl {
+    pub(crate) minutes: u32,
+    /// Added to the clock of a team after each of its turns
+    pub(crate) increment_seconds: u32,
+    /// How long a team can think at the start of each turn before its clock starts running
+    #[serde(default)]
+    pub(crate) delay_seconds: u32,
+}
+
+impl TimeControl {
+    /// Like 5+3, with the delay after a d if there is one
+    pub(crate) fn display(&self) -> String {
+        let mut display = format!("{}+{}", self.minutes, self.increment_seconds);
+        if self.delay_seconds > 0 {
+            display.push_str(&format!(" d{}", self.delay_seconds));
+        }
+        display
+    }
+}
+
+/// The units a team starts without, like in odds games
+#[derive(Clone, Copy, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub(crate) enum Odds {
+    #[default]
+    None,
+    Knight,
+    Rook,
+    Queen,
+    QueenAndRook,
+}
+
+impl Odds {
+    pub(crate) const ALL: [Odds; 5] = [
+        Odds::None,
+        Odds::Knight,
+        Odds::Rook,
+        Odds::Queen,
+        Odds::QueenAndRook,
+    ];
+
+    pub(crate) fn removed_units(self) -> &'static [UnitType] {
+        match self {
+            Odds::None => &[],
+            Odds::Knight => &[UnitType::Knight],
+            Odds::Rook => &[UnitType::Rook],
+            Odds::Queen => &[UnitType::Queen],
+            Odds::QueenAndRook => &[UnitType::Queen, UnitType::Rook],
+        }
+    }
+
+    pub(crate) fn display(self) -> &'static str {
+        match self {
+            Odds::None => "nothing",
+            Odds::Knight => "knight",
+            Odds::Rook => "rook",
+            Odds::Queen => "queen",
+            Odds::QueenAndRook => "queen and rook",
+        }
+    }
+}
+
+/// What the stronger player gives up to make the game even
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Handicap {
+    /// The team that gives the handicap
+    pub(crate) team: Team,
+    /// The units the team starts without
+    #[serde(default)]
+    pub(crate) odds: Odds,
+    /// The other team moves twice after every this many moves of its own, see
+    /// [`Game::next_player_turn`]
+    #[serde(default)]
+    pub(crate) extra_move_every: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct AiSettings {
+    pub(crate) team: Team,
+    /// How many moves ahead the AI searches. None uses the depth from the settings.
+    pub(crate) depth: Option<u32>,
+}
+
+/// Everything that is chosen before a game starts, saved under a name so it can be picked again
+/// with one key press
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct SetupPreset {
+    pub(crate) name: String,
+    pub(crate) cube_side_length: u32,
+    pub(crate) variant: Variant,
+    /// None for a game without clocks
+    pub(crate) time_control: Option<TimeControl>,
+    /// None for a game between two players on the same computer
+    pub(crate) ai: Option<AiSettings>,
+    /// None uses the palette from the settings
+    pub(crate) palette: Option<Palette>,
+    /// A CubeFEN to start from instead of the starting position. Its cube size and variant are
+    /// played instead of the ones above.
+    #[serde(default)]
+    pub(crate) position: Option<String>,
+    /// Shuffles the units behind the pawns, see
+    /// [`crate::engine::units::Units::shuffled_starting_configuration`]
+    #[serde(default)]
+    pub(crate) shuffled: bool,
+    /// The seed of the shuffled setup. A new one is picked for every game if None.
+    #[serde(default)]
+    pub(crate) setup_seed: Option<u64>,
+    #[serde(default)]
+    pub(crate) handicap: Option<Handicap>,
+}
+
+impl SetupPreset {
+    pub(crate) fn new_game(&self, settings: &Settings) -> Game {
+        let starting_position = || Game::new(self.cube_side_length, self.variant, settings);
+        let mut game = match &self.position {
+            Some(position) => Game::from_cubefen(position, settings).unwrap_or_else(|err| {
+                warn!(
+                    "Starting {:?} from the starting position: {}",
+                    self.name, err
+                );
+                starting_position()
+            }),
+            None if self.shuffled => {
+                let mut game = starting_position();
+                game.shuffle_setup(self.setup_seed.unwrap_or_else(|| rand::thread_rng().gen()));
+                game
+            }
+            None => starting_position(),
+        };
+        if let Some(handicap) = self.handicap {
+            game.give_handicap(handicap);
+        }
+        game.ai_playing = self.ai.map(|ai| ai.team);
+        if let Some(depth) = self.ai.and_then(|ai| ai.depth) {
+            game.ai_depth = depth;
+        }
+        if let Some(palette) = self.palette {
+            game.palette = palette;
+        }
+        game.time_control = self.time_control;
+        game
+    }
+
+    pub(crate) fn display(&self) -> String {
+        match self.time_control {
+            Some(time_control) => format!("{} ({})", self.name, time_control.display()),
+            None => self.name.clone(),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Serialize, Deserialize)]
+pub(crate) struct SetupPresets {
+    pub(crate) presets: Vec<SetupPreset>,
+    /// The preset that is played when no other preset is picked
+    chosen: usize,
+}
+
+impl SetupPresets {
+    /// Reads the presets from disk, next to the settings. The built-in presets are written there on
+    /// the first launch, so that they can be edited and added to.
+    pub(crate) fn load(load_errors: &mut LoadErrors) -> Self {
+        let Some(mut presets) = settings::load_ron::<SetupPresets>(PRESETS_PATH, load_errors)
+        else {
+            let presets = Self::default();
+            presets.save();
+            return presets;
+        };
+
+        presets.presets.retain(|preset| {
+            let fits = CUBE_SIDE_LENGTHS.contains(&preset.cube_side_length);
+            if !fits {
+                warn!(
+                    "Skipping preset {:?}, the cube has to be {} to {} cells wide",
+                    preset.name,
+                    CUBE_SIDE_LENGTHS.start(),
+                    CUBE_SIDE_LENGTHS.end()
+                );
+            }
+            fits
+        });
+        if presets.presets.is_empty() {
+            presets.presets = Self::default().presets;
+        }
+        presets.chosen = presets.chosen.min(presets.presets.len() - 1);
+        presets
+    }
+
+    pub(crate) fn save(&self) {
+        settings::save_ron(PRESETS_PATH, self);
+    }
+
+    pub(crate) fn chosen(&self) -> &SetupPreset {
+        &self.presets[self.chosen]
+    }
+
+    /// Makes the preset the one that is played by default, and remembers that on disk
+    pub(crate) fn choose(&mut self, index: usize) {
+        if index >= self.presets.len() || index == self.chosen {
+            return;
+        }
+        self.chosen = index;
+        self.save();
+    }
+
+    /// Makes the cube of the chosen preset one cell wider or narrower, and remembers that on disk
+    pub(crate) fn resize_chosen(&mut self, wider: bool) {
+        let preset = &mut self.presets[self.chosen];
+        let cube_side_length = if wider {
+            preset.cube_side_length + 1
+        } else {
+            preset.cube_side_length - 1
+        };
+        if !CUBE_SIDE_LENGTHS.contains(&cube_side_length) {
+            return;
+        }
+        preset.cube_side_length = cube_side_length;
+        self.save();
+    }
+}
+
+impl Default for SetupPresets {
+    fn default() -> Self {
+        let preset = |name: &str, cube_side_length, variant, time_control, ai| SetupPreset {
+            name: name.to_string(),
+            cube_side_length,
+            variant,
+            time_control,
+            ai,
+            palette: None,
+            position: None,
+            shuffled: false,
+            setup_seed: None,
+            handicap: None,
+        };
+        let ai = |depth| {
+            Some(AiSettings {
+                team: Team::Black,
+                depth,
+            })
+        };
+        let blitz = Some(TimeControl {
+            minutes: 5,
+            increment_seconds: 3,
+            delay_seconds: 0,
+        });
+        SetupPresets {
+            presets: vec![
+                preset("Classic vs AI", 4, Variant::Standard, None, ai(None)),
+                preset(
+                    "Quick blitz vs Hard AI on 5-cube",
+                    5,
+                    Variant::Standard,
+                    blitz,
+                    ai(Some(4)),
+                ),
+                preset("Easy AI", 4, Variant::Standard, None, ai(Some(1))),
+                preset("Two players", 4, Variant::Standard, None, None),
+                preset(
+                    "Reinforcements vs AI",
+                    4,
+                    Variant::Reinforcements,
+                    None,
+                    ai(Some(2)),
+                ),
+                SetupPreset {
+                    palette: Some(Palette::Filippa),
+                    ..preset(
+                        "Wandering comet, two players",
+                        4,
+                        Variant::Wandering,
+                        None,
+                        None,
+                    )
+                },
+            ],
+            chosen: 0,
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Lets the player choose what a pawn is promoted to when it reaches the home face of the other
+//! team. The move waits in [`Game::pending_promotion`] until a unit is chosen on the chooser next to
+//! the cell it moves to, or with Q, R, B, N or E for the amazon. Escape takes the move back.
+
+use bevy::prelude::*;
+
+use crate::engine::movement::PROMOTIONS;
+use crate::engine::units::UnitType;
+use crate::gamemanager::{self, Game, GameEvents};
+use crate::rng::GameRng;
+use crate::ui::{BUTTON_COLOR, CLICKED_BUTTON_COLOR, HOVERED_BUTTON_COLOR};
+use crate::MainCamera;
+
+/// The keys that choose the units of [`PROMOTIONS`], in the same order
+const PROMOTION_KEYS: [KeyCode; 5] = [KeyCode::Q, KeyCode::R, KeyCode::B, KeyCode::N, KeyCode::E];
+
+/// How far to the right of the cell the chooser is shown, in pixels
+const CELL_OFFSET: f32 = 24.;
+
+#[derive(Component)]
+pub(crate) struct PromotionChooser;
+
+#[derive(Component)]
+pub(crate) struct PromotionButton(UnitType);
+
+/// Spawns the chooser when a move is waiting for a promotion, and despawns it once it isn't
+pub(crate) fn update_promotion_chooser(
+    mut commands: Commands,
+    game: Res<Game>,
+    query: Query<Entity, With<PromotionChooser>>,
+) {
+    match (game.pending_promotion, query.is_empty()) {
+        (Some(_), true) => spawn_promotion_chooser(&mut commands),
+        (None, false) => despawn_promotion_chooser(commands, query),
+        _ => {}
+    }
+}
+
+fn spawn_promotion_chooser(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    padding: UiRect::all(Val::Px(4.)),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.6).into(),
+                // Hidden until it has been placed next to the cell
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            PromotionChooser,
+        ))
+        .with_children(|parent| {
+            for unit_type in PROMOTIONS {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(48.), Val::Px(48.)),
+                                margin: UiRect::all(Val::Px(2.)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: BUTTON_COLOR.into(),
+                            ..default()
+                        },
+                        PromotionButton(unit_type),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            unit_type.symbol().to_string(),
+                            TextStyle {
+                                font_size: 36.,
+                                color: Color::WHITE,
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+pub(crate) fn despawn_promotion_chooser(
+    mut commands: Commands,
+    query: Query<Entity, With<PromotionChooser>>,
+) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Keeps the chooser next to the cell the pawn moves to while the cube is rotated
+pub(crate) fn place_promotion_chooser(
+    game: Res<Game>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    cell_query: Query<&GlobalTransform>,
+    mut chooser_query: Query<(&mut Style, &mut Visibility), With<PromotionChooser>>,
+) {
+    let Some(game_move) = game.pending_promotion else {
+        return;
+    };
+    let Some(cell) = game.board.get_cell(game_move.to) else {
+        return;
+    };
+    let (Ok((camera, camera_transform)), Ok(cell_transform)) =
+        (camera_query.get_single(), cell_query.get(cell.plane.into()))
+    else {
+        return;
+    };
+    // The viewport starts at the bottom left corner of the window
+    let on_screen = camera.world_to_viewport(camera_transform, cell_transform.translation());
+    for (mut style, mut visibility) in &mut chooser_query {
+        let Some(on_screen) = on_screen else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        style.position = UiRect {
+            left: Val::Px(on_screen.x + CELL_OFFSET),
+            bottom: Val::Px(on_screen.y),
+            ..default()
+        };
+        *visibility = Visibility::Inherited;
+    }
+}
+
+/// Makes the waiting move once a unit is chosen, and takes the move back on Escape
+pub(crate) fn choose_promotion(
+    input: Res<Input<KeyCode>>,
+    mut button_query: Query<
+        (&Interaction, &PromotionButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+    mut game: ResMut<Game>,
+    mut events: GameEvents,
+    mut rng: ResMut<GameRng>,
+) {
+    let Some(game_move) = game.pending_promotion else {
+        return;
+    };
+    let mut chosen = None;
+    for (interaction, button, mut color) in &mut button_query {
+        *color = match interaction {
+            Interaction::Clicked => CLICKED_BUTTON_COLOR,
+            Interaction::Hovered => HOVERED_BUTTON_COLOR,
+            Interaction::None => BUTTON_COLOR,
+        }
+        .into();
+        if *interaction == Interaction::Clicked {
+            chosen = Some(button.0);
+        }
+    }
+    for (keycode, unit_type) in PROMOTION_KEYS.into_iter().zip(PROMOTIONS) {
+        if input.just_pressed(keycode) {
+            chosen = Some(unit_type);
+        }
+    }
+    if input.just_pressed(KeyCode::Escape) {
+        game.pending_promotion = None;
+        return;
+    }
+    let Some(unit_type) = chosen else {
+        return;
+    };
+    game.pending_promotion = None;
+    if gamemanager::make_promoting_move(game_move, unit_type, &mut game, &mut events) {
+        gamemanager::end_turn(&mut game, &mut events, &mut rng);
+    }
+}
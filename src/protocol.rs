@@ -0,0 +1,539 @@
+//! The messages of online games, sent as lines of RON over TCP. Kept free of Bevy, since the
+//! dedicated server uses it too.
+
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::units::Team;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Message {
+    /// Sent by the joining player as soon as it has connected
+    Join {
+        version: String,
+    },
+    /// Sent instead of [`Message::Join`] to watch the game without playing in it
+    Spectate {
+        version: String,
+    },
+    /// Sent after losing the connection, to take the place of the player again. The key is the
+    /// one from [`Message::Welcome`].
+    Rejoin {
+        version: String,
+        team: Team,
+        key: u64,
+    },
+    /// The answer of the host, with the team the joining player plays and the position in CubeFEN.
+    /// The key lets the player rejoin after losing the connection.
+    Welcome {
+        team: Team,
+        position: String,
+        key: u64,
+    },
+    /// The answer to a spectator, with the game so far written like a saved game. The moves made
+    /// after it are sent as they are made.
+    Watching {
+        record: String,
+    },
+    /// The answer to [`Message::Rejoin`], with the game so far written like a saved game, which
+    /// replaces the game of the player
+    Rejoined {
+        record: String,
+    },
+    /// The host doesn't let the player join
+    Refused {
+        reason: String,
+    },
+    /// The cells are written like [`CellCoordinates::display`]. The clocks are stamped on moves of
+    /// timed games, see [`Clocks::stamp`]. A pawn that is promoted by the move without a promotion
+    /// being sent becomes a queen.
+    Move {
+        from: String,
+        to: String,
+        /// The symbol of the unit the pawn is promoted to
+        #[serde(default)]
+        promotion: Option<String>,
+        clocks: Option<[u64; 2]>,
+    },
+    /// The unit is written as its symbol, like in [`crate::engine::notation::write_summon`]
+    Summon {
+        unit: String,
+        cell: String,
+        clocks: Option<[u64; 2]>,
+    },
+    Resign,
+    OfferDraw,
+    AcceptDraw,
+    /// Asks for another game once the game is over, with the colours swapped
+    OfferRematch,
+    /// The host then starts the rematch by sending [`Message::Welcome`] again, and
+    /// [`Message::Watching`] to the spectators
+    AcceptRematch,
+    /// Asks the host for its position, after a move of the host couldn't be made
+    RequestResync,
+    /// The position of the host in CubeFEN, which replaces the position of the joining player
+    Resync {
+        position: String,
+    },
+    /// The team has run out of time on the clocks of the host or the dedicated server, and lost
+    Flagged {
+        team: Team,
+    },
+    /// The clocks of a timed game, sent after [`Message::Welcome`], [`Message::Watching`] and
+    /// [`Message::Rejoined`]. After that the clocks are stamped on every move.
+    Clock {
+        remaining: [u64; 2],
+    },
+    /// Tells spectators that the game ended by resigning or agreeing to a draw, which they can't
+    /// see in the moves. The winner is None for a draw.
+    Ended {
+        winner: Option<Team>,
+    },
+}
+
+/// The most time that is given back to a player for every move, for the time the move took to
+/// arrive
+pub(crate) const MAX_LAG: Duration = Duration::from_secs(1);
+
+/// How long a player who has lost the connection has to rejoin, before the game goes on without
+/// them
+pub(crate) const REJOIN_TIME: Duration = Duration::from_secs(60);
+
+/// The chess clocks of an online game. The clocks of the host or the dedicated server decide when
+/// a team has run out of time. The players and spectators keep their own clocks to show them, and
+/// set them to the clocks stamped on every move.
+#[derive(Debug, Clone)]
+pub(crate) struct Clocks {
+    /// The time each team had left when the current turn started, indexed by [`Team::index`]
+    remaining: [Duration; 2],
+    turn_started: Instant,
+}
+
+impl Clocks {
+    pub(crate) fn new(time: Duration) -> Self {
+        Clocks {
+            remaining: [time; 2],
+            turn_started: Instant::now(),
+        }
+    }
+
+    /// The clocks as they were stamped on a move, with the clock of the team to move running from
+    /// now on
+    // Only the players set their clocks from a stamp, the server keeps its own
+    #[allow(dead_code)]
+    pub(crate) fn from_stamp(stamp: [u64; 2]) -> Self {
+        Clocks {
+            remaining: stamp.map(Duration::from_millis),
+            turn_started: Instant::now(),
+        }
+    }
+
+    /// Starts the current turn over, for when the game starts
+    pub(crate) fn restart(&mut self) {
+        self.turn_started = Instant::now();
+    }
+
+    /// The time left right now, while the clock of `turn` runs
+    pub(crate) fn remaining(&self, turn: Team) -> [Duration; 2] {
+        let mut remaining = self.remaining;
+        let clock = &mut remaining[turn.index()];
+        *clock = clock.saturating_sub(self.turn_started.elapsed());
+        remaining
+    }
+
+    /// Milliseconds left on each clock, indexed by [`Team::index`]
+    pub(crate) fn stamp(&self, turn: Team) -> [u64; 2] {
+        self.remaining(turn).map(|time| time.as_millis() as u64)
+    }
+
+    /// Stops the clock of `team`, which has just moved, and starts the clock of the other team.
+    /// `stamp` is what the player stamped on their move. The time the move took to arrive is
+    /// given back to them, up to [`MAX_LAG`].
+    pub(crate) fn end_turn(&mut self, team: Team, stamp: Option<[u64; 2]>) {
+        let measured = self.turn_started.elapsed();
+        let clock = &mut self.remaining[team.index()];
+        let spent = match stamp {
+            Some(stamp) => clock
+                .saturating_sub(Duration::from_millis(stamp[team.index()]))
+                .clamp(measured.saturating_sub(MAX_LAG), measured),
+            None => measured,
+        };
+        *clock = clock.saturating_sub(spent);
+        self.turn_started = Instant::now();
+    }
+
+    /// If `turn` has run out of time, waiting for a move that may still be on its way
+    pub(crate) fn has_flagged(&self, turn: Team) -> bool {
+        self.turn_started.elapsed() >= self.remaining[turn.index()] + MAX_LAG
+    }
+}
+
+/// What someone who has just connected wants to do
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Greeting {
+    Play,
+    Watch,
+    Rejoin { team: Team, key: u64 },
+}
+
+impl Message {
+    pub(crate) fn encode(&self) -> String {
+        // RON is written on a single line unless pretty printing is asked for
+        let mut line = ron::to_string(self).expect("Messages can always be written");
+        line.push('\n');
+        line
+    }
+
+    /// Messages about the neutral team are refused, since only white and black play
+    pub(crate) fn decode(line: &str) -> Result<Self, String> {
+        let message = ron::from_str(line)
+            .map_err(|err| format!("Couldn't read the message {}: {}", line, err))?;
+        match message {
+            Message::Rejoin {
+                team: Team::Neutral,
+                ..
+            }
+            | Message::Welcome {
+                team: Team::Neutral,
+                ..
+            }
+            | Message::Flagged {
+                team: Team::Neutral,
+            }
+            | Message::Ended {
+                winner: Some(Team::Neutral),
+            } => Err(format!("The message {} is about the neutral team", line)),
+            message => Ok(message),
+        }
+    }
+}
+
+/// The longest line that is read before the connection is dropped, far longer than the record of
+/// any real game
+const MAX_LINE_LENGTH: usize = 1 << 20;
+
+/// How much can be waiting to be sent before the other side is taken to have stopped reading
+const MAX_UNSENT_LENGTH: usize = 16 * MAX_LINE_LENGTH;
+
+pub(crate) struct Connection {
+    stream: TcpStream,
+    /// The start of a message that hasn't been received in full yet
+    received: Vec<u8>,
+    /// Messages that have been received but not returned yet
+    pending: Vec<Result<Message, String>>,
+    /// What the socket hasn't taken yet of the messages that have been sent
+    unsent: Vec<u8>,
+}
+
+impl Connection {
+    pub(crate) fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        stream.set_nonblocking(true)?;
+        Ok(Connection {
+            stream,
+            received: Vec::new(),
+            pending: Vec::new(),
+            unsent: Vec::new(),
+        })
+    }
+
+    /// Sends as much of `message` as the socket takes right away, and the rest on the next calls
+    /// to [`Connection::send`] or [`Connection::receive`]
+    pub(crate) fn send(&mut self, message: &Message) -> io::Result<()> {
+        self.unsent.extend_from_slice(message.encode().as_bytes());
+        self.flush()
+    }
+
+    /// Writes what is waiting to be sent until the socket is full. Fails if the other side hasn't
+    /// read more than [`MAX_UNSENT_LENGTH`] of it.
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.unsent.is_empty() {
+            match self.stream.write(&self.unsent) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(written) => {
+                    self.unsent.drain(..written);
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        if self.unsent.len() > MAX_UNSENT_LENGTH {
+            return Err(io::Error::new(
+                ErrorKind::TimedOut,
+                "The other side stopped reading",
+            ));
+        }
+        Ok(())
+    }
+
+    /// The messages that have arrived since the last call, with an error for every line that isn't
+    /// a message. Also sends what is left of the messages sent before. Fails once the other side
+    /// has left, or has sent a line longer than [`MAX_LINE_LENGTH`], after which the connection
+    /// should be dropped.
+    pub(crate) fn receive(&mut self) -> io::Result<Vec<Result<Message, String>>> {
+        self.flush()?;
+        let mut buffer = [0; 1024];
+        let mut messages = std::mem::take(&mut self.pending);
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(read) => {
+                    self.received.extend_from_slice(&buffer[..read]);
+                    self.take_messages(&mut messages)?;
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Moves the lines that have been received in full to `messages`, checking the length of
+    /// every line as it comes in so that the buffer never grows past [`MAX_LINE_LENGTH`]
+    fn take_messages(&mut self, messages: &mut Vec<Result<Message, String>>) -> io::Result<()> {
+        while let Some(end) = self.received.iter().position(|&byte| byte == b'\n') {
+            if end > MAX_LINE_LENGTH {
+                break;
+            }
+            let line: Vec<u8> = self.received.drain(..=end).collect();
+            messages.push(Message::decode(String::from_utf8_lossy(&line).trim()));
+        }
+        if self.received.len() > MAX_LINE_LENGTH {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "The other side sent a line that is too long",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reads the first message of someone who has just connected, which is None until it has
+    /// arrived. Someone with another version is refused.
+    pub(crate) fn greeting(&mut self) -> Result<Option<Greeting>, String> {
+        let mut messages = self.receive().map_err(|err| err.to_string())?.into_iter();
+        let first = messages.next();
+        // Whatever was sent right after the greeting is returned by the next receive
+        self.pending.extend(messages);
+        let (greeting, version) = match first {
+            None => return Ok(None),
+            Some(Ok(Message::Join { version })) => (Greeting::Play, version),
+            Some(Ok(Message::Spectate { version })) => (Greeting::Watch, version),
+            Some(Ok(Message::Rejoin { version, team, key })) => {
+                (Greeting::Rejoin { team, key }, version)
+            }
+            Some(Ok(message)) => return Err(format!("Expected a join, got {:?}", message)),
+            Some(Err(err)) => return Err(err),
+        };
+        if version != env!("CARGO_PKG_VERSION") {
+            let reason = format!("This side runs version {}", env!("CARGO_PKG_VERSION"));
+            let _ = self.send(&Message::Refused { reason });
+            return Err(format!("Refused someone with version {}", version));
+        }
+        Ok(Some(greeting))
+    }
+}
+
+/// Sends `message` to every spectator, and forgets the ones it can't be sent to, since they have
+/// left
+pub(crate) fn send_to_spectators(spectators: &mut Vec<Connection>, message: &Message) {
+    spectators.retain_mut(|spectator| spectator.send(message).is_ok());
+}
+
+/// Sends the position in CubeFEN, written by `position`, to the spectators that ask for it, and
+/// forgets the ones that have left
+pub(crate) fn serve_spectators(spectators: &mut Vec<Connection>, position: impl Fn() -> String) {
+    spectators.retain_mut(|spectator| {
+        let Ok(messages) = spectator.receive() else {
+            return false;
+        };
+        let asked = messages
+            .iter()
+            .any(|message| matches!(message, Ok(Message::RequestResync)));
+        !asked
+            || spectator
+                .send(&Message::Resync {
+                    position: position(),
+                })
+                .is_ok()
+    });
+}
+
+/// Connections that haven't said yet if they play or watch
+#[derive(Default)]
+pub(crate) struct Newcomers(Vec<(Connection, Instant)>);
+
+impl Newcomers {
+    /// Someone who hasn't said anything after this long is turned away
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    pub(crate) fn add(&mut self, connection: Connection) {
+        self.0.push((connection, Instant::now()));
+    }
+
+    /// The newcomers that have said what they want, who are no longer newcomers, and an error for
+    /// every newcomer that has been turned away
+    pub(crate) fn greeted(&mut self) -> Vec<Result<(Connection, Greeting), String>> {
+        let mut greeted = Vec::new();
+        let mut waiting = Vec::new();
+        for (mut connection, connected) in self.0.drain(..) {
+            match connection.greeting() {
+                Ok(Some(greeting)) => greeted.push(Ok((connection, greeting))),
+                Ok(None) if connected.elapsed() < Self::TIMEOUT => {
+                    waiting.push((connection, connected))
+                }
+                Ok(None) => greeted.push(Err("Someone connected but didn't join".to_string())),
+                Err(err) => greeted.push(Err(err)),
+            }
+        }
+        self.0 = waiting;
+        greeted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip() {
+        let messages = [
+            Message::Join {
+                version: "0.1.0".to_string(),
+            },
+            Message::Welcome {
+                team: Team::Black,
+                position: "3 - w 0/0 standard".to_string(),
+                key: 12345,
+            },
+            Message::Move {
+                from: "Xa1".to_string(),
+                to: "Yb2".to_string(),
+                promotion: Some("♕".to_string()),
+                clocks: Some([59_000, 60_000]),
+            },
+            Message::Summon {
+                unit: "♘".to_string(),
+                cell: "zc3".to_string(),
+                clocks: None,
+            },
+            Message::Resign,
+        ];
+        let mut encoded = String::new();
+        for message in &messages {
+            encoded.push_str(&message.encode());
+        }
+        let decoded: Vec<Message> = encoded
+            .lines()
+            .map(|line| Message::decode(line).unwrap())
+            .collect();
+        assert_eq!(decoded, messages);
+    }
+
+    #[test]
+    fn the_neutral_team_is_refused() {
+        for message in [
+            Message::Welcome {
+                team: Team::Neutral,
+                position: "3 - w 0/0 standard".to_string(),
+                key: 1,
+            },
+            Message::Rejoin {
+                version: "0.1.0".to_string(),
+                team: Team::Neutral,
+                key: 1,
+            },
+            Message::Flagged {
+                team: Team::Neutral,
+            },
+        ] {
+            assert!(Message::decode(message.encode().trim()).is_err());
+        }
+        let flagged = Message::Flagged { team: Team::White };
+        assert_eq!(Message::decode(flagged.encode().trim()), Ok(flagged));
+    }
+
+    #[test]
+    fn too_long_lines_drop_the_connection() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let sender = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            // The write fails once the connection has been dropped
+            let _ = stream.write_all(&vec![b'x'; 2 * MAX_LINE_LENGTH]);
+        });
+        let mut connection = Connection::new(listener.accept().unwrap().0).unwrap();
+        let err = loop {
+            if let Err(err) = connection.receive() {
+                break err;
+            }
+        };
+        drop(connection.stream.shutdown(std::net::Shutdown::Both));
+        sender.join().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn messages_after_the_greeting_are_kept() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let join = Message::Join {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        };
+        let sent = format!("{}{}", join.encode(), Message::RequestResync.encode());
+        stream.write_all(sent.as_bytes()).unwrap();
+        let mut connection = Connection::new(listener.accept().unwrap().0).unwrap();
+        let greeting = loop {
+            if let Some(greeting) = connection.greeting().unwrap() {
+                break greeting;
+            }
+        };
+        assert!(matches!(greeting, Greeting::Play));
+        let messages = connection.receive().unwrap();
+        assert_eq!(messages, vec![Ok(Message::RequestResync)]);
+    }
+
+    #[test]
+    fn messages_are_sent_in_full_when_the_socket_is_full() {
+        use std::io::BufRead;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (all_sent, wait_for_all_sent) = std::sync::mpsc::channel();
+        let receiver = std::thread::spawn(move || {
+            let stream = TcpStream::connect(address).unwrap();
+            wait_for_all_sent.recv().unwrap();
+            io::BufReader::new(stream).lines().count()
+        });
+        let mut connection = Connection::new(listener.accept().unwrap().0).unwrap();
+        let message = Message::Resync {
+            position: "x".repeat(MAX_LINE_LENGTH / 2),
+        };
+        // Far more than the socket takes before the other side starts reading
+        for _ in 0..24 {
+            connection.send(&message).unwrap();
+        }
+        all_sent.send(()).unwrap();
+        while !connection.unsent.is_empty() {
+            connection.receive().unwrap();
+        }
+        drop(connection);
+        assert_eq!(receiver.join().unwrap(), 24);
+    }
+
+    #[test]
+    fn lag_is_given_back_up_to_the_limit() {
+        let mut clocks = Clocks::new(Duration::from_secs(60));
+        clocks.turn_started -= Duration::from_secs(5);
+        // The player says the move took 2 seconds, but it arrived 5 seconds after the turn started
+        clocks.end_turn(Team::White, Some([58_000, 60_000]));
+        assert_eq!(clocks.remaining[0].as_secs_f32().round() as u64, 56);
+
+        clocks.turn_started -= Duration::from_secs(3);
+        clocks.end_turn(Team::Black, Some([59_500, 57_500]));
+        assert_eq!(clocks.remaining[1].as_millis(), 57_500);
+    }
+}
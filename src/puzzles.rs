@@ -0,0 +1,630 @@
+//! Puzzle mode, where the player has to find a forced mate. The puzzles come from the packs in
+//! `assets/puzzles`, which are picked on the puzzle browser, or are found by
+//! [`crate::engine::puzzles`] in the saved games, or in games of random moves on the cube of the
+//! chosen preset when there are none to be found there. A wrong move is answered with the move that
+//! refutes it, and both are taken back after a moment so that the player can try again. Solved
+//! puzzles of the packs are remembered in the config dir. The blunders of the
+//! [`BlunderDeck`] are practiced here too, as puzzles to find the better move in.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::LaunchGame;
+use crate::engine::notation;
+use crate::engine::puzzles::{self, Puzzle, PuzzlePack};
+use crate::engine::units::Team;
+use crate::game_record;
+use crate::gamemanager::{self, Game, GameEvents};
+use crate::match_play::Match;
+use crate::presets::SetupPresets;
+use crate::rng::GameRng;
+use crate::scene;
+use crate::settings::{self, LoadErrors, Settings};
+use crate::training::BlunderDeck;
+use crate::ui::{self, MenuRoot};
+use crate::AppState;
+
+const PACKS_FOLDER: &str = "puzzles";
+const SOLVED_PATH: &str = "solved-puzzles.ron";
+
+/// How long the main menu waits for a puzzle to be found
+const SEARCH_TIME: Duration = Duration::from_secs(5);
+/// The length of the games of random moves that are searched
+const RANDOM_GAME_MOVES: u32 = 60;
+/// Seconds the refutation of a wrong move is shown before both moves are taken back
+const REFUTATION_TIME: f32 = 2.;
+
+/// Exists while a puzzle is played
+#[derive(Resource)]
+pub(crate) struct PuzzleMode {
+    puzzle: Puzzle,
+    /// The moves the player has left to mate in
+    moves_left: u32,
+    /// When the refutation of a wrong move was made
+    refuted_at: Option<Duration>,
+    /// Once solved, the AI plays the rest of the game
+    solved: bool,
+    /// The index of the pack and of the puzzle in it, if the puzzle is from a pack
+    from_pack: Option<(usize, usize)>,
+    /// The card of the [`BlunderDeck`] that is practiced, and whether it has been answered. Only
+    /// the first answer counts.
+    blunder: Option<(usize, bool)>,
+    prompt: String,
+}
+
+impl PuzzleMode {
+    fn solver(&self) -> Team {
+        self.puzzle.position.turn
+    }
+}
+
+/// The puzzle of the game that is about to start. It becomes the [`PuzzleMode`] once the game has
+/// started, since the puzzle that was played before is forgotten on the main menu.
+#[derive(Resource, Default)]
+pub(crate) struct LaunchPuzzle(Option<PuzzleMode>);
+
+/// The puzzle packs in `assets/puzzles`, read at startup
+#[derive(Resource)]
+pub(crate) struct PuzzlePacks(Vec<PuzzlePack>);
+
+impl PuzzlePacks {
+    /// Puzzles that can't be played are skipped, and why is kept to be shown to the player
+    pub(crate) fn load(assets_dir: &Path, load_errors: &mut LoadErrors) -> Self {
+        let (packs, errors) = puzzles::load_packs(&assets_dir.join(PACKS_FOLDER));
+        for err in &errors {
+            warn!("{}", err);
+        }
+        load_errors.0.extend(errors);
+        PuzzlePacks(packs)
+    }
+}
+
+/// The positions of the solved puzzles of the packs, as CubeFEN
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub(crate) struct SolvedPuzzles(BTreeSet<String>);
+
+impl SolvedPuzzles {
+    pub(crate) fn load(load_errors: &mut LoadErrors) -> Self {
+        settings::load_ron(SOLVED_PATH, load_errors).unwrap_or_default()
+    }
+
+    fn contains(&self, puzzle: &Puzzle) -> bool {
+        self.0.contains(&puzzle.position.to_string())
+    }
+
+    /// Remembers the puzzle as solved, saving it right away
+    fn insert(&mut self, puzzle: &Puzzle) {
+        if self.0.insert(puzzle.position.to_string()) {
+            settings::save_ron(SOLVED_PATH, self);
+        }
+    }
+}
+
+/// Starts the game of the puzzle once the main menu or the puzzle browser is left. Returns why it
+/// can't if the position of the puzzle can't be played.
+fn launch_puzzle(
+    commands: &mut Commands,
+    puzzle: Puzzle,
+    from_pack: Option<(usize, usize)>,
+    packs: &PuzzlePacks,
+    settings: &Settings,
+) -> Result<(), String> {
+    let mut game = Game::from_cubefen(&puzzle.position.to_string(), settings)?;
+    game.ai_playing = None;
+    info!(
+        "Puzzle: {}, mate in {} with {}",
+        puzzle.position,
+        puzzle.mate_in,
+        puzzle.written_solution()
+    );
+    let task = format!(
+        "{:?} to move and mate in {}",
+        puzzle.position.turn, puzzle.mate_in
+    );
+    let prompt = match from_pack {
+        Some((pack, index)) => format!(
+            "{} {}/{}: {}\nPageUp and PageDown: previous and next puzzle",
+            packs.0[pack].name,
+            index + 1,
+            packs.0[pack].puzzles.len(),
+            task
+        ),
+        None => task,
+    };
+    let puzzle = PuzzleMode {
+        moves_left: puzzle.mate_in,
+        puzzle,
+        refuted_at: None,
+        solved: false,
+        from_pack,
+        blunder: None,
+        prompt,
+    };
+    insert_launch(commands, puzzle, game);
+    Ok(())
+}
+
+fn insert_launch(commands: &mut Commands, puzzle: PuzzleMode, game: Game) {
+    commands.insert_resource(LaunchPuzzle(Some(puzzle)));
+    commands.insert_resource(LaunchGame(Some(game)));
+    commands.remove_resource::<Match>();
+}
+
+/// Starts the most overdue blunder of the deck, see [`launch_puzzle`]
+fn start_blunder_puzzle(
+    commands: &mut Commands,
+    deck: &BlunderDeck,
+    settings: &Settings,
+) -> Result<(), String> {
+    let (card, position) = deck
+        .due_card()
+        .ok_or("No blunders are due for practice".to_string())?;
+    let mut game = Game::from_cubefen(&position.to_string(), settings)?;
+    game.ai_playing = None;
+    let (played, better) = deck.moves(card);
+    info!("Blunder: {}, better is {:?}", position, better);
+    let prompt = format!(
+        "{:?} played {} here, find a better move",
+        position.turn,
+        notation::write_move(played, &game.board, &position.units)
+    );
+    let puzzle = PuzzleMode {
+        puzzle: Puzzle {
+            position,
+            // A single better move is asked for
+            mate_in: 1,
+            solution: vec![better],
+            themes: vec!["blunder".to_string()],
+        },
+        moves_left: 1,
+        refuted_at: None,
+        solved: false,
+        from_pack: None,
+        blunder: Some((card, false)),
+        prompt,
+    };
+    insert_launch(commands, puzzle, game);
+    Ok(())
+}
+
+/// Looks for a puzzle and starts it. Returns why it couldn't if none was found in time.
+fn start_found_puzzle(
+    commands: &mut Commands,
+    presets: &SetupPresets,
+    packs: &PuzzlePacks,
+    settings: &Settings,
+    rng: &mut GameRng,
+) -> Result<(), String> {
+    let puzzle = find_puzzle(presets, settings, rng).ok_or(format!(
+        "No puzzle was found in {} seconds",
+        SEARCH_TIME.as_secs()
+    ))?;
+    launch_puzzle(commands, puzzle, None, packs, settings)
+}
+
+/// Starts the puzzle of a pack, see [`launch_puzzle`]
+fn start_pack_puzzle(
+    commands: &mut Commands,
+    (pack, index): (usize, usize),
+    packs: &PuzzlePacks,
+    settings: &Settings,
+) -> Result<(), String> {
+    let puzzle = packs.0[pack].puzzles[index].clone();
+    launch_puzzle(commands, puzzle, Some((pack, index)), packs, settings)
+}
+
+/// Part of the start of a game, which is a puzzle if one was launched
+pub(crate) fn begin_puzzle(mut commands: Commands, mut launch_puzzle: ResMut<LaunchPuzzle>) {
+    if let Some(puzzle) = launch_puzzle.0.take() {
+        commands.insert_resource(puzzle);
+    }
+}
+
+/// The first puzzle in the saved games, or else in games of random moves from the starting
+/// position of the chosen preset
+fn find_puzzle(presets: &SetupPresets, settings: &Settings, rng: &mut GameRng) -> Option<Puzzle> {
+    let deadline = Instant::now() + SEARCH_TIME;
+    let saved_games = game_record::saved_games(settings);
+    let from_saved_games = saved_games.iter().find_map(|game| {
+        let positions = game
+            .history
+            .positions()
+            .iter()
+            .map(|position| (position.units.clone(), position.turn));
+        puzzles::find_puzzle(&game.board, game.variant, positions, deadline)
+    });
+    if from_saved_games.is_some() {
+        return from_saved_games;
+    }
+    let start = presets.chosen().new_game(settings);
+    let random_positions = std::iter::repeat_with(|| {
+        puzzles::random_game(
+            &start.board,
+            &start.units,
+            start.turn,
+            start.variant,
+            RANDOM_GAME_MOVES,
+            rng.rng(),
+        )
+    })
+    .flatten();
+    puzzles::find_puzzle(&start.board, start.variant, random_positions, deadline)
+}
+
+/// Checks the moves of the player and answers them. A move that keeps the mate within the moves
+/// that are left is right, even if it isn't the move of the solution. In the practice of a blunder,
+/// a wrong move is taken back without an answer.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn play_puzzle(
+    mut commands: Commands,
+    puzzle: Option<ResMut<PuzzleMode>>,
+    mut game: ResMut<Game>,
+    mut events: GameEvents,
+    mut rng: ResMut<GameRng>,
+    mut solved: ResMut<SolvedPuzzles>,
+    mut deck: ResMut<BlunderDeck>,
+    time: Res<Time>,
+) {
+    let Some(mut puzzle) = puzzle else {
+        return;
+    };
+    let puzzle = &mut *puzzle;
+    let game = &mut *game;
+    if puzzle.solved || game.result.is_some() {
+        return;
+    }
+    if let Some(refuted_at) = puzzle.refuted_at {
+        if time.elapsed() - refuted_at < Duration::from_secs_f32(REFUTATION_TIME) {
+            return;
+        }
+        // Takes back the refutation and the wrong move
+        puzzle.refuted_at = None;
+        let previous_units = game.units.clone();
+        let num_moves = if puzzle.blunder.is_some() { 1 } else { 2 };
+        for _ in 0..num_moves {
+            game.undo();
+        }
+        scene::resync_unit_entities(&mut commands, game, &previous_units);
+        return;
+    }
+    let solver = puzzle.solver();
+    if game.turn == solver {
+        return;
+    }
+    let positions = game.history.positions();
+    let Some(played) = game
+        .history
+        .last_turn_moves()
+        .iter()
+        .rev()
+        .find_map(|recorded_move| recorded_move.game_move)
+    else {
+        return;
+    };
+    let before = &positions[positions.len() - 2].units;
+    let (board, variant) = (&game.board, game.variant);
+    let written = notation::write_move(played, board, before);
+
+    if let Some((card, answered)) = puzzle.blunder {
+        let right = deck.is_right(card, played, board);
+        if !answered {
+            deck.answer(card, right);
+            puzzle.blunder = Some((card, true));
+        }
+        if right {
+            puzzle.solved = true;
+            puzzle.prompt = format!("{} is better, solved!", written);
+            game.ai_playing = Some(solver.opposite());
+        } else {
+            let (_, better) = deck.moves(card);
+            puzzle.refuted_at = Some(time.elapsed());
+            puzzle.prompt = format!(
+                "{} isn't better, {} was, try again",
+                written,
+                notation::write_move(better, board, before)
+            );
+        }
+        return;
+    }
+
+    let reply = if puzzles::forces_mate(board, before, solver, played, puzzle.moves_left, variant) {
+        let king_taken = variant.rules().winner(&game.units).is_some();
+        if puzzle.moves_left == 1 || king_taken {
+            puzzle.solved = true;
+            puzzle.prompt = format!("{} mates, solved!", written);
+            if puzzle.from_pack.is_some() {
+                solved.insert(&puzzle.puzzle);
+                puzzle.prompt.push_str("\nPageDown: next puzzle");
+            }
+            game.ai_playing = Some(solver.opposite());
+            return;
+        }
+        let reply =
+            puzzles::longest_defence(board, &game.units, solver, puzzle.moves_left, variant);
+        puzzle.moves_left -= 1;
+        puzzle.prompt = format!("{} is right, mate in {} more", written, puzzle.moves_left);
+        reply
+    } else {
+        let reply = puzzles::refutation(board, &game.units, solver, puzzle.moves_left, variant);
+        puzzle.refuted_at = Some(time.elapsed());
+        puzzle.prompt = match reply {
+            Some(reply) => format!(
+                "{} is answered with {}, try again",
+                written,
+                notation::write_move(reply, board, &game.units)
+            ),
+            None => format!("{} leaves {:?} without moves", written, solver.opposite()),
+        };
+        reply
+    };
+    // Without a reply the other team has no moves, and the game is over
+    if let Some(reply) = reply {
+        if gamemanager::make_move(reply, game, &mut events) {
+            gamemanager::end_turn(game, &mut events, &mut rng);
+        }
+    }
+}
+
+/// PageUp and PageDown go to the previous and the next puzzle of the pack during a puzzle of a
+/// pack, also once it is over
+pub(crate) fn change_pack_puzzle(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    puzzle: Option<Res<PuzzleMode>>,
+    packs: Res<PuzzlePacks>,
+    settings: Res<Settings>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if !matches!(state.0, AppState::InGame | AppState::GameOver) {
+        return;
+    }
+    let Some((pack, index)) = puzzle.and_then(|puzzle| puzzle.from_pack) else {
+        return;
+    };
+    let num_puzzles = packs.0[pack].puzzles.len();
+    let index = if input.just_pressed(KeyCode::PageDown) {
+        (index + 1) % num_puzzles
+    } else if input.just_pressed(KeyCode::PageUp) {
+        (index + num_puzzles - 1) % num_puzzles
+    } else {
+        return;
+    };
+    match start_pack_puzzle(&mut commands, (pack, index), &packs, &settings) {
+        // The cube of the puzzle is built again on the way through the main menu
+        Ok(()) => next_state.set(AppState::MainMenu),
+        Err(err) => warn!("{}", err),
+    }
+}
+
+/// A puzzle is over once its game is left, and the browser is closed once the main menu is back
+pub(crate) fn forget_puzzle(mut commands: Commands) {
+    commands.remove_resource::<PuzzleMode>();
+    commands.remove_resource::<PuzzleBrowser>();
+}
+
+/// Picking a puzzle of the packs. It exists only while the browser is open, which also keeps the
+/// game from starting when the main menu is left for it.
+#[derive(Resource)]
+pub(crate) struct PuzzleBrowser {
+    pack: usize,
+    puzzle: usize,
+    /// Why the last puzzle couldn't be started
+    message: Option<String>,
+}
+
+/// Opens the browser on the first puzzle that isn't solved yet
+pub(crate) fn open_puzzle_browser(
+    commands: &mut Commands,
+    packs: &PuzzlePacks,
+    solved: &SolvedPuzzles,
+    next_state: &mut NextState<AppState>,
+) {
+    let unsolved = packs.0.iter().enumerate().find_map(|(pack, puzzle_pack)| {
+        let puzzle = puzzle_pack
+            .puzzles
+            .iter()
+            .position(|puzzle| !solved.contains(puzzle))?;
+        Some((pack, puzzle))
+    });
+    let (pack, puzzle) = unsolved.unwrap_or((0, 0));
+    commands.insert_resource(PuzzleBrowser {
+        pack,
+        puzzle,
+        message: None,
+    });
+    next_state.set(AppState::Puzzles);
+}
+
+pub(crate) fn spawn_puzzle_browser(
+    mut commands: Commands,
+    browser: Res<PuzzleBrowser>,
+    packs: Res<PuzzlePacks>,
+    solved: Res<SolvedPuzzles>,
+    deck: Res<BlunderDeck>,
+) {
+    spawn_puzzle_browser_for(&mut commands, &browser, &packs, &solved, &deck);
+}
+
+fn spawn_puzzle_browser_for(
+    commands: &mut Commands,
+    browser: &PuzzleBrowser,
+    packs: &PuzzlePacks,
+    solved: &SolvedPuzzles,
+    deck: &BlunderDeck,
+) {
+    let mut hint = String::new();
+    match packs.0.get(browser.pack) {
+        Some(pack) => {
+            let num_solved = pack
+                .puzzles
+                .iter()
+                .filter(|puzzle| solved.contains(puzzle))
+                .count();
+            hint.push_str(&format!(
+                "{} ({}/{}), {} of {} solved\n\n",
+                pack.name,
+                browser.pack + 1,
+                packs.0.len(),
+                num_solved,
+                pack.puzzles.len()
+            ));
+            for (i, puzzle) in pack.puzzles.iter().enumerate() {
+                let marker = if i == browser.puzzle { "> " } else { "  " };
+                hint.push_str(&format!(
+                    "{}{}. Cube size {}, {:?} mates in {}",
+                    marker,
+                    i + 1,
+                    puzzle.position.cube_side_length,
+                    puzzle.position.turn,
+                    puzzle.mate_in
+                ));
+                if !puzzle.themes.is_empty() {
+                    hint.push_str(&format!(" ({})", puzzle.themes.join(", ")));
+                }
+                if solved.contains(puzzle) {
+                    hint.push_str(", solved");
+                }
+                hint.push('\n');
+            }
+            hint.push_str(
+                "\nUp and Down pick a puzzle, Left and Right the pack\n\
+                 Press Enter to play it, ",
+            );
+        }
+        None => hint.push_str("There are no puzzle packs in assets/puzzles\n\n"),
+    }
+    hint.push_str(&format!(
+        "G to look for a puzzle in your games, B to practice your blunders ({} due), or Escape to \
+         go back",
+        deck.num_due()
+    ));
+    if let Some(message) = &browser.message {
+        hint.push_str(&format!("\n\n{}", message));
+    }
+    ui::spawn_menu(commands, "Puzzles", &hint, &[]);
+}
+
+/// Enter plays the picked puzzle, G one that is found in the saved or random games, see
+/// [`find_puzzle`], and B the most overdue blunder. Escape goes back to the main menu.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn puzzle_browser_input(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    mut browser: ResMut<PuzzleBrowser>,
+    packs: Res<PuzzlePacks>,
+    solved: Res<SolvedPuzzles>,
+    deck: Res<BlunderDeck>,
+    presets: Res<SetupPresets>,
+    settings: Res<Settings>,
+    mut rng: ResMut<GameRng>,
+    mut next_state: ResMut<NextState<AppState>>,
+    menu_query: Query<Entity, With<MenuRoot>>,
+) {
+    if input.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::MainMenu);
+        return;
+    }
+    let has_puzzle = packs
+        .0
+        .get(browser.pack)
+        .is_some_and(|pack| browser.puzzle < pack.puzzles.len());
+    let started = if input.just_pressed(KeyCode::Return) && has_puzzle {
+        Some(start_pack_puzzle(
+            &mut commands,
+            (browser.pack, browser.puzzle),
+            &packs,
+            &settings,
+        ))
+    } else if input.just_pressed(KeyCode::G) {
+        Some(start_found_puzzle(
+            &mut commands,
+            &presets,
+            &packs,
+            &settings,
+            &mut rng,
+        ))
+    } else if input.just_pressed(KeyCode::B) {
+        Some(start_blunder_puzzle(&mut commands, &deck, &settings))
+    } else {
+        None
+    };
+    match started {
+        Some(Ok(())) => {
+            commands.remove_resource::<PuzzleBrowser>();
+            next_state.set(AppState::InGame);
+            return;
+        }
+        Some(Err(err)) => {
+            warn!("{}", err);
+            browser.message = Some(err);
+        }
+        None if packs.0.is_empty() => return,
+        None => {
+            let num_packs = packs.0.len();
+            let num_puzzles = packs.0[browser.pack].puzzles.len().max(1);
+            if input.just_pressed(KeyCode::Down) {
+                browser.puzzle = (browser.puzzle + 1) % num_puzzles;
+            } else if input.just_pressed(KeyCode::Up) {
+                browser.puzzle = (browser.puzzle + num_puzzles - 1) % num_puzzles;
+            } else if input.any_just_pressed([KeyCode::Left, KeyCode::Right]) {
+                let forward = input.just_pressed(KeyCode::Right);
+                browser.pack = if forward {
+                    (browser.pack + 1) % num_packs
+                } else {
+                    (browser.pack + num_packs - 1) % num_packs
+                };
+                browser.puzzle = 0;
+            } else {
+                return;
+            }
+            browser.message = None;
+        }
+    }
+    for entity in &menu_query {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_puzzle_browser_for(&mut commands, &browser, &packs, &solved, &deck);
+}
+
+#[derive(Component)]
+pub(crate) struct PuzzlePrompt;
+
+pub(crate) fn setup_puzzle_prompt(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(10.),
+                left: Val::Percent(30.),
+                ..default()
+            },
+            ..default()
+        }),
+        PuzzlePrompt,
+    ));
+}
+
+pub(crate) fn update_puzzle_prompt(
+    puzzle: Option<Res<PuzzleMode>>,
+    mut query: Query<&mut Text, With<PuzzlePrompt>>,
+) {
+    let prompt = puzzle.as_ref().map_or("", |puzzle| puzzle.prompt.as_str());
+    for mut text in &mut query {
+        if text.sections[0].value != prompt {
+            text.sections[0].value = prompt.to_string();
+        }
+    }
+}
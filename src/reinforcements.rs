@@ -0,0 +1,191 @@
+use bevy::prelude::*;
+
+use crate::engine::cell::CellCoordinates;
+use crate::engine::direction::{CartesianDirection, RadialDirection};
+use crate::engine::notation;
+use crate::engine::units::{Team, Unit, UnitType};
+use crate::gamemanager::Game;
+
+pub(crate) const ENERGY_PER_TURN: u32 = 1;
+pub(crate) const MAX_ENERGY: u32 = 12;
+
+/// The unit types that can be summoned, in the order of the number keys that select them
+pub(crate) const SUMMONABLE: [(KeyCode, UnitType); 5] = [
+    (
+        KeyCode::Key1,
+        UnitType::Pawn(RadialDirection::ClockwiseX, false),
+    ),
+    (KeyCode::Key2, UnitType::Knight),
+    (KeyCode::Key3, UnitType::Bishop),
+    (KeyCode::Key4, UnitType::Rook),
+    (KeyCode::Key5, UnitType::Queen),
+];
+
+/// Energy needed to summon a unit, scaling with how much the unit is worth
+pub(crate) fn summon_cost(unit_type: UnitType) -> u32 {
+    unit_type.material_value().ceil() as u32
+}
+
+/// The summonable unit type written as `symbol`, as in [`notation::write_summon`]
+pub(crate) fn summonable_from_symbol(symbol: &str) -> Option<UnitType> {
+    SUMMONABLE
+        .into_iter()
+        .map(|(_, unit_type)| unit_type)
+        .find(|unit_type| unit_type.symbol().to_string() == symbol)
+}
+
+/// Number keys choose which unit the next click on the home face summons
+pub(crate) fn choose_summon(input: Res<Input<KeyCode>>, mut game: ResMut<Game>) {
+    let summons = game.variant.rules().summons;
+    if !summons || game.ai_playing == Some(game.turn) || !game.is_played_here() {
+        return;
+    }
+    for (keycode, unit_type) in SUMMONABLE {
+        if !input.just_pressed(keycode) {
+            continue;
+        }
+        if game.energy(game.turn) >= summon_cost(unit_type) {
+            game.pending_summon = Some(unit_type);
+        } else {
+            info!("Not enough energy to summon {:?}", unit_type);
+        }
+    }
+    if input.just_pressed(KeyCode::Escape) {
+        game.pending_summon = None;
+    }
+}
+
+/// Summons the pending unit at `coords` if it is an empty cell on the home face of the team
+/// whose turn it is. Returns true if the unit was summoned, which uses up the turn.
+pub(crate) fn try_summon(game: &mut Game, coords: CellCoordinates) -> bool {
+    let Some(unit_type) = game.pending_summon.take() else {
+        return false;
+    };
+    let team = game.turn;
+    let cost = summon_cost(unit_type);
+    if coords.normal_direction() != game.home_face(team)
+        || game.units.is_unit_at(coords)
+        || game.energy(team) < cost
+    {
+        return false;
+    }
+
+    let unit_type = summoned_type(unit_type, coords, game.board.cube_side_length);
+    game.units.add_unit(Unit::new(unit_type, team, coords));
+    game.energy[team.index()] -= cost;
+    game.history
+        .record_move(team, None, notation::write_summon(unit_type, coords), None);
+    true
+}
+
+/// Every unit `team` has the energy to summon on every empty cell of its home face, with its cost
+pub(crate) fn possible_summons(game: &Game, team: Team) -> Vec<(Unit, u32)> {
+    let energy = game.energy(team);
+    let mut summons = Vec::new();
+    for cell in game.board.get_all_cells() {
+        let coords = cell.coords;
+        if coords.normal_direction() != game.home_face(team) || game.units.is_unit_at(coords) {
+            continue;
+        }
+        for (_, unit_type) in SUMMONABLE {
+            let cost = summon_cost(unit_type);
+            if cost <= energy {
+                let unit_type = summoned_type(unit_type, coords, game.board.cube_side_length);
+                summons.push((Unit::new(unit_type, team, coords), cost));
+            }
+        }
+    }
+    summons
+}
+
+/// The unit type that is summoned at `coords`, which for pawns depends on where they are summoned
+fn summoned_type(unit_type: UnitType, coords: CellCoordinates, cube_side_length: u32) -> UnitType {
+    match unit_type {
+        UnitType::Pawn(_, _) => UnitType::Pawn(pawn_direction(coords, cube_side_length), false),
+        unit_type => unit_type,
+    }
+}
+
+/// Summoned pawns walk toward the edge of the home face they are closest to
+pub(crate) fn pawn_direction(coords: CellCoordinates, cube_side_length: u32) -> RadialDirection {
+    let normal = coords.normal_direction();
+    let center = (cube_side_length + 1) as f32 / 2.;
+    let distance_toward = |direction: CartesianDirection| {
+        let from_center = coords[direction.axis_num() as usize] as f32 - center;
+        if direction.is_negative() {
+            -from_center
+        } else {
+            from_center
+        }
+    };
+
+    RadialDirection::directions()
+        .into_iter()
+        .filter_map(|radial| Some((radial, radial.to_cartesian_direction(normal)?)))
+        .max_by(|a, b| distance_toward(a.1).total_cmp(&distance_toward(b.1)))
+        .map(|(radial, _)| radial)
+        .unwrap()
+}
+
+#[derive(Component)]
+pub(crate) struct EnergyMeter;
+
+pub(crate) fn setup_energy_meter(mut commands: Commands, game: Res<Game>) {
+    if !game.variant.rules().summons {
+        return;
+    }
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(10.),
+                left: Val::Px(10.),
+                ..default()
+            },
+            ..default()
+        }),
+        EnergyMeter,
+    ));
+}
+
+pub(crate) fn despawn_energy_meter(
+    mut commands: Commands,
+    query: Query<Entity, With<EnergyMeter>>,
+) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub(crate) fn update_energy_meter(game: Res<Game>, mut query: Query<&mut Text, With<EnergyMeter>>) {
+    for mut text in &mut query {
+        let meter = |team: Team| {
+            let energy = game.energy(team);
+            format!(
+                "{:?}: {}{} {}/{}",
+                team,
+                "|".repeat(energy as usize),
+                ".".repeat((MAX_ENERGY - energy) as usize),
+                energy,
+                MAX_ENERGY
+            )
+        };
+        let mut value = format!("Energy\n{}\n{}", meter(Team::White), meter(Team::Black));
+        value.push_str(&match game.pending_summon {
+            Some(unit_type) => format!(
+                "\nClick your home face to summon {} (Esc to cancel)",
+                unit_type.model_name()
+            ),
+            None => "\n1-5: Summon pawn/knight/bishop/rook/queen".to_string(),
+        });
+        text.sections[0].value = value;
+    }
+}
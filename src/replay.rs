@@ -0,0 +1,139 @@
+//! Reviewing a game turn by turn after it has ended. The replay steps through the undo history of
+//! the game, so leaving it puts the game back at its last position.
+
+use bevy::prelude::*;
+
+use crate::gamemanager::Game;
+use crate::scene;
+use crate::AppState;
+
+/// R on the game over screen starts the replay
+pub(crate) fn enter_replay(
+    input: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if input.just_pressed(KeyCode::R) {
+        next_state.set(AppState::Replay);
+    }
+}
+
+/// The turn to start the replay at, instead of the first position of the game
+#[derive(Resource)]
+pub(crate) struct ReplayFrom(pub(crate) usize);
+
+/// The replay starts from the first position of the game, unless another turn was picked
+pub(crate) fn start_replay(
+    mut commands: Commands,
+    mut game: ResMut<Game>,
+    replay_from: Option<Res<ReplayFrom>>,
+) {
+    let turn = replay_from.map_or(0, |replay_from| replay_from.0);
+    commands.remove_resource::<ReplayFrom>();
+    jump(&mut commands, &mut game, turn);
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: Val::Px(10.),
+                left: Val::Px(10.),
+                ..default()
+            },
+            ..default()
+        }),
+        ReplayPanel,
+    ));
+}
+
+/// Comma and period step one turn back and forward, Home and End jump to the start and the end,
+/// and Escape goes back to the game over screen
+pub(crate) fn replay_input(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    mut game: ResMut<Game>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let game = &mut *game;
+    if input.just_pressed(KeyCode::Comma) {
+        step(&mut commands, game, Game::undo, false);
+    } else if input.just_pressed(KeyCode::Period) {
+        step(&mut commands, game, Game::redo, false);
+    } else if input.just_pressed(KeyCode::Home) {
+        step(&mut commands, game, Game::undo, true);
+    } else if input.just_pressed(KeyCode::End) {
+        step(&mut commands, game, Game::redo, true);
+    } else if input.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::GameOver);
+    }
+}
+
+/// Takes one step, or as many as possible if `all` is set, moving the unit entities along
+fn step(commands: &mut Commands, game: &mut Game, step: fn(&mut Game) -> bool, all: bool) {
+    let previous_units = game.units.clone();
+    if !step(game) {
+        return;
+    }
+    while all && step(game) {}
+    scene::resync_unit_entities(commands, game, &previous_units);
+}
+
+/// Undoes or redoes turns until `turn` turns have been played, moving the unit entities along
+pub(crate) fn jump(commands: &mut Commands, game: &mut Game, turn: usize) {
+    let previous_units = game.units.clone();
+    let mut moved = false;
+    while game.history.turns_played() > turn && game.undo() {
+        moved = true;
+    }
+    while game.history.turns_played() < turn && game.redo() {
+        moved = true;
+    }
+    if moved {
+        scene::resync_unit_entities(commands, game, &previous_units);
+    }
+}
+
+/// Puts the game back at its last position
+pub(crate) fn end_replay(
+    mut commands: Commands,
+    mut game: ResMut<Game>,
+    query: Query<Entity, With<ReplayPanel>>,
+) {
+    step(&mut commands, &mut game, Game::redo, true);
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Shows which turn of the game is on the board, and the moves that led to it
+#[derive(Component)]
+pub(crate) struct ReplayPanel;
+
+pub(crate) fn update_replay_panel(game: Res<Game>, mut query: Query<&mut Text, With<ReplayPanel>>) {
+    if !game.is_changed() {
+        return;
+    }
+    let moves: Vec<String> = game
+        .history
+        .last_turn_moves()
+        .iter()
+        .map(|recorded_move| format!("{:?} {}", recorded_move.team, recorded_move.notation))
+        .collect();
+    for mut text in &mut query {
+        text.sections[0].value = format!(
+            "Turn {} of {}: {}\n, and . step, Home and End jump, Escape leaves the replay",
+            game.history.turns_played(),
+            game.history.turns_played() + game.history.turns_undone(),
+            match moves.is_empty() {
+                true => "Starting position".to_string(),
+                false => moves.join(", "),
+            }
+        );
+    }
+}
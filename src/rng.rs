@@ -0,0 +1,39 @@
+//! All randomness in the game comes from one seeded generator, so a game can be played out the
+//! same way again by starting it with the same seed
+
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+#[derive(Resource)]
+pub(crate) struct GameRng {
+    /// A new seed is picked for every game if none was chosen
+    chosen_seed: Option<u64>,
+    seed: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub(crate) fn new(chosen_seed: Option<u64>) -> Self {
+        let seed = chosen_seed.unwrap_or_else(|| rand::thread_rng().gen());
+        GameRng {
+            chosen_seed,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// The seed of the current game, which can be passed with `--seed` to play it again
+    pub(crate) fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Starts over for a new game
+    pub(crate) fn restart(&mut self) {
+        *self = GameRng::new(self.chosen_seed);
+    }
+
+    pub(crate) fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+}
@@ -1,20 +1,31 @@
 use bevy::prelude::*;
+use bevy::render::mesh::{Indices, VertexAttributeValues};
+use bevy::render::render_resource::PrimitiveTopology;
 use bevy::scene::SceneInstance;
 use bevy_mod_picking::prelude::*;
 use std::f32::consts::PI;
 
 use bevy::prelude::Vec3;
 
-use crate::cell::{Cell, CellColor, CellCoordinates};
-use crate::gamemanager::{self, spawn_unit_entity, Game};
-use crate::materials;
+use crate::engine::cell::{Cell, CellColor, CellCoordinates};
+use crate::engine::direction::CartesianDirection;
+use crate::engine::units::{Team, Unit, UnitType, Units};
+use crate::engine::ObjectId;
+use crate::gamemanager::{self, spawn_unit_entity, Game, MoveMade, UnitCaptured, UnitPromoted};
+use crate::materials::{CellMaterials, CellState, CellView};
+use crate::peek::Peek;
+use crate::piece_sets::{PieceSet, PieceSets};
+use crate::settings::Settings;
+
+/// Cube side length from which each face is rendered as one merged mesh instead of one entity
+/// with its own mesh per cell
+const MERGE_FACES_FROM_SIDE_LENGTH: u32 = 8;
 
 pub(crate) fn construct_cube(
     side_length: u32,
     meshes: &mut ResMut<Assets<Mesh>>,
     commands: &mut Commands,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    material: &StandardMaterial,
+    cell_materials: &CellMaterials,
     game: &mut ResMut<Game>,
 ) {
     fn choose_color(
@@ -43,7 +54,16 @@ pub(crate) fn construct_cube(
         }
     }
 
-    let plane_mesh: Handle<Mesh> = meshes.add(shape::Plane::default().into());
+    let mut plane = Mesh::from(shape::Plane::default());
+    // Needed by the normal maps of the board themes
+    plane.generate_tangents().unwrap();
+    let plane_mesh: Handle<Mesh> = meshes.add(plane);
+    let merge_faces = side_length >= MERGE_FACES_FROM_SIDE_LENGTH;
+    let mut face_builders: Vec<FaceMeshBuilder> = (0..6).map(|_| default()).collect();
+    let mut face_normals = [CartesianDirection::X; 6];
+    let face_meshes: Vec<Handle<Mesh>> = (0..6)
+        .map(|_| meshes.add(Mesh::new(PrimitiveTopology::TriangleList)))
+        .collect();
     let spacing = 1. / (side_length) as f32;
     let offset = 0.5 - spacing / 2.;
     // The total side length of cube is always 1, so we offset
@@ -131,27 +151,213 @@ pub(crate) fn construct_cube(
                 rotation.z -= if rotation.z == 0. { 0. } else { PI };
             }
 
-            let plane = commands
-                .spawn((
-                    PbrBundle {
-                        mesh: plane_mesh.clone(),
-                        material: materials.add(material.clone()),
-                        transform: Transform::from_translation(translation)
-                            .with_scale(Vec3::splat(spacing))
-                            .with_rotation(Quat::from_scaled_axis(rotation)),
-                        ..default()
-                    },
-                    PickableBundle::default(),
-                    RaycastPickTarget::default(),
-                    MainCube { coords },
-                    OnPointer::<Click>::run_callback(gamemanager::on_cell_clicked),
-                ))
-                .id();
-
-            let cell = Cell::new(plane, coords, color);
+            face_normals[side] = coords.normal_direction();
+            let transform = Transform::from_translation(translation)
+                .with_scale(Vec3::splat(spacing))
+                .with_rotation(Quat::from_scaled_axis(rotation));
+            let plane = if merge_faces {
+                // The cell entity is kept for its transform, but is drawn and picked by its face
+                let first_vertex =
+                    face_builders[side].add_cell(&transform, game.palette.cell_color(color));
+                commands
+                    .spawn((
+                        TransformBundle::from_transform(transform),
+                        MainCube { coords },
+                        MergedCell {
+                            mesh: face_meshes[side].clone(),
+                            first_vertex,
+                        },
+                    ))
+                    .id()
+            } else {
+                commands
+                    .spawn((
+                        PbrBundle {
+                            mesh: plane_mesh.clone(),
+                            material: cell_materials
+                                .get(color, CellState::Normal, CellView::Solid)
+                                .clone(),
+                            transform,
+                            ..default()
+                        },
+                        PickableBundle::default(),
+                        RaycastPickTarget::default(),
+                        MainCube { coords },
+                        OnPointer::<Click>::run_callback(gamemanager::on_cell_clicked),
+                        OnPointer::<Over>::run_callback(gamemanager::on_cell_hovered),
+                        OnPointer::<Out>::run_callback(gamemanager::on_cell_unhovered),
+                    ))
+                    .id()
+            };
+
+            let cell = Cell::new(plane.into(), coords, color);
             game.board.new_cell(coords, cell);
         }
     }
+
+    if !merge_faces {
+        return;
+    }
+    let faces = face_builders.into_iter().zip(face_meshes).zip(face_normals);
+    for ((face_builder, face_mesh), normal) in faces {
+        *meshes.get_mut(&face_mesh).unwrap() = face_builder.build();
+        commands.spawn((
+            PbrBundle {
+                mesh: face_mesh,
+                material: cell_materials.vertex_colored(CellView::Solid).clone(),
+                ..default()
+            },
+            PickableBundle::default(),
+            RaycastPickTarget::default(),
+            CubeFace { normal },
+            OnPointer::<Click>::run_callback(gamemanager::on_face_clicked),
+        ));
+    }
+}
+
+/// Builds the cube for the game that is about to start, together with the materials of its cells
+pub(crate) fn build_cube(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut Assets<StandardMaterial>,
+    game: &mut ResMut<Game>,
+) {
+    let cell_materials = CellMaterials::new(game.palette, materials);
+    construct_cube(
+        game.board.cube_side_length,
+        meshes,
+        commands,
+        &cell_materials,
+        game,
+    );
+    commands.insert_resource(cell_materials);
+}
+
+/// Removes the cells, faces and units of the cube, so that another game can be built
+pub(crate) fn despawn_cube(
+    mut commands: Commands,
+    mut game: ResMut<Game>,
+    cell_query: Query<Entity, With<MainCube>>,
+    face_query: Query<Entity, With<CubeFace>>,
+) {
+    for entity in cell_query.iter().chain(&face_query) {
+        commands.entity(entity).despawn_recursive();
+    }
+    for unit in game.units.all_units_iter_mut() {
+        if let Some(entity) = unit.entity.take() {
+            kill_unit(&mut commands, entity.into());
+        }
+    }
+    game.entities_to_move.clear();
+    commands.remove_resource::<CellMaterials>();
+}
+
+/// Collects the cells of one face of the cube into a single mesh
+#[derive(Default)]
+struct FaceMeshBuilder {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl FaceMeshBuilder {
+    /// Adds the same quad as a plane mesh with `transform` would have, returning the index of its
+    /// first vertex
+    fn add_cell(&mut self, transform: &Transform, color: Color) -> usize {
+        let first_vertex = self.positions.len();
+        let normal = transform.rotation * Vec3::Y;
+        for (x, z) in [(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)] {
+            let position = transform.transform_point(Vec3::new(x, 0., z));
+            self.positions.push(position.to_array());
+            self.normals.push(normal.to_array());
+            self.uvs.push([x + 0.5, z + 0.5]);
+            self.colors.push(color.as_linear_rgba_f32());
+        }
+        let i = first_vertex as u32;
+        self.indices.extend([i, i + 3, i + 2, i, i + 2, i + 1]);
+        first_vertex
+    }
+
+    fn build(self) -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, self.colors);
+        mesh.set_indices(Some(Indices::U32(self.indices)));
+        mesh.generate_tangents().unwrap();
+        mesh
+    }
+}
+
+/// The merged mesh of a face of a large cube
+#[derive(Component)]
+pub(crate) struct CubeFace {
+    normal: CartesianDirection,
+}
+
+/// A cell that is drawn as part of a merged face mesh, colored through its vertices
+#[derive(Component)]
+pub(crate) struct MergedCell {
+    mesh: Handle<Mesh>,
+    first_vertex: usize,
+}
+
+impl MergedCell {
+    /// Only changes the mesh if the color is different, since changing it means uploading it again
+    fn set_color(&self, color: Color, meshes: &mut Assets<Mesh>) {
+        let color = color.as_linear_rgba_f32();
+        let Some(VertexAttributeValues::Float32x4(colors)) = meshes
+            .get(&self.mesh)
+            .and_then(|mesh| mesh.attribute(Mesh::ATTRIBUTE_COLOR))
+        else {
+            return;
+        };
+        if colors.get(self.first_vertex) == Some(&color) {
+            return;
+        }
+        let Some(VertexAttributeValues::Float32x4(colors)) = meshes
+            .get_mut(&self.mesh)
+            .and_then(|mesh| mesh.attribute_mut(Mesh::ATTRIBUTE_COLOR))
+        else {
+            return;
+        };
+        colors[self.first_vertex..self.first_vertex + 4].fill(color);
+    }
+}
+
+/// How close to an edge, in cells, a hit has to be for the face that faces the camera the most to
+/// be chosen instead of the face that was hit
+const EDGE_PICK_MARGIN: f32 = 0.25;
+
+/// The cell on the surface of the cube at `position`, given relative to the cube.
+/// `view_direction` is the direction from the camera to the position.
+pub(crate) fn coords_at_cube_position(
+    position: Vec3,
+    view_direction: Vec3,
+    side_length: u32,
+) -> CellCoordinates {
+    // The face that was hit is the one the position is furthest out towards. Near an edge the hit
+    // can land on either face, and the one facing the camera the most is the one that was meant.
+    let margin = EDGE_PICK_MARGIN / side_length as f32;
+    let furthest_out = position.abs().max_element();
+    let normal_axis = (0..3)
+        .filter(|&axis| position[axis].abs() >= furthest_out - margin)
+        .min_by(|&a, &b| {
+            let facing = |axis: usize| view_direction[axis] * position[axis].signum();
+            facing(a).total_cmp(&facing(b))
+        })
+        .unwrap();
+    let mut coords = [0; 3];
+    for (axis, coord) in coords.iter_mut().enumerate() {
+        if axis != normal_axis {
+            let index = ((position[axis] + 0.5) * side_length as f32).floor() as i64;
+            *coord = index.clamp(0, side_length as i64 - 1) as u32 + 1;
+        }
+    }
+    CellCoordinates::new(coords[0], coords[1], coords[2], position[normal_axis] > 0.)
 }
 
 #[derive(Component)]
@@ -159,22 +365,70 @@ pub(crate) struct MainCube {
     pub(crate) coords: CellCoordinates,
 }
 
+impl From<Entity> for ObjectId {
+    fn from(entity: Entity) -> Self {
+        ObjectId(entity.to_bits())
+    }
+}
+
+impl From<ObjectId> for Entity {
+    fn from(id: ObjectId) -> Self {
+        Entity::from_bits(id.0)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn update_cell_colors(
-    query: Query<(&mut Handle<StandardMaterial>, &MainCube)>,
+    mut query: Query<&mut Handle<StandardMaterial>, With<MainCube>>,
+    merged_cells: Query<&MergedCell>,
+    mut face_query: Query<(&CubeFace, &mut Handle<StandardMaterial>), Without<MainCube>>,
+    mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    game: ResMut<Game>,
+    mut images: ResMut<Assets<Image>>,
+    mut cell_materials: ResMut<CellMaterials>,
+    game: Res<Game>,
+    settings: Res<Settings>,
+    peek: Res<Peek>,
 ) {
-    for cell in game.board.get_all_cells() {
-        let plane = cell.plane;
+    let theme = settings.board_theme;
+    if cell_materials.palette() != game.palette || cell_materials.theme() != theme {
+        cell_materials.set_look(game.palette, theme, &mut materials, &mut images);
+    }
+
+    for (face, mut material) in &mut face_query {
+        let wanted_material = cell_materials.vertex_colored(peek.view(face.normal));
+        if *material != *wanted_material {
+            *material = wanted_material.clone();
+        }
+    }
 
-        let query_result = query.get(plane).unwrap();
-        let material = materials.get_mut(query_result.0).unwrap();
-        if game.selected_cell.map_or(false, |x| x == cell.coords) {
-            materials::select_cell_material(material, game.palette, cell.color);
+    for cell in game.board.get_all_cells() {
+        let state = if game.selected_cell.map_or(false, |x| x == cell.coords) {
+            CellState::Selected
+        } else if game.hovered_cell == Some(cell.coords) {
+            CellState::Hovered
         } else if cell.selected_unit_can_move_to {
-            materials::can_go_cell_material(material, game.palette, cell.color);
+            CellState::CanGo
         } else {
-            materials::normal_cell_material(material, game.palette, cell.color);
+            CellState::Normal
+        };
+
+        if let Ok(merged_cell) = merged_cells.get(cell.plane.into()) {
+            // The face it is part of is made see-through instead
+            let color_material = cell_materials.get(cell.color, state, CellView::Solid);
+            if let Some(material) = materials.get(color_material) {
+                merged_cell.set_color(material.base_color, &mut meshes);
+            }
+            continue;
+        }
+
+        let view = peek.view(cell.coords.normal_direction());
+        let wanted_material = cell_materials.get(cell.color, state, view);
+
+        let Ok(mut material) = query.get_mut(cell.plane.into()) else { continue; };
+        // Only swap when needed to not trigger change detection every frame
+        if *material != *wanted_material {
+            *material = wanted_material.clone();
         }
     }
 }
@@ -183,15 +437,17 @@ pub(crate) fn update_cell_colors(
 #[derive(Component, Default, Debug)]
 pub(crate) struct PrepareUnit;
 
+/// Spawns the model of the unit type in the piece set
 pub(crate) fn spawn_unit(
     commands: &mut Commands,
     asset_server: &AssetServer,
-    model_name: &str,
+    piece_set: &PieceSet,
+    unit_type: UnitType,
 ) -> Entity {
     let entity = commands
         .spawn((
             SceneBundle {
-                scene: asset_server.load(format!("models/{}.glb#Scene0", model_name)),
+                scene: asset_server.load(piece_set.scene_path(unit_type)),
                 ..default()
             },
             PrepareUnit,
@@ -211,6 +467,7 @@ pub(crate) fn prepare_unit_entity(
     mut unloaded_instances: Query<(Entity, &SceneInstance), With<PrepareUnit>>,
     mut material_query: Query<&mut Handle<StandardMaterial>>,
     game: Res<Game>,
+    settings: Res<Settings>,
     scene_manager: Res<SceneSpawner>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
@@ -220,8 +477,8 @@ pub(crate) fn prepare_unit_entity(
         }
         commands.entity(parent_entity).remove::<PrepareUnit>();
 
-        let unit = game.units.get_unit_from_entity(parent_entity);
-        let color = unit.unwrap().team.color();
+        let unit = game.units.get_unit_from_entity(parent_entity.into());
+        let team_material = settings.team_material(unit.unwrap().team);
 
         // Iterate over all entities in scene (once it's loaded)
         let handles = scene_manager.iter_instance_entities(**instance);
@@ -230,6 +487,8 @@ pub(crate) fn prepare_unit_entity(
                 PickableBundle::default(),
                 RaycastPickTarget::default(),
                 OnPointer::<Click>::run_callback(gamemanager::on_unit_clicked),
+                OnPointer::<Over>::run_callback(gamemanager::on_unit_hovered),
+                OnPointer::<Out>::run_callback(gamemanager::on_unit_unhovered),
                 SceneChild { parent_entity },
             ));
 
@@ -240,7 +499,7 @@ pub(crate) fn prepare_unit_entity(
                 let material_handle = material_handle.into_inner();
                 let material = materials.get_mut(material_handle).unwrap();
                 let mut material_cloned = material.clone();
-                material_cloned.base_color = color;
+                team_material.apply(&mut material_cloned);
                 let material_cloned_handle = materials.add(material_cloned);
                 *material_handle = material_cloned_handle;
             }
@@ -252,9 +511,12 @@ pub(crate) fn spawn_missing_unit_entities(
     mut commands: Commands,
     mut game: ResMut<Game>,
     asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    piece_sets: Res<PieceSets>,
 ) {
     let game = &mut *game;
     let asset_server = &*asset_server;
+    let piece_set = piece_sets.get(&settings.piece_set);
     for unit in game
         .units
         .all_units_iter_mut()
@@ -265,40 +527,537 @@ pub(crate) fn spawn_missing_unit_entities(
             unit,
             &mut game.entities_to_move,
             asset_server,
+            piece_set,
         )
     }
 }
 
+/// Marks the small spheres above a unit that show its level in the experience variant
+#[derive(Component)]
+pub(crate) struct LevelBadge;
+
+/// The level that the badges of a unit entity currently show
+#[derive(Component)]
+pub(crate) struct ShownLevel(u32);
+
+pub(crate) fn update_level_badges(
+    mut commands: Commands,
+    game: Res<Game>,
+    unit_query: Query<(Option<&ShownLevel>, Option<&Children>)>,
+    badge_query: Query<(), With<LevelBadge>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut badge_assets: Local<Option<(Handle<Mesh>, Handle<StandardMaterial>)>>,
+) {
+    for unit in game.units.all_units_iter() {
+        let Some(entity) = unit.entity.map(Entity::from) else { continue; };
+        let Ok((shown_level, children)) = unit_query.get(entity) else { continue; };
+        if shown_level.map_or(0, |shown_level| shown_level.0) == unit.level {
+            continue;
+        }
+
+        for &child in children.into_iter().flatten() {
+            if badge_query.contains(child) {
+                commands.entity(child).despawn_recursive();
+            }
+        }
+
+        let (mesh, material) = badge_assets
+            .get_or_insert_with(|| {
+                (
+                    meshes.add(
+                        shape::UVSphere {
+                            radius: 0.04,
+                            ..default()
+                        }
+                        .into(),
+                    ),
+                    materials.add(StandardMaterial {
+                        base_color: Color::GOLD,
+                        unlit: true,
+                        ..default()
+                    }),
+                )
+            })
+            .clone();
+        let level = unit.level;
+        commands
+            .entity(entity)
+            .insert(ShownLevel(level))
+            .with_children(|parent| {
+                for i in 0..level {
+                    // Placed in a row above the model, which is about 0.65 high. The unit
+                    // entity is squashed along y, which the badges undo
+                    let x = (i as f32 - (level - 1) as f32 / 2.) * 0.1;
+                    parent.spawn((
+                        PbrBundle {
+                            mesh: mesh.clone(),
+                            material: material.clone(),
+                            transform: Transform::from_xyz(x, 0.75, 0.)
+                                .with_scale(Vec3::new(1., 2., 1.)),
+                            ..default()
+                        },
+                        LevelBadge,
+                    ));
+                }
+            });
+    }
+}
+
 pub(crate) fn kill_unit(commands: &mut Commands, entity: Entity) {
     commands.entity(entity).despawn_recursive();
 }
 
+/// How long a captured unit takes to shrink away, in seconds
+const CAPTURE_DURATION: f32 = 0.35;
+/// How many sparks fly out where a unit is captured
+const CAPTURE_SPARKS: usize = 10;
+/// How long the sparks fly, in seconds
+const SPARK_DURATION: f32 = 0.5;
+
+/// A unit entity that has been captured and shrinks away before it is despawned, see
+/// [`animate_captures`]
+#[derive(Component)]
+pub(crate) struct CapturedUnit {
+    start: Transform,
+    elapsed: f32,
+}
+
+/// A spark flying out from where a unit was captured
+#[derive(Component)]
+pub(crate) struct CaptureSpark {
+    velocity: Vec3,
+    size: f32,
+    elapsed: f32,
+}
+
+/// Lets a captured unit shrink away and throws sparks in the color of its team from its cell,
+/// instead of removing it right away like [`kill_unit`]
+fn capture_unit(
+    commands: &mut Commands,
+    entity: Entity,
+    transform: Transform,
+    spark_mesh: Handle<Mesh>,
+    spark_material: Handle<StandardMaterial>,
+) {
+    commands
+        .entity(entity)
+        .remove::<UnitMovement>()
+        .insert(CapturedUnit {
+            start: transform,
+            elapsed: 0.,
+        });
+    let up = transform.rotation * Vec3::Y;
+    let side = up.any_orthonormal_vector();
+    let forward = up.cross(side);
+    // The scale of a unit is the size of a cell
+    let speed = transform.scale.x * 1.5;
+    for i in 0..CAPTURE_SPARKS {
+        let angle = i as f32 / CAPTURE_SPARKS as f32 * 2. * PI;
+        let direction = side * angle.cos() + forward * angle.sin() + up;
+        commands.spawn((
+            PbrBundle {
+                mesh: spark_mesh.clone(),
+                material: spark_material.clone(),
+                transform: Transform::from_translation(transform.translation)
+                    .with_scale(Vec3::splat(transform.scale.x)),
+                ..default()
+            },
+            CaptureSpark {
+                velocity: direction.normalize() * speed,
+                size: transform.scale.x,
+                elapsed: 0.,
+            },
+        ));
+    }
+}
+
+/// Shrinks captured units until they are gone, and moves the sparks out until they fade away
+pub(crate) fn animate_captures(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut unit_query: Query<(Entity, &mut CapturedUnit, &mut Transform), Without<CaptureSpark>>,
+    mut spark_query: Query<(Entity, &mut CaptureSpark, &mut Transform), Without<CapturedUnit>>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut captured, mut transform) in &mut unit_query {
+        captured.elapsed += delta;
+        let left = 1. - (captured.elapsed / CAPTURE_DURATION).min(1.);
+        if left <= 0. {
+            kill_unit(&mut commands, entity);
+            continue;
+        }
+        transform.scale = captured.start.scale * left;
+    }
+    for (entity, mut spark, mut transform) in &mut spark_query {
+        spark.elapsed += delta;
+        let left = 1. - (spark.elapsed / SPARK_DURATION).min(1.);
+        if left <= 0. {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        transform.translation += spark.velocity * delta;
+        transform.scale = Vec3::splat(spark.size * left);
+    }
+}
+
+/// Makes the unit entities match the units after the game has jumped to another position, like when
+/// a turn is undone. `previous_units` are the units before the jump. Units that came back to the
+/// board, or that are another kind of unit than before, get new entities from
+/// [`spawn_missing_unit_entities`].
+pub(crate) fn resync_unit_entities(
+    commands: &mut Commands,
+    game: &mut Game,
+    previous_units: &Units,
+) {
+    for unit in previous_units.all_units_iter() {
+        let Some(entity) = unit.entity else {
+            continue;
+        };
+        let now = game.units.get_unit_from_entity(entity);
+        if !now.is_some_and(|now| same_model(now, unit)) {
+            kill_unit(commands, entity.into());
+        }
+    }
+    game.entities_to_move.clear();
+    for unit in game.units.all_units_iter_mut() {
+        let Some(entity) = unit.entity else {
+            continue;
+        };
+        let before = previous_units.get_unit_from_entity(entity);
+        if before.is_some_and(|before| same_model(before, unit)) {
+            game.entities_to_move.push((entity.into(), unit.coords));
+        } else {
+            unit.entity = None;
+        }
+    }
+}
+
+fn same_model(unit: &Unit, other: &Unit) -> bool {
+    unit.unit_type.model_name() == other.unit_type.model_name()
+}
+
+/// Makes the unit entities follow the moves, captures and promotions made in the game. A promoted
+/// unit gets a new entity from [`spawn_missing_unit_entities`].
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn apply_game_events(
+    mut commands: Commands,
+    mut moves_made: EventReader<MoveMade>,
+    mut units_captured: EventReader<UnitCaptured>,
+    mut units_promoted: EventReader<UnitPromoted>,
+    mut game: ResMut<Game>,
+    // Units whose model hasn't loaded yet are removed right away
+    transform_query: Query<&Transform, Without<PrepareUnit>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut spark_mesh: Local<Option<Handle<Mesh>>>,
+    settings: Res<Settings>,
+) {
+    let mut captured_entities = Vec::new();
+    for unit_captured in units_captured.iter() {
+        let Some(entity) = unit_captured.unit.entity.map(Entity::from) else {
+            continue;
+        };
+        captured_entities.push(entity);
+        let Ok(&transform) = transform_query.get(entity) else {
+            kill_unit(&mut commands, entity);
+            continue;
+        };
+        let spark_mesh = spark_mesh
+            .get_or_insert_with(|| {
+                meshes.add(
+                    shape::UVSphere {
+                        radius: 0.04,
+                        ..default()
+                    }
+                    .into(),
+                )
+            })
+            .clone();
+        let spark_material = materials.add(StandardMaterial {
+            base_color: settings.team_material(unit_captured.unit.team).base_color,
+            unlit: true,
+            ..default()
+        });
+        capture_unit(&mut commands, entity, transform, spark_mesh, spark_material);
+    }
+    for move_made in moves_made.iter() {
+        // A unit blown up by its own capture in the atomic variant goes off where it stood
+        if captured_entities.contains(&move_made.entity) {
+            continue;
+        }
+        game.entities_to_move
+            .push((move_made.entity, move_made.game_move.to));
+    }
+    for unit_promoted in units_promoted.iter() {
+        kill_unit(&mut commands, unit_promoted.entity);
+        game.entities_to_move
+            .retain(|&(entity, _)| entity != unit_promoted.entity);
+        if let Some(unit) = game.units.get_unit_mut(unit_promoted.coords) {
+            unit.entity = None;
+        }
+    }
+}
+
+/// How long a unit takes to move to another cell, in seconds
+const MOVE_DURATION: f32 = 0.3;
+/// How far above the cube a unit moving over an edge goes at the top of its arc, relative to the
+/// distance between the cells
+const EDGE_ARC_HEIGHT: f32 = 0.5;
+
+/// Marks the unit entities that have been placed on their cell, so that later moves are animated
+/// while new entities appear on their cell right away
+#[derive(Component)]
+pub(crate) struct PlacedUnit;
+
+/// Moves a unit entity along the cube to another cell, see [`animate_unit_movement`]
+#[derive(Component)]
+pub(crate) struct UnitMovement {
+    start: Transform,
+    end: Transform,
+    elapsed: f32,
+}
+
+/// The way a unit entity looks along the face it stands on, see [`unit_facing`]
+#[derive(Component)]
+pub(crate) struct Facing(Option<Vec3>);
+
+/// The way a unit looks along the face it stands on. Pawns look where they walk, and the other
+/// units look at the king of the other team. Units that can't see that king from their face look
+/// away from the corner their team starts around. Neutral units don't look any particular way.
+pub(crate) fn unit_facing(unit: &Unit, units: &Units, cube_side_length: u32) -> Option<Vec3> {
+    let normal = unit.coords.normal_direction();
+    let along_face = |direction: Vec3| {
+        let up = normal.as_vec3();
+        Some((direction - up * direction.dot(up)).normalize_or_zero())
+            .filter(|direction| *direction != Vec3::ZERO)
+    };
+    if let UnitType::Pawn(direction, _) = unit.unit_type {
+        if let Some(forward) = direction.to_cartesian_direction(normal) {
+            return Some(forward.as_vec3());
+        }
+    }
+    let other_king = units.all_units_iter().find(|other| {
+        other.team != Team::Neutral
+            && other.team == unit.team.opposite()
+            && other.unit_type == UnitType::King
+            && other.coords.normal_direction() != normal.opposite()
+    });
+    let center = |coords| cell_center(coords, cube_side_length);
+    other_king
+        .and_then(|king| along_face(center(king.coords) - center(unit.coords)))
+        .or_else(|| along_face(Vec3::ONE * -unit.team.sign() as f32))
+}
+
+/// Where the center of the cell is, on a cube 1 wide around the origin
+fn cell_center(coords: CellCoordinates, cube_side_length: u32) -> Vec3 {
+    let normal = coords.normal_direction().as_vec3();
+    let axis = |i: usize| match coords[i] {
+        0 => normal[i] / 2.,
+        coordinate => (coordinate as f32 - 0.5) / cube_side_length as f32 - 0.5,
+    };
+    Vec3::new(axis(0), axis(1), axis(2))
+}
+
+/// How a unit standing on the cell at `coords`, whose center is at `cell_translation`, is placed.
+/// Models look along -Z, like cameras do in Bevy.
+pub(crate) fn unit_transform(
+    cell_translation: Vec3,
+    coords: CellCoordinates,
+    cube_side_length: u32,
+    facing: Option<Vec3>,
+) -> Transform {
+    let scale = 3. / cube_side_length as f32;
+    let up = coords.normal_direction().as_vec3();
+    let transform = Transform {
+        translation: cell_translation,
+        rotation: Quat::from_rotation_arc(Vec3::Y, up),
+        scale: Vec3::new(scale, scale / 2., scale),
+    };
+    match facing {
+        Some(facing) => transform.looking_to(facing, up),
+        None => transform,
+    }
+}
+
+fn cell_plane(game: &Game, coords: CellCoordinates) -> Entity {
+    game.board.get_cell(coords).unwrap().plane.into()
+}
+
+/// Places new unit entities on their cell and starts moving the others there. Once a unit has
+/// moved, the units that look at a king turn to keep looking at it, see [`unit_facing`].
 pub(crate) fn move_unit_entities(
-    mut query: Query<(Option<&MainCube>, &mut Transform)>,
+    mut commands: Commands,
+    mut query: Query<(Option<&MainCube>, &mut Transform, Option<&PlacedUnit>)>,
+    facing_query: Query<&Facing>,
     mut game: ResMut<Game>,
 ) {
+    let cube_side_length = game.board.cube_side_length;
     let mut success = Vec::with_capacity(game.entities_to_move.len());
     for unit_to_move in &game.entities_to_move {
-        let plane = game.board.get_cell(unit_to_move.1).unwrap().plane;
-        let target_translation = query.get(plane).unwrap().1.translation;
-        let scale = 3. / game.board.cube_side_length as f32;
-        let rotation =
-            Quat::from_rotation_arc(Vec3::Y, unit_to_move.1.normal_direction().as_vec3());
+        let facing = game
+            .units
+            .get_unit_from_entity(unit_to_move.0.into())
+            .and_then(|unit| unit_facing(unit, &game.units, cube_side_length));
+        let (_, cell_transform, _) = query.get(cell_plane(&game, unit_to_move.1)).unwrap();
+        let target = unit_transform(
+            cell_transform.translation,
+            unit_to_move.1,
+            cube_side_length,
+            facing,
+        );
 
-        let Ok(transform_entity) = query.get_mut(unit_to_move.0) else {
+        let Ok((_, mut transform_entity, placed)) = query.get_mut(unit_to_move.0) else {
             success.push(false);
             return;
         };
-        let mut transform_entity = transform_entity.1;
-        transform_entity.translation = target_translation;
-        transform_entity.scale = Vec3::new(scale, scale / 2., scale);
-        transform_entity.rotation = rotation;
+        if placed.is_some() {
+            commands.entity(unit_to_move.0).insert(UnitMovement {
+                start: *transform_entity,
+                end: target,
+                elapsed: 0.,
+            });
+        } else {
+            *transform_entity = target;
+            commands.entity(unit_to_move.0).insert(PlacedUnit);
+        }
+        commands.entity(unit_to_move.0).insert(Facing(facing));
         success.push(true);
     }
+    let moved: Vec<Entity> = game
+        .entities_to_move
+        .iter()
+        .zip(&success)
+        .filter(|(_, &success)| success)
+        .map(|((entity, _), _)| *entity)
+        .collect();
     let mut index = 0;
     game.entities_to_move.retain(|_| {
         let out = !success[index];
         index += 1;
         out
     });
+    if moved.is_empty() {
+        return;
+    }
+
+    for unit in game.units.all_units_iter() {
+        let Some(entity) = unit.entity.map(Entity::from) else {
+            continue;
+        };
+        let facing = unit_facing(unit, &game.units, cube_side_length);
+        let turned = facing_query.get(entity).is_ok_and(|old| old.0 != facing);
+        if !turned || moved.contains(&entity) {
+            continue;
+        }
+        let Ok((_, transform, _)) = query.get(entity) else {
+            continue;
+        };
+        let (_, cell_transform, _) = query.get(cell_plane(&game, unit.coords)).unwrap();
+        let target = unit_transform(
+            cell_transform.translation,
+            unit.coords,
+            cube_side_length,
+            facing,
+        );
+        commands.entity(entity).insert((
+            Facing(facing),
+            UnitMovement {
+                start: *transform,
+                end: target,
+                elapsed: 0.,
+            },
+        ));
+    }
+}
+
+/// Slides units to their new cell over [`MOVE_DURATION`]. A unit crossing an edge arcs over it
+/// while it turns to stand on the other side.
+pub(crate) fn animate_unit_movement(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut UnitMovement, &mut Transform)>,
+) {
+    for (entity, mut movement, mut transform) in &mut query {
+        movement.elapsed += time.delta_seconds();
+        let progress = (movement.elapsed / MOVE_DURATION).min(1.);
+        // Starts and stops smoothly
+        let eased = progress * progress * (3. - 2. * progress);
+        let (start, end) = (movement.start, movement.end);
+
+        let mut translation = start.translation.lerp(end.translation, eased);
+        let (start_up, end_up) = (start.rotation * Vec3::Y, end.rotation * Vec3::Y);
+        if start_up.dot(end_up) < 0.99 {
+            let height = start.translation.distance(end.translation) * EDGE_ARC_HEIGHT;
+            translation += (start_up + end_up).normalize_or_zero() * height * (PI * eased).sin();
+        }
+        transform.translation = translation;
+        transform.rotation = start.rotation.slerp(end.rotation, eased);
+        transform.scale = end.scale;
+
+        if progress >= 1. {
+            commands.entity(entity).remove::<UnitMovement>();
+        }
+    }
+}
+
+/// How far the selected unit is raised off its cell, relative to the size of the unit
+const SELECTED_LIFT: f32 = 0.15;
+/// The glow of the selected unit
+const SELECTED_EMISSIVE: Color = Color::rgb(0.35, 0.3, 0.);
+
+/// The selected unit entity, raised by `offset` from where it stands
+#[derive(Component)]
+pub(crate) struct LiftedUnit {
+    offset: Vec3,
+}
+
+/// Raises the selected unit off its cell and makes it glow, which is easier to see than the color
+/// of its cell. The unit is put back once it isn't selected anymore.
+pub(crate) fn lift_selected_unit(
+    mut commands: Commands,
+    game: Res<Game>,
+    mut unit_query: Query<(&mut Transform, Option<&UnitMovement>)>,
+    lifted_query: Query<(Entity, &LiftedUnit)>,
+    child_query: Query<(&SceneChild, &Handle<StandardMaterial>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let selected = game
+        .selected_cell
+        .and_then(|coords| game.units.get_unit(coords))
+        .and_then(|unit| unit.entity)
+        .map(Entity::from);
+
+    let mut set_glow = |entity: Entity, emissive: Color| {
+        for (scene_child, material) in &child_query {
+            if scene_child.parent_entity != entity {
+                continue;
+            }
+            if let Some(material) = materials.get_mut(material) {
+                material.emissive = emissive;
+            }
+        }
+    };
+    for (entity, lifted) in &lifted_query {
+        if Some(entity) == selected {
+            continue;
+        }
+        // A unit moving away ends up on its new cell, so only a unit that stays is lowered
+        if let Ok((mut transform, None)) = unit_query.get_mut(entity) {
+            transform.translation -= lifted.offset;
+        }
+        commands.entity(entity).remove::<LiftedUnit>();
+        set_glow(entity, Color::BLACK);
+    }
+
+    let Some(entity) = selected.filter(|&entity| !lifted_query.contains(entity)) else {
+        return;
+    };
+    let Ok((mut transform, None)) = unit_query.get_mut(entity) else {
+        return;
+    };
+    let offset = transform.rotation * Vec3::Y * SELECTED_LIFT * transform.scale.x;
+    transform.translation += offset;
+    commands.entity(entity).insert(LiftedUnit { offset });
+    set_glow(entity, SELECTED_EMISSIVE);
 }
@@ -0,0 +1,639 @@
+//! The dedicated server, built as a second binary that runs online games without a window. It
+//! waits for two players to join, checks their moves with the rules of [`engine`] and passes them
+//! on, keeps the clocks, and saves the record of every game once it is over. Then it waits for the
+//! next two players. Anyone else who joins watches the game, and is sent every move and the clocks.
+//! A player who loses the connection loses the game unless they rejoin within [`REJOIN_TIME`].
+//! Once a game is over, the players can agree to a rematch with the colours swapped, instead of
+//! making room for the next two players.
+//!
+//! Only the standard and experience variants can be played on it, since the other variants need
+//! the parts of the game that run in Bevy.
+
+// The game uses more of the engine than the server does
+#[allow(dead_code)]
+mod engine;
+mod protocol;
+
+use std::fs;
+use std::io::ErrorKind;
+use std::mem;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::engine::cell::{Board, CellCoordinates};
+use crate::engine::cubefen::CubeFen;
+use crate::engine::movement::{self, GameMove, Variant};
+use crate::engine::notation;
+use crate::engine::pieces;
+use crate::engine::units::{Team, UnitType, Units};
+use crate::protocol::{Clocks, Connection, Greeting, Message, Newcomers, REJOIN_TIME};
+
+const USAGE: &str = "\
+Usage: server [options]
+
+Options:
+    --port <port>            The port the players join on, 7878 by default
+    --size <2-8>             Cells along each edge of the cube, 4 by default
+    --variant <name>         standard or experience
+    --position <cubefen>     Start from this position, which sets the size and variant too
+    --minutes <minutes>      The time each player has for all their moves, 10 by default
+    --records <folder>       Where the records of the games are saved, server-games by default
+    --help                   Print this";
+
+/// How often the server looks for new messages
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long the players have to agree to a rematch once a game is over
+const REMATCH_TIME: Duration = Duration::from_secs(60);
+
+struct Options {
+    port: u16,
+    position: CubeFen,
+    time: Duration,
+    records: PathBuf,
+}
+
+impl Options {
+    /// None if the usage should be printed instead
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut port = 7878;
+        let mut size = 4;
+        let mut variant = Variant::Standard;
+        let mut position = None;
+        let mut minutes = 10;
+        let mut records = PathBuf::from("server-games");
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--port" => port = parse_number(&value()?)?,
+                "--size" => size = parse_number(&value()?)?,
+                "--variant" => {
+                    let name = value()?;
+                    variant =
+                        Variant::from_name(&name).ok_or(format!("Unknown variant {}", name))?
+                }
+                "--position" => position = Some(value()?.parse::<CubeFen>()?),
+                "--minutes" => minutes = parse_number(&value()?)?,
+                "--records" => records = PathBuf::from(value()?),
+                "--help" | "-h" => return Ok(None),
+                _ => return Err(format!("Unknown option {}", arg)),
+            }
+        }
+        if !(2..=8).contains(&size) {
+            return Err(format!("The cube can't be {} cells wide", size));
+        }
+        let position = position.unwrap_or(CubeFen {
+            cube_side_length: size,
+            units: Units::game_starting_configuration(size),
+            turn: Team::White,
+            energy: [0; 2],
+            variant,
+        });
+        if !(2..=8).contains(&position.cube_side_length) {
+            return Err(format!(
+                "The cube of the position can't be {} cells wide",
+                position.cube_side_length
+            ));
+        }
+        if !matches!(position.variant, Variant::Standard | Variant::Experience) {
+            return Err(format!(
+                "The {} variant can't be played on the server",
+                position.variant.name()
+            ));
+        }
+        Ok(Some(Options {
+            port,
+            position,
+            time: Duration::from_secs(minutes * 60),
+            records,
+        }))
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} is not a number", value))
+}
+
+fn main() {
+    for err in pieces::load(&Path::new("assets").join(pieces::PIECES_FILE)) {
+        eprintln!("{}", err);
+    }
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    };
+    let listener = TcpListener::bind(("0.0.0.0", options.port)).and_then(|listener| {
+        listener.set_nonblocking(true)?;
+        Ok(listener)
+    });
+    let listener = match listener {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("Couldn't listen on port {}: {}", options.port, err);
+            std::process::exit(1);
+        }
+    };
+    println!("Listening on port {}", options.port);
+    let mut newcomers = Newcomers::default();
+    let (mut players, mut spectators) = wait_for_players(&listener, &mut newcomers);
+    loop {
+        let mut game = ServerGame::new(&options, players, spectators);
+        game.play(&listener, &mut newcomers);
+        game.save_record(&options.records);
+        (players, spectators) = match game.wait_for_rematch() {
+            Some(rematch) => rematch,
+            None => wait_for_players(&listener, &mut newcomers),
+        };
+    }
+}
+
+/// Accepts the connections that have arrived, and returns the newcomers that have said if they
+/// play or watch
+fn greet_newcomers(
+    listener: &TcpListener,
+    newcomers: &mut Newcomers,
+) -> Vec<(Connection, Greeting)> {
+    loop {
+        match listener
+            .accept()
+            .and_then(|(stream, _)| Connection::new(stream))
+        {
+            Ok(connection) => newcomers.add(connection),
+            Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+            Err(err) => {
+                eprintln!("Couldn't accept a connection: {}", err);
+                break;
+            }
+        }
+    }
+    newcomers
+        .greeted()
+        .into_iter()
+        .filter_map(|greeted| {
+            greeted
+                .map_err(|err| println!("Turned someone away: {}", err))
+                .ok()
+        })
+        .collect()
+}
+
+/// The connections of white and black, and of those who want to watch their game. The first
+/// player to join plays white.
+fn wait_for_players(
+    listener: &TcpListener,
+    newcomers: &mut Newcomers,
+) -> ([Option<Connection>; 2], Vec<Connection>) {
+    let mut players = Vec::new();
+    let mut spectators = Vec::new();
+    while players.len() < 2 {
+        for (connection, greeting) in greet_newcomers(listener, newcomers) {
+            match greeting {
+                Greeting::Play if players.len() < 2 => {
+                    println!("{:?} has joined", [Team::White, Team::Black][players.len()]);
+                    players.push(connection);
+                }
+                Greeting::Play | Greeting::Rejoin { .. } => refuse_player(connection),
+                Greeting::Watch => spectators.push(connection),
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    let black = players.pop();
+    let white = players.pop();
+    ([white, black], spectators)
+}
+
+fn refuse_player(mut connection: Connection) {
+    let reason = "A game is being played, try again once it is over".to_string();
+    let _ = connection.send(&Message::Refused { reason });
+}
+
+enum GameResult {
+    Win(Team),
+    Draw,
+}
+
+struct ServerGame {
+    board: Board,
+    units: Units,
+    turn: Team,
+    variant: Variant,
+    starting_position: String,
+    /// The connections of white and black, indexed by [`Team::index`]
+    players: [Option<Connection>; 2],
+    /// Let the players rejoin, see [`Message::Rejoin`]
+    keys: [u64; 2],
+    /// When each player lost the connection, if they haven't rejoined yet
+    connection_lost: [Option<Instant>; 2],
+    spectators: Vec<Connection>,
+    clocks: Clocks,
+    /// The team that has offered a draw that hasn't been answered
+    draw_offer: Option<Team>,
+    /// Every move in notation, in the order they were made
+    moves: Vec<(Team, String)>,
+    result: Option<GameResult>,
+}
+
+impl ServerGame {
+    fn new(
+        options: &Options,
+        players: [Option<Connection>; 2],
+        spectators: Vec<Connection>,
+    ) -> Self {
+        let position = &options.position;
+        ServerGame {
+            board: Board::new(position.cube_side_length),
+            units: position.units.clone(),
+            turn: position.turn,
+            variant: position.variant,
+            starting_position: position.to_string(),
+            players,
+            keys: [rand::random(), rand::random()],
+            connection_lost: [None; 2],
+            spectators,
+            clocks: Clocks::new(options.time),
+            draw_offer: None,
+            moves: Vec::new(),
+            result: None,
+        }
+    }
+
+    fn play(&mut self, listener: &TcpListener, newcomers: &mut Newcomers) {
+        for team in [Team::White, Team::Black] {
+            let welcome = Message::Welcome {
+                team,
+                position: self.to_cubefen(),
+                key: self.keys[team.index()],
+            };
+            self.send(team, &welcome);
+        }
+        self.clocks.restart();
+        let clock = self.clock_message();
+        self.send(Team::White, &clock);
+        self.send(Team::Black, &clock);
+        for spectator in mem::take(&mut self.spectators) {
+            self.add_spectator(spectator);
+        }
+        while self.result.is_none() {
+            for team in [Team::White, Team::Black] {
+                self.receive(team);
+            }
+            self.serve_spectators();
+            for (connection, greeting) in greet_newcomers(listener, newcomers) {
+                match greeting {
+                    Greeting::Play => refuse_player(connection),
+                    Greeting::Watch => self.add_spectator(connection),
+                    Greeting::Rejoin { team, key } => self.rejoin(team, key, connection),
+                }
+            }
+            for team in [Team::White, Team::Black] {
+                let lost = self.connection_lost[team.index()];
+                if matches!(lost, Some(lost) if lost.elapsed() >= REJOIN_TIME) {
+                    println!("{:?} didn't rejoin", team);
+                    self.connection_lost[team.index()] = None;
+                    self.send(team.opposite(), &Message::Resign);
+                    self.end(GameResult::Win(team.opposite()));
+                }
+            }
+            if self.clocks.has_flagged(self.turn) {
+                let team = self.turn;
+                println!("{:?} ran out of time", team);
+                self.send_to_all(&Message::Flagged { team });
+                self.result = Some(GameResult::Win(team.opposite()));
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Waits for the players to agree to a rematch, and returns their connections with the colours
+    /// swapped, and the spectators. None if either player leaves or the time is up.
+    fn wait_for_rematch(mut self) -> Option<([Option<Connection>; 2], Vec<Connection>)> {
+        let started = Instant::now();
+        let mut offer = None;
+        while started.elapsed() < REMATCH_TIME {
+            for team in [Team::White, Team::Black] {
+                let messages = self.players[team.index()].as_mut()?.receive().ok()?;
+                for message in messages.into_iter().flatten() {
+                    match message {
+                        Message::OfferRematch if offer.is_none() => {
+                            offer = Some(team);
+                            self.send(team.opposite(), &message);
+                        }
+                        Message::AcceptRematch if offer == Some(team.opposite()) => {
+                            println!("The players have agreed to a rematch");
+                            self.send(team.opposite(), &message);
+                            let [white, black] = self.players;
+                            return Some(([black, white], self.spectators));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        None
+    }
+
+    /// Gives a player who has lost the connection their place back, and sends them the game so far
+    fn rejoin(&mut self, team: Team, key: u64, mut connection: Connection) {
+        let can_rejoin = matches!(team, Team::White | Team::Black)
+            && self.connection_lost[team.index()].is_some()
+            && key == self.keys[team.index()];
+        if !can_rejoin {
+            let reason = "The game can't be rejoined".to_string();
+            let _ = connection.send(&Message::Refused { reason });
+            return;
+        }
+        let record = self.record();
+        let sent = connection
+            .send(&Message::Rejoined { record })
+            .and_then(|()| connection.send(&self.clock_message()));
+        if sent.is_ok() {
+            println!("{:?} has rejoined", team);
+            self.players[team.index()] = Some(connection);
+            self.connection_lost[team.index()] = None;
+        }
+    }
+
+    /// Sends the game so far to a new spectator
+    fn add_spectator(&mut self, mut spectator: Connection) {
+        let record = self.record();
+        let sent = spectator
+            .send(&Message::Watching { record })
+            .and_then(|()| spectator.send(&self.clock_message()));
+        if sent.is_ok() {
+            println!("A spectator has joined");
+            self.spectators.push(spectator);
+        }
+    }
+
+    fn serve_spectators(&mut self) {
+        let position = self.to_cubefen();
+        protocol::serve_spectators(&mut self.spectators, || position.clone());
+    }
+
+    fn receive(&mut self, team: Team) {
+        let Some(connection) = &mut self.players[team.index()] else {
+            return;
+        };
+        let messages = match connection.receive() {
+            Ok(messages) => messages,
+            Err(_) => {
+                println!("{:?} lost the connection", team);
+                self.players[team.index()] = None;
+                self.connection_lost[team.index()] = Some(Instant::now());
+                return;
+            }
+        };
+        for message in messages {
+            match message {
+                Ok(message) => self.handle_message(team, message),
+                Err(err) => eprintln!("{:?} sent a bad message: {}", team, err),
+            }
+            if self.result.is_some() {
+                return;
+            }
+        }
+    }
+
+    fn handle_message(&mut self, team: Team, message: Message) {
+        match message {
+            Message::Move {
+                from,
+                to,
+                promotion,
+                clocks,
+            } => {
+                if !self.make_move(team, &from, &to, promotion.as_deref(), clocks) {
+                    println!("{:?} made an illegal move from {} to {}", team, from, to);
+                    let position = self.to_cubefen();
+                    self.send(team, &Message::Resync { position });
+                    return;
+                }
+                // The clocks of the server replace the clocks of the player
+                let message = Message::Move {
+                    from,
+                    to,
+                    promotion,
+                    clocks: Some(self.clocks.stamp(self.turn)),
+                };
+                self.send(team.opposite(), &message);
+                self.send_to_spectators(&message);
+                // Moving declines the draw offered by the other player
+                self.draw_offer = None;
+                self.check_game_over();
+            }
+            Message::Summon { .. } => {
+                let position = self.to_cubefen();
+                self.send(team, &Message::Resync { position });
+            }
+            Message::Resign => {
+                self.send(team.opposite(), &message);
+                self.end(GameResult::Win(team.opposite()));
+            }
+            Message::OfferDraw => {
+                self.draw_offer = Some(team);
+                self.send(team.opposite(), &message);
+            }
+            Message::AcceptDraw => {
+                if self.draw_offer == Some(team.opposite()) {
+                    self.send(team.opposite(), &message);
+                    self.end(GameResult::Draw);
+                }
+            }
+            Message::RequestResync => {
+                let position = self.to_cubefen();
+                self.send(team, &Message::Resync { position });
+            }
+            // Only newcomers and the server send these
+            Message::Join { .. }
+            | Message::Spectate { .. }
+            | Message::Rejoin { .. }
+            | Message::Welcome { .. }
+            | Message::Watching { .. }
+            | Message::Rejoined { .. }
+            | Message::Refused { .. }
+            | Message::Resync { .. }
+            | Message::Flagged { .. }
+            | Message::Clock { .. }
+            | Message::Ended { .. } => {}
+            // The game isn't over yet
+            Message::OfferRematch | Message::AcceptRematch => {}
+        }
+    }
+
+    /// Makes the move if it is legal, and starts the clock of the other team. The clocks are the
+    /// ones the player stamped on the move. A pawn that is promoted without a promotion becomes a
+    /// queen.
+    fn make_move(
+        &mut self,
+        team: Team,
+        from: &str,
+        to: &str,
+        promotion: Option<&str>,
+        clocks: Option<[u64; 2]>,
+    ) -> bool {
+        let on_board = |cell: &str| {
+            CellCoordinates::from_display(cell)
+                .ok()
+                .filter(|coords| (0..3).all(|axis| coords[axis] <= self.board.cube_side_length))
+        };
+        let (Some(from), Some(to)) = (on_board(from), on_board(to)) else {
+            return false;
+        };
+        let game_move = GameMove { from, to };
+        if team != self.turn
+            || !movement::is_legal(
+                game_move,
+                &self.board,
+                &self.units,
+                team,
+                self.variant.rules(),
+            )
+        {
+            return false;
+        }
+        let home_faces = movement::home_faces(self.board.cube_side_length);
+        let promotion = match promotion {
+            _ if !movement::promotes(game_move, &self.units, self.variant, home_faces) => None,
+            Some(symbol) => match notation::promotion_from_symbol(symbol) {
+                Some(unit_type) => Some(unit_type),
+                None => return false,
+            },
+            None => Some(UnitType::Queen),
+        };
+        let notation =
+            notation::write_promoting_move(game_move, promotion, &self.board, &self.units);
+        movement::apply_move(game_move, &mut self.units, self.variant.rules());
+        if let (Some(unit_type), Some(unit)) = (promotion, self.units.get_unit_mut(game_move.to)) {
+            unit.unit_type = unit_type;
+        }
+        self.moves.push((team, notation));
+
+        self.clocks.end_turn(team, clocks);
+        self.turn = team.opposite();
+        true
+    }
+
+    /// Ends the game in a way the spectators can't see in the moves
+    fn end(&mut self, result: GameResult) {
+        let winner = match result {
+            GameResult::Win(team) => Some(team),
+            GameResult::Draw => None,
+        };
+        self.send_to_spectators(&Message::Ended { winner });
+        self.result = Some(result);
+    }
+
+    /// The time left on both clocks right now
+    fn clock_message(&self) -> Message {
+        Message::Clock {
+            remaining: self.clocks.stamp(self.turn),
+        }
+    }
+
+    /// The same checks as the game makes, so the server and the players agree on when it is over
+    fn check_game_over(&mut self) {
+        self.result = if let Some(winner) = self.variant.rules().winner(&self.units) {
+            Some(GameResult::Win(winner))
+        } else if self
+            .units
+            .all_units_iter()
+            .filter(|unit| unit.team == self.turn)
+            .all(|unit| {
+                movement::get_legal_moves(unit, &self.board, &self.units, self.variant.rules())
+                    .is_empty()
+            })
+        {
+            Some(GameResult::Draw)
+        } else {
+            None
+        };
+    }
+
+    fn to_cubefen(&self) -> String {
+        CubeFen {
+            cube_side_length: self.board.cube_side_length,
+            units: self.units.clone(),
+            turn: self.turn,
+            energy: [0; 2],
+            variant: self.variant,
+        }
+        .to_string()
+    }
+
+    fn send(&mut self, team: Team, message: &Message) {
+        let Some(connection) = &mut self.players[team.index()] else {
+            return;
+        };
+        if let Err(err) = connection.send(message) {
+            eprintln!("Couldn't send {:?} to {:?}: {}", message, team, err);
+        }
+    }
+
+    fn send_to_spectators(&mut self, message: &Message) {
+        protocol::send_to_spectators(&mut self.spectators, message);
+    }
+
+    fn send_to_all(&mut self, message: &Message) {
+        self.send(Team::White, message);
+        self.send(Team::Black, message);
+        self.send_to_spectators(message);
+    }
+
+    /// Written in the same format as the games saved by the game, so they can be loaded there
+    fn record(&self) -> String {
+        let result = match self.result {
+            Some(GameResult::Win(Team::White)) => "1-0",
+            Some(GameResult::Win(_)) => "0-1",
+            Some(GameResult::Draw) => "1/2-1/2",
+            None => "*",
+        };
+        let tags = [
+            ("Event", "Chess on a cube server"),
+            ("White", "Player 1"),
+            ("Black", "Player 2"),
+            ("Result", result),
+            ("CubeSize", &self.board.cube_side_length.to_string()),
+            ("Variant", self.variant.name()),
+            ("CubeFEN", &self.starting_position),
+        ]
+        .map(|(name, value)| format!("[{} \"{}\"]\n", name, value));
+        let mut movetext = String::new();
+        let mut number = 0;
+        for (index, (team, notation)) in self.moves.iter().enumerate() {
+            if *team == Team::White || index == 0 {
+                number += 1;
+                let dots = if *team == Team::White { "." } else { "..." };
+                movetext.push_str(&format!("{}{} ", number, dots));
+            }
+            movetext.push_str(notation);
+            movetext.push(' ');
+        }
+        format!("{}\n{}{}\n", tags.concat(), movetext, result)
+    }
+
+    fn save_record(&self, folder: &Path) {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = folder.join(format!("game-{}.pgn", seconds));
+        let written = fs::create_dir_all(folder).and_then(|()| fs::write(&path, self.record()));
+        match written {
+            Ok(()) => println!("Saved the game to {}", path.display()),
+            Err(err) => eprintln!("Couldn't write {}: {}", path.display(), err),
+        }
+    }
+}
@@ -0,0 +1,450 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::board_theme::BoardTheme;
+use crate::cube_rotation::ROTATION_DURATION;
+use crate::engine::units::Team;
+use crate::gamemanager::Palette;
+use crate::piece_sets;
+use crate::team_looks::{CustomTeamLook, TeamLook, TeamMaterial};
+
+const SETTINGS_PATH: &str = "settings.ron";
+
+/// Written on the first line of every saved file, followed by the version and the checksum
+const SAVE_HEADER: &str = "// chess-on-a-cube save";
+/// The version of the saved files written by this build. Files from before the header was added
+/// count as version 0.
+const SAVE_VERSION: u32 = 1;
+
+/// The folder in the config dir that the files of the game are saved in
+const CONFIG_FOLDER: &str = "chess-on-a-cube";
+
+#[derive(Resource, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Settings {
+    /// The onboarding is only shown on the first launch
+    pub(crate) seen_onboarding: bool,
+    /// Used by presets that don't pick their own palette
+    pub(crate) palette: Palette,
+    /// The textures of the cells, on top of the colors of the palette
+    pub(crate) board_theme: BoardTheme,
+    /// The name of the set of models for the units, see [`crate::piece_sets`]
+    pub(crate) piece_set: String,
+    /// The colors and materials of the units of the two teams
+    pub(crate) team_look: TeamLook,
+    /// Used when the team look is [`TeamLook::Custom`]
+    pub(crate) custom_team_look: CustomTeamLook,
+    /// Seconds a quarter turn of the cube takes
+    pub(crate) rotation_duration: f32,
+    /// If the camera is dragged freely around the cube with the right mouse button instead of
+    /// turning a quarter turn at a time, see [`crate::cube_rotation::orbit`]
+    pub(crate) orbit_camera: bool,
+    /// If the cube turns to show a move of the AI on a face that can't be seen
+    pub(crate) reveal_ai_moves: bool,
+    /// If the cube turns to the king of the player to move after every move in hot-seat games,
+    /// instead of going back to the view that player had, see
+    /// [`crate::cube_rotation::face_king_on_turn_change`]
+    pub(crate) hot_seat_face_king: bool,
+    /// Degrees between the face of a clicked cell and the camera beyond which the click turns the
+    /// cube toward the face instead of selecting the cell
+    pub(crate) far_face_angle: f32,
+    /// How many moves ahead the AI searches, in presets that don't pick their own difficulty
+    pub(crate) ai_depth: u32,
+    /// The volume of the background music, see [`crate::music`]
+    pub(crate) music_volume: f32,
+    /// The volume of the sound effects, of which there are none yet
+    #[serde(alias = "volume")]
+    pub(crate) effects_volume: f32,
+    pub(crate) key_bindings: KeyBindings,
+    /// If the cells the other team attacks are shaded and the hanging units of the player framed,
+    /// see [`crate::threat_overlay`]
+    pub(crate) show_threats: bool,
+    /// If the notation of every cell is written on it, see [`crate::coordinate_labels`]
+    pub(crate) show_coordinates: bool,
+    /// If the cube slowly turns by itself when nothing has been pressed for a while
+    pub(crate) idle_rotation: bool,
+    /// Seconds without input before the cube starts turning by itself
+    pub(crate) idle_rotation_delay: f32,
+    /// The points needed to win a match
+    pub(crate) match_points: u32,
+    /// Makes every game play out the same, for debugging. A new seed is picked for every game if
+    /// this is None.
+    pub(crate) seed: Option<u64>,
+    /// The port online games are hosted on, and joined on if the address doesn't have one
+    pub(crate) online_port: u16,
+    /// The time each player has for all their moves in hosted online games, which are untimed if
+    /// this is None
+    pub(crate) online_minutes: Option<u64>,
+    /// The command that starts an external engine, which plays instead of the built-in AI. See
+    /// [`crate::external_engine`].
+    pub(crate) engine: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            seen_onboarding: false,
+            palette: Palette::Pinkish,
+            board_theme: BoardTheme::Flat,
+            piece_set: piece_sets::CLASSIC.to_string(),
+            team_look: TeamLook::Classic,
+            custom_team_look: CustomTeamLook::default(),
+            rotation_duration: ROTATION_DURATION,
+            orbit_camera: false,
+            reveal_ai_moves: true,
+            hot_seat_face_king: false,
+            far_face_angle: 75.,
+            ai_depth: 3,
+            music_volume: 0.5,
+            effects_volume: 1.,
+            key_bindings: KeyBindings::default(),
+            show_threats: false,
+            show_coordinates: false,
+            idle_rotation: true,
+            idle_rotation_delay: 30.,
+            match_points: 3,
+            seed: None,
+            online_port: 7878,
+            online_minutes: Some(10),
+            engine: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct KeyBindings {
+    pub(crate) rotate_left: KeyCode,
+    pub(crate) rotate_right: KeyCode,
+    pub(crate) rotate_up: KeyCode,
+    pub(crate) rotate_down: KeyCode,
+    /// Turns the camera a quarter turn clockwise around the face in front of it
+    pub(crate) turn_camera: KeyCode,
+    /// Held while turning the camera to turn it counterclockwise instead
+    pub(crate) reverse_camera_turn: KeyCode,
+    pub(crate) pause: KeyCode,
+    pub(crate) new_game: KeyCode,
+    /// Pressed together with Ctrl
+    pub(crate) undo: KeyCode,
+    /// Pressed together with Ctrl
+    pub(crate) redo: KeyCode,
+    /// Turns the practice overlay on and off, see [`crate::threat_overlay`]
+    pub(crate) threat_overlay: KeyCode,
+    /// Turns the cube to the king of the player, see [`crate::cube_rotation::face_king`]
+    pub(crate) face_king: KeyCode,
+    /// Move the cell cursor of [`crate::cell_cursor`], as do h, j, k and l
+    pub(crate) cursor_up: KeyCode,
+    pub(crate) cursor_down: KeyCode,
+    pub(crate) cursor_left: KeyCode,
+    pub(crate) cursor_right: KeyCode,
+    /// Opens the prompt of [`crate::move_prompt`] to type a move
+    pub(crate) type_move: KeyCode,
+    /// Held to see through the cube, see [`crate::peek`]
+    pub(crate) peek: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            rotate_left: KeyCode::Left,
+            rotate_right: KeyCode::Right,
+            rotate_up: KeyCode::Up,
+            rotate_down: KeyCode::Down,
+            turn_camera: KeyCode::Space,
+            reverse_camera_turn: KeyCode::LShift,
+            pause: KeyCode::P,
+            new_game: KeyCode::N,
+            undo: KeyCode::Z,
+            redo: KeyCode::Y,
+            threat_overlay: KeyCode::G,
+            // K moves the cell cursor
+            face_king: KeyCode::F,
+            cursor_up: KeyCode::W,
+            cursor_down: KeyCode::S,
+            cursor_left: KeyCode::A,
+            cursor_right: KeyCode::D,
+            type_move: KeyCode::Slash,
+            peek: KeyCode::Tab,
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Every action with its key, named like in the settings file
+    fn actions(&self) -> [(&'static str, KeyCode); 22] {
+        [
+            ("rotate_left", self.rotate_left),
+            ("rotate_right", self.rotate_right),
+            ("rotate_up", self.rotate_up),
+            ("rotate_down", self.rotate_down),
+            ("turn_camera", self.turn_camera),
+            ("reverse_camera_turn", self.reverse_camera_turn),
+            ("pause", self.pause),
+            ("new_game", self.new_game),
+            ("undo", self.undo),
+            ("redo", self.redo),
+            ("threat_overlay", self.threat_overlay),
+            ("face_king", self.face_king),
+            ("cursor_up", self.cursor_up),
+            ("cursor_down", self.cursor_down),
+            ("cursor_left", self.cursor_left),
+            ("cursor_right", self.cursor_right),
+            ("type_move", self.type_move),
+            ("peek", self.peek),
+            // The cell cursor always moves with these too
+            ("h of the cell cursor", KeyCode::H),
+            ("j of the cell cursor", KeyCode::J),
+            ("k of the cell cursor", KeyCode::K),
+            ("l of the cell cursor", KeyCode::L),
+        ]
+    }
+
+    /// The pairs of actions that are bound to the same key
+    fn duplicates(&self) -> Vec<String> {
+        let actions = self.actions();
+        let mut duplicates = Vec::new();
+        for (i, (name, key)) in actions.iter().enumerate() {
+            for (other_name, other_key) in &actions[i + 1..] {
+                if key == other_key {
+                    duplicates.push(format!("{} and {} are both {:?}", name, other_name, key));
+                }
+            }
+        }
+        duplicates
+    }
+}
+
+impl Settings {
+    /// Key bindings that bind two actions to the same key are refused, and the default bindings
+    /// are used instead
+    pub(crate) fn load(load_errors: &mut LoadErrors) -> Self {
+        let mut settings: Settings = load_ron(SETTINGS_PATH, load_errors).unwrap_or_default();
+        let duplicates = settings.key_bindings.duplicates();
+        if !duplicates.is_empty() {
+            let err = format!(
+                "The key bindings in {} weren't used, since {}",
+                SETTINGS_PATH,
+                duplicates.join(", ")
+            );
+            warn!("{}", err);
+            load_errors.0.push(err);
+            settings.key_bindings = KeyBindings::default();
+        }
+        settings
+    }
+
+    pub(crate) fn save(&self) {
+        save_ron(SETTINGS_PATH, self);
+    }
+
+    /// The material of the units of the team, in the look that is picked
+    pub(crate) fn team_material(&self, team: Team) -> TeamMaterial {
+        self.team_look.material(team, &self.custom_team_look)
+    }
+}
+
+/// Saves the settings whenever they are changed
+pub(crate) fn save_settings(settings: Res<Settings>) {
+    if settings.is_changed() && !settings.is_added() {
+        settings.save();
+    }
+}
+
+/// `$XDG_CONFIG_HOME` or `~/.config` on Linux, `~/Library/Application Support` on macOS and
+/// `%APPDATA%` on Windows. Falls back to the working directory if none of them are set.
+pub(crate) fn config_dir() -> PathBuf {
+    let home = || env::var_os("HOME").map(PathBuf::from);
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home().map(|home| home.join(".config")))
+    };
+    match base {
+        Some(base) => base.join(CONFIG_FOLDER),
+        None => PathBuf::new(),
+    }
+}
+
+/// Saved files that couldn't be read at startup, shown to the player in a dialog
+#[derive(Resource, Debug, Default)]
+pub(crate) struct LoadErrors(pub(crate) Vec<String>);
+
+/// Reads `file_name` in the config dir. Returns Ok(None) if the file doesn't exist.
+///
+/// A file that is corrupted or from a newer version of the game is moved aside to
+/// `<file_name>.corrupted` before returning the error, so that it isn't overwritten by the defaults
+/// and can still be recovered.
+pub(crate) fn read_ron<T: DeserializeOwned>(file_name: &str) -> Result<Option<T>, String> {
+    let path = config_dir().join(file_name);
+    let Ok(text) = fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+    // Files from before the header was added are read like the current version, since the header
+    // is all that changed. When the format of a file changes, older versions get converted here.
+    let result = check_header(&text)
+        .and_then(|body| ron::from_str(body).map_err(|err| format!("it is corrupted ({})", err)));
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(err) => {
+            let backup = config_dir().join(format!("{}.corrupted", file_name));
+            let moved = match fs::rename(&path, &backup) {
+                Ok(()) => format!(", it was moved to {}", backup.display()),
+                Err(_) => String::new(),
+            };
+            Err(format!(
+                "Couldn't read {}: {}{}",
+                path.display(),
+                err,
+                moved
+            ))
+        }
+    }
+}
+
+/// Like [`read_ron`], but keeps the error to be shown to the player
+pub(crate) fn load_ron<T: DeserializeOwned>(
+    file_name: &str,
+    load_errors: &mut LoadErrors,
+) -> Option<T> {
+    match read_ron(file_name) {
+        Ok(value) => value,
+        Err(err) => {
+            warn!("{}", err);
+            load_errors.0.push(err);
+            None
+        }
+    }
+}
+
+/// Returns the text after the header, checking that the file isn't from a newer version and that
+/// the checksum in the header matches
+fn check_header(text: &str) -> Result<&str, String> {
+    let Some(header) = text
+        .lines()
+        .next()
+        .filter(|line| line.starts_with(SAVE_HEADER))
+    else {
+        return Ok(text);
+    };
+    let body = text[header.len()..].trim_start_matches(['\r', '\n']);
+    let mut version = None;
+    let mut checksum = None;
+    for field in header[SAVE_HEADER.len()..].split(',').map(str::trim) {
+        if let Some(value) = field.strip_prefix("version ") {
+            version = value.parse::<u32>().ok();
+        } else if let Some(value) = field.strip_prefix("checksum ") {
+            checksum = u64::from_str_radix(value, 16).ok();
+        }
+    }
+    let (Some(version), Some(checksum)) = (version, checksum) else {
+        return Err("its header is corrupted".to_string());
+    };
+    if version > SAVE_VERSION {
+        return Err(format!(
+            "it was saved by a newer version of the game (file version {}, this version reads up \
+             to {})",
+            version, SAVE_VERSION
+        ));
+    }
+    if checksum != fnv1a(body.as_bytes()) {
+        return Err("it is corrupted (the checksum doesn't match)".to_string());
+    }
+    Ok(body)
+}
+
+/// 64 bit FNV-1a, which stays the same between builds unlike the hasher of the standard library
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// The body with the header read by [`check_header`] in front of it
+fn with_header(body: &str, version: u32) -> String {
+    format!(
+        "{}, version {}, checksum {:016x}\n{}",
+        SAVE_HEADER,
+        version,
+        fnv1a(body.as_bytes()),
+        body
+    )
+}
+
+/// Writes `file_name` in the config dir, creating the dir if needed
+pub(crate) fn save_ron<T: Serialize>(file_name: &str, value: &T) {
+    let path = config_dir().join(file_name);
+    let body = match ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()) {
+        Ok(body) => body,
+        Err(err) => {
+            warn!("Couldn't serialize {}: {}", path.display(), err);
+            return;
+        }
+    };
+    let text = with_header(&body, SAVE_VERSION);
+    if let Some(dir) = path.parent() {
+        if let Err(err) = fs::create_dir_all(dir) {
+            warn!("Couldn't create {}: {}", dir.display(), err);
+            return;
+        }
+    }
+    if let Err(err) = fs::write(&path, text) {
+        warn!("Couldn't write {}: {}", path.display(), err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_key_bindings_are_all_different() {
+        assert_eq!(KeyBindings::default().duplicates(), Vec::<String>::new());
+        let key_bindings = KeyBindings {
+            cursor_left: KeyCode::A,
+            reverse_camera_turn: KeyCode::A,
+            ..default()
+        };
+        assert_eq!(
+            key_bindings.duplicates(),
+            ["reverse_camera_turn and cursor_left are both A"]
+        );
+    }
+
+    #[test]
+    fn saved_files_are_checked() {
+        let body = "(music_volume: 0.5)";
+        assert_eq!(check_header(&with_header(body, SAVE_VERSION)), Ok(body));
+        // Files from before the header are read as they are
+        assert_eq!(check_header(body), Ok(body));
+
+        let changed = with_header(body, SAVE_VERSION).replace("0.5", "0.6");
+        let err = check_header(&changed).unwrap_err();
+        assert!(err.contains("checksum"), "{}", err);
+
+        let newer = with_header(body, SAVE_VERSION + 1);
+        let err = check_header(&newer).unwrap_err();
+        assert!(err.contains("newer version"), "{}", err);
+
+        let missing_checksum = format!("{}, version {}\n{}", SAVE_HEADER, SAVE_VERSION, body);
+        assert!(check_header(&missing_checksum).is_err());
+    }
+
+    #[test]
+    fn fnv1a_matches_the_reference_values() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+    }
+}
@@ -0,0 +1,267 @@
+use bevy::prelude::*;
+
+use crate::board_theme::BoardTheme;
+use crate::game_setup::{cycle, AI_DEPTHS};
+use crate::gamemanager::{Game, Palette};
+use crate::network::JoinPrompt;
+use crate::piece_sets::PieceSets;
+use crate::settings::Settings;
+use crate::team_looks::TeamLook;
+use crate::ui::{self, MenuRoot};
+use crate::AppState;
+
+const PALETTES: [Palette; 2] = [Palette::Filippa, Palette::Pinkish];
+
+/// Seconds a quarter turn of the cube takes, from fastest to slowest
+const ROTATION_DURATIONS: [f32; 6] = [0.25, 0.5, 0.75, 1., 1.5, 2.];
+
+const VOLUMES: [f32; 11] = [0., 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 1.];
+
+/// The settings on the settings menu, in the order they are shown
+#[derive(Clone, Copy, PartialEq)]
+enum SettingsRow {
+    Palette,
+    BoardTheme,
+    PieceSet,
+    TeamLook,
+    Camera,
+    RevealAiMoves,
+    HotSeatView,
+    RotationSpeed,
+    AiDepth,
+    Threats,
+    Coordinates,
+    MusicVolume,
+    EffectsVolume,
+}
+
+impl SettingsRow {
+    const ALL: [SettingsRow; 13] = [
+        SettingsRow::Palette,
+        SettingsRow::BoardTheme,
+        SettingsRow::PieceSet,
+        SettingsRow::TeamLook,
+        SettingsRow::Camera,
+        SettingsRow::RevealAiMoves,
+        SettingsRow::HotSeatView,
+        SettingsRow::RotationSpeed,
+        SettingsRow::AiDepth,
+        SettingsRow::Threats,
+        SettingsRow::Coordinates,
+        SettingsRow::MusicVolume,
+        SettingsRow::EffectsVolume,
+    ];
+}
+
+/// Exists only while the settings menu is open, which also keeps a game from starting when the
+/// main menu is left for it
+#[derive(Resource)]
+pub(crate) struct SettingsMenu {
+    row: usize,
+    /// The main menu or the pause menu, where the settings menu was opened from
+    return_to: AppState,
+}
+
+/// Opens the settings menu, which goes back to `return_to` when it is closed
+pub(crate) fn open_settings_menu(
+    commands: &mut Commands,
+    next_state: &mut NextState<AppState>,
+    return_to: AppState,
+) {
+    commands.insert_resource(SettingsMenu { row: 0, return_to });
+    next_state.set(AppState::Settings);
+}
+
+/// Changes the setting on `row` to the next or the previous choice. The settings are saved by
+/// [`crate::settings::save_settings`] once they are changed.
+fn change(
+    row: SettingsRow,
+    settings: &mut Settings,
+    game: &mut Game,
+    piece_sets: &PieceSets,
+    forward: bool,
+) {
+    match row {
+        SettingsRow::Palette => {
+            settings.palette = cycle(&PALETTES, settings.palette, forward);
+            // Recolors the cube of the current game right away
+            game.palette = settings.palette;
+        }
+        SettingsRow::BoardTheme => {
+            settings.board_theme = cycle(&BoardTheme::ALL, settings.board_theme, forward);
+        }
+        SettingsRow::PieceSet => {
+            // The units on the board get the new models right away, see
+            // [`crate::piece_sets::respawn_units_on_set_change`]
+            let set = cycle(&piece_sets.names(), &settings.piece_set, forward).to_string();
+            settings.piece_set = set;
+        }
+        SettingsRow::TeamLook => {
+            // See [`crate::team_looks::recolor_units_on_look_change`]
+            settings.team_look = cycle(&TeamLook::ALL, settings.team_look, forward);
+        }
+        SettingsRow::Camera => settings.orbit_camera = !settings.orbit_camera,
+        SettingsRow::RevealAiMoves => settings.reveal_ai_moves = !settings.reveal_ai_moves,
+        SettingsRow::HotSeatView => settings.hot_seat_face_king = !settings.hot_seat_face_king,
+        SettingsRow::RotationSpeed => {
+            // Faster is a shorter quarter turn
+            settings.rotation_duration =
+                cycle(&ROTATION_DURATIONS, settings.rotation_duration, !forward);
+        }
+        SettingsRow::AiDepth => settings.ai_depth = cycle(&AI_DEPTHS, settings.ai_depth, forward),
+        SettingsRow::Threats => settings.show_threats = !settings.show_threats,
+        SettingsRow::Coordinates => settings.show_coordinates = !settings.show_coordinates,
+        SettingsRow::MusicVolume => {
+            settings.music_volume = cycle(&VOLUMES, settings.music_volume, forward)
+        }
+        SettingsRow::EffectsVolume => {
+            settings.effects_volume = cycle(&VOLUMES, settings.effects_volume, forward)
+        }
+    }
+}
+
+fn display_row(row: SettingsRow, settings: &Settings) -> String {
+    match row {
+        SettingsRow::Palette => format!("Palette: {:?}", settings.palette),
+        SettingsRow::BoardTheme => format!("Board: {:?}", settings.board_theme),
+        SettingsRow::PieceSet => format!("Pieces: {}", settings.piece_set),
+        SettingsRow::TeamLook => match settings.team_look {
+            TeamLook::Custom => "Unit colors: Custom, from the settings file".to_string(),
+            look => format!("Unit colors: {:?}", look),
+        },
+        SettingsRow::Camera => match settings.orbit_camera {
+            true => "Camera: free, drag it with the right mouse button".to_string(),
+            false => "Camera: quarter turns with the arrow keys".to_string(),
+        },
+        SettingsRow::RevealAiMoves => format!(
+            "Turn to moves of the AI that can't be seen: {}",
+            if settings.reveal_ai_moves {
+                "on"
+            } else {
+                "off"
+            }
+        ),
+        SettingsRow::HotSeatView => match settings.hot_seat_face_king {
+            true => "Two players on one computer: turn to the king of the player to move",
+            false => "Two players on one computer: each player keeps their own view",
+        }
+        .to_string(),
+        SettingsRow::RotationSpeed => format!(
+            "Rotation speed: {}s a quarter turn",
+            settings.rotation_duration
+        ),
+        SettingsRow::AiDepth => format!(
+            "AI depth: {} (from the next game, in presets that don't pick one)",
+            settings.ai_depth
+        ),
+        SettingsRow::Threats => format!(
+            "Show attacked cells and hanging units: {} (or press {:?} while playing)",
+            if settings.show_threats { "on" } else { "off" },
+            settings.key_bindings.threat_overlay
+        ),
+        SettingsRow::Coordinates => format!(
+            "Write the coordinates on the cells: {}",
+            if settings.show_coordinates {
+                "on"
+            } else {
+                "off"
+            }
+        ),
+        SettingsRow::MusicVolume => format!("Music: {}", volume_slider(settings.music_volume)),
+        SettingsRow::EffectsVolume => format!(
+            "Sound effects: {} (there are none yet)",
+            volume_slider(settings.effects_volume)
+        ),
+    }
+}
+
+/// The volume as a bar with a notch for every step of [`VOLUMES`]
+fn volume_slider(volume: f32) -> String {
+    let steps = VOLUMES.len() - 1;
+    let filled = ((volume * steps as f32).round() as usize).min(steps);
+    format!(
+        "[{}{}] {}%",
+        "#".repeat(filled),
+        "-".repeat(steps - filled),
+        (volume * 100.).round()
+    )
+}
+
+pub(crate) fn spawn_settings_menu(
+    mut commands: Commands,
+    menu: Res<SettingsMenu>,
+    settings: Res<Settings>,
+) {
+    spawn_settings_menu_for(&mut commands, &menu, &settings);
+}
+
+fn spawn_settings_menu_for(commands: &mut Commands, menu: &SettingsMenu, settings: &Settings) {
+    let mut hint = String::new();
+    for (i, row) in SettingsRow::ALL.into_iter().enumerate() {
+        let marker = if i == menu.row { "> " } else { "  " };
+        hint.push_str(&format!("{}{}\n", marker, display_row(row, settings)));
+    }
+    hint.push_str(
+        "\nUp and Down pick a setting, Left and Right change it\n\
+         Press Enter or Escape to go back",
+    );
+    ui::spawn_menu(commands, "Settings", &hint, &[]);
+}
+
+/// O opens the settings menu from the main menu and the pause menu
+pub(crate) fn settings_key(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    state: Res<State<AppState>>,
+    join_prompt: Option<Res<JoinPrompt>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    // The prompt for the address covers the main menu until it is closed
+    if !input.just_pressed(KeyCode::O) || join_prompt.is_some() {
+        return;
+    }
+    if matches!(state.0, AppState::MainMenu | AppState::Paused) {
+        open_settings_menu(&mut commands, &mut next_state, state.0);
+    }
+}
+
+/// Enter or Escape go back to the menu the settings menu was opened from
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn settings_menu_input(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    mut menu: ResMut<SettingsMenu>,
+    mut settings: ResMut<Settings>,
+    mut game: ResMut<Game>,
+    piece_sets: Res<PieceSets>,
+    mut next_state: ResMut<NextState<AppState>>,
+    menu_query: Query<Entity, With<MenuRoot>>,
+) {
+    if input.any_just_pressed([KeyCode::Return, KeyCode::Escape]) {
+        next_state.set(menu.return_to);
+        commands.remove_resource::<SettingsMenu>();
+        return;
+    }
+
+    let rows = SettingsRow::ALL.len();
+    if input.just_pressed(KeyCode::Down) {
+        menu.row = (menu.row + 1) % rows;
+    } else if input.just_pressed(KeyCode::Up) {
+        menu.row = (menu.row + rows - 1) % rows;
+    } else if input.any_just_pressed([KeyCode::Left, KeyCode::Right]) {
+        let row = SettingsRow::ALL[menu.row];
+        change(
+            row,
+            &mut settings,
+            &mut game,
+            &piece_sets,
+            input.just_pressed(KeyCode::Right),
+        );
+    } else {
+        return;
+    }
+    for entity in &menu_query {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_settings_menu_for(&mut commands, &menu, &settings);
+}
@@ -0,0 +1,135 @@
+//! How the units of the two teams look. A look is picked from presets in the settings, some of
+//! them with more contrast between the teams than the classic one, or made in the settings file as
+//! the custom look.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::units::Team;
+use crate::gamemanager::Game;
+use crate::scene::SceneChild;
+use crate::settings::Settings;
+
+/// Neutral units look the same in every look
+const NEUTRAL: TeamMaterial = TeamMaterial::new(Color::PURPLE, 0., Color::BLACK);
+
+const CLASSIC_WHITE: TeamMaterial = TeamMaterial::new(Color::BISQUE, 0., Color::BLACK);
+const CLASSIC_BLACK: TeamMaterial = TeamMaterial::new(Color::DARK_GRAY, 0., Color::BLACK);
+
+/// The material of the units of one team
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) struct TeamMaterial {
+    pub(crate) base_color: Color,
+    /// From 0 for plastic to 1 for metal
+    pub(crate) metallic: f32,
+    /// Glows even in the shade, which sets dark units apart from dark cells
+    pub(crate) emissive: Color,
+}
+
+impl TeamMaterial {
+    const fn new(base_color: Color, metallic: f32, emissive: Color) -> Self {
+        TeamMaterial {
+            base_color,
+            metallic,
+            emissive,
+        }
+    }
+
+    /// Puts the material on the material of a model, keeping the rest of it
+    pub(crate) fn apply(&self, material: &mut StandardMaterial) {
+        material.base_color = self.base_color;
+        material.metallic = self.metallic;
+        material.emissive = self.emissive;
+    }
+}
+
+/// The materials of the custom look, which are only changed in the settings file
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) struct CustomTeamLook {
+    pub(crate) white: TeamMaterial,
+    pub(crate) black: TeamMaterial,
+}
+
+impl Default for CustomTeamLook {
+    fn default() -> Self {
+        CustomTeamLook {
+            white: CLASSIC_WHITE,
+            black: CLASSIC_BLACK,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub(crate) enum TeamLook {
+    #[default]
+    Classic,
+    /// Polished silver against dark steel
+    Metal,
+    /// White against black, with black glowing orange so that it stands out on dark cells
+    HighContrast,
+    /// Orange against blue, which can also be told apart with most kinds of color blindness
+    OrangeBlue,
+    /// The materials in the settings file, see [`CustomTeamLook`]
+    Custom,
+}
+
+impl TeamLook {
+    pub(crate) const ALL: [TeamLook; 5] = [
+        Self::Classic,
+        Self::Metal,
+        Self::HighContrast,
+        Self::OrangeBlue,
+        Self::Custom,
+    ];
+
+    pub(crate) fn material(self, team: Team, custom: &CustomTeamLook) -> TeamMaterial {
+        let (white, black) = match self {
+            Self::Classic => (CLASSIC_WHITE, CLASSIC_BLACK),
+            Self::Metal => (
+                TeamMaterial::new(Color::SILVER, 0.9, Color::BLACK),
+                TeamMaterial::new(Color::rgb(0.15, 0.15, 0.17), 0.9, Color::BLACK),
+            ),
+            Self::HighContrast => (
+                TeamMaterial::new(Color::WHITE, 0., Color::rgb(0.2, 0.2, 0.2)),
+                TeamMaterial::new(Color::BLACK, 0., Color::rgb(0.6, 0.2, 0.)),
+            ),
+            Self::OrangeBlue => (
+                TeamMaterial::new(Color::rgb(1., 0.55, 0.1), 0., Color::rgb(0.25, 0.1, 0.)),
+                TeamMaterial::new(Color::rgb(0.1, 0.3, 1.), 0., Color::rgb(0., 0.05, 0.3)),
+            ),
+            Self::Custom => (custom.white, custom.black),
+        };
+        match team {
+            Team::White => white,
+            Team::Black => black,
+            Team::Neutral => NEUTRAL,
+        }
+    }
+}
+
+/// Puts the look that was just picked on the units on the board. Every unit has its own copy of
+/// the materials of its model, see [`crate::scene::prepare_unit_entity`].
+pub(crate) fn recolor_units_on_look_change(
+    settings: Res<Settings>,
+    game: Res<Game>,
+    child_query: Query<(&SceneChild, &Handle<StandardMaterial>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut shown: Local<Option<(TeamLook, CustomTeamLook)>>,
+) {
+    let look = (settings.team_look, settings.custom_team_look);
+    if !settings.is_changed() || *shown == Some(look) {
+        return;
+    }
+    // The units of the first game are prepared with the look already
+    if shown.replace(look).is_none() {
+        return;
+    }
+    for (child, handle) in &child_query {
+        let Some(unit) = game.units.get_unit_from_entity(child.parent_entity.into()) else {
+            continue;
+        };
+        if let Some(material) = materials.get_mut(handle) {
+            settings.team_material(unit.team).apply(material);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! An overlay for practice, which shades every cell the other team attacks and frames the units of
+//! the player that are attacked without being defended, so that a player can see which cells
+//! aren't safe for their units. It is worked out again from the attacked cells after every move,
+//! from the view of the player even while the AI or the other player online is moving. Turned on
+//! and off with a key or on the settings menu.
+
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+
+use crate::cell_cursor;
+use crate::engine::cell::CellCoordinates;
+use crate::engine::movement;
+use crate::engine::units::{Team, UnitType};
+use crate::gamemanager::Game;
+use crate::settings::Settings;
+use crate::AppState;
+
+const THREAT_COLOR: Color = Color::rgba(1., 0., 0., 0.3);
+const HANGING_COLOR: Color = Color::rgba(1., 0.6, 0., 0.9);
+
+/// How far above the cell the shading is, so that it isn't hidden in it. The move markers are above
+/// the shading.
+const THREAT_LIFT: f32 = 0.002;
+
+/// The shading of a cell, or the frame around a hanging unit
+#[derive(Component)]
+pub(crate) struct ThreatShade;
+
+/// The team the overlay is shown for. When only one team is played by a person on this computer
+/// that is their team, and otherwise the team whose turn it is.
+fn practicing_team(game: &Game) -> Team {
+    match (game.ai_playing, game.remote_team) {
+        (Some(other), _) | (_, Some(other)) if !game.spectating => other.opposite(),
+        _ => game.turn,
+    }
+}
+
+/// The threat key turns the overlay on and off while playing
+pub(crate) fn toggle_threat_overlay(
+    input: Res<Input<KeyCode>>,
+    state: Res<State<AppState>>,
+    mut settings: ResMut<Settings>,
+) {
+    if state.0.is_playing() && input.just_pressed(settings.key_bindings.threat_overlay) {
+        settings.show_threats = !settings.show_threats;
+    }
+}
+
+/// The cells the other team attacks, and the cells of the units of `team` there that no unit of
+/// `team` defends. Kings are left out, since a king that is attacked is in check anyway.
+fn threats(game: &Game, team: Team) -> (BTreeSet<CellCoordinates>, BTreeSet<CellCoordinates>) {
+    let threatened = movement::get_attacked_cells(&game.board, &game.units, team.opposite());
+    let defended = movement::get_attacked_cells(&game.board, &game.units, team);
+    let hanging = game
+        .units
+        .all_units_iter()
+        .filter(|unit| unit.team == team && unit.unit_type != UnitType::King)
+        .map(|unit| unit.coords)
+        .filter(|coords| threatened.contains(coords) && !defended.contains(coords))
+        .collect();
+    (threatened, hanging)
+}
+
+/// Shades the cells and frames the hanging units again after every move
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn update_threat_overlay(
+    mut commands: Commands,
+    game: Res<Game>,
+    settings: Res<Settings>,
+    shade_query: Query<Entity, With<ThreatShade>>,
+    cell_query: Query<&Transform, Without<ThreatShade>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut assets: Local<Option<(Handle<Mesh>, Handle<StandardMaterial>)>>,
+    mut shown: Local<(BTreeSet<CellCoordinates>, BTreeSet<CellCoordinates>)>,
+) {
+    let (threatened, hanging) = match settings.show_threats && game.result.is_none() {
+        true => threats(&game, practicing_team(&game)),
+        false => (BTreeSet::new(), BTreeSet::new()),
+    };
+    // The shading is also gone when the cube of the last game was removed
+    let num_shown = shown.0.len() + shown.1.len();
+    if shown.0 == threatened && shown.1 == hanging && shade_query.iter().count() == num_shown {
+        return;
+    }
+    for entity in &shade_query {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let (mesh, material) = assets.get_or_insert_with(|| {
+        (
+            meshes.add(shape::Plane::default().into()),
+            materials.add(StandardMaterial {
+                base_color: THREAT_COLOR,
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            }),
+        )
+    });
+    let mut spawned = BTreeSet::new();
+    let mut framed = BTreeSet::new();
+    for &coords in &hanging {
+        let Some(cell) = game.board.get_cell(coords) else {
+            continue;
+        };
+        let Ok(cell_transform) = cell_query.get(cell.plane.into()) else {
+            continue;
+        };
+        let transform = cell_cursor::frame_transform(coords, cell_transform);
+        let frame = cell_cursor::spawn_frame(
+            &mut commands,
+            transform,
+            HANGING_COLOR,
+            &mut meshes,
+            &mut materials,
+        );
+        commands.entity(frame).insert(ThreatShade);
+        framed.insert(coords);
+    }
+    for &coords in &threatened {
+        let Some(cell) = game.board.get_cell(coords) else {
+            continue;
+        };
+        let Ok(&cell_transform) = cell_query.get(cell.plane.into()) else {
+            continue;
+        };
+        let mut transform = cell_transform;
+        transform.translation += coords.normal_direction().as_vec3() * THREAT_LIFT;
+        commands.spawn((
+            PbrBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                transform,
+                ..default()
+            },
+            ThreatShade,
+        ));
+        spawned.insert(coords);
+    }
+    // Cells whose entities haven't been spawned yet are tried again on the next frame
+    if spawned == threatened && framed == hanging {
+        *shown = (threatened, hanging);
+    }
+}
+
+pub(crate) fn despawn_threat_overlay(
+    mut commands: Commands,
+    query: Query<Entity, With<ThreatShade>>,
+) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
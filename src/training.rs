@@ -0,0 +1,297 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::ai::{self, AICache};
+use crate::engine::cell::Board;
+use crate::engine::cubefen::CubeFen;
+use crate::engine::movement::{GameMove, Variant};
+use crate::engine::notation;
+use crate::engine::puzzles;
+use crate::engine::units::{Team, Unit, Units};
+use crate::gamemanager::Game;
+use crate::puzzles::PuzzleMode;
+use crate::settings::{self, LoadErrors};
+
+/// How many pawns worse than the best move a move has to be to count as a blunder
+const BLUNDER_THRESHOLD: f32 = 1.5;
+const REVIEW_DEPTH: u32 = 2;
+/// Cards that make it to the last box are considered learned and aren't shown again
+const NUM_BOXES: u32 = 5;
+const DECK_PATH: &str = "blunder-deck.ron";
+
+/// A position where a blunder was played, together with the move that should have been played
+#[derive(Debug, Serialize, Deserialize)]
+struct BlunderCard {
+    /// The position in CubeFEN, with the team that blundered to move
+    position: String,
+    played: GameMove,
+    better: GameMove,
+    /// Leitner box of the card, cards in higher boxes are shown less often
+    box_index: u32,
+    /// The card is due again once this many cards have been practiced
+    due: u32,
+}
+
+/// Blunders made by the players, resurfaced as puzzles in the puzzle mode. Spacing is counted in
+/// practiced cards rather than time. The deck is kept in the config dir.
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub(crate) struct BlunderDeck {
+    cards: Vec<BlunderCard>,
+    num_practiced: u32,
+    #[serde(skip)]
+    ai_cache: AICache,
+}
+
+impl BlunderDeck {
+    pub(crate) fn load(load_errors: &mut LoadErrors) -> Self {
+        settings::load_ron(DECK_PATH, load_errors).unwrap_or_default()
+    }
+
+    /// The index and the position of the most overdue card
+    pub(crate) fn due_card(&self) -> Option<(usize, CubeFen)> {
+        let (index, card) = self
+            .cards
+            .iter()
+            .enumerate()
+            .filter(|(_, card)| card.box_index < NUM_BOXES && card.due <= self.num_practiced)
+            .min_by_key(|(_, card)| card.due)?;
+        Some((index, card.position.parse().ok()?))
+    }
+
+    /// How many cards are due to be practiced
+    pub(crate) fn num_due(&self) -> usize {
+        self.cards
+            .iter()
+            .filter(|card| card.box_index < NUM_BOXES && card.due <= self.num_practiced)
+            .count()
+    }
+
+    /// The move that was played in the position of the card, and the better move
+    pub(crate) fn moves(&self, card: usize) -> (GameMove, GameMove) {
+        (self.cards[card].played, self.cards[card].better)
+    }
+
+    /// If `answer` is as good as the better move of the card. Any move the AI values as high as
+    /// the better move counts.
+    pub(crate) fn is_right(&mut self, card: usize, answer: GameMove, board: &Board) -> bool {
+        let better = self.cards[card].better;
+        if answer == better {
+            return true;
+        }
+        let Ok(position) = self.cards[card].position.parse::<CubeFen>() else {
+            return false;
+        };
+        let mut value = |game_move| {
+            ai::move_value(
+                board,
+                &position.units,
+                game_move,
+                position.turn,
+                REVIEW_DEPTH - 1,
+                position.variant,
+                &mut self.ai_cache,
+            )
+        };
+        value(answer) >= value(better)
+    }
+
+    /// Moves the card to the next box if it was answered right and back to the first box if not,
+    /// saving the deck right away
+    pub(crate) fn answer(&mut self, card: usize, right: bool) {
+        self.num_practiced += 1;
+        let card = &mut self.cards[card];
+        card.box_index = if right { card.box_index + 1 } else { 0 };
+        card.due = self.num_practiced + 2u32.pow(card.box_index) - 1;
+        settings::save_ron(DECK_PATH, self);
+    }
+}
+
+/// Compares the moves of the human players to the best move found by the AI, and adds the
+/// blunders to the deck
+pub(crate) fn flag_blunders(
+    game: Res<Game>,
+    mut deck: ResMut<BlunderDeck>,
+    puzzle: Option<Res<PuzzleMode>>,
+    mut last_position: Local<Option<(Team, Units)>>,
+) {
+    let deck = &mut *deck;
+    // Puzzles are only practiced, and only variants that have puzzles can be practiced
+    if puzzle.is_some() || !puzzles::has_puzzles(game.variant) {
+        *last_position = None;
+        return;
+    }
+
+    if let Some((team, units)) = &*last_position {
+        let team = *team;
+        if team != game.turn && game.ai_playing != Some(team) {
+            if let Some(played) = find_move(units, &game.units, team) {
+                check_for_blunder(units, team, played, &game, deck);
+            }
+        }
+    }
+
+    match &mut *last_position {
+        Some((team, units)) => {
+            *team = game.turn;
+            units.copy_from(&game.units);
+        }
+        None => *last_position = Some((game.turn, game.units.clone())),
+    }
+}
+
+fn check_for_blunder(
+    units: &Units,
+    team: Team,
+    played: GameMove,
+    game: &Game,
+    deck: &mut BlunderDeck,
+) {
+    let Some(better) = find_better_move(
+        &game.board,
+        units,
+        team,
+        played,
+        game.variant,
+        &mut deck.ai_cache,
+    ) else {
+        return;
+    };
+
+    info!(
+        "Blunder added to practice deck: {}",
+        notation::write_move(played, &game.board, units)
+    );
+    let position = CubeFen {
+        cube_side_length: game.board.cube_side_length,
+        units: units.clone(),
+        turn: team,
+        energy: [0; 2],
+        variant: game.variant,
+    };
+    deck.cards.push(BlunderCard {
+        position: position.to_string(),
+        played,
+        better,
+        box_index: 0,
+        due: deck.num_practiced,
+    });
+    settings::save_ron(DECK_PATH, deck);
+}
+
+/// The move the AI would have played instead of `played`, if `played` is a blunder
+fn find_better_move(
+    board: &Board,
+    units: &Units,
+    team: Team,
+    played: GameMove,
+    variant: Variant,
+    ai_cache: &mut AICache,
+) -> Option<GameMove> {
+    let better = ai::next_move(board, units, team, REVIEW_DEPTH, variant, ai_cache)?;
+    if better == played {
+        return None;
+    }
+    let mut value = |game_move| {
+        ai::move_value(
+            board,
+            units,
+            game_move,
+            team,
+            REVIEW_DEPTH - 1,
+            variant,
+            ai_cache,
+        )
+    };
+    (value(better) - value(played) >= BLUNDER_THRESHOLD).then_some(better)
+}
+
+/// The move `team` made to get from `before` to `after`
+fn find_move(before: &Units, after: &Units, team: Team) -> Option<GameMove> {
+    let has_unit_at = |units: &Units, unit: &Unit| {
+        units
+            .all_units_iter()
+            .any(|other| other.team == team && other.coords == unit.coords)
+    };
+    let from = before
+        .all_units_iter()
+        .find(|unit| unit.team == team && !has_unit_at(after, unit))?;
+    let to = after
+        .all_units_iter()
+        .find(|unit| unit.team == team && !has_unit_at(before, unit))?;
+    Some(GameMove {
+        from: from.coords,
+        to: to.coords,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::cell::CellCoordinates;
+    use crate::engine::units::UnitType;
+
+    fn game_move(from: &str, to: &str) -> GameMove {
+        GameMove {
+            from: CellCoordinates::from_display(from).unwrap(),
+            to: CellCoordinates::from_display(to).unwrap(),
+        }
+    }
+
+    #[test]
+    fn hanging_the_rook_is_a_blunder() {
+        let board = Board::new(4);
+        let mut units = Units::default();
+        for (unit_type, team, cell) in [
+            (UnitType::King, Team::White, "Yd4"),
+            (UnitType::Rook, Team::White, "Za1"),
+            (UnitType::King, Team::Black, "zd1"),
+            (UnitType::Queen, Team::Black, "Za4"),
+        ] {
+            let coords = CellCoordinates::from_display(cell).unwrap();
+            units.add_unit(Unit::new(unit_type, team, coords));
+        }
+        let king = CellCoordinates::from_display("Yd4").unwrap();
+        let king_move = ai::get_possible_moves(&board, &units, Team::White, Variant::Standard)
+            .into_iter()
+            .find(|game_move| game_move.from == king)
+            .unwrap();
+        let capture = game_move("Za1", "Za4");
+
+        let mut deck = BlunderDeck::default();
+        let better = find_better_move(
+            &board,
+            &units,
+            Team::White,
+            king_move,
+            Variant::Standard,
+            &mut deck.ai_cache,
+        );
+        assert_eq!(better, Some(capture));
+        let better = find_better_move(
+            &board,
+            &units,
+            Team::White,
+            capture,
+            Variant::Standard,
+            &mut deck.ai_cache,
+        );
+        assert_eq!(better, None);
+
+        let position = CubeFen {
+            cube_side_length: 4,
+            units,
+            turn: Team::White,
+            energy: [0; 2],
+            variant: Variant::Standard,
+        };
+        deck.cards.push(BlunderCard {
+            position: position.to_string(),
+            played: king_move,
+            better: capture,
+            box_index: 0,
+            due: 0,
+        });
+        assert!(deck.is_right(0, capture, &board));
+        assert!(!deck.is_right(0, king_move, &board));
+    }
+}
@@ -0,0 +1,107 @@
+//! Shows whose turn it is under the board, and when the AI is thinking about its move
+
+use bevy::prelude::*;
+
+use crate::gamemanager::Game;
+use crate::settings::Settings;
+
+/// The root node of the indicator, which is hidden once the game is over
+#[derive(Component)]
+pub(crate) struct TurnIndicator;
+
+/// A square in the color of the units of the team whose turn it is
+#[derive(Component)]
+pub(crate) struct TurnSwatch;
+
+#[derive(Component)]
+pub(crate) struct TurnText;
+
+pub(crate) fn setup_turn_indicator(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        bottom: Val::Px(10.),
+                        ..default()
+                    },
+                    size: Size::width(Val::Percent(100.)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            TurnIndicator,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(20.), Val::Px(20.)),
+                        margin: UiRect::right(Val::Px(8.)),
+                        ..default()
+                    },
+                    ..default()
+                },
+                TurnSwatch,
+            ));
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 24.,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                TurnText,
+            ));
+        });
+}
+
+pub(crate) fn despawn_turn_indicator(
+    mut commands: Commands,
+    query: Query<Entity, With<TurnIndicator>>,
+) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub(crate) fn update_turn_indicator(
+    game: Res<Game>,
+    settings: Res<Settings>,
+    mut indicator_query: Query<&mut Visibility, With<TurnIndicator>>,
+    mut swatch_query: Query<&mut BackgroundColor, With<TurnSwatch>>,
+    mut text_query: Query<&mut Text, With<TurnText>>,
+) {
+    if !game.is_changed() && !settings.is_changed() {
+        return;
+    }
+    for mut visibility in &mut indicator_query {
+        *visibility = match game.result {
+            Some(_) => Visibility::Hidden,
+            None => Visibility::Inherited,
+        };
+    }
+    for mut color in &mut swatch_query {
+        *color = settings.team_material(game.turn).base_color.into();
+    }
+    let value = if game.ai_playing == Some(game.turn) {
+        format!("{:?} is thinking\u{2026}", game.turn)
+    } else if game.remote_team == Some(game.turn) {
+        format!("{:?} to move, waiting for the opponent", game.turn)
+    } else if game.is_hot_seat() && settings.hot_seat_face_king {
+        // The cube turns to the king of the team to move, so the other player hands over
+        format!("{:?} to move, your king is facing you", game.turn)
+    } else {
+        format!("{:?} to move", game.turn)
+    };
+    for mut text in &mut text_query {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
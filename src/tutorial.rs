@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+
+use crate::engine::movement::Variant;
+use crate::engine::units::Team;
+use crate::gamemanager::{Game, GamePhase, MoveMade};
+use crate::scene;
+use crate::settings::Settings;
+use crate::AppState;
+
+/// What the player has to do to finish a step of a tutorial
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum TutorialGoal {
+    RotateCube,
+    SelectUnit,
+    MoveAcrossEdge,
+}
+
+pub(crate) struct TutorialStep {
+    pub(crate) instruction: &'static str,
+    pub(crate) goal: TutorialGoal,
+}
+
+const ONBOARDING: [TutorialStep; 3] = [
+    TutorialStep {
+        instruction: "Use the arrow keys to rotate the cube",
+        goal: TutorialGoal::RotateCube,
+    },
+    TutorialStep {
+        instruction: "Click one of your white units to select it",
+        goal: TutorialGoal::SelectUnit,
+    },
+    TutorialStep {
+        instruction: "Move a unit over an edge of the cube, onto another face",
+        goal: TutorialGoal::MoveAcrossEdge,
+    },
+];
+
+/// A scripted list of steps that the player is guided through one at a time
+#[derive(Resource)]
+pub(crate) struct Tutorial {
+    steps: &'static [TutorialStep],
+    current: usize,
+}
+
+impl Tutorial {
+    fn current_step(&self) -> Option<&'static TutorialStep> {
+        self.steps.get(self.current)
+    }
+}
+
+#[derive(Component)]
+pub(crate) struct TutorialText;
+
+pub(crate) fn start_onboarding(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut game: ResMut<Game>,
+    settings: Res<Settings>,
+) {
+    *game = Game::new(4, Variant::Standard, &settings);
+    game.ai_playing = None;
+    game.phase = GamePhase::Play;
+    scene::build_cube(&mut commands, &mut meshes, &mut materials, &mut game);
+
+    commands.insert_resource(Tutorial {
+        steps: &ONBOARDING,
+        current: 0,
+    });
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 28.,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(10.),
+                left: Val::Percent(30.),
+                ..default()
+            },
+            ..default()
+        }),
+        TutorialText,
+    ));
+}
+
+pub(crate) fn advance_tutorial(
+    input: Res<Input<KeyCode>>,
+    game: Res<Game>,
+    mut moves_made: EventReader<MoveMade>,
+    mut tutorial: ResMut<Tutorial>,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let moved_across_edge = moves_made.iter().any(|move_made| {
+        move_made.team == Team::White
+            && move_made.game_move.from.normal_direction()
+                != move_made.game_move.to.normal_direction()
+    });
+    let skipped = input.just_pressed(KeyCode::Return);
+
+    if let Some(step) = tutorial.current_step() {
+        let done = match step.goal {
+            TutorialGoal::RotateCube => {
+                let key_bindings = &settings.key_bindings;
+                input.any_just_pressed([
+                    key_bindings.rotate_left,
+                    key_bindings.rotate_right,
+                    key_bindings.rotate_up,
+                    key_bindings.rotate_down,
+                ])
+            }
+            TutorialGoal::SelectUnit => {
+                let selected_unit = game
+                    .selected_cell
+                    .and_then(|coords| game.units.get_unit(coords));
+                matches!(selected_unit, Some(unit) if unit.team == Team::White)
+            }
+            TutorialGoal::MoveAcrossEdge => moved_across_edge,
+        };
+        if done {
+            tutorial.current += 1;
+        }
+    }
+
+    if skipped || tutorial.current_step().is_none() {
+        settings.seen_onboarding = true;
+        next_state.set(AppState::MainMenu);
+    }
+}
+
+pub(crate) fn update_tutorial_text(
+    tutorial: Res<Tutorial>,
+    mut query: Query<&mut Text, With<TutorialText>>,
+) {
+    let Some(step) = tutorial.current_step() else {
+        return;
+    };
+    for mut text in &mut query {
+        text.sections[0].value = format!(
+            "Step {}/{}: {}\n(Enter to skip)",
+            tutorial.current + 1,
+            tutorial.steps.len(),
+            step.instruction
+        );
+    }
+}
+
+pub(crate) fn end_onboarding(mut commands: Commands, query: Query<Entity, With<TutorialText>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<Tutorial>();
+}
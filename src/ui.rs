@@ -0,0 +1,549 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+use crate::board_editor;
+use crate::captures;
+use crate::cli::LaunchGame;
+use crate::clipboard;
+use crate::engine::analysis;
+use crate::engine::units::Team;
+use crate::game_record;
+use crate::game_setup::GameSetup;
+use crate::gamemanager::{Game, GameResult};
+use crate::match_play::Match;
+use crate::network::{JoinPrompt, Network};
+use crate::piece_guide;
+use crate::presets::SetupPresets;
+use crate::protocol::Greeting;
+use crate::puzzles::{self, PuzzlePacks, SolvedPuzzles};
+use crate::settings::{LoadErrors, Settings};
+use crate::settings_menu;
+use crate::AppState;
+
+/// Pressing one of these on the main menu starts a game with the preset at the same position
+const PRESET_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+pub(crate) const BUTTON_COLOR: Color = Color::rgb(0.15, 0.15, 0.15);
+pub(crate) const HOVERED_BUTTON_COLOR: Color = Color::rgb(0.25, 0.25, 0.25);
+pub(crate) const CLICKED_BUTTON_COLOR: Color = Color::rgb(0.35, 0.55, 0.35);
+
+/// Marks the root node of the menu or screen that is currently shown
+#[derive(Component)]
+pub(crate) struct MenuRoot;
+
+/// What a button on a menu does when it is clicked. Each does the same as a key on the menu.
+#[derive(Component, Clone, Copy)]
+pub(crate) enum MenuButton {
+    Play,
+    Resume,
+    NewGame,
+    Rematch,
+    Replay,
+    Analysis,
+    Settings,
+    Quit,
+}
+
+/// A text screen on top of the cube, darkening it, with a row of buttons under the text
+pub(crate) fn spawn_menu(
+    commands: &mut Commands,
+    title: &str,
+    hint: &str,
+    buttons: &[(&str, MenuButton)],
+) -> Entity {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.6).into(),
+                ..default()
+            },
+            MenuRoot,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                title,
+                TextStyle {
+                    font_size: 60.,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+            parent.spawn(TextBundle::from_section(
+                hint,
+                TextStyle {
+                    font_size: 24.,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::top(Val::Px(20.)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for &(label, button) in buttons {
+                        spawn_button(parent, label, button);
+                    }
+                });
+        })
+        .id()
+}
+
+fn spawn_button(parent: &mut ChildBuilder, label: &str, button: MenuButton) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    size: Size::new(Val::Px(180.), Val::Px(50.)),
+                    margin: UiRect::all(Val::Px(8.)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: BUTTON_COLOR.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: 28.,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// Highlights the button under the mouse, and does what a button says when it is clicked
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn button_system(
+    mut commands: Commands,
+    mut query: Query<(&Interaction, &MenuButton, &mut BackgroundColor), Changed<Interaction>>,
+    join_prompt: Option<Res<JoinPrompt>>,
+    game: Res<Game>,
+    settings: Res<Settings>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for (interaction, button, mut color) in &mut query {
+        *color = match interaction {
+            Interaction::Clicked => CLICKED_BUTTON_COLOR,
+            Interaction::Hovered => HOVERED_BUTTON_COLOR,
+            Interaction::None => BUTTON_COLOR,
+        }
+        .into();
+        // The prompt for the address covers the main menu until it is closed
+        if *interaction != Interaction::Clicked || join_prompt.is_some() {
+            continue;
+        }
+        match button {
+            MenuButton::Play | MenuButton::Resume => next_state.set(AppState::InGame),
+            MenuButton::NewGame => next_state.set(AppState::MainMenu),
+            MenuButton::Rematch => start_rematch(&mut commands, &game, &settings, &mut next_state),
+            MenuButton::Replay => next_state.set(AppState::Replay),
+            MenuButton::Analysis => next_state.set(AppState::Analysis),
+            MenuButton::Settings => {
+                settings_menu::open_settings_menu(&mut commands, &mut next_state, state.0)
+            }
+            MenuButton::Quit => exit.send(AppExit),
+        }
+    }
+}
+
+/// Tells the player which saved files couldn't be read when the game was started
+#[derive(Component)]
+pub(crate) struct LoadErrorDialog;
+
+pub(crate) fn spawn_load_error_dialog(mut commands: Commands, load_errors: Res<LoadErrors>) {
+    if load_errors.0.is_empty() {
+        return;
+    }
+    let hint = format!(
+        "{}\n\nThe defaults are used instead. Press Escape to close",
+        load_errors.0.join("\n")
+    );
+    let dialog = spawn_menu(
+        &mut commands,
+        "Some saved files couldn't be read",
+        &hint,
+        &[],
+    );
+    commands
+        .entity(dialog)
+        .remove::<MenuRoot>()
+        .insert((LoadErrorDialog, ZIndex::Global(1)));
+}
+
+pub(crate) fn close_load_error_dialog(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    query: Query<Entity, With<LoadErrorDialog>>,
+) {
+    if !input.just_pressed(KeyCode::Escape) {
+        return;
+    }
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub(crate) fn despawn_menu(mut commands: Commands, query: Query<Entity, With<MenuRoot>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub(crate) fn spawn_main_menu(
+    mut commands: Commands,
+    presets: Res<SetupPresets>,
+    settings: Res<Settings>,
+    current_match: Option<Res<Match>>,
+) {
+    spawn_main_menu_for(&mut commands, &presets, &settings, current_match.as_deref());
+}
+
+fn spawn_main_menu_for(
+    commands: &mut Commands,
+    presets: &SetupPresets,
+    settings: &Settings,
+    current_match: Option<&Match>,
+) {
+    let chosen = presets.chosen();
+    let mut hint = String::new();
+    if game_record::has_autosave() {
+        hint.push_str("Press C to continue the last game\n\n");
+    }
+    hint.push_str(&match current_match {
+        Some(current_match) => format!(
+            "Press Enter to play the next game of the match\n{}\n\n",
+            current_match.display_score()
+        ),
+        None => String::new(),
+    });
+    hint.push_str(&format!(
+        "Press Enter to play {}\nPress M to play a match of it to {} points\n\
+         Cube size: {} (Left and Right to change)\n\
+         Press V to play it from a position copied to the clipboard\n\
+         Press H to host it online, or J to join an online game\n\
+         Press W to watch an online game\n\
+         Press S to set up a new game\n\
+         Press E to set up a position in the board editor\n\
+         Press P for mate puzzles\n\
+         Press G to see how the pieces move\n\
+         Press O for the settings\n",
+        chosen.display(),
+        settings.match_points,
+        chosen.cube_side_length
+    ));
+    for (i, preset) in presets.presets.iter().take(PRESET_KEYS.len()).enumerate() {
+        hint.push_str(&format!("\n{}: {}", i + 1, preset.display()));
+    }
+    spawn_menu(
+        commands,
+        "Chess on a cube",
+        &hint,
+        &[
+            ("Play", MenuButton::Play),
+            ("Settings", MenuButton::Settings),
+            ("Quit", MenuButton::Quit),
+        ],
+    );
+}
+
+/// Picking a preset, continuing the last game, playing a pasted position or starting a new match
+/// gives up the current match
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn main_menu_input(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    mut presets: ResMut<SetupPresets>,
+    settings: Res<Settings>,
+    current_match: Option<Res<Match>>,
+    packs: Res<PuzzlePacks>,
+    solved: Res<SolvedPuzzles>,
+    mut next_state: ResMut<NextState<AppState>>,
+    menu_query: Query<Entity, With<MenuRoot>>,
+) {
+    if input.any_just_pressed([KeyCode::Left, KeyCode::Right]) {
+        presets.resize_chosen(input.just_pressed(KeyCode::Right));
+        for entity in &menu_query {
+            commands.entity(entity).despawn_recursive();
+        }
+        spawn_main_menu_for(&mut commands, &presets, &settings, current_match.as_deref());
+    }
+    if input.just_pressed(KeyCode::Return) {
+        next_state.set(AppState::InGame);
+    }
+    if input.just_pressed(KeyCode::C) && game_record::has_autosave() {
+        match game_record::load_autosave(&settings) {
+            Ok(game) => {
+                commands.insert_resource(LaunchGame(Some(game)));
+                commands.remove_resource::<Match>();
+                next_state.set(AppState::InGame);
+            }
+            Err(err) => warn!("{}", err),
+        }
+    }
+    if input.just_pressed(KeyCode::V) {
+        // The chosen preset decides who plays, the pasted position decides the rest
+        let position = clipboard::paste().and_then(|position| {
+            Game::from_cubefen(&position, &settings)?;
+            Ok(position)
+        });
+        match position {
+            Ok(position) => {
+                let mut preset = presets.chosen().clone();
+                preset.position = Some(position);
+                commands.insert_resource(LaunchGame(Some(preset.new_game(&settings))));
+                commands.remove_resource::<Match>();
+                next_state.set(AppState::InGame);
+            }
+            Err(err) => warn!("{}", err),
+        }
+    }
+    if input.just_pressed(KeyCode::H) {
+        match Network::host(presets.chosen(), &settings) {
+            Ok((network, game)) => {
+                commands.insert_resource(network);
+                commands.insert_resource(LaunchGame(Some(game)));
+                commands.remove_resource::<Match>();
+                next_state.set(AppState::InGame);
+            }
+            Err(err) => warn!("{}", err),
+        }
+    }
+    if input.just_pressed(KeyCode::J) {
+        commands.insert_resource(JoinPrompt::new(Greeting::Play));
+    }
+    if input.just_pressed(KeyCode::W) {
+        commands.insert_resource(JoinPrompt::new(Greeting::Watch));
+    }
+    if input.just_pressed(KeyCode::E) {
+        board_editor::open_board_editor(&mut commands, &presets, &settings, &mut next_state);
+    }
+    if input.just_pressed(KeyCode::G) {
+        piece_guide::open_piece_guide(&mut commands, &settings, &mut next_state);
+    }
+    if input.just_pressed(KeyCode::P) {
+        puzzles::open_puzzle_browser(&mut commands, &packs, &solved, &mut next_state);
+    }
+    if input.just_pressed(KeyCode::S) {
+        commands.insert_resource(GameSetup::new(&presets));
+        next_state.set(AppState::Setup);
+    }
+    if input.just_pressed(KeyCode::M) {
+        commands.insert_resource(Match::new(presets.chosen().clone(), settings.match_points));
+        next_state.set(AppState::InGame);
+    }
+    for (i, keycode) in PRESET_KEYS.into_iter().enumerate() {
+        if input.just_pressed(keycode) && i < presets.presets.len() {
+            presets.choose(i);
+            commands.remove_resource::<Match>();
+            next_state.set(AppState::InGame);
+        }
+    }
+}
+
+pub(crate) fn spawn_pause_menu(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    game: Res<Game>,
+    network: Option<Res<Network>>,
+) {
+    let key_bindings = &settings.key_bindings;
+    let mut hint = format!(
+        "Press {:?} or Escape to continue\nPress {:?} for a new game\nPress B to save a bug report\n\
+         Press C to copy the position\nPress O for the settings\n\
+         Ctrl+{:?} and Ctrl+{:?} undo and redo turns\n\
+         {:?} shades the cells the other team attacks while playing\n\
+         {:?} turns the cube to your king\n\
+         {:?} opens a prompt to type a move\n\
+         Hold {:?} to see through the cube",
+        key_bindings.pause,
+        key_bindings.new_game,
+        key_bindings.undo,
+        key_bindings.redo,
+        key_bindings.threat_overlay,
+        key_bindings.face_king,
+        key_bindings.type_move,
+        key_bindings.peek
+    );
+    if matches!(network, Some(network) if network.is_playing()) {
+        hint.push_str("\nPress D to offer or accept a draw\nPress X to resign");
+    }
+    if let Some(seed) = game.setup_seed {
+        hint.push_str(&format!(
+            "\n\nShuffled setup {}, --setup-seed {} plays it again",
+            seed, seed
+        ));
+    }
+    spawn_menu(
+        &mut commands,
+        "Paused",
+        &hint,
+        &[
+            ("Continue", MenuButton::Resume),
+            ("Settings", MenuButton::Settings),
+            ("New game", MenuButton::NewGame),
+        ],
+    );
+}
+
+/// The pause key or Escape pauses and continues the game. Escape first cancels a pending summon or
+/// closes the dialog about saved files that couldn't be read.
+pub(crate) fn toggle_pause(
+    input: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    game: Res<Game>,
+    dialog_query: Query<(), With<LoadErrorDialog>>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let escape = input.just_pressed(KeyCode::Escape)
+        && game.pending_summon.is_none()
+        && game.pending_promotion.is_none()
+        && dialog_query.is_empty();
+    if !input.just_pressed(settings.key_bindings.pause) && !escape {
+        return;
+    }
+    match state.0 {
+        AppState::InGame => next_state.set(AppState::Paused),
+        AppState::Paused => next_state.set(AppState::InGame),
+        _ => {}
+    }
+}
+
+/// The new game key on the pause menu or the game over screen goes back to the main menu, where the
+/// next game is picked
+pub(crate) fn new_game_input(
+    input: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let pressed = input.just_pressed(settings.key_bindings.new_game);
+    if pressed && matches!(state.0, AppState::Paused | AppState::GameOver) {
+        next_state.set(AppState::MainMenu);
+    }
+}
+
+/// Shows who won and how, the material left on the board, and what can be done next
+pub(crate) fn spawn_game_over_screen(
+    mut commands: Commands,
+    game: Res<Game>,
+    settings: Res<Settings>,
+    current_match: Option<Res<Match>>,
+    network: Option<Res<Network>>,
+) {
+    let title = match game.result {
+        Some(GameResult::Win(team)) => format!("{:?} wins", team),
+        Some(GameResult::Draw) => "Draw".to_string(),
+        None => "Game over".to_string(),
+    };
+    let mut summary = match game.end_reason {
+        Some(reason) => format!("{}\n", reason.display()),
+        None => String::new(),
+    };
+    summary.push_str(&format!(
+        "Material left: White {}, Black {}\n\n",
+        captures::material_on_board(&game, Team::White),
+        captures::material_on_board(&game, Team::Black)
+    ));
+    if let Some(seed) = game.setup_seed {
+        summary.push_str(&format!("Shuffled setup {}\n\n", seed));
+    }
+    let new_game = settings.key_bindings.new_game;
+    let hint = match current_match.as_deref() {
+        Some(current_match) if current_match.winner().is_none() => format!(
+            "{}\nPress {:?} for the next game",
+            current_match.display_score(),
+            new_game
+        ),
+        Some(current_match) => format!(
+            "{}\nPress {:?} for a new game",
+            current_match.display_score(),
+            new_game
+        ),
+        None => format!("Press {:?} for a new game", new_game),
+    };
+    let mut hint = format!("{}{}\nPress R to review the game", summary, hint);
+    let mut buttons = vec![("New game", MenuButton::NewGame)];
+    match (&network, &current_match) {
+        (Some(network), _) if network.is_playing() => {
+            hint.push_str("\nPress T to offer or accept a rematch");
+        }
+        (None, None) => {
+            hint.push_str("\nPress T for a rematch with the colors swapped");
+            buttons.push(("Rematch", MenuButton::Rematch));
+        }
+        _ => {}
+    }
+    buttons.push(("Review game", MenuButton::Replay));
+    if analysis::can_analyse(game.variant) {
+        hint.push_str("\nPress A for an analysis of the moves");
+        buttons.push(("Analysis", MenuButton::Analysis));
+    }
+    spawn_menu(&mut commands, &title, &hint, &buttons);
+}
+
+/// T on the game over screen of a game that isn't online or part of a match plays it again, with
+/// the AI playing the other team
+pub(crate) fn rematch_input(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    game: Res<Game>,
+    settings: Res<Settings>,
+    current_match: Option<Res<Match>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if input.just_pressed(KeyCode::T) && current_match.is_none() {
+        start_rematch(&mut commands, &game, &settings, &mut next_state);
+    }
+}
+
+/// The rematch goes through the main menu, which clears the cube of the last game
+fn start_rematch(
+    commands: &mut Commands,
+    game: &Game,
+    settings: &Settings,
+    next_state: &mut NextState<AppState>,
+) {
+    commands.insert_resource(LaunchGame(Some(game.rematch(settings))));
+    next_state.set(AppState::MainMenu);
+}
+
+/// A game that was picked on the way to the main menu, like a rematch, starts right away
+pub(crate) fn launch_waiting_game(
+    launch_game: Res<LaunchGame>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if launch_game.0.is_some() && next_state.0.is_none() {
+        next_state.set(AppState::InGame);
+    }
+}